@@ -17,36 +17,76 @@
  * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
  */
 use std::{
+    any::Any,
     path::{ Path },
-    collections::vec_deque::VecDeque
+    collections::vec_deque::{ VecDeque, Iter, IterMut }
 };
 
+use futurecommander_representation::PathComparison;
+
 use crate::{
     errors:: { DomainError, QueryError },
     capability::{
-        RegistrarGuard
+        Guard,
+        RegistrarGuard,
+        AnswerFile
     },
     event::{
         Listener,
         Delayer,
         FileSystemEvent,
+        Plan
     },
+    recovery::Recovery,
+    lock::Locks,
+    naming::{ NameValidator, NameWarning },
     port::{
         ReadableFileSystem,
+        WriteableFileSystem,
         FileSystemAdapter,
         EntryAdapter,
-        EntryCollection
-
+        EntryCollection,
+        Atomic,
+        Metadata,
+        ShellScript
     },
     infrastructure::{
         VirtualFileSystem,
         VirtualStatus,
-        RealFileSystem
+        RealFileSystem,
+        Changes,
+        errors::InfrastructureError
     }
 };
 
 type Commitment = (FileSystemEvent, RegistrarGuard);
 
+/*
+Lists every child on read_maintained, so that atomization against the virtual file system
+enumerates directory content explicitly instead of relying on virtual parent bindings.
+*/
+struct Exhaustive<'a, F: ReadableFileSystem>(&'a F);
+
+impl <'a, F: ReadableFileSystem> ReadableFileSystem for Exhaustive<'a, F> {
+    type Item = F::Item;
+
+    fn read_dir(&self, path: &Path) -> Result<EntryCollection<Self::Item>,QueryError> {
+        self.0.read_dir(path)
+    }
+
+    fn status(&self, path: &Path) -> Result<Self::Item, QueryError> {
+        self.0.status(path)
+    }
+
+    fn read_maintained(&self, path: &Path) -> Result<EntryCollection<Self::Item>,QueryError> {
+        self.0.read_dir(path)
+    }
+
+    fn metadata(&self, path: &Path) -> Result<Option<Metadata>, QueryError> {
+        self.0.metadata(path)
+    }
+}
+
 #[derive(Debug)]
 pub struct EventQueue(VecDeque<Commitment>);
 
@@ -69,6 +109,30 @@ impl EventQueue {
         self.0.clear()
     }
 
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Commitment> {
+        self.0.remove(index)
+    }
+
+    pub fn insert(&mut self, index: usize, commitment: Commitment) {
+        self.0.insert(index, commitment)
+    }
+
+    pub fn iter(&self) -> Iter<'_, Commitment> {
+        self.0.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> IterMut<'_, Commitment> {
+        self.0.iter_mut()
+    }
+
     pub fn serialize(&self) -> Result<String, serde_json::Error> {
         let mut serializable : Vec<(&FileSystemEvent, &RegistrarGuard)> = Vec::new();
         for (event, guard) in self.0.iter() {
@@ -82,7 +146,11 @@ impl EventQueue {
 pub struct Container {
     virtual_fs  : FileSystemAdapter<VirtualFileSystem>,
     real_fs     : FileSystemAdapter<RealFileSystem>,
-    event_queue : EventQueue
+    event_queue : EventQueue,
+    session     : Option<Locks>,
+    naming      : Option<NameValidator>,
+    warnings    : Vec<NameWarning>,
+    attachment  : Option<Box<dyn Any + Send>>
 }
 
 impl Default for Container {
@@ -96,11 +164,70 @@ impl Container {
         Container {
             virtual_fs: FileSystemAdapter(VirtualFileSystem::default()),
             real_fs:    FileSystemAdapter(RealFileSystem::default()),
-            event_queue: EventQueue::default()
+            event_queue: EventQueue::default(),
+            session: None,
+            naming: None,
+            warnings: Vec::new(),
+            attachment: None
+        }
+    }
+
+    //Locks every directory the queue changes until dropped, those already locked by the session are left as they are
+    fn lock_queue(&self) -> Result<Locks, DomainError> {
+        let mut locks = Locks::default();
+        locks.acquire(&Locks::roots(self.events()))?;
+        Ok(locks)
+    }
+
+    //Keeps directories locked from the moment an event changing them is emitted until unlock_session
+    pub fn lock_session(&mut self) -> Result<(), DomainError> {
+        if self.session.is_none() {
+            let mut locks = Locks::session();
+            locks.acquire(&Locks::roots(self.events()))?;
+            self.session = Some(locks);
         }
+        Ok(())
+    }
+
+    pub fn unlock_session(&mut self) {
+        self.session = None;
+    }
+
+    pub fn is_session_locked(&self) -> bool {
+        self.session.is_some()
+    }
+
+    //Staged entries folded together by the new comparison are refused
+    pub fn set_path_comparison(&mut self, comparison: PathComparison) -> Result<(), DomainError> {
+        self.virtual_fs.as_inner_mut().set_comparison(comparison)
+            .map_err(|error| DomainError::from(QueryError::from(error)))
+    }
+
+    pub fn path_comparison(&self) -> PathComparison {
+        self.virtual_fs.as_inner().comparison()
+    }
+
+    //Names put in place by emitted events are checked until set back to None
+    pub fn validate_names(&mut self, validator: Option<NameValidator>) {
+        self.naming = validator;
+    }
+
+    pub fn name_validator(&self) -> Option<&NameValidator> {
+        self.naming.as_ref()
+    }
+
+    //Attached to the guard of every event emitted afterwards, those loaded from json included
+    pub fn attach_to_guards<A: Any + Send>(&mut self, attachment: A) {
+        self.attachment = Some(Box::new(attachment));
+    }
+
+    //Issues reported since the last call
+    pub fn take_warnings(&mut self) -> Vec<NameWarning> {
+        self.warnings.drain(..).collect()
     }
 
     pub fn apply(&mut self) -> Result<(), DomainError> {
+        let _locks = self.lock_queue()?;
         while let Some((event, mut guard)) = self.event_queue.pop_front() {
             event.atomize(&self.real_fs, &mut guard)?
                 .apply(&mut self.real_fs)?;
@@ -109,6 +236,27 @@ impl Container {
         Ok(())
     }
 
+    //Same as apply, recording how to revert it in recovery, returns the name of the session
+    pub fn apply_recoverable(&mut self, recovery: &Recovery) -> Result<String, DomainError> {
+        let _locks = self.lock_queue()?;
+        let mut journal = recovery.start()?;
+        while let Some((event, mut guard)) = self.event_queue.pop_front() {
+            let transaction = event.atomize(&self.real_fs, &mut guard)?;
+            journal.apply(transaction, &mut self.real_fs)?;
+        }
+        self.reset();
+        recovery.expire()?;
+        Ok(journal.name().to_string())
+    }
+
+    //Returns the name of the session journaling the revert
+    pub fn revert(&mut self, recovery: &Recovery, session: &str) -> Result<String, DomainError> {
+        if !self.event_queue.is_empty() || !self.is_empty() {
+            return Err(DomainError::PendingChanges);
+        }
+        recovery.revert(session, &mut self.real_fs)
+    }
+
     pub fn reset(&mut self) {
         self.virtual_fs.as_inner_mut().reset();
         self.event_queue.clear()
@@ -118,6 +266,67 @@ impl Container {
         self.virtual_fs.as_inner().is_empty()
     }
 
+    //Every path the queue changes, compared with the real file system
+    pub fn changes(&self) -> Result<Changes, DomainError> {
+        Ok(self.virtual_fs.as_inner().changes().map_err(InfrastructureError::from)?)
+    }
+
+    //Guard answers recorded along the queue
+    pub fn decisions(&self) -> AnswerFile {
+        AnswerFile::new(self.event_queue.iter().flat_map(|(_event, guard)| guard.decisions()).collect())
+    }
+
+    pub fn events(&self) -> impl Iterator<Item = &FileSystemEvent> {
+        self.event_queue.iter().map(|(event, _guard)| event)
+    }
+
+    //The queue is left as it was when the remaining events no longer apply
+    pub fn remove_event(&mut self, index: usize) -> Result<FileSystemEvent, DomainError> {
+        let commitment = self.event_queue.remove(index).ok_or(DomainError::EventOutOfRange(index))?;
+        if let Err(error) = self.replay() {
+            self.event_queue.insert(index, commitment);
+            return Err(error);
+        }
+        Ok(commitment.0)
+    }
+
+    pub fn move_event(&mut self, from: usize, to: usize) -> Result<(), DomainError> {
+        for index in [from, to] {
+            if index >= self.event_queue.len() {
+                return Err(DomainError::EventOutOfRange(index));
+            }
+        }
+
+        let commitment = self.event_queue.remove(from).ok_or(DomainError::EventOutOfRange(from))?;
+        self.event_queue.insert(to, commitment);
+        if let Err(error) = self.replay() {
+            let commitment = self.event_queue.remove(to).ok_or(DomainError::EventOutOfRange(to))?;
+            self.event_queue.insert(from, commitment);
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    //Rebuilds the virtual file system from the queue, which is only swapped in once every event applied
+    fn replay(&mut self) -> Result<(), DomainError> {
+        let mut virtual_fs = self.blank_virtual_fs()?;
+        for (index, (event, guard)) in self.event_queue.iter_mut().enumerate() {
+            event.atomize(&virtual_fs, guard)
+                .and_then(|transaction| Ok(transaction.apply(&mut virtual_fs)?))
+                .map_err(|error| DomainError::ReplayFailed(index, event.to_string(), Box::new(error)))?;
+        }
+        self.virtual_fs = virtual_fs;
+        Ok(())
+    }
+
+    //Compares paths like the staged virtual file system
+    fn blank_virtual_fs(&self) -> Result<FileSystemAdapter<VirtualFileSystem>, DomainError> {
+        let mut virtual_fs = VirtualFileSystem::default();
+        virtual_fs.set_comparison(self.path_comparison())
+            .map_err(|error| DomainError::from(QueryError::from(error)))?;
+        Ok(FileSystemAdapter(virtual_fs))
+    }
+
     pub fn vfs(&self) -> &FileSystemAdapter<VirtualFileSystem> {
         &self.virtual_fs
     }
@@ -130,8 +339,78 @@ impl Container {
         Ok(self.event_queue.serialize()?)
     }
 
+    pub fn to_shell_script(&mut self) -> Result<String, DomainError> {
+        let mut simulation = self.blank_virtual_fs()?;
+        let mut script = ShellScript::default();
+
+        for (event, guard) in self.event_queue.iter_mut() {
+            let transaction = event.atomize(&Exhaustive(&simulation), guard)?;
+            script.add(event.to_string().as_str(), &transaction);
+
+            for atomic in transaction {
+                match atomic {
+                    //Children are already explicit, binding would make them appear twice
+                    Atomic::BindDirectoryToDirectory { destination, .. } =>
+                        simulation.create_empty_directory(destination.as_path())?,
+                    atomic => atomic.apply(&mut simulation)?
+                }
+            }
+        }
+        Ok(script.to_string())
+    }
+
+    pub fn to_plan(&self, base: Option<&Path>) -> Result<Plan, DomainError> {
+        let mut plan = Plan::new(base);
+        for (event, _guard) in self.event_queue.iter() {
+            plan.add(event)?;
+        }
+        Ok(plan)
+    }
+
+    pub fn emit_plan(&mut self, plan: &Plan, cwd: &Path) -> Result<(), DomainError> {
+        for event in plan.events(cwd)? {
+            let guard = self.emit(&event, RegistrarGuard::default())?;
+            self.delay(event, guard);
+        }
+        Ok(())
+    }
+
+    //Stages every event or none of them, each one is atomized against what the previous ones staged
+    pub fn emit_all(&mut self, commitments: Vec<Commitment>) -> Result<(), DomainError> {
+        let mut virtual_fs = FileSystemAdapter(self.virtual_fs.as_inner().clone());
+        let mut warnings = Vec::new();
+        let mut staged = Vec::new();
+        for (event, mut guard) in commitments {
+            if let Some(attachment) = self.attachment.as_deref() {
+                guard.attach(attachment);
+            }
+            warnings.append(&mut Self::stage(self.naming.as_ref(), &mut virtual_fs, &event, &mut guard)?);
+            staged.push((event, guard));
+        }
+
+        if let Some(session) = self.session.as_mut() {
+            session.acquire(&Locks::roots(staged.iter().map(|(event, _guard)| event)))?;
+        }
+        self.virtual_fs = virtual_fs;
+        self.warnings.append(&mut warnings);
+        for commitment in staged {
+            self.event_queue.push_back(commitment);
+        }
+        Ok(())
+    }
+
+    fn stage(naming: Option<&NameValidator>, virtual_fs: &mut FileSystemAdapter<VirtualFileSystem>, event: &FileSystemEvent, guard: &mut RegistrarGuard) -> Result<Vec<NameWarning>, DomainError> {
+        let transaction = event.atomize(virtual_fs, guard)?;
+        let warnings = match naming {
+            Some(validator) => validator.validate(&transaction, virtual_fs)?,
+            None => Vec::new()
+        };
+        transaction.apply(virtual_fs)?;
+        Ok(warnings)
+    }
+
     pub fn emit_json(&mut self, json: String) -> Result<(), DomainError> {
-        let events : Vec<(FileSystemEvent, RegistrarGuard)> = serde_json::from_str(json.as_str()).unwrap();
+        let events : Vec<(FileSystemEvent, RegistrarGuard)> = serde_json::from_str(json.as_str())?;
         for (event, guard) in events {
             let guard = self.emit(&event, guard)?;
             self.delay(event, guard );
@@ -150,6 +429,10 @@ impl ReadableFileSystem for Container {
     fn status(&self, path: &Path) -> Result<Self::Item, QueryError> {
         self.virtual_fs.status(path)
     }
+
+    fn metadata(&self, path: &Path) -> Result<Option<Metadata>, QueryError> {
+        self.virtual_fs.metadata(path)
+    }
 }
 
 impl Delayer for Container {
@@ -161,8 +444,14 @@ impl Delayer for Container {
 
 impl Listener for Container {
     fn emit(&mut self, event: &FileSystemEvent, mut guard: RegistrarGuard) -> Result<RegistrarGuard, DomainError> {
-        event.atomize(&self.virtual_fs, &mut guard)?
-             .apply(&mut self.virtual_fs)?;
+        if let Some(session) = self.session.as_mut() {
+            session.acquire(&Locks::roots([event]))?;
+        }
+        if let Some(attachment) = self.attachment.as_deref() {
+            guard.attach(attachment);
+        }
+        let mut warnings = Self::stage(self.naming.as_ref(), &mut self.virtual_fs, event, &mut guard)?;
+        self.warnings.append(&mut warnings);
         Ok(guard)
     }
 }
@@ -172,9 +461,16 @@ impl Listener for Container {
 mod tests {
     use super::*;
 
+    use std::{
+        fs::write,
+        process
+    };
+
     use crate::{
-        event::CopyEvent,
+        event::{ CopyEvent, MoveEvent, RemoveEvent },
         sample::Samples,
+        lock::LOCK_FILE,
+        naming::{ Portability, NameIssue },
         Entry
     };
 
@@ -252,4 +548,274 @@ mod tests {
         assert!(b_stat.exists());
         assert!(b_stat.is_dir());
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn can_import_virtual_state_with_names_which_are_not_utf8() {
+        use std::{ ffi::OsStr, os::unix::ffi::OsStrExt };
+
+        let chroot = Samples::init_simple_chroot("can_import_virtual_state_with_names_which_are_not_utf8");
+        let copied = chroot.join(OsStr::from_bytes(b"COPI\xc9"));
+        let mut container_a = Container::new();
+        let event = FileSystemEvent::Copy(
+            CopyEvent::new(
+                chroot.join("RDIR").as_path(),
+                copied.as_path(),
+                false,
+                false
+            )
+        );
+
+        let guard = container_a.emit(&event, RegistrarGuard::default()).unwrap();
+        container_a.delay(event, guard);
+
+        let json = container_a.to_json().unwrap();
+        assert!(json.contains("COPI\\\\xc9"));
+
+        let mut container_b = Container::new();
+        container_b.emit_json(json).unwrap();
+
+        assert!(container_b.status(copied.join("RFILEA").as_path()).unwrap().is_file());
+        assert!(container_b.emit_json("[[{\"Copy\":".to_string()).is_err());
+    }
+
+    #[test]
+    fn can_import_virtual_state_from_plan() {
+        let chroot = Samples::init_simple_chroot("can_import_virtual_state_from_plan");
+        let mut container_a = Container::new();
+        let event = FileSystemEvent::Copy(
+            CopyEvent::new(
+                chroot.join("RDIR").as_path(),
+                chroot.join("COPIED").as_path(),
+                false,
+                false
+            )
+        );
+
+        let guard = container_a.emit(&event, RegistrarGuard::default()).unwrap();
+        container_a.delay(event, guard);
+
+        let toml = container_a.to_plan(Some(chroot.as_path())).unwrap().to_toml().unwrap();
+        assert!(toml.contains("from = \"RDIR\""));
+
+        let mut container_b = Container::new();
+        container_b.emit_plan(&Plan::from_toml(toml.as_str()).unwrap(), chroot.as_path()).unwrap();
+
+        let b_stat = container_b.status(chroot.join("COPIED/RFILEA").as_path()).unwrap();
+        assert!(b_stat.exists());
+        assert!(b_stat.is_file());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn can_export_queue_as_rerunnable_shell_script() {
+        let chroot = Samples::init_simple_chroot("can_export_queue_as_rerunnable_shell_script");
+        let mut container = Container::new();
+
+        for event in [
+            FileSystemEvent::Copy(CopyEvent::new(chroot.join("RDIR").as_path(), chroot.join("COPIED").as_path(), false, false)),
+            FileSystemEvent::Move(MoveEvent::new(chroot.join("COPIED/RFILEA").as_path(), chroot.join("RDIR3/MOVED").as_path(), false, false)),
+            FileSystemEvent::Remove(RemoveEvent::new(chroot.join("RDIR2").as_path(), true))
+        ] {
+            let guard = container.emit(&event, RegistrarGuard::default()).unwrap();
+            container.delay(event, guard);
+        }
+
+        let script = container.to_shell_script().unwrap();
+        assert!(script.contains(format!("# cp {} {}", chroot.join("RDIR").to_string_lossy(), chroot.join("COPIED").to_string_lossy()).as_str()));
+        assert!(script.contains(format!("cp -- '{}' '{}'", chroot.join("RDIR/RFILEB").to_string_lossy(), chroot.join("COPIED/RFILEB").to_string_lossy()).as_str()));
+
+        //Nothing touched the disk until the script runs
+        assert!(!chroot.join("COPIED").exists());
+
+        let script_path = chroot.join("plan.sh");
+        write(script_path.as_path(), script).unwrap();
+        for _ in 0..2 {
+            assert!(process::Command::new("sh").arg(script_path.as_path()).status().unwrap().success());
+        }
+
+        assert!(chroot.join("COPIED/RFILEB").exists());
+        assert!(!chroot.join("COPIED/RFILEA").exists());
+        assert!(chroot.join("RDIR3/MOVED").exists());
+        assert!(chroot.join("RDIR/RFILEA").exists());
+        assert!(!chroot.join("RDIR2").exists());
+    }
+
+    #[test]
+    fn can_revert_an_applied_session() {
+        let chroot = Samples::init_simple_chroot("container_can_revert_an_applied_session");
+        let recovery = Recovery::new(Samples::init_empty_chroot("container_can_revert_an_applied_session_recovery").as_path());
+        let overwritten = std::fs::read(chroot.join("RDIR2/RFILEA")).unwrap();
+        let mut container = Container::new();
+
+        for event in [
+            FileSystemEvent::Copy(CopyEvent::new(chroot.join("RDIR").as_path(), chroot.join("RDIR2").as_path(), true, true)),
+            FileSystemEvent::Move(MoveEvent::new(chroot.join("RDIR3").as_path(), chroot.join("MOVED").as_path(), false, false)),
+            FileSystemEvent::Remove(RemoveEvent::new(chroot.join("RDIR").as_path(), true))
+        ] {
+            let guard = container.emit(&event, RegistrarGuard::default()).unwrap();
+            container.delay(event, guard);
+        }
+
+        let session = container.apply_recoverable(&recovery).unwrap();
+        assert!(!chroot.join("RDIR").exists());
+        assert!(chroot.join("RDIR2/RFILEB").exists());
+
+        let revert = container.revert(&recovery, session.as_str()).unwrap();
+        assert!(chroot.join("RDIR/RFILEA").exists());
+        assert!(chroot.join("RDIR/RFILEB").exists());
+        assert!(!chroot.join("RDIR2/RFILEB").exists());
+        assert_eq!(std::fs::read(chroot.join("RDIR2/RFILEA")).unwrap(), overwritten);
+        assert!(chroot.join("RDIR3").is_dir());
+        assert!(!chroot.join("MOVED").exists());
+        assert_eq!(
+            recovery.sessions().unwrap().iter().map(|session| session.name().to_string()).collect::<Vec<String>>(),
+            vec![revert]
+        );
+    }
+
+    #[test]
+    fn refuse_to_revert_over_changes() {
+        let chroot = Samples::init_simple_chroot("container_refuse_to_revert_over_changes");
+        let recovery = Recovery::new(Samples::init_empty_chroot("container_refuse_to_revert_over_changes_recovery").as_path());
+        let mut container = Container::new();
+
+        let event = FileSystemEvent::Move(MoveEvent::new(chroot.join("RDIR3").as_path(), chroot.join("MOVED").as_path(), false, false));
+        let guard = container.emit(&event, RegistrarGuard::default()).unwrap();
+        container.delay(event, guard);
+        let session = container.apply_recoverable(&recovery).unwrap();
+
+        std::fs::create_dir(chroot.join("RDIR3")).unwrap();
+        match container.revert(&recovery, session.as_str()) {
+            Err(DomainError::RevertConflict(path)) => assert_eq!(path, chroot.join("RDIR3")),
+            _ => panic!("a path recreated since the session must not be overwritten")
+        }
+        assert!(chroot.join("MOVED").is_dir());
+        assert_eq!(recovery.sessions().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn can_edit_the_queue() {
+        let chroot = Samples::init_simple_chroot("container_can_edit_the_queue");
+        let mut container = Container::new();
+
+        for event in [
+            FileSystemEvent::Copy(CopyEvent::new(chroot.join("RDIR").as_path(), chroot.join("COPIED").as_path(), false, false)),
+            FileSystemEvent::Remove(RemoveEvent::new(chroot.join("RDIR2").as_path(), true)),
+            FileSystemEvent::Move(MoveEvent::new(chroot.join("COPIED/RFILEA").as_path(), chroot.join("MOVED").as_path(), false, false))
+        ] {
+            let guard = container.emit(&event, RegistrarGuard::default()).unwrap();
+            container.delay(event, guard);
+        }
+
+        container.move_event(1, 0).unwrap();
+        assert!(matches!(container.events().next(), Some(FileSystemEvent::Remove(_))));
+
+        match container.remove_event(1) {
+            Err(DomainError::ReplayFailed(index, _, _)) => assert_eq!(index, 1),
+            _ => panic!("the move relies on the removed copy")
+        }
+        assert_eq!(container.events().count(), 3);
+        assert!(container.status(chroot.join("MOVED").as_path()).unwrap().exists());
+
+        assert!(matches!(container.remove_event(0).unwrap(), FileSystemEvent::Remove(_)));
+        assert!(container.status(chroot.join("RDIR2").as_path()).unwrap().exists());
+        assert!(container.status(chroot.join("MOVED").as_path()).unwrap().exists());
+        assert!(!container.status(chroot.join("COPIED/RFILEA").as_path()).unwrap().exists());
+
+        assert!(matches!(container.move_event(0, 2), Err(DomainError::EventOutOfRange(2))));
+    }
+
+    #[test]
+    fn apply_is_locked_out_by_other_sessions() {
+        let chroot = Samples::init_simple_chroot("container_apply_is_locked_out_by_other_sessions");
+        let mut container = Container::new();
+        let event = FileSystemEvent::Copy(CopyEvent::new(chroot.join("RDIR/RFILEA").as_path(), chroot.join("RDIR2/COPIED").as_path(), false, false));
+
+        container.lock_session().unwrap();
+        let guard = container.emit(&event, RegistrarGuard::default()).unwrap();
+        container.delay(event.clone(), guard);
+        assert!(chroot.join("RDIR2").join(LOCK_FILE).exists());
+        assert!(container.read_dir(chroot.join("RDIR2").as_path()).unwrap().iter().all(|child| child.name().unwrap() != LOCK_FILE));
+
+        container.apply().unwrap();
+        assert!(chroot.join("RDIR2/COPIED").exists());
+        assert!(chroot.join("RDIR2").join(LOCK_FILE).exists());
+        container.unlock_session();
+        assert!(!chroot.join("RDIR2").join(LOCK_FILE).exists());
+
+        write(chroot.join(LOCK_FILE), "{\"pid\":1,\"host\":\"elsewhere\",\"started\":\"2019-10-07T10:20:30\"}").unwrap();
+        let event = FileSystemEvent::Remove(RemoveEvent::new(chroot.join("RDIR2/COPIED").as_path(), false));
+        let guard = container.emit(&event, RegistrarGuard::default()).unwrap();
+        container.delay(event, guard);
+        match container.apply() {
+            Err(DomainError::Locked(directory, owner)) => {
+                assert_eq!(directory, chroot);
+                assert_eq!(owner.host(), "elsewhere");
+            },
+            _ => panic!("a tree locked by another session must not be applied")
+        }
+        assert!(chroot.join("RDIR2/COPIED").exists());
+    }
+
+    #[test]
+    fn emit_reports_names_that_are_not_portable() {
+        let chroot = Samples::init_simple_chroot("container_emit_reports_names_that_are_not_portable");
+        let mut container = Container::new();
+        container.validate_names(Some(NameValidator::new(Portability::Windows)));
+
+        let event = FileSystemEvent::Copy(CopyEvent::new(chroot.join("RDIR").as_path(), chroot.join("NUL.d").as_path(), false, false));
+        let guard = container.emit(&event, RegistrarGuard::default()).unwrap();
+        container.delay(event, guard);
+        let warnings = container.take_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].issue(), &NameIssue::Reserved);
+        assert!(container.take_warnings().is_empty());
+
+        container.validate_names(Some(NameValidator::new(Portability::Windows).with_strict(true)));
+        let event = FileSystemEvent::Copy(CopyEvent::new(chroot.join("RDIR/RFILEA").as_path(), chroot.join("rdir2").as_path(), false, false));
+        assert!(matches!(container.emit(&event, RegistrarGuard::default()), Err(DomainError::NotPortable(_, Portability::Windows, NameIssue::CaseCollision(_)))));
+        assert!(!container.status(chroot.join("rdir2").as_path()).unwrap().exists());
+    }
+
+    #[test]
+    fn case_insensitive_comparison_detects_collisions() {
+        let chroot = Samples::init_simple_chroot("container_case_insensitive_comparison_detects_collisions");
+        let mut container = Container::new();
+        container.set_path_comparison(PathComparison::exact().with_case_insensitive(true)).unwrap();
+
+        let event = FileSystemEvent::Copy(CopyEvent::new(chroot.join("RDIR2/RFILEC").as_path(), chroot.join("RDIR/rfilea").as_path(), false, false));
+        assert!(matches!(container.emit(&event, RegistrarGuard::default()), Err(DomainError::OverwriteNotAllowed(_))));
+
+        let event = FileSystemEvent::Copy(CopyEvent::new(chroot.join("RDIR2/RFILEC").as_path(), chroot.join("RDIR/rfilea").as_path(), false, true));
+        let guard = container.emit(&event, RegistrarGuard::default()).unwrap();
+        container.delay(event, guard);
+        assert_eq!(container.read_dir(chroot.join("RDIR").as_path()).unwrap().len(), 2);
+        assert_eq!(container.status(chroot.join("RDIR/RFILEA").as_path()).unwrap().source(), Some(chroot.join("RDIR2/RFILEC").as_path()));
+
+        let event = FileSystemEvent::Copy(CopyEvent::new(chroot.join("RDIR2/RFILEC").as_path(), chroot.join("RDIR/RFILEC").as_path(), false, false));
+        let guard = container.emit(&event, RegistrarGuard::default()).unwrap();
+        container.delay(event, guard);
+        let event = FileSystemEvent::Copy(CopyEvent::new(chroot.join("RDIR/RFILEB").as_path(), chroot.join("RDIR/rfilec").as_path(), false, false));
+        assert!(matches!(container.emit(&event, RegistrarGuard::default()), Err(DomainError::OverwriteNotAllowed(_))));
+    }
+
+    #[test]
+    fn replay_keeps_path_comparison() {
+        let chroot = Samples::init_simple_chroot("container_replay_keeps_path_comparison");
+        let mut container = Container::new();
+        container.set_path_comparison(PathComparison::exact().with_case_insensitive(true)).unwrap();
+
+        for event in [
+            FileSystemEvent::Copy(CopyEvent::new(chroot.join("RDIR/RFILEB").as_path(), chroot.join("NEW").as_path(), false, false)),
+            FileSystemEvent::Copy(CopyEvent::new(chroot.join("RDIR2/RFILEC").as_path(), chroot.join("RDIR/rfilea").as_path(), false, true))
+        ] {
+            let guard = container.emit(&event, RegistrarGuard::default()).unwrap();
+            container.delay(event, guard);
+        }
+
+        container.move_event(1, 0).unwrap();
+        assert_eq!(container.read_dir(chroot.join("RDIR").as_path()).unwrap().len(), 2);
+        assert_eq!(container.status(chroot.join("RDIR/RFILEA").as_path()).unwrap().source(), Some(chroot.join("RDIR2/RFILEC").as_path()));
+    }
 }
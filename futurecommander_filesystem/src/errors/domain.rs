@@ -32,12 +32,19 @@ use crate::{
     infrastructure::{
         errors::InfrastructureError
     },
+    lock::LockOwner,
+    capability::Capability,
+    event::Danger,
+    naming::{ Portability, NameIssue }
 };
 
 #[derive(Debug)]
 pub enum DomainError {
     IoError(io::Error),
     JsonError(serde_json::Error),
+    TomlDeserialize(toml::de::Error),
+    TomlSerialize(toml::ser::Error),
+    YamlError(serde_yaml::Error),
     Infrastructure(InfrastructureError),
     Query(QueryError),
     CopyIntoItSelf(PathBuf, PathBuf),
@@ -49,7 +56,36 @@ pub enum DomainError {
     CreateUnknown(PathBuf),
     DoesNotExists(PathBuf),
     RecursiveNotAllowed(PathBuf),
+    DeleteNotAllowed(PathBuf),
+    RenameNotAllowed(PathBuf),
+    RenameCollision(PathBuf, PathBuf),
+    InvalidName(String),
+    TrashUnavailable,
+    PolicyUnavailable,
+    PolicyDenied(Capability, PathBuf),
+    BulkNotAllowed(PathBuf),
+    CrossDeviceNotAllowed(PathBuf),
+    FollowSymlinksNotAllowed(PathBuf),
+    DangerousTarget(PathBuf, Danger),
+    NotPortable(PathBuf, Portability, NameIssue),
+    InvalidPortability(String),
+    InvalidEncodedPath(String),
+    InvalidThresholds(String),
+    RecoveryUnavailable,
+    UnknownSession(String),
+    RevertConflict(PathBuf),
+    PendingChanges,
+    Locked(PathBuf, LockOwner),
+    EventOutOfRange(usize),
+    ReplayFailed(usize, String, Box<DomainError>),
     SourceDoesNotExists(PathBuf),
+    OutsideOfRoot(PathBuf, PathBuf),
+    InvalidPattern(String, String),
+    InvalidTemplate(String),
+    InvalidOverwritePolicy(String),
+    InvalidBackup(String),
+    InvalidMode(String),
+    LinkToDirectory(PathBuf),
     UserCancelled,
     Custom(String)
 }
@@ -60,6 +96,24 @@ impl From<serde_json::Error> for DomainError {
     }
 }
 
+impl From<toml::de::Error> for DomainError {
+    fn from(error: toml::de::Error) -> Self {
+        DomainError::TomlDeserialize(error)
+    }
+}
+
+impl From<toml::ser::Error> for DomainError {
+    fn from(error: toml::ser::Error) -> Self {
+        DomainError::TomlSerialize(error)
+    }
+}
+
+impl From<serde_yaml::Error> for DomainError {
+    fn from(error: serde_yaml::Error) -> Self {
+        DomainError::YamlError(error)
+    }
+}
+
 impl From<io::Error> for DomainError {
     fn from(error: io::Error) -> Self {
         DomainError::IoError(error)
@@ -84,6 +138,9 @@ impl fmt::Display for DomainError {
         match self {
             DomainError::IoError(error) => write!(f, "I/O error {}", error),
             DomainError::JsonError(error) => write!(f, "Json error {}", error),
+            DomainError::TomlDeserialize(error) => write!(f, "Toml error {}", error),
+            DomainError::TomlSerialize(error) => write!(f, "Toml error {}", error),
+            DomainError::YamlError(error) => write!(f, "Yaml error {}", error),
             DomainError::Infrastructure(error) => write!(f, "Infrastructure error {}", error),
             DomainError::Query(error) => write!(f, "Query error {}", error),
             DomainError::CopyIntoItSelf(source, dst) => write!(f, "Cannot copy {} into itself {}", source.to_string_lossy(), dst.to_string_lossy()),
@@ -95,7 +152,36 @@ impl fmt::Display for DomainError {
             DomainError::CreateUnknown(path) => write!(f, "Cannot create unknown kind at path {}", path.to_string_lossy()),
             DomainError::DoesNotExists(path) => write!(f, "Path {} does not exists", path.to_string_lossy()),
             DomainError::RecursiveNotAllowed(path) => write!(f, "Delete recursively {} is not allowed", path.to_string_lossy()),
+            DomainError::DeleteNotAllowed(path) => write!(f, "Permanent removal of {} is not allowed", path.to_string_lossy()),
+            DomainError::RenameNotAllowed(path) => write!(f, "Keeping both entries at {} is not allowed", path.to_string_lossy()),
+            DomainError::RenameCollision(path, destination) => write!(f, "Cannot rename {} to {}, the name is already taken", path.to_string_lossy(), destination.to_string_lossy()),
+            DomainError::InvalidName(name) => write!(f, "Invalid file name {:?}", name),
+            DomainError::TrashUnavailable => write!(f, "Cannot locate the trash, neither XDG_DATA_HOME nor HOME are absolute paths"),
+            DomainError::PolicyUnavailable => write!(f, "Cannot locate the policy file, set FUTURECOMMANDER_POLICY or HOME"),
+            DomainError::PolicyDenied(capability, path) => write!(f, "Policy denies {} of {}", capability, path.to_string_lossy()),
+            DomainError::BulkNotAllowed(path) => write!(f, "Operation on {} goes beyond thresholds, it needs an explicit confirmation", path.to_string_lossy()),
+            DomainError::CrossDeviceNotAllowed(path) => write!(f, "Move to {} crosses devices, copying then removing the source is not allowed", path.to_string_lossy()),
+            DomainError::FollowSymlinksNotAllowed(path) => write!(f, "{} is a symbolic link, recursing into it is not allowed", path.to_string_lossy()),
+            DomainError::NotPortable(path, portability, issue) => write!(f, "{} is not {} portable : {}", path.to_string_lossy(), portability, issue),
+            DomainError::InvalidPortability(profile) => write!(f, "Unknown portability profile {}, expected native, posix, windows or macos", profile),
+            DomainError::InvalidEncodedPath(text) => write!(f, "Invalid encoded path {}", text),
+            DomainError::DangerousTarget(path, danger) => write!(f, "Refusing to remove or move {}, it is {}", path.to_string_lossy(), danger),
+            DomainError::InvalidThresholds(thresholds) => write!(f, "Invalid thresholds {}, expected entries=N,bytes=SIZE,deletions=N", thresholds),
+            DomainError::RecoveryUnavailable => write!(f, "Cannot locate the recovery directory, neither XDG_STATE_HOME nor HOME are absolute paths"),
+            DomainError::UnknownSession(session) => write!(f, "No applied session {} can be reverted", session),
+            DomainError::RevertConflict(path) => write!(f, "Cannot revert, {} has changed since the session was applied", path.to_string_lossy()),
+            DomainError::PendingChanges => write!(f, "Virtual changes are pending, apply or reset them first"),
+            DomainError::Locked(path, owner) => write!(f, "{} is locked by {}", path.to_string_lossy(), owner),
+            DomainError::EventOutOfRange(index) => write!(f, "No queued event at index {}", index),
+            DomainError::ReplayFailed(index, event, error) => write!(f, "Queued event {} `{}` no longer applies : {}", index, event, error),
             DomainError::SourceDoesNotExists(source) => write!(f, "Source {} does not exists", source.to_string_lossy()),
+            DomainError::OutsideOfRoot(path, root) => write!(f, "Path {} is outside of root {}", path.to_string_lossy(), root.to_string_lossy()),
+            DomainError::InvalidPattern(pattern, reason) => write!(f, "Invalid pattern {} : {}", pattern, reason),
+            DomainError::InvalidOverwritePolicy(policy) => write!(f, "Invalid overwrite policy {}, expected never, always, if-newer, if-larger or if-different", policy),
+            DomainError::InvalidBackup(backup) => write!(f, "Invalid backup control {}, expected none, simple or numbered", backup),
+            DomainError::InvalidMode(mode) => write!(f, "Invalid mode {}, expected an octal mode or clauses like u+rwX,go-w", mode),
+            DomainError::LinkToDirectory(path) => write!(f, "Directory {} cannot be hard linked", path.to_string_lossy()),
+            DomainError::InvalidTemplate(template) => write!(f, "Invalid rename template {}, it must contain {{n}}", template),
             DomainError::UserCancelled => write!(f, "User cancelled operation"),
             DomainError::Custom(s) => write!(f, "Custom error {}", s),
         }
@@ -108,8 +194,12 @@ impl error::Error for DomainError {
         match self {
             DomainError::IoError(err) => Some(err),
             DomainError::JsonError(err) => Some(err),
+            DomainError::TomlDeserialize(err) => Some(err),
+            DomainError::TomlSerialize(err) => Some(err),
+            DomainError::YamlError(err) => Some(err),
             DomainError::Query(err) => Some(err),
             DomainError::Infrastructure(err) => Some(err),
+            DomainError::ReplayFailed(_, _, err) => Some(err.as_ref()),
             _ => None
         }
     }
@@ -0,0 +1,238 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    fmt::{ Display, Formatter, Result as FmtResult },
+    path::{ Path, PathBuf, Component },
+    str::FromStr
+};
+
+use serde::{ Serialize, Deserialize };
+
+use crate::{
+    errors::DomainError,
+    lossless,
+    event::create::ensure_directory,
+    port::{
+        Entry,
+        ReadableFileSystem,
+        Atomic,
+        AtomicTransaction
+    }
+};
+
+/*
+What happens to a file before it gets overwritten, like cp --backup.
+Simple appends ~ to its name, numbered appends .~N~ after the highest existing backup
+and a directory keeps it under its absolute path, numbered when already taken.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Backup {
+    #[default]
+    None,
+    Simple,
+    Numbered,
+    Directory(#[serde(with = "crate::lossless")] PathBuf)
+}
+
+impl Backup {
+    pub fn is_none(&self) -> bool {
+        *self == Backup::None
+    }
+
+    //Name of target followed by suffix, bytes which are not UTF-8 are kept as they are
+    fn suffixed(target: &Path, suffix: &str) -> Result<PathBuf, DomainError> {
+        let mut name = target.file_name().map(lossless::bytes).unwrap_or_default();
+        name.extend_from_slice(suffix.as_bytes());
+        Ok(target.with_file_name(lossless::from_bytes(name)?))
+    }
+
+    fn numbered<E: Entry, F: ReadableFileSystem<Item=E>>(fs: &F, target: &Path, transaction: &AtomicTransaction) -> Result<PathBuf, DomainError> {
+        let mut prefix = target.file_name().map(lossless::bytes).unwrap_or_default();
+        prefix.extend_from_slice(b".~");
+        let parent = target.parent().unwrap_or_else(|| Path::new("/"));
+
+        let mut highest : u64 = 0;
+        if fs.status(parent)?.exists() {
+            for sibling in fs.read_dir(parent)?.iter() {
+                let number = sibling.name()
+                    .map(lossless::bytes)
+                    .and_then(|sibling| sibling.strip_prefix(prefix.as_slice()).and_then(|n| n.strip_suffix(b"~")).map(<[u8]>::to_vec))
+                    .and_then(|n| String::from_utf8(n).ok())
+                    .and_then(|n| n.parse::<u64>().ok());
+                if let Some(number) = number {
+                    highest = highest.max(number);
+                }
+            }
+        }
+
+        let mut n = highest + 1;
+        loop {
+            let candidate = Self::suffixed(target, format!(".~{}~", n).as_str())?;
+            if !transaction.creates(candidate.as_path()) {
+                return Ok(candidate);
+            }
+            n += 1;
+        }
+    }
+
+    //Takes the place of the removal of an authorized overwrite
+    pub fn atomize<E: Entry, F: ReadableFileSystem<Item=E>>(&self, fs: &F, destination: &Path, transaction: &mut AtomicTransaction) -> Result<(), DomainError> {
+        let backup = match self {
+            Backup::None => {
+                transaction.add(Atomic::RemoveFile(destination.to_path_buf()));
+                return Ok(());
+            },
+            Backup::Simple => {
+                let backup = Self::suffixed(destination, "~")?;
+                let existing = fs.status(backup.as_path())?;
+                if existing.is_dir() {
+                    return Err(DomainError::OverwriteDirectoryWithFile(destination.to_path_buf(), backup));
+                } else if existing.exists() {
+                    transaction.add(Atomic::RemoveFile(backup.clone()));
+                }
+                backup
+            },
+            Backup::Numbered => Self::numbered(fs, destination, transaction)?,
+            Backup::Directory(directory) => {
+                let backup = directory.join(
+                    destination.components()
+                        .filter(|component| matches!(component, Component::Normal(_)))
+                        .collect::<PathBuf>()
+                );
+                ensure_directory(fs, backup.parent().unwrap_or(directory.as_path()), transaction)?;
+                if fs.status(backup.as_path())?.exists() || transaction.creates(backup.as_path()) {
+                    Self::numbered(fs, backup.as_path(), transaction)?
+                } else {
+                    backup
+                }
+            }
+        };
+
+        transaction.add(Atomic::MoveFileToFile {
+            source: destination.to_path_buf(),
+            destination: backup
+        });
+        Ok(())
+    }
+}
+
+impl Display for Backup {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Backup::None => write!(f, "none"),
+            Backup::Simple => write!(f, "simple"),
+            Backup::Numbered => write!(f, "numbered"),
+            Backup::Directory(directory) => write!(f, "{}", directory.to_string_lossy())
+        }
+    }
+}
+
+//Same control values as cp --backup, directories are given apart
+impl FromStr for Backup {
+    type Err = DomainError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "none" | "off" => Ok(Backup::None),
+            "simple" | "never" => Ok(Backup::Simple),
+            "numbered" | "t" => Ok(Backup::Numbered),
+            _ => Err(DomainError::InvalidBackup(s.to_string()))
+        }
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::write;
+
+    use crate::{
+        sample::Samples,
+        port::FileSystemAdapter,
+        infrastructure::RealFileSystem
+    };
+
+    #[test]
+    fn backup_locations() {
+        let chroot = Samples::init_simple_chroot("backup_locations");
+        let fs = FileSystemAdapter(RealFileSystem::default());
+        let target = chroot.join("RDIR/RFILEA");
+        write(chroot.join("RDIR/RFILEA.~2~"), "").unwrap();
+        write(chroot.join("RDIR/RFILEA~"), "").unwrap();
+
+        let backup = |backup: Backup| {
+            let mut transaction = AtomicTransaction::default();
+            backup.atomize(&fs, target.as_path(), &mut transaction).unwrap();
+            transaction.iter()
+                .filter_map(|atomic| match atomic {
+                    Atomic::MoveFileToFile { destination, .. } => Some(destination.clone()),
+                    _ => None
+                })
+                .next()
+        };
+
+        assert_eq!(backup(Backup::None), None);
+        assert_eq!(backup(Backup::Simple), Some(chroot.join("RDIR/RFILEA~")));
+        assert_eq!(backup(Backup::Numbered), Some(chroot.join("RDIR/RFILEA.~3~")));
+        assert_eq!(
+            backup(Backup::Directory(chroot.join("BACKUP"))),
+            Some(chroot.join("BACKUP").join(target.strip_prefix("/").unwrap()))
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn backup_names_keep_bytes_which_are_not_utf8() {
+        use std::{ ffi::OsStr, os::unix::ffi::OsStrExt };
+
+        let chroot = Samples::init_simple_chroot("backup_names_keep_bytes_which_are_not_utf8");
+        let fs = FileSystemAdapter(RealFileSystem::default());
+        let target = chroot.join(OsStr::from_bytes(b"caf\xe9"));
+        write(target.as_path(), "").unwrap();
+        //Another name, the same once made lossy
+        write(chroot.join(OsStr::from_bytes(b"caf\xe8~")), "").unwrap();
+        write(chroot.join(OsStr::from_bytes(b"caf\xe8.~4~")), "").unwrap();
+
+        let mut transaction = AtomicTransaction::default();
+        Backup::Simple.atomize(&fs, target.as_path(), &mut transaction).unwrap();
+        assert_eq!(
+            transaction.0,
+            vec![Atomic::MoveFileToFile { source: target.clone(), destination: chroot.join(OsStr::from_bytes(b"caf\xe9~")) }]
+        );
+
+        let mut transaction = AtomicTransaction::default();
+        Backup::Numbered.atomize(&fs, target.as_path(), &mut transaction).unwrap();
+        assert_eq!(
+            transaction.0,
+            vec![Atomic::MoveFileToFile { source: target.clone(), destination: chroot.join(OsStr::from_bytes(b"caf\xe9.~1~")) }]
+        );
+    }
+
+    #[test]
+    fn backup_control_values() {
+        assert_eq!("t".parse::<Backup>().unwrap(), Backup::Numbered);
+        assert_eq!("simple".parse::<Backup>().unwrap(), Backup::Simple);
+        assert!("sometimes".parse::<Backup>().is_err());
+        assert_eq!(serde_json::to_string(&Backup::Directory(PathBuf::from("/bak"))).unwrap(), "{\"directory\":\"/bak\"}");
+    }
+}
@@ -0,0 +1,192 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use std::{
+    any::Any,
+    fs::read_to_string,
+    path::{ Path, PathBuf }
+};
+
+use serde::{ Serialize, Deserialize };
+
+use crate::{
+    DomainError,
+    capability::{
+        Capability,
+        Authorization,
+        Guard,
+        Impact
+    }
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Decision {
+    #[serde(with = "crate::lossless")]
+    path: PathBuf,
+    capability: Capability,
+    answer: Authorization
+}
+
+impl Decision {
+    pub fn new(path: &Path, capability: Capability, answer: Authorization) -> Decision {
+        Decision {
+            path: path.to_path_buf(),
+            capability,
+            answer
+        }
+    }
+
+    pub fn path(&self) -> &Path { self.path.as_path() }
+    pub fn capability(&self) -> Capability { self.capability }
+    pub fn answer(&self) -> Authorization { self.answer }
+}
+
+/*
+Decisions recorded by guards, written as toml to be replayed later.
+Like in the registrar, keeping both entries answers every capability of its path.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct AnswerFile {
+    #[serde(default, rename = "decision", skip_serializing_if = "Vec::is_empty")]
+    decisions: Vec<Decision>
+}
+
+impl AnswerFile {
+    pub fn new(decisions: Vec<Decision>) -> AnswerFile {
+        let mut answers = AnswerFile::default();
+        for decision in decisions {
+            if !answers.decisions.contains(&decision) {
+                answers.decisions.push(decision);
+            }
+        }
+        answers
+    }
+
+    pub fn from_file(path: &Path) -> Result<AnswerFile, DomainError> {
+        Ok(toml::from_str(read_to_string(path)?.as_str())?)
+    }
+
+    pub fn to_toml(&self) -> Result<String, DomainError> {
+        Ok(toml::to_string(self)?)
+    }
+
+    pub fn decisions(&self) -> &[Decision] { &self.decisions }
+    pub fn is_empty(&self) -> bool { self.decisions.is_empty() }
+
+    pub fn answer(&self, capability: Capability, target: &Path) -> Option<Authorization> {
+        let decisions = || self.decisions.iter().filter(|decision| decision.path == target);
+        if decisions().any(|decision| decision.answer == Authorization::Rename) {
+            return Some(Authorization::Rename);
+        }
+        decisions()
+            .find(|decision| decision.capability == capability)
+            .map(Decision::answer)
+    }
+}
+
+//Replays recorded answers, the wrapped guard decides what was never asked
+#[derive(Serialize, Deserialize, Debug)]
+pub struct AnswerFileGuard {
+    answers: AnswerFile,
+    inner: Box<dyn Guard>
+}
+
+impl AnswerFileGuard {
+    pub fn new(answers: AnswerFile, inner: Box<dyn Guard>) -> AnswerFileGuard {
+        AnswerFileGuard {
+            answers,
+            inner
+        }
+    }
+}
+
+#[typetag::serde]
+impl Guard for AnswerFileGuard {
+    fn authorize(&mut self, capability: Capability, default: bool, target: &Path) -> Result<Authorization, DomainError> {
+        match self.answers.answer(capability, target) {
+            Some(answer) => Ok(answer),
+            None => self.inner.authorize(capability, default, target)
+        }
+    }
+
+    fn authorize_entry(&mut self, capability: Capability, default: bool, target: &Path, is_dir: bool) -> Result<Authorization, DomainError> {
+        match self.answers.answer(capability, target) {
+            Some(answer) => Ok(answer),
+            None => self.inner.authorize_entry(capability, default, target, is_dir)
+        }
+    }
+
+    fn measures_impact(&self) -> bool {
+        self.inner.measures_impact()
+    }
+
+    fn authorize_impact(&mut self, impact: &Impact, target: &Path) -> Result<Authorization, DomainError> {
+        match self.answers.answer(Capability::Bulk, target) {
+            Some(answer) => Ok(answer),
+            None => self.inner.authorize_impact(impact, target)
+        }
+    }
+
+    fn attach(&mut self, attachment: &dyn Any) {
+        self.inner.attach(attachment);
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::capability::{
+        RegistrarGuard,
+        BlindGuard,
+        QuietGuard,
+        ZealedGuard
+    };
+
+    #[test]
+    fn replay_recorded_decisions(){
+        let mut registrar = RegistrarGuard::from(Box::new(BlindGuard));
+        registrar.authorize(Capability::Merge, false, Path::new("/a")).unwrap();
+        registrar.authorize(Capability::Overwrite, false, Path::new("/a/b")).unwrap();
+        let mut quiet = RegistrarGuard::from(Box::new(QuietGuard));
+        quiet.authorize(Capability::Overwrite, false, Path::new("/a/c")).unwrap();
+
+        let mut decisions = registrar.decisions();
+        decisions.extend(quiet.decisions());
+        let written = AnswerFile::new(decisions).to_toml().unwrap();
+        let answers : AnswerFile = toml::from_str(written.as_str()).unwrap();
+        assert_eq!(answers.decisions().len(), 3);
+
+        let mut guard = AnswerFileGuard::new(answers, Box::new(ZealedGuard));
+        assert!(guard.authorize(Capability::Merge, false, Path::new("/a")).unwrap().is_allowed());
+        assert!(guard.authorize(Capability::Overwrite, false, Path::new("/a/b")).unwrap().is_allowed());
+        assert_eq!(guard.authorize(Capability::Overwrite, false, Path::new("/a/c")).unwrap(), Authorization::Skip);
+        assert!(matches!(
+            guard.authorize(Capability::Overwrite, false, Path::new("/a/d")),
+            Err(DomainError::OverwriteNotAllowed(_))
+        ));
+    }
+
+    #[test]
+    fn renaming_answers_every_capability(){
+        let answers = AnswerFile::new(vec![Decision::new(Path::new("/a"), Capability::Rename, Authorization::Rename)]);
+        assert_eq!(answers.answer(Capability::Merge, Path::new("/a")), Some(Authorization::Rename));
+        assert_eq!(answers.answer(Capability::Merge, Path::new("/b")), None);
+    }
+}
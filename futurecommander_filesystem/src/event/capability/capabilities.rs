@@ -18,43 +18,82 @@
  */
 
 use std::{
+    collections::BTreeMap,
     ops::{ Add }
 };
 
-use serde::{ Serialize, Deserialize };
+use serde::{
+    Serialize, Serializer,
+    Deserialize, Deserializer,
+    ser::SerializeMap
+};
 
 use crate::{
-    event::capability::{
-        Capability
+    event::{
+        capability::{
+            Capability
+        }
     }
 };
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
-pub struct Capabilities {
-    merge: bool,
-    overwrite: bool,
-    recursive: bool
-}
+/*
+Set of named capabilities, every capability has its own bit.
+Serialized as a map of names, merge, overwrite and recursive always written.
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities(u16);
 
 impl Capabilities {
+    fn with_bits(bits: u16, added: u16) -> Capabilities {
+        Capabilities(bits | added)
+    }
+
     pub fn merge(self) -> bool {
-        self.merge
+        self.authorize(Capability::Merge)
     }
 
     pub fn overwrite(self) -> bool {
-        self.overwrite
+        self.authorize(Capability::Overwrite)
     }
 
     pub fn recursive(self) -> bool {
-        self.recursive
+        self.authorize(Capability::Recursive)
+    }
+
+    pub fn delete(self) -> bool {
+        self.authorize(Capability::Delete)
+    }
+
+    pub fn rename(self) -> bool {
+        self.authorize(Capability::Rename)
+    }
+
+    pub fn bulk(self) -> bool {
+        self.authorize(Capability::Bulk)
+    }
+
+    pub fn cross_device(self) -> bool {
+        self.authorize(Capability::CrossDevice)
+    }
+
+    pub fn follow_symlinks(self) -> bool {
+        self.authorize(Capability::FollowSymlinks)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
     }
 
     pub fn authorize(self, capability: Capability) -> bool {
-        match capability {
-            Capability::Merge => self.merge(),
-            Capability::Recursive => self.recursive(),
-            Capability::Overwrite => self.overwrite()
-        }
+        self.0 & capability.bit() != 0
+    }
+
+    pub fn granted(self) -> Vec<Capability> {
+        Capability::ALL
+            .iter()
+            .copied()
+            .filter(|capability| self.authorize(*capability))
+            .collect()
     }
 }
 
@@ -62,11 +101,34 @@ impl Add<Capability> for Capabilities {
     type Output = Capabilities;
 
     fn add(self, right_cap: Capability) -> Capabilities {
-        Capabilities {
-            merge: self.merge() || right_cap == Capability::Merge,
-            overwrite: self.overwrite() || right_cap == Capability::Overwrite,
-            recursive: self.recursive() || right_cap == Capability::Recursive,
+        Capabilities::with_bits(self.0, right_cap.bit())
+    }
+}
+
+impl Serialize for Capabilities {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let written : Vec<Capability> = Capability::ALL
+            .iter()
+            .copied()
+            .filter(|capability| capability.is_core() || self.authorize(*capability))
+            .collect();
+
+        let mut map = serializer.serialize_map(Some(written.len()))?;
+        for capability in written {
+            map.serialize_entry(&capability, &self.authorize(capability))?;
         }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Capabilities {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Capabilities, D::Error> {
+        Ok(
+            BTreeMap::<Capability, bool>::deserialize(deserializer)?
+                .into_iter()
+                .filter(|(_, granted)| *granted)
+                .fold(Capabilities::default(), |capabilities, (capability, _)| capabilities + capability)
+        )
     }
 }
 
@@ -93,5 +155,31 @@ mod tests {
         assert!(caps.authorize(Capability::Overwrite));
         assert!(caps.authorize(Capability::Merge));
         assert!(caps.authorize(Capability::Recursive));
+        assert!(!caps.authorize(Capability::Delete));
+
+        caps = caps + Capability::Delete;
+        assert!(caps.authorize(Capability::Delete));
+        assert!(!caps.authorize(Capability::Rename));
+
+        caps = caps + Capability::Rename;
+        assert!(caps.authorize(Capability::Rename));
+        assert!(!caps.is_empty());
+        assert!(Capabilities::default().is_empty());
+
+        caps = caps + Capability::CrossDevice;
+        assert!(caps.cross_device());
+        assert!(!caps.follow_symlinks());
+        assert_eq!(caps.granted().len(), 6);
+    }
+
+    #[test]
+    fn serialize_named_capabilities() {
+        let caps = Capabilities::default() + Capability::Overwrite + Capability::FollowSymlinks;
+        assert_eq!(
+            serde_json::to_string(&caps).unwrap(),
+            r#"{"merge":false,"overwrite":true,"recursive":false,"follow-symlinks":true}"#
+        );
+        assert_eq!(serde_json::from_str::<Capabilities>(r#"{"merge":false,"overwrite":true,"recursive":false,"follow-symlinks":true}"#).unwrap(), caps);
+        assert!(serde_json::from_str::<Capabilities>(r#"{"teleport":true}"#).is_err());
     }
 }
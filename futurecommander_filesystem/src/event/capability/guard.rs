@@ -18,6 +18,7 @@
  */
 
 use std::{
+    any::Any,
     path::{ Path },
     fmt::{ Debug }
 };
@@ -28,13 +29,33 @@ use serde::{ Serialize, Deserialize };
 use crate::{
     DomainError,
     capability::{
-        Capability
+        Capability,
+        Authorization,
+        Impact
     }
 };
 
 #[typetag::serde(tag = "type")]
 pub trait Guard : Debug + Send {
-    fn authorize(&mut self, capability: Capability, default: bool, target: &Path) -> Result<bool, DomainError>;
+    fn authorize(&mut self, capability: Capability, default: bool, target: &Path) -> Result<Authorization, DomainError>;
+
+    //Same as authorize, knowing whether target is a directory in the view the event is atomized against, which may not be the real one
+    fn authorize_entry(&mut self, capability: Capability, default: bool, target: &Path, _is_dir: bool) -> Result<Authorization, DomainError> {
+        self.authorize(capability, default, target)
+    }
+
+    //Whether authorize_impact needs the impact of events, measuring it walks the directories they bind
+    fn measures_impact(&self) -> bool {
+        false
+    }
+
+    //Called once an event is atomized, with everything it would touch
+    fn authorize_impact(&mut self, _impact: &Impact, _target: &Path) -> Result<Authorization, DomainError> {
+        Ok(Authorization::Allow)
+    }
+
+    //Gives back what is not serialized, like the way to ask questions, to the guard and those it wraps
+    fn attach(&mut self, _attachment: &dyn Any) {}
 }
 
 #[derive(Serialize, Deserialize,Debug, Clone)]
@@ -42,8 +63,8 @@ pub struct BlindGuard;
 
 #[typetag::serde]
 impl Guard for BlindGuard {
-    fn authorize(&mut self, _capability: Capability, _default: bool, _target: &Path) -> Result<bool, DomainError> {
-        Ok(true)
+    fn authorize(&mut self, _capability: Capability, _default: bool, _target: &Path) -> Result<Authorization, DomainError> {
+        Ok(Authorization::Allow)
     }
 }
 
@@ -53,8 +74,8 @@ pub struct QuietGuard;
 
 #[typetag::serde]
 impl Guard for QuietGuard {
-    fn authorize(&mut self, _capability: Capability, default: bool, _target: &Path) -> Result<bool, DomainError> {
-        Ok(default)
+    fn authorize(&mut self, _capability: Capability, default: bool, _target: &Path) -> Result<Authorization, DomainError> {
+        Ok(default.into())
     }
 }
 
@@ -64,11 +85,11 @@ pub struct ZealedGuard;
 
 #[typetag::serde]
 impl Guard for ZealedGuard {
-    fn authorize(&mut self, capability: Capability, default: bool, target: &Path) -> Result<bool, DomainError> {
+    fn authorize(&mut self, capability: Capability, default: bool, target: &Path) -> Result<Authorization, DomainError> {
         match capability {
             Capability::Merge => {
                 if default {
-                    Ok(true)
+                    Ok(Authorization::Allow)
                 } else {
                     Err(
                         DomainError::MergeNotAllowed(
@@ -79,7 +100,7 @@ impl Guard for ZealedGuard {
             },
             Capability::Overwrite => {
                 if default {
-                    Ok(true)
+                    Ok(Authorization::Allow)
                 } else {
                     Err(
                         DomainError::OverwriteNotAllowed(
@@ -90,7 +111,7 @@ impl Guard for ZealedGuard {
             },
             Capability::Recursive => {
                 if default {
-                    Ok(true)
+                    Ok(Authorization::Allow)
                 } else {
                     Err(
                         DomainError::RecursiveNotAllowed(
@@ -98,6 +119,61 @@ impl Guard for ZealedGuard {
                         )
                     )
                 }
+            },
+            Capability::Delete => {
+                if default {
+                    Ok(Authorization::Allow)
+                } else {
+                    Err(
+                        DomainError::DeleteNotAllowed(
+                            target.to_path_buf()
+                        )
+                    )
+                }
+            },
+            Capability::Rename => {
+                if default {
+                    Ok(Authorization::Allow)
+                } else {
+                    Err(
+                        DomainError::RenameNotAllowed(
+                            target.to_path_buf()
+                        )
+                    )
+                }
+            },
+            Capability::Bulk => {
+                if default {
+                    Ok(Authorization::Allow)
+                } else {
+                    Err(
+                        DomainError::BulkNotAllowed(
+                            target.to_path_buf()
+                        )
+                    )
+                }
+            },
+            Capability::CrossDevice => {
+                if default {
+                    Ok(Authorization::Allow)
+                } else {
+                    Err(
+                        DomainError::CrossDeviceNotAllowed(
+                            target.to_path_buf()
+                        )
+                    )
+                }
+            },
+            Capability::FollowSymlinks => {
+                if default {
+                    Ok(Authorization::Allow)
+                } else {
+                    Err(
+                        DomainError::FollowSymlinksNotAllowed(
+                            target.to_path_buf()
+                        )
+                    )
+                }
             }
         }
     }
@@ -122,19 +198,19 @@ mod tests {
         let default = true;
 
         let mut zealed = ZealedGuard;
-        assert!(zealed.authorize(Capability::Overwrite, default, target).unwrap());
-        assert!(zealed.authorize(Capability::Merge, default, target).unwrap());
-        assert!(zealed.authorize(Capability::Recursive, default, target).unwrap());
+        assert!(zealed.authorize(Capability::Overwrite, default, target).unwrap().is_allowed());
+        assert!(zealed.authorize(Capability::Merge, default, target).unwrap().is_allowed());
+        assert!(zealed.authorize(Capability::Recursive, default, target).unwrap().is_allowed());
 
         let mut blind = BlindGuard;
-        assert!(blind.authorize(Capability::Overwrite, default, target).unwrap());
-        assert!(blind.authorize(Capability::Merge, default, target).unwrap());
-        assert!(blind.authorize(Capability::Recursive, default, target).unwrap());
+        assert!(blind.authorize(Capability::Overwrite, default, target).unwrap().is_allowed());
+        assert!(blind.authorize(Capability::Merge, default, target).unwrap().is_allowed());
+        assert!(blind.authorize(Capability::Recursive, default, target).unwrap().is_allowed());
 
         let mut quiet = QuietGuard;
-        assert!(quiet.authorize(Capability::Overwrite, default, target).unwrap());
-        assert!(quiet.authorize(Capability::Merge, default, target).unwrap());
-        assert!(quiet.authorize(Capability::Recursive, default, target).unwrap());
+        assert!(quiet.authorize(Capability::Overwrite, default, target).unwrap().is_allowed());
+        assert!(quiet.authorize(Capability::Merge, default, target).unwrap().is_allowed());
+        assert!(quiet.authorize(Capability::Recursive, default, target).unwrap().is_allowed());
     }
 
     #[test]
@@ -155,6 +231,14 @@ mod tests {
             &guard.authorize(Capability::Recursive, default, target).err().unwrap(),
             &DomainError::RecursiveNotAllowed(target.to_path_buf())
         );
+        assert_two_errors_equals(
+            &guard.authorize(Capability::Delete, default, target).err().unwrap(),
+            &DomainError::DeleteNotAllowed(target.to_path_buf())
+        );
+        assert_two_errors_equals(
+            &guard.authorize(Capability::Rename, default, target).err().unwrap(),
+            &DomainError::RenameNotAllowed(target.to_path_buf())
+        );
     }
 
     #[test]
@@ -163,9 +247,9 @@ mod tests {
         let target = Path::new("/virtual/directory");
         let default = false;
 
-        assert_eq!(guard.authorize(Capability::Overwrite, default, target).unwrap(), false);
-        assert_eq!(guard.authorize(Capability::Merge, default, target).unwrap(), false);
-        assert_eq!(guard.authorize(Capability::Recursive, default, target).unwrap(), false);
+        assert_eq!(guard.authorize(Capability::Overwrite, default, target).unwrap(), Authorization::Skip);
+        assert_eq!(guard.authorize(Capability::Merge, default, target).unwrap(), Authorization::Skip);
+        assert_eq!(guard.authorize(Capability::Recursive, default, target).unwrap(), Authorization::Skip);
     }
 
     #[test]
@@ -174,8 +258,8 @@ mod tests {
         let target = Path::new("/virtual/directory");
         let default = false;
 
-        assert_eq!(guard.authorize(Capability::Overwrite, default, target).unwrap(), true);
-        assert_eq!(guard.authorize(Capability::Merge, default, target).unwrap(), true);
-        assert_eq!(guard.authorize(Capability::Recursive, default, target).unwrap(), true);
+        assert_eq!(guard.authorize(Capability::Overwrite, default, target).unwrap(), Authorization::Allow);
+        assert_eq!(guard.authorize(Capability::Merge, default, target).unwrap(), Authorization::Allow);
+        assert_eq!(guard.authorize(Capability::Recursive, default, target).unwrap(), Authorization::Allow);
     }
 }
@@ -20,22 +20,71 @@
 mod capabilities;
 mod guard;
 mod registrar_guard;
+mod policy;
+mod answers;
+mod threshold;
 
 use std::{
     fmt::{ Display, Formatter, Result as FmtResult }
 };
 
+use serde::{ Serialize, Deserialize };
+
 pub use self::{
     capabilities::Capabilities,
     guard::{ Guard, ZealedGuard, BlindGuard, QuietGuard },
-    registrar_guard::{ RegistrarGuard }
+    registrar_guard::{ RegistrarGuard },
+    policy::{ Policy, PolicyRule, PolicyAction, PolicyGuard },
+    answers::{ Decision, AnswerFile, AnswerFileGuard },
+    threshold::{ Impact, Thresholds, ThresholdGuard }
 };
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "kebab-case")]
 pub enum Capability {
     Merge,
     Overwrite,
-    Recursive
+    Recursive,
+    Delete,
+    Rename,
+    //Touching more than what thresholds allow in a single event
+    Bulk,
+    //Moving to another device, degraded to copy and remove
+    CrossDevice,
+    //Recursing into a directory reached through a symbolic link
+    FollowSymlinks
+}
+
+impl Capability {
+    pub const ALL : [Capability; 8] = [
+        Capability::Merge,
+        Capability::Overwrite,
+        Capability::Recursive,
+        Capability::Delete,
+        Capability::Rename,
+        Capability::Bulk,
+        Capability::CrossDevice,
+        Capability::FollowSymlinks
+    ];
+
+    //Granted unless the event or a guard says otherwise, permanent removal is what removing means
+    pub fn default_granted(self) -> bool {
+        matches!(self, Capability::Delete)
+    }
+
+    //Only conflicting entries can be kept both
+    pub fn can_rename(self) -> bool {
+        matches!(self, Capability::Merge | Capability::Overwrite)
+    }
+
+    //Always part of serialized capabilities
+    pub fn is_core(self) -> bool {
+        matches!(self, Capability::Merge | Capability::Overwrite | Capability::Recursive)
+    }
+
+    fn bit(self) -> u16 {
+        1 << self as u16
+    }
 }
 
 impl Display for Capability {
@@ -46,7 +95,12 @@ impl Display for Capability {
             match self {
                 Capability::Merge => "merge",
                 Capability::Recursive => "recursive",
-                Capability::Overwrite => "overwrite"
+                Capability::Overwrite => "overwrite",
+                Capability::Delete => "delete",
+                Capability::Rename => "rename",
+                Capability::Bulk => "bulk",
+                Capability::CrossDevice => "cross-device",
+                Capability::FollowSymlinks => "follow-symlinks"
             }
         )
     }
@@ -55,3 +109,30 @@ impl Display for Capability {
 impl Eq for Capability {}
 
 
+
+/*
+Answer of a guard, renaming keeps both entries by giving another name to the incoming one.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Authorization {
+    Allow,
+    Skip,
+    Rename
+}
+
+impl Authorization {
+    pub fn is_allowed(self) -> bool {
+        self == Authorization::Allow
+    }
+}
+
+impl From<bool> for Authorization {
+    fn from(allowed: bool) -> Self {
+        if allowed {
+            Authorization::Allow
+        } else {
+            Authorization::Skip
+        }
+    }
+}
@@ -0,0 +1,280 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    any::Any,
+    env::var_os,
+    fs::read_to_string,
+    path::{ Path, PathBuf }
+};
+
+use serde::{ Serialize, Deserialize, Deserializer };
+
+use crate::{
+    DomainError,
+    event::Pattern,
+    tools::{ normalize, canonical },
+    capability::{
+        Capability,
+        Authorization,
+        Guard,
+        ZealedGuard,
+        Impact
+    }
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PolicyAction {
+    Allow,
+    Ask,
+    Deny
+}
+
+//A leading ~/ stands for the home directory
+fn expand(text: &str) -> String {
+    match (text.strip_prefix("~/"), var_os("HOME")) {
+        (Some(relative), Some(home)) => PathBuf::from(home).join(relative).to_string_lossy().to_string(),
+        _ => text.to_string()
+    }
+}
+
+fn deserialize_path<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pattern, D::Error> {
+    Pattern::new(expand(String::deserialize(deserializer)?.as_str()).as_str()).map_err(serde::de::Error::custom)
+}
+
+/*
+Action taken when a capability is needed on a path matching the pattern, for every capability when none are listed.
+Patterns are the same as filters, matched from the root directory.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PolicyRule {
+    #[serde(deserialize_with = "deserialize_path")]
+    path: Pattern,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    capabilities: Vec<Capability>,
+    action: PolicyAction
+}
+
+impl PolicyRule {
+    pub fn new(path: Pattern, capabilities: Vec<Capability>, action: PolicyAction) -> PolicyRule {
+        PolicyRule {
+            path,
+            capabilities,
+            action
+        }
+    }
+
+    pub fn path(&self) -> &Pattern { &self.path }
+    pub fn capabilities(&self) -> &[Capability] { &self.capabilities }
+    pub fn action(&self) -> PolicyAction { self.action }
+
+    /*
+    is_dir comes from the view the event is atomized against, a directory may only exist virtually.
+    Links are resolved as far as the target exists, so a link into a covered tree is covered too.
+    */
+    pub fn applies(&self, capability: Capability, target: &Path, is_dir: bool) -> bool {
+        (self.capabilities.is_empty() || self.capabilities.contains(&capability))
+            && [normalize(target), canonical(target)].iter()
+                .any(|path| self.path.matches(path.strip_prefix("/").unwrap_or(path), is_dir))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Policy {
+    #[serde(default, rename = "rule", skip_serializing_if = "Vec::is_empty")]
+    rules: Vec<PolicyRule>
+}
+
+impl Policy {
+    pub fn new(rules: Vec<PolicyRule>) -> Policy {
+        Policy {
+            rules
+        }
+    }
+
+    //$FUTURECOMMANDER_POLICY, or policy.toml in $XDG_CONFIG_HOME/futurecommander, $XDG_CONFIG_HOME defaulting to ~/.config
+    pub fn home() -> Result<PathBuf, DomainError> {
+        match (var_os("FUTURECOMMANDER_POLICY").map(PathBuf::from), var_os("XDG_CONFIG_HOME").map(PathBuf::from), var_os("HOME").map(PathBuf::from)) {
+            (Some(policy), _, _) if !policy.as_os_str().is_empty() => Ok(policy),
+            (_, Some(config_home), _) if config_home.is_absolute() => Ok(config_home.join("futurecommander/policy.toml")),
+            (_, _, Some(home)) if home.is_absolute() => Ok(home.join(".config/futurecommander/policy.toml")),
+            _ => Err(DomainError::PolicyUnavailable)
+        }
+    }
+
+    pub fn from_file(path: &Path) -> Result<Policy, DomainError> {
+        Ok(toml::from_str(read_to_string(path)?.as_str())?)
+    }
+
+    pub fn rules(&self) -> &[PolicyRule] { &self.rules }
+
+    //First matching rule wins
+    pub fn decide(&self, capability: Capability, target: &Path, is_dir: bool) -> Option<PolicyAction> {
+        self.rules.iter()
+            .find(|rule| rule.applies(capability, target, is_dir))
+            .map(PolicyRule::action)
+    }
+}
+
+/*
+Enforces a policy whatever the wrapped guard, which only decides what the policy does not cover.
+Asking is delegated to another guard, as if the capability was not granted by default.
+*/
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PolicyGuard {
+    policy: Policy,
+    inner: Box<dyn Guard>,
+    ask: Box<dyn Guard>
+}
+
+impl PolicyGuard {
+    pub fn new(policy: Policy, inner: Box<dyn Guard>) -> PolicyGuard {
+        PolicyGuard {
+            policy,
+            inner,
+            ask: Box::new(ZealedGuard)
+        }
+    }
+
+    pub fn with_ask(mut self, ask: Box<dyn Guard>) -> PolicyGuard {
+        self.ask = ask;
+        self
+    }
+}
+
+#[typetag::serde]
+impl Guard for PolicyGuard {
+    //Without the kind of the target in the view, the real one is the best guess
+    fn authorize(&mut self, capability: Capability, default: bool, target: &Path) -> Result<Authorization, DomainError> {
+        self.authorize_entry(capability, default, target, target.is_dir())
+    }
+
+    fn authorize_entry(&mut self, capability: Capability, default: bool, target: &Path, is_dir: bool) -> Result<Authorization, DomainError> {
+        match self.policy.decide(capability, target, is_dir) {
+            Some(PolicyAction::Allow) => Ok(Authorization::Allow),
+            Some(PolicyAction::Deny) => Err(DomainError::PolicyDenied(capability, target.to_path_buf())),
+            Some(PolicyAction::Ask) => self.ask.authorize_entry(capability, false, target, is_dir),
+            None => self.inner.authorize_entry(capability, default, target, is_dir)
+        }
+    }
+
+    fn measures_impact(&self) -> bool {
+        self.inner.measures_impact()
+    }
+
+    fn authorize_impact(&mut self, impact: &Impact, target: &Path) -> Result<Authorization, DomainError> {
+        self.inner.authorize_impact(impact, target)
+    }
+
+    fn attach(&mut self, attachment: &dyn Any) {
+        self.inner.attach(attachment);
+        self.ask.attach(attachment);
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        Kind,
+        sample::Samples,
+        capability::{ BlindGuard, QuietGuard },
+        event::{ CreateEvent, RemoveEvent, FileSystemEvent },
+        port::FileSystemAdapter,
+        infrastructure::{ RealFileSystem, VirtualFileSystem }
+    };
+
+    #[test]
+    fn policy_overrides_wrapped_guard(){
+        let policy : Policy = toml::from_str(r#"
+            [[rule]]
+            path = "/etc/**"
+            capabilities = ["delete", "recursive"]
+            action = "deny"
+
+            [[rule]]
+            path = "*.db"
+            capabilities = ["overwrite"]
+            action = "ask"
+
+            [[rule]]
+            path = "/tmp/scratch/**"
+            action = "allow"
+        "#).unwrap();
+        assert_eq!(policy.rules().len(), 3);
+
+        let mut guard = PolicyGuard::new(policy.clone(), Box::new(BlindGuard)).with_ask(Box::new(QuietGuard));
+        assert!(matches!(
+            guard.authorize(Capability::Delete, true, Path::new("/etc/hosts")),
+            Err(DomainError::PolicyDenied(Capability::Delete, _))
+        ));
+        assert!(guard.authorize(Capability::Overwrite, true, Path::new("/etc/hosts")).unwrap().is_allowed());
+        assert_eq!(guard.authorize(Capability::Overwrite, true, Path::new("/srv/app/data.db")).unwrap(), Authorization::Skip);
+        assert!(guard.authorize(Capability::Merge, false, Path::new("/srv/app/data.db")).unwrap().is_allowed());
+
+        let mut guard = PolicyGuard::new(policy, Box::new(QuietGuard));
+        assert!(guard.authorize(Capability::Merge, false, Path::new("/tmp/scratch/a/b")).unwrap().is_allowed());
+        assert_eq!(guard.authorize(Capability::Merge, false, Path::new("/tmp/elsewhere")).unwrap(), Authorization::Skip);
+    }
+
+    #[test]
+    fn expand_home_in_rules(){
+        if let Some(home) = var_os("HOME") {
+            let policy : Policy = toml::from_str("[[rule]]\npath = \"~/scratch/**\"\naction = \"deny\"\n").unwrap();
+            assert_eq!(policy.decide(Capability::Merge, PathBuf::from(home).join("scratch/a").as_path(), false), Some(PolicyAction::Deny));
+            assert_eq!(policy.decide(Capability::Merge, Path::new("/scratch/a"), false), None);
+        }
+    }
+
+    #[test]
+    fn policy_matches_virtual_directories_and_resolved_links(){
+        let chroot = Samples::init_simple_chroot("policy_matches_virtual_directories_and_resolved_links").canonicalize().unwrap();
+        std::os::unix::fs::symlink(chroot.join("RDIR2"), chroot.join("RDIR/LINK")).unwrap();
+        let policy : Policy = toml::from_str(format!(
+            "[[rule]]\npath = \"{0}/VDIR/\"\naction = \"deny\"\n\n[[rule]]\npath = \"{0}/RDIR2/**\"\ncapabilities = [\"delete\"]\naction = \"deny\"\n",
+            chroot.display()
+        ).as_str()).unwrap();
+
+        let mut fs = FileSystemAdapter(VirtualFileSystem::default());
+        FileSystemEvent::Create(CreateEvent::new(chroot.join("VDIR").as_path(), Kind::Directory, false, false))
+            .atomize(&fs, &mut BlindGuard).unwrap()
+            .apply(&mut fs).unwrap();
+        assert!(!chroot.join("VDIR").exists());
+        assert!(matches!(
+            FileSystemEvent::Remove(RemoveEvent::new(chroot.join("VDIR").as_path(), false))
+                .atomize(&fs, &mut PolicyGuard::new(policy.clone(), Box::new(BlindGuard))),
+            Err(DomainError::PolicyDenied(Capability::Delete, _))
+        ));
+
+        let fs = FileSystemAdapter(RealFileSystem::default());
+        assert!(matches!(
+            FileSystemEvent::Remove(RemoveEvent::new(chroot.join("RDIR/LINK/RFILEC").as_path(), false))
+                .atomize(&fs, &mut PolicyGuard::new(policy.clone(), Box::new(BlindGuard))),
+            Err(DomainError::PolicyDenied(Capability::Delete, _))
+        ));
+        assert!(
+            FileSystemEvent::Remove(RemoveEvent::new(chroot.join("RDIR/RFILEA").as_path(), false))
+                .atomize(&fs, &mut PolicyGuard::new(policy, Box::new(BlindGuard)))
+                .is_ok()
+        );
+    }
+}
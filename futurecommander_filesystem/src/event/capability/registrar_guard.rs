@@ -17,6 +17,7 @@
  * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
  */
 use std::{
+    any::Any,
     path::{ Path, PathBuf },
     collections::HashMap
 };
@@ -27,16 +28,23 @@ use crate::{
     DomainError,
     capability::{
         Capability,
+        Authorization,
         Guard,
         Capabilities,
-        ZealedGuard
+        ZealedGuard,
+        Decision,
+        Impact
     }
 };
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct RegistrarGuard {
     inner: Box<dyn Guard>,
-    registry: HashMap<PathBuf, Capabilities>
+    #[serde(with = "crate::lossless::map")]
+    registry: HashMap<PathBuf, Capabilities>,
+    //Only kept to be exported, skipped capabilities are asked again
+    #[serde(default, skip_serializing_if = "HashMap::is_empty", with = "crate::lossless::map")]
+    skipped: HashMap<PathBuf, Capabilities>
 }
 
 impl Default for RegistrarGuard {
@@ -49,25 +57,88 @@ impl RegistrarGuard {
     pub fn from(guard: Box<dyn Guard>) -> Self {
         RegistrarGuard {
             inner: guard,
-            registry: HashMap::new()
+            registry: HashMap::new(),
+            skipped: HashMap::new()
         }
     }
-}
 
-#[typetag::serde]
-impl Guard for RegistrarGuard {
-    fn authorize(&mut self, capability: Capability, default: bool, target: &Path) -> Result<bool, DomainError> {
+    //Every answer recorded, by path
+    pub fn decisions(&self) -> Vec<Decision> {
+        let mut decisions : Vec<Decision> = Vec::new();
+        for (registry, granted) in [(&self.registry, true), (&self.skipped, false)] {
+            for (path, capabilities) in registry.iter() {
+                for capability in capabilities.granted() {
+                    let answer = match (capability, granted) {
+                        (Capability::Rename, _) => Authorization::Rename,
+                        (_, true) => Authorization::Allow,
+                        (_, false) => Authorization::Skip
+                    };
+                    decisions.push(Decision::new(path.as_path(), capability, answer));
+                }
+            }
+        }
+        decisions.sort_by(|a, b| a.path().cmp(b.path()));
+        decisions
+    }
+
+    //Replays what was already granted for target, records what inner decides otherwise
+    fn register<F>(&mut self, capability: Capability, target: &Path, ask: F) -> Result<Authorization, DomainError>
+        where F: FnOnce(&mut dyn Guard) -> Result<Authorization, DomainError> {
         let capabilities = match self.registry.get(&target.to_path_buf()) {
             Some(capabilities) => *capabilities,
             None => (Capabilities::default())
         };
 
-        if capabilities.authorize(capability) || self.inner.authorize(capability, default, target)? {
-            self.registry.insert(target.to_path_buf(), capabilities + capability);
-            Ok(true)
-        } else {
-            Ok(false)
+        if capabilities.rename() && capability.can_rename() {
+            return Ok(Authorization::Rename);
+        }
+
+        if capabilities.authorize(capability) {
+            return Ok(Authorization::Allow);
         }
+
+        let authorization = ask(self.inner.as_mut())?;
+        match authorization {
+            Authorization::Allow => { self.registry.insert(target.to_path_buf(), capabilities + capability); },
+            Authorization::Rename => { self.registry.insert(target.to_path_buf(), capabilities + Capability::Rename); },
+            Authorization::Skip => {
+                let skipped = self.skipped.get(target).copied().unwrap_or_default();
+                self.skipped.insert(target.to_path_buf(), skipped + capability);
+            }
+        }
+        Ok(authorization)
+    }
+}
+
+#[typetag::serde]
+impl Guard for RegistrarGuard {
+    fn authorize(&mut self, capability: Capability, default: bool, target: &Path) -> Result<Authorization, DomainError> {
+        self.register(capability, target, |inner| inner.authorize(capability, default, target))
+    }
+
+    fn authorize_entry(&mut self, capability: Capability, default: bool, target: &Path, is_dir: bool) -> Result<Authorization, DomainError> {
+        self.register(capability, target, |inner| inner.authorize_entry(capability, default, target, is_dir))
+    }
+
+    fn measures_impact(&self) -> bool {
+        self.inner.measures_impact()
+    }
+
+    fn authorize_impact(&mut self, impact: &Impact, target: &Path) -> Result<Authorization, DomainError> {
+        if self.registry.get(target).is_some_and(|capabilities| capabilities.bulk()) {
+            return Ok(Authorization::Allow);
+        }
+
+        let authorization = self.inner.authorize_impact(impact, target)?;
+        if authorization.is_allowed() {
+            let capabilities = self.registry.get(target).copied().unwrap_or_default();
+            self.registry.insert(target.to_path_buf(), capabilities + Capability::Bulk);
+        }
+        Ok(authorization)
+    }
+
+    fn attach(&mut self, attachment: &dyn Any) {
+        self.inner.attach(attachment);
     }
 }
 
@@ -91,13 +162,13 @@ mod tests {
         let target = Path::new("/virtual/directory");
         let default = false;
 
-        assert!(registrar.authorize(Capability::Overwrite, default, target).unwrap());
+        assert!(registrar.authorize(Capability::Overwrite, default, target).unwrap().is_allowed());
         assert!(registrar.registry[&target.to_path_buf()].overwrite());
 
-        assert!(registrar.authorize(Capability::Merge, default, target).unwrap());
+        assert!(registrar.authorize(Capability::Merge, default, target).unwrap().is_allowed());
         assert!(registrar.registry[&target.to_path_buf()].merge());
 
-        assert!(registrar.authorize(Capability::Recursive, default, target).unwrap());
+        assert!(registrar.authorize(Capability::Recursive, default, target).unwrap().is_allowed());
         assert!(registrar.registry[&target.to_path_buf()].recursive());
     }
 
@@ -107,13 +178,38 @@ mod tests {
         let target = Path::new("/virtual/directory");
         let default = false;
 
-        assert!(!registrar.authorize(Capability::Overwrite, default, target).unwrap());
+        assert!(!registrar.authorize(Capability::Overwrite, default, target).unwrap().is_allowed());
         assert!(registrar.registry.get(&target.to_path_buf()).is_none());
 
-        assert!(!registrar.authorize(Capability::Merge, default, target).unwrap());
+        assert!(!registrar.authorize(Capability::Merge, default, target).unwrap().is_allowed());
         assert!(registrar.registry.get(&target.to_path_buf()).is_none());
 
-        assert!(!registrar.authorize(Capability::Recursive, default, target).unwrap());
+        assert!(!registrar.authorize(Capability::Recursive, default, target).unwrap().is_allowed());
         assert!(registrar.registry.get(&target.to_path_buf()).is_none());
     }
+
+    #[derive(Serialize, Deserialize, Debug)]
+    struct RenamingGuard;
+
+    #[typetag::serde]
+    impl Guard for RenamingGuard {
+        fn authorize(&mut self, _capability: Capability, _default: bool, _target: &Path) -> Result<Authorization, DomainError> {
+            Ok(Authorization::Rename)
+        }
+    }
+
+    #[test]
+    fn registrar_replay_rename(){
+        let mut registrar = RegistrarGuard::from(Box::new(RenamingGuard));
+        let target = Path::new("/virtual/file");
+
+        assert_eq!(registrar.authorize(Capability::Overwrite, false, target).unwrap(), Authorization::Rename);
+        assert!(registrar.registry[&target.to_path_buf()].rename());
+
+        registrar.inner = Box::new(QuietGuard);
+        assert_eq!(registrar.authorize(Capability::Overwrite, false, target).unwrap(), Authorization::Rename);
+        assert_eq!(registrar.authorize(Capability::Merge, false, target).unwrap(), Authorization::Rename);
+        assert_eq!(registrar.authorize(Capability::Recursive, false, target).unwrap(), Authorization::Skip);
+        assert_eq!(registrar.authorize(Capability::Delete, false, target).unwrap(), Authorization::Skip);
+    }
 }
@@ -0,0 +1,330 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use std::{
+    any::Any,
+    env::var,
+    fmt::{ Display, Formatter, Result as FmtResult },
+    fs::{ read_dir, symlink_metadata },
+    path::{ Path, PathBuf },
+    str::FromStr
+};
+
+use serde::{ Serialize, Deserialize };
+
+use crate::{
+    DomainError,
+    capability::{
+        Capability,
+        Authorization,
+        Guard,
+        ZealedGuard
+    },
+    port::{
+        Entry,
+        ReadableFileSystem,
+        Atomic,
+        AtomicTransaction
+    }
+};
+
+//Entries and bytes of a real tree, itself included
+fn walk(path: &Path) -> (u64, u64) {
+    match symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => read_dir(path)
+            .map(|children| children.filter_map(Result::ok)
+                .map(|child| walk(child.path().as_path()))
+                .fold((1, 0), |(entries, bytes), (child_entries, child_bytes)| (entries + child_entries, bytes + child_bytes)))
+            .unwrap_or((1, 0)),
+        Ok(metadata) => (1, metadata.len()),
+        Err(_) => (1, 0)
+    }
+}
+
+//What a single event touches once applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Impact {
+    entries: u64,
+    bytes: u64,
+    deletions: u64
+}
+
+impl Impact {
+    pub fn new(entries: u64, bytes: u64, deletions: u64) -> Impact {
+        Impact {
+            entries,
+            bytes,
+            deletions
+        }
+    }
+
+    //Bytes are read from the real entries behind the virtual ones
+    pub fn measure<E: Entry, F: ReadableFileSystem<Item=E>>(transaction: &AtomicTransaction, fs: &F) -> Impact {
+        let real = |path: &Path| -> PathBuf {
+            fs.status(path).ok()
+                .and_then(|entry| entry.source().map(Path::to_path_buf))
+                .unwrap_or_else(|| path.to_path_buf())
+        };
+
+        //A bound tree is walked at once, atomics putting its real entries in place again are not counted twice
+        let mut bound : Vec<(PathBuf, PathBuf)> = Vec::new();
+        let covered = |bound: &[(PathBuf, PathBuf)], path: &Path| bound.iter().any(|(destination, source)|
+            path.strip_prefix(destination)
+                .is_ok_and(|relative| !relative.as_os_str().is_empty() && symlink_metadata(source.join(relative)).is_ok())
+        );
+
+        let mut impact = Impact::default();
+        for atomic in transaction.iter() {
+            let target = match atomic {
+                Atomic::CreateEmptyDirectory(path)
+                | Atomic::CreateEmptyFile(path)
+                | Atomic::CreateFileWithContent { path, .. }
+                | Atomic::SetMetadata { path, .. } => Some(path),
+                Atomic::BindDirectoryToDirectory { destination, .. }
+                | Atomic::CopyFileToFile { destination, .. }
+                | Atomic::MoveFileToFile { destination, .. }
+                | Atomic::HardLink { destination, .. } => Some(destination),
+                _ => None
+            };
+            if target.is_some_and(|target| covered(&bound, target)) {
+                continue;
+            }
+
+            let (entries, bytes) = match atomic {
+                Atomic::BindDirectoryToDirectory { source, destination } => {
+                    let source = real(source);
+                    let walked = walk(source.as_path());
+                    bound.push((destination.to_path_buf(), source));
+                    walked
+                },
+                Atomic::CopyFileToFile { source, .. }
+                | Atomic::MoveFileToFile { source, .. } => walk(real(source).as_path()),
+                Atomic::RemoveFile(path) => {
+                    impact.deletions += 1;
+                    walk(real(path).as_path())
+                },
+                Atomic::RemoveEmptyDirectory(_)
+                | Atomic::RemoveMaintainedEmptyDirectory(_) => {
+                    impact.deletions += 1;
+                    (1, 0)
+                },
+                _ => (1, 0)
+            };
+            impact.entries += entries;
+            impact.bytes += bytes;
+        }
+        impact
+    }
+
+    pub fn entries(&self) -> u64 { self.entries }
+    pub fn bytes(&self) -> u64 { self.bytes }
+    pub fn deletions(&self) -> u64 { self.deletions }
+}
+
+impl Display for Impact {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{} entries, {} bytes, {} deletions", self.entries, self.bytes, self.deletions)
+    }
+}
+
+fn parse_size(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let (number, unit) = match text.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+        Some((index, _)) => text.split_at(index),
+        None => (text, "")
+    };
+    let multiplier : u64 = match unit.trim().to_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1 << 10,
+        "M" | "MB" => 1 << 20,
+        "G" | "GB" => 1 << 30,
+        "T" | "TB" => 1 << 40,
+        _ => return None
+    };
+    number.parse::<u64>().ok().and_then(|number| number.checked_mul(multiplier))
+}
+
+/*
+Limits an event may reach before needing an explicit confirmation, like entries=10000,bytes=10G,deletions=500.
+Bytes accept K, M, G and T binary suffixes.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Thresholds {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    entries: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    bytes: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    deletions: Option<u64>
+}
+
+impl Thresholds {
+    pub fn new(entries: Option<u64>, bytes: Option<u64>, deletions: Option<u64>) -> Thresholds {
+        Thresholds {
+            entries,
+            bytes,
+            deletions
+        }
+    }
+
+    //$FUTURECOMMANDER_THRESHOLDS, if set
+    pub fn from_env() -> Result<Option<Thresholds>, DomainError> {
+        match var("FUTURECOMMANDER_THRESHOLDS") {
+            Ok(thresholds) if !thresholds.trim().is_empty() => Ok(Some(thresholds.parse()?)),
+            _ => Ok(None)
+        }
+    }
+
+    pub fn exceeded(&self, impact: &Impact) -> bool {
+        self.entries.is_some_and(|entries| impact.entries > entries)
+            || self.bytes.is_some_and(|bytes| impact.bytes > bytes)
+            || self.deletions.is_some_and(|deletions| impact.deletions > deletions)
+    }
+}
+
+impl FromStr for Thresholds {
+    type Err = DomainError;
+
+    fn from_str(text: &str) -> Result<Thresholds, DomainError> {
+        let invalid = || DomainError::InvalidThresholds(text.to_string());
+        let mut thresholds = Thresholds::default();
+        for limit in text.split(',').map(str::trim).filter(|limit| !limit.is_empty()) {
+            let (name, value) = limit.split_once('=').ok_or_else(invalid)?;
+            match name.trim() {
+                "entries" => thresholds.entries = Some(value.trim().parse().map_err(|_| invalid())?),
+                "bytes" => thresholds.bytes = Some(parse_size(value).ok_or_else(invalid)?),
+                "deletions" => thresholds.deletions = Some(value.trim().parse().map_err(|_| invalid())?),
+                _ => return Err(invalid())
+            }
+        }
+        Ok(thresholds)
+    }
+}
+
+/*
+Events beyond the thresholds need the bulk capability from the confirming guard, whatever the wrapped guard.
+Confirmation refuses unless given another guard.
+*/
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ThresholdGuard {
+    thresholds: Thresholds,
+    inner: Box<dyn Guard>,
+    confirm: Box<dyn Guard>
+}
+
+impl ThresholdGuard {
+    pub fn new(thresholds: Thresholds, inner: Box<dyn Guard>) -> ThresholdGuard {
+        ThresholdGuard {
+            thresholds,
+            inner,
+            confirm: Box::new(ZealedGuard)
+        }
+    }
+
+    pub fn with_confirm(mut self, confirm: Box<dyn Guard>) -> ThresholdGuard {
+        self.confirm = confirm;
+        self
+    }
+}
+
+#[typetag::serde]
+impl Guard for ThresholdGuard {
+    fn authorize(&mut self, capability: Capability, default: bool, target: &Path) -> Result<Authorization, DomainError> {
+        self.inner.authorize(capability, default, target)
+    }
+
+    fn authorize_entry(&mut self, capability: Capability, default: bool, target: &Path, is_dir: bool) -> Result<Authorization, DomainError> {
+        self.inner.authorize_entry(capability, default, target, is_dir)
+    }
+
+    fn measures_impact(&self) -> bool {
+        true
+    }
+
+    fn authorize_impact(&mut self, impact: &Impact, target: &Path) -> Result<Authorization, DomainError> {
+        if self.thresholds.exceeded(impact) {
+            self.confirm.authorize(Capability::Bulk, false, target)
+        } else {
+            self.inner.authorize_impact(impact, target)
+        }
+    }
+
+    fn attach(&mut self, attachment: &dyn Any) {
+        self.inner.attach(attachment);
+        self.confirm.attach(attachment);
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::write;
+
+    use crate::{
+        sample::Samples,
+        capability::{ BlindGuard, QuietGuard },
+        event::{ CopyEvent, FileSystemEvent },
+        port::FileSystemAdapter,
+        infrastructure::{ RealFileSystem, VirtualFileSystem }
+    };
+
+    #[test]
+    fn parse_thresholds(){
+        assert_eq!(
+            "entries=100, bytes=2M,deletions=3".parse::<Thresholds>().unwrap(),
+            Thresholds::new(Some(100), Some(2 << 20), Some(3))
+        );
+        assert_eq!("bytes=10".parse::<Thresholds>().unwrap(), Thresholds::new(None, Some(10), None));
+        assert!("bytes=10X".parse::<Thresholds>().is_err());
+        assert!("files=10".parse::<Thresholds>().is_err());
+    }
+
+    #[test]
+    fn blind_guard_needs_confirmation_beyond_thresholds(){
+        let target = Path::new("/virtual/directory");
+        let mut guard = ThresholdGuard::new(Thresholds::new(None, Some(1024), Some(2)), Box::new(BlindGuard));
+
+        assert!(guard.authorize_impact(&Impact::new(10, 1024, 2), target).unwrap().is_allowed());
+        assert!(matches!(
+            guard.authorize_impact(&Impact::new(10, 1025, 0), target),
+            Err(DomainError::BulkNotAllowed(_))
+        ));
+        assert!(guard.authorize(Capability::Overwrite, false, target).unwrap().is_allowed());
+
+        let mut guard = guard.with_confirm(Box::new(QuietGuard));
+        assert_eq!(guard.authorize_impact(&Impact::new(0, 0, 3), target).unwrap(), Authorization::Skip);
+    }
+
+    #[test]
+    fn measure_bound_tree_once(){
+        let chroot = Samples::init_simple_chroot("threshold_measure_bound_tree_once");
+        write(chroot.join("RDIR/RFILEA"), "0123456789").unwrap();
+        write(chroot.join("RDIR/RFILEB"), "").unwrap();
+        let event = FileSystemEvent::Copy(CopyEvent::new(chroot.join("RDIR").as_path(), chroot.join("COPIED").as_path(), false, false));
+
+        let real = FileSystemAdapter(RealFileSystem::default());
+        let transaction = event.atomize(&real, &mut BlindGuard).unwrap();
+        assert_eq!(Impact::measure(&transaction, &real), Impact::new(3, 10, 0));
+
+        let simulated = FileSystemAdapter(VirtualFileSystem::default());
+        let transaction = event.atomize(&simulated, &mut BlindGuard).unwrap();
+        assert_eq!(Impact::measure(&transaction, &simulated), Impact::new(3, 10, 0));
+    }
+}
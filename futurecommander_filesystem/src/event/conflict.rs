@@ -0,0 +1,324 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    fmt::{ Display, Formatter, Result as FmtResult },
+    fs::metadata,
+    path::{ Path, PathBuf, MAIN_SEPARATOR },
+    str::FromStr
+};
+
+use serde::{ Serialize, Serializer, Deserialize, Deserializer };
+
+use crate::{
+    errors::DomainError,
+    lossless,
+    tools::same_content,
+    capability::{
+        Guard,
+        Capability,
+        Authorization
+    },
+    port::{
+        Entry,
+        ReadableFileSystem
+    }
+};
+
+pub const DEFAULT_RENAME_TEMPLATE : &str = "{name} ({n}){ext}";
+
+/*
+Decides, file by file, whether an existing destination is worth overwriting.
+Never and always are serialized as the former overwrite boolean.
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverwritePolicy {
+    #[default]
+    Never,
+    Always,
+    IfNewer,
+    IfLarger,
+    IfDifferent
+}
+
+impl OverwritePolicy {
+    //Default answer given to the guard
+    pub fn allowed(self) -> bool {
+        self != OverwritePolicy::Never
+    }
+
+    pub fn is_never(&self) -> bool {
+        *self == OverwritePolicy::Never
+    }
+
+    //Some conflicting files may be left in place
+    pub fn is_conditional(self) -> bool {
+        !matches!(self, OverwritePolicy::Never | OverwritePolicy::Always)
+    }
+
+    //Entries without real content, like virtually created files, are always worth it
+    pub fn applies<E: Entry>(self, source: &E, destination: &E) -> bool {
+        let (source, destination) = match (source.source(), destination.source()) {
+            (Some(source), Some(destination)) => (source, destination),
+            _ => return true
+        };
+
+        match self {
+            OverwritePolicy::Never | OverwritePolicy::Always => true,
+            OverwritePolicy::IfNewer => match (metadata(source).and_then(|m| m.modified()), metadata(destination).and_then(|m| m.modified())) {
+                (Ok(source), Ok(destination)) => source > destination,
+                _ => true
+            },
+            OverwritePolicy::IfLarger => match (metadata(source), metadata(destination)) {
+                (Ok(source), Ok(destination)) => source.len() > destination.len(),
+                _ => true
+            },
+            OverwritePolicy::IfDifferent => source != destination && !same_content(source, destination).unwrap_or(false)
+        }
+    }
+}
+
+impl From<bool> for OverwritePolicy {
+    fn from(overwrite: bool) -> Self {
+        if overwrite {
+            OverwritePolicy::Always
+        } else {
+            OverwritePolicy::Never
+        }
+    }
+}
+
+impl Display for OverwritePolicy {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{}",
+            match self {
+                OverwritePolicy::Never => "never",
+                OverwritePolicy::Always => "always",
+                OverwritePolicy::IfNewer => "if-newer",
+                OverwritePolicy::IfLarger => "if-larger",
+                OverwritePolicy::IfDifferent => "if-different"
+            }
+        )
+    }
+}
+
+impl FromStr for OverwritePolicy {
+    type Err = DomainError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "never" => Ok(OverwritePolicy::Never),
+            "always" => Ok(OverwritePolicy::Always),
+            "if-newer" => Ok(OverwritePolicy::IfNewer),
+            "if-larger" => Ok(OverwritePolicy::IfLarger),
+            "if-different" => Ok(OverwritePolicy::IfDifferent),
+            _ => Err(DomainError::InvalidOverwritePolicy(s.to_string()))
+        }
+    }
+}
+
+impl Serialize for OverwritePolicy {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            OverwritePolicy::Never => serializer.serialize_bool(false),
+            OverwritePolicy::Always => serializer.serialize_bool(true),
+            policy => serializer.collect_str(policy)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for OverwritePolicy {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Serialized {
+            Boolean(bool),
+            Named(String)
+        }
+
+        match Serialized::deserialize(deserializer)? {
+            Serialized::Boolean(overwrite) => Ok(overwrite.into()),
+            Serialized::Named(name) => name.parse().map_err(serde::de::Error::custom)
+        }
+    }
+}
+
+/*
+A conflict is renamed away without asking when keep both is requested and the capability is not granted by default.
+*/
+pub fn resolve(guard: &mut dyn Guard, capability: Capability, default: bool, keep_both: bool, target: &Path, is_dir: bool) -> Result<Authorization, DomainError> {
+    if keep_both && !default {
+        Ok(Authorization::Rename)
+    } else {
+        guard.authorize_entry(capability, default, target, is_dir)
+    }
+}
+
+pub fn validate_template(template: &str) -> Result<(), DomainError> {
+    if !template.contains("{n}") || template.contains(MAIN_SEPARATOR) {
+        return Err(DomainError::InvalidTemplate(template.to_string()));
+    }
+    Ok(())
+}
+
+/*
+Sibling of target built from template, where {name} is the name without extension,
+{ext} the extension with its dot and {n} the given counter. Directories have no extension.
+Bytes of the name which are not UTF-8 are kept as they are.
+*/
+pub fn numbered(target: &Path, is_dir: bool, template: &str, n: u64) -> Result<PathBuf, DomainError> {
+    let parent = target.parent().unwrap_or_else(|| Path::new(""));
+    let (name, ext) = if is_dir {
+        (target.file_name().map(lossless::bytes).unwrap_or_default(), Vec::new())
+    } else {
+        let mut ext = Vec::new();
+        if let Some(extension) = target.extension() {
+            ext.push(b'.');
+            ext.append(&mut lossless::bytes(extension));
+        }
+        (target.file_stem().map(lossless::bytes).unwrap_or_default(), ext)
+    };
+
+    let mut bytes = Vec::new();
+    let mut rest = template;
+    while let Some(c) = rest.chars().next() {
+        let (replacement, placeholder) = if rest.starts_with("{name}") {
+            (name.clone(), "{name}".len())
+        } else if rest.starts_with("{ext}") {
+            (ext.clone(), "{ext}".len())
+        } else if rest.starts_with("{n}") {
+            (n.to_string().into_bytes(), "{n}".len())
+        } else {
+            (rest[..c.len_utf8()].as_bytes().to_vec(), c.len_utf8())
+        };
+        bytes.extend_from_slice(&replacement);
+        rest = &rest[placeholder..];
+    }
+
+    Ok(parent.join(lossless::from_bytes(bytes)?))
+}
+
+//First free sibling of target, counting from 1
+pub fn keep_both<E: Entry, F: ReadableFileSystem<Item=E>>(fs: &F, target: &Path, is_dir: bool, template: &str) -> Result<PathBuf, DomainError> {
+    validate_template(template)?;
+
+    let mut n : u64 = 1;
+    loop {
+        let candidate = numbered(target, is_dir, template, n)?;
+        if !fs.status(candidate.as_path())?.exists() {
+            return Ok(candidate);
+        }
+        n += 1;
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::write;
+
+    use crate::{
+        sample::Samples,
+        port::{ FileSystemAdapter, EntryAdapter },
+        infrastructure::RealFileSystem,
+        capability::ZealedGuard
+    };
+
+    #[test]
+    fn keep_both_picks_first_free_name() {
+        let chroot = Samples::init_simple_chroot("conflict_keep_both_picks_first_free_name");
+        let fs = FileSystemAdapter(RealFileSystem::default());
+        write(chroot.join("RDIR/report.pdf"), "").unwrap();
+        write(chroot.join("RDIR/report (1).pdf"), "").unwrap();
+
+        assert_eq!(
+            keep_both(&fs, chroot.join("RDIR/report.pdf").as_path(), false, DEFAULT_RENAME_TEMPLATE).unwrap(),
+            chroot.join("RDIR/report (2).pdf")
+        );
+        assert_eq!(
+            keep_both(&fs, chroot.join("RDIR/report.pdf").as_path(), false, "{name}-copy{n}{ext}").unwrap(),
+            chroot.join("RDIR/report-copy1.pdf")
+        );
+        assert_eq!(
+            keep_both(&fs, chroot.join("RDIR.old").as_path(), true, DEFAULT_RENAME_TEMPLATE).unwrap(),
+            chroot.join("RDIR.old (1)")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn numbered_keeps_bytes_which_are_not_utf8() {
+        use std::{ ffi::OsStr, os::unix::ffi::OsStrExt };
+
+        assert_eq!(
+            numbered(Path::new(OsStr::from_bytes(b"/tmp/caf\xe9.txt")), false, DEFAULT_RENAME_TEMPLATE, 2).unwrap(),
+            Path::new(OsStr::from_bytes(b"/tmp/caf\xe9 (2).txt"))
+        );
+        assert_eq!(
+            numbered(Path::new(OsStr::from_bytes(b"/tmp/r\xe9p.d")), true, "{name}.{n}", 1).unwrap(),
+            Path::new(OsStr::from_bytes(b"/tmp/r\xe9p.d.1"))
+        );
+    }
+
+    #[test]
+    fn refuse_template_without_counter() {
+        let fs = FileSystemAdapter(RealFileSystem::default());
+        match keep_both(&fs, Path::new("/tmp/a.txt"), false, "{name}-copy{ext}") {
+            Err(DomainError::InvalidTemplate(template)) => assert_eq!(template, "{name}-copy{ext}"),
+            _ => panic!("a template without counter must be refused")
+        }
+    }
+
+    #[test]
+    fn overwrite_policy_serialization() {
+        assert_eq!(serde_json::to_string(&OverwritePolicy::Never).unwrap(), "false");
+        assert_eq!(serde_json::to_string(&OverwritePolicy::Always).unwrap(), "true");
+        assert_eq!(serde_json::to_string(&OverwritePolicy::IfNewer).unwrap(), "\"if-newer\"");
+        assert_eq!(serde_json::from_str::<OverwritePolicy>("true").unwrap(), OverwritePolicy::Always);
+        assert_eq!(serde_json::from_str::<OverwritePolicy>("\"if-different\"").unwrap(), OverwritePolicy::IfDifferent);
+        assert!(serde_json::from_str::<OverwritePolicy>("\"sometimes\"").is_err());
+    }
+
+    #[test]
+    fn overwrite_policy_compares_files() {
+        let chroot = Samples::init_simple_chroot("conflict_overwrite_policy_compares_files");
+        write(chroot.join("RDIR/SAME"), "content").unwrap();
+        write(chroot.join("RDIR2/SAME"), "content").unwrap();
+        write(chroot.join("RDIR2/LARGER"), "larger content").unwrap();
+
+        let entry = |path: &str| EntryAdapter(chroot.join(path));
+        assert!(!OverwritePolicy::IfDifferent.applies(&entry("RDIR/SAME"), &entry("RDIR2/SAME")));
+        assert!(OverwritePolicy::IfDifferent.applies(&entry("RDIR/SAME"), &entry("RDIR2/LARGER")));
+        assert!(OverwritePolicy::IfLarger.applies(&entry("RDIR2/LARGER"), &entry("RDIR/SAME")));
+        assert!(!OverwritePolicy::IfLarger.applies(&entry("RDIR/SAME"), &entry("RDIR2/LARGER")));
+        assert!(OverwritePolicy::Always.applies(&entry("RDIR/SAME"), &entry("RDIR2/SAME")));
+    }
+
+    #[test]
+    fn keep_both_bypass_guard_only_when_not_granted() {
+        let target = Path::new("/virtual/file");
+        assert_eq!(resolve(&mut ZealedGuard, Capability::Overwrite, false, true, target, false).unwrap(), Authorization::Rename);
+        assert_eq!(resolve(&mut ZealedGuard, Capability::Overwrite, true, true, target, false).unwrap(), Authorization::Allow);
+        assert!(resolve(&mut ZealedGuard, Capability::Overwrite, false, false, target, false).is_err());
+    }
+}
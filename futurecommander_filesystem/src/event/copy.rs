@@ -18,6 +18,7 @@
  */
 
 use std::{
+    collections::HashMap,
     path::{ PathBuf, Path },
 };
 
@@ -26,8 +27,15 @@ use serde::{ Serialize, Deserialize };
 
 use crate::{
     errors::{DomainError},
+    event::{
+        is_false,
+        Filter,
+        Backup,
+        conflict::{ self, DEFAULT_RENAME_TEMPLATE, OverwritePolicy }
+    },
     capability::{
         Capability,
+        Authorization,
         Guard
     },
     port::{
@@ -40,35 +48,103 @@ use crate::{
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CopyEvent {
+    #[serde(with = "crate::lossless")]
     source: PathBuf,
+    #[serde(with = "crate::lossless")]
     destination: PathBuf,
     merge: bool,
-    overwrite: bool
+    overwrite: OverwritePolicy,
+    #[serde(default, skip_serializing_if = "Filter::is_empty")]
+    filter: Filter,
+    #[serde(default, skip_serializing_if = "is_false")]
+    keep_both: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rename_template: Option<String>,
+    #[serde(default, skip_serializing_if = "Backup::is_none")]
+    backup: Backup,
+    #[serde(default, skip_serializing_if = "is_false")]
+    follow_symlinks: bool
 }
 
 impl CopyEvent {
-    pub fn new(source: &Path, destination: &Path, merge: bool, overwrite: bool) -> CopyEvent {
+    pub fn new(source: &Path, destination: &Path, merge: bool, overwrite: impl Into<OverwritePolicy>) -> CopyEvent {
         CopyEvent {
             source: source.to_path_buf(),
             destination: destination.to_path_buf(),
             merge,
-            overwrite
+            overwrite: overwrite.into(),
+            filter: Filter::default(),
+            keep_both: false,
+            rename_template: None,
+            backup: Backup::None,
+            follow_symlinks: false
+        }
+    }
+
+    pub fn with_filter(mut self, filter: Filter) -> CopyEvent {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_keep_both(mut self, keep_both: bool) -> CopyEvent {
+        self.keep_both = keep_both;
+        self
+    }
+
+    pub fn with_rename_template(mut self, template: Option<&str>) -> CopyEvent {
+        self.rename_template = template.map(str::to_string);
+        self
+    }
+
+    pub fn with_backup(mut self, backup: Backup) -> CopyEvent {
+        self.backup = backup;
+        self
+    }
+
+    pub fn with_follow_symlinks(mut self, follow_symlinks: bool) -> CopyEvent {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    fn child(&self, source: &Path, destination: &Path, filter: Filter) -> CopyEvent {
+        CopyEvent {
+            source: source.to_path_buf(),
+            destination: destination.to_path_buf(),
+            filter,
+            ..self.clone()
         }
     }
 
     pub fn source(&self) -> &Path { self.source.as_path() }
     pub fn destination(&self) -> &Path { self.destination.as_path() }
     pub fn merge(&self) -> bool { self.merge }
-    pub fn overwrite(&self) -> bool { self.overwrite }
+    pub fn overwrite(&self) -> bool { self.overwrite.allowed() }
+    pub fn overwrite_policy(&self) -> OverwritePolicy { self.overwrite }
+    pub fn filter(&self) -> &Filter { &self.filter }
+    pub fn keep_both(&self) -> bool { self.keep_both }
+    pub fn rename_template(&self) -> &str { self.rename_template.as_deref().unwrap_or(DEFAULT_RENAME_TEMPLATE) }
+    pub fn backup(&self) -> &Backup { &self.backup }
+    pub fn follow_symlinks(&self) -> bool { self.follow_symlinks }
 }
 
 pub fn atomize<E: Entry, F: ReadableFileSystem<Item=E>>(event: &CopyEvent, fs: &F, guard: &mut dyn Guard) -> Result<AtomicTransaction, DomainError> {
+    copy(event, fs, guard, &mut HashMap::new())
+}
+
+//Files linked more than once in the source are copied once, then linked to that first copy
+fn copy<E: Entry, F: ReadableFileSystem<Item=E>>(event: &CopyEvent, fs: &F, guard: &mut dyn Guard, links: &mut HashMap<(u64, u64), PathBuf>) -> Result<AtomicTransaction, DomainError> {
     let source = fs.status(event.source())?;
 
     if !source.exists() {
         return Err(DomainError::SourceDoesNotExists(event.source().to_path_buf()))
     }
 
+    //Children of a linked directory are reached through the link
+    if source.is_dir() && source.is_symlink()
+        && !guard.authorize_entry(Capability::FollowSymlinks, event.follow_symlinks(), event.source(), source.is_dir())?.is_allowed() {
+        return Ok(AtomicTransaction::default());
+    }
+
     let mut transaction = AtomicTransaction::default();
     let destination = fs.status(event.destination())?;
 
@@ -79,40 +155,88 @@ pub fn atomize<E: Entry, F: ReadableFileSystem<Item=E>>(event: &CopyEvent, fs: &
     if destination.exists() {
         if source.is_dir() {
             if destination.is_dir() {
-                if guard.authorize(Capability::Merge, event.merge(), event.destination())? {
-                    for child in fs.read_dir(source.path())? {
+                match conflict::resolve(guard, Capability::Merge, event.merge(), event.keep_both(), event.destination(), destination.is_dir())? {
+                    Authorization::Allow => {
+                        let filter = event.filter().descend(source.path(), source.source());
+                        for child in fs.read_dir(source.path())? {
+                            if !filter.accepts(child.path(), child.is_dir()) {
+                                continue;
+                            }
+                            transaction.merge(
+                                copy(
+                                    &event.child(
+                                        child.path(),
+                                        destination.path()
+                                            .join(child.name().unwrap())
+                                            .as_path(),
+                                        filter.clone()
+                                    ),
+                                    fs,
+                                    guard,
+                                    links
+                                )?
+                            );
+                        }
+                    },
+                    Authorization::Rename => {
+                        let renamed = conflict::keep_both(fs, destination.path(), true, event.rename_template())?;
                         transaction.merge(
-                            atomize(
-                                &CopyEvent::new(
-                                    child.path(),
-                                    destination.path()
-                                        .join(child.name().unwrap())
-                                        .as_path(),
-                                        event.merge(),
-                                        event.overwrite()
-                                ),
-                                fs, 
-                                guard
-                            )?
+                            copy(&event.child(source.path(), renamed.as_path(), event.filter().clone()), fs, guard, links)?
                         );
-                    }
+                    },
+                    Authorization::Skip => {}
                 }
             } else {
                 return Err(DomainError::MergeFileWithDirectory(source.to_path(), destination.to_path()))
             }
         } else if source.is_file() {
             if destination.is_file() {
-                if guard.authorize(Capability::Overwrite, event.overwrite(), event.destination())? {
-                    transaction.add(Atomic::RemoveFile(destination.to_path()));
-                    transaction.add(Atomic::CopyFileToFile {
-                        source: source.to_path(),
-                        destination: destination.to_path()
-                    });
+                //Up to date destinations are left alone, without asking the guard
+                if event.overwrite_policy().applies(&source, &destination) {
+                    match conflict::resolve(guard, Capability::Overwrite, event.overwrite(), event.keep_both(), event.destination(), destination.is_dir())? {
+                        Authorization::Allow => {
+                            event.backup().atomize(fs, destination.path(), &mut transaction)?;
+                            transaction.add(Atomic::CopyFileToFile {
+                                source: source.to_path(),
+                                destination: destination.to_path()
+                            });
+                        },
+                        Authorization::Rename => {
+                            transaction.add(Atomic::CopyFileToFile {
+                                source: source.to_path(),
+                                destination: conflict::keep_both(fs, destination.path(), false, event.rename_template())?
+                            });
+                        },
+                        Authorization::Skip => {}
+                    }
                 }
             } else {
                 return Err(DomainError::OverwriteDirectoryWithFile(source.to_path(), destination.to_path()))
             }
         }
+    } else if source.is_dir() && !event.filter().is_empty() {
+        //A bound directory would bring every child along
+        transaction.add(Atomic::CreateEmptyDirectory(destination.to_path()));
+        let filter = event.filter().descend(source.path(), source.source());
+        for child in fs.read_dir(source.path())? {
+            if !filter.accepts(child.path(), child.is_dir()) {
+                continue;
+            }
+            transaction.merge(
+                copy(
+                    &event.child(
+                        child.path(),
+                        destination.path()
+                            .join(child.name().unwrap())
+                            .as_path(),
+                        filter.clone()
+                    ),
+                    fs,
+                    guard,
+                    links
+                )?
+            );
+        }
     } else if source.is_dir() {
         transaction.add(Atomic::BindDirectoryToDirectory {
             source: source.to_path(),
@@ -120,25 +244,37 @@ pub fn atomize<E: Entry, F: ReadableFileSystem<Item=E>>(event: &CopyEvent, fs: &
         });
         for child in fs.read_maintained(source.path())? {
             transaction.merge(
-                atomize(
-                    &CopyEvent::new(
+                copy(
+                    &event.child(
                         child.path(),
                         destination.path()
                             .join(child.name().unwrap())
                             .as_path(),
-                            event.merge(),
-                            event.overwrite()                            
+                        event.filter().clone()
                     ),
-                    fs, 
-                    guard
+                    fs,
+                    guard,
+                    links
                 )?
             );
         }
     } else if source.is_file() {
-        transaction.add(Atomic::CopyFileToFile{
-            source: source.to_path(),
-            destination: destination.to_path()
-        });
+        let identity = fs.hard_link_identity(source.path())?;
+        match identity.and_then(|identity| links.get(&identity)) {
+            Some(linked) => transaction.add(Atomic::HardLink {
+                source: linked.clone(),
+                destination: destination.to_path()
+            }),
+            None => {
+                if let Some(identity) = identity {
+                    links.insert(identity, destination.to_path());
+                }
+                transaction.add(Atomic::CopyFileToFile{
+                    source: source.to_path(),
+                    destination: destination.to_path()
+                });
+            }
+        }
     }
     Ok(transaction)
 } 
@@ -157,7 +293,8 @@ mod real_tests {
         },
         capability::{
             ZealedGuard
-        }
+        },
+        event::{ Filter, Pattern, OverwritePolicy, Backup }
     };
 
     #[test]
@@ -261,6 +398,166 @@ mod real_tests {
             chroot.join("RDIR2/RFILEB").metadata().unwrap().len()
         )
     }
+
+    #[test]
+    fn copy_operation_dir_filtered(){
+        let chroot = Samples::init_simple_chroot("copy_operation_dir_filtered");
+        let copied = Samples::init_empty_chroot("copy_operation_dir_filtered_destination").join("COPIED");
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+
+        atomize(
+            &CopyEvent::new(
+                chroot.as_path(),
+                copied.as_path(),
+                false,
+                false
+            ).with_filter(Filter::new(vec![Pattern::new("RFILEA").unwrap()], vec![Pattern::new("RDIR2/").unwrap()], false)),
+            &fs,
+            &mut ZealedGuard
+        ).unwrap()
+         .apply(&mut fs)
+         .unwrap();
+
+        assert!(copied.join("RDIR/RFILEA").exists());
+        assert!(!copied.join("RDIR/RFILEB").exists());
+        assert!(!copied.join("RDIR2").exists());
+        assert!(copied.join("RDIR3").is_dir());
+    }
+
+    #[test]
+    fn copy_operation_file_keep_both(){
+        let chroot = Samples::init_simple_chroot("copy_operation_file_keep_both");
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+
+        let a_len = chroot.join("RDIR2/RFILEA").metadata().unwrap().len();
+
+        atomize(
+            &CopyEvent::new(
+                chroot.join("RDIR/RFILEA").as_path(),
+                chroot.join("RDIR2/RFILEA").as_path(),
+                false,
+                false
+            ).with_keep_both(true),
+            &fs,
+            &mut ZealedGuard
+        ).unwrap()
+         .apply(&mut fs)
+         .unwrap();
+
+        assert_eq!(a_len, chroot.join("RDIR2/RFILEA").metadata().unwrap().len());
+        assert!(chroot.join("RDIR2/RFILEA (1)").exists());
+    }
+
+    #[test]
+    fn copy_operation_dir_merge_if_different(){
+        let chroot = Samples::init_simple_chroot("copy_operation_dir_merge_if_different");
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+        std::fs::copy(chroot.join("RDIR/RFILEA"), chroot.join("RDIR2/RFILEA")).unwrap();
+        std::fs::write(chroot.join("RDIR2/RFILEB"), "outdated").unwrap();
+
+        let transaction = atomize(
+            &CopyEvent::new(
+                chroot.join("RDIR").as_path(),
+                chroot.join("RDIR2").as_path(),
+                true,
+                OverwritePolicy::IfDifferent
+            ),
+            &fs,
+            &mut ZealedGuard
+        ).unwrap();
+
+        assert!(!transaction.iter().any(|atomic| match atomic {
+            Atomic::CopyFileToFile { destination, .. } => destination == &chroot.join("RDIR2/RFILEA"),
+            _ => false
+        }));
+
+        transaction.apply(&mut fs).unwrap();
+        assert_eq!(
+            std::fs::read(chroot.join("RDIR/RFILEB")).unwrap(),
+            std::fs::read(chroot.join("RDIR2/RFILEB")).unwrap()
+        );
+    }
+
+    #[test]
+    fn copy_operation_file_overwrite_backup(){
+        let chroot = Samples::init_simple_chroot("copy_operation_file_overwrite_backup");
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+        std::fs::write(chroot.join("RDIR2/RFILEB"), "outdated").unwrap();
+
+        atomize(
+            &CopyEvent::new(
+                chroot.join("RDIR/RFILEB").as_path(),
+                chroot.join("RDIR2/RFILEB").as_path(),
+                false,
+                true
+            ).with_backup(Backup::Simple),
+            &fs,
+            &mut ZealedGuard
+        ).unwrap()
+         .apply(&mut fs)
+         .unwrap();
+
+        assert_eq!(std::fs::read_to_string(chroot.join("RDIR2/RFILEB~")).unwrap(), "outdated");
+        assert_eq!(
+            std::fs::read(chroot.join("RDIR/RFILEB")).unwrap(),
+            std::fs::read(chroot.join("RDIR2/RFILEB")).unwrap()
+        );
+    }
+
+    #[test]
+    fn copy_operation_dir_merge_backup_directory(){
+        let chroot = Samples::init_simple_chroot("copy_operation_dir_merge_backup_directory");
+        let backup = Samples::init_empty_chroot("copy_operation_dir_merge_backup_directory_backup");
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+        std::fs::write(chroot.join("RDIR2/RFILEA"), "outdated a").unwrap();
+        std::fs::write(chroot.join("RDIR2/RFILEB"), "outdated b").unwrap();
+
+        atomize(
+            &CopyEvent::new(
+                chroot.join("RDIR").as_path(),
+                chroot.join("RDIR2").as_path(),
+                true,
+                true
+            ).with_backup(Backup::Directory(backup.clone())),
+            &fs,
+            &mut ZealedGuard
+        ).unwrap()
+         .apply(&mut fs)
+         .unwrap();
+
+        let kept = backup.join(chroot.join("RDIR2").strip_prefix("/").unwrap());
+        assert_eq!(std::fs::read_to_string(kept.join("RFILEA")).unwrap(), "outdated a");
+        assert_eq!(std::fs::read_to_string(kept.join("RFILEB")).unwrap(), "outdated b");
+        assert!(!kept.join("RFILEC").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copy_operation_keeps_hard_links(){
+        use std::{ fs::hard_link, os::unix::fs::MetadataExt };
+
+        let chroot = Samples::init_simple_chroot("copy_operation_keeps_hard_links");
+        hard_link(chroot.join("RDIR/RFILEA"), chroot.join("RDIR/LINKED")).unwrap();
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+
+        let transaction = atomize(
+            &CopyEvent::new(
+                chroot.join("RDIR").as_path(),
+                chroot.join("COPIED").as_path(),
+                false,
+                false
+            ),
+            &fs,
+            &mut ZealedGuard
+        ).unwrap();
+        assert_eq!(transaction.iter().filter(|atomic| matches!(atomic, Atomic::HardLink { .. })).count(), 1);
+        transaction.apply(&mut fs).unwrap();
+
+        let inode = |path: &str| chroot.join(path).metadata().unwrap().ino();
+        assert_eq!(inode("COPIED/RFILEA"), inode("COPIED/LINKED"));
+        assert_ne!(inode("COPIED/RFILEA"), inode("RDIR/RFILEA"));
+        assert_ne!(inode("COPIED/RFILEB"), inode("COPIED/RFILEA"));
+    }
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -279,7 +576,8 @@ mod virtual_tests {
         Kind,
         capability::{
             ZealedGuard
-        }
+        },
+        event::Filter
     };
 
     #[test]
@@ -373,4 +671,51 @@ mod virtual_tests {
         assert!(fs.as_inner().virtual_state().unwrap().is_virtual(samples_path.join("A/C").as_path()).unwrap());
         assert!(fs.as_inner().virtual_state().unwrap().is_file(samples_path.join("A/C").as_path()).unwrap());
     }
+
+    #[test]
+    fn virtual_copy_operation_directory_ignore_files(){
+        let chroot = Samples::init_simple_chroot("virtual_copy_operation_directory_ignore_files");
+        std::fs::write(chroot.join("RDIR/.fcignore"), "RFILEB\n").unwrap();
+        let mut fs = FileSystemAdapter(VirtualFileSystem::default());
+
+        atomize(
+            &CopyEvent::new(
+                chroot.join("RDIR").as_path(),
+                chroot.join("COPIED").as_path(),
+                false,
+                false
+            ).with_filter(Filter::new(vec![], vec![], true)),
+            &fs,
+            &mut ZealedGuard
+        ).unwrap()
+         .apply(&mut fs)
+         .unwrap();
+
+        assert!(fs.status(chroot.join("COPIED/RFILEA").as_path()).unwrap().exists());
+        assert!(fs.status(chroot.join("COPIED/.fcignore").as_path()).unwrap().exists());
+        assert!(!fs.status(chroot.join("COPIED/RFILEB").as_path()).unwrap().exists());
+    }
+
+    #[test]
+    fn virtual_copy_operation_directory_keep_both(){
+        let chroot = Samples::init_simple_chroot("virtual_copy_operation_directory_keep_both");
+        let mut fs = FileSystemAdapter(VirtualFileSystem::default());
+
+        atomize(
+            &CopyEvent::new(
+                chroot.join("RDIR").as_path(),
+                chroot.join("RDIR2").as_path(),
+                false,
+                false
+            ).with_keep_both(true)
+             .with_rename_template(Some("{name}_{n}")),
+            &fs,
+            &mut ZealedGuard
+        ).unwrap()
+         .apply(&mut fs)
+         .unwrap();
+
+        assert!(fs.status(chroot.join("RDIR2_1/RFILEB").as_path()).unwrap().exists());
+        assert!(!fs.status(chroot.join("RDIR2/RFILEB").as_path()).unwrap().exists());
+    }
 }
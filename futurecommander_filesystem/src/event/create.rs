@@ -69,6 +69,7 @@ impl From<SerializableKind> for Kind {
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct CreateEvent {
+    #[serde(with = "crate::lossless")]
     path: PathBuf,
     kind: SerializableKind,
     recursive: bool,
@@ -106,6 +107,21 @@ fn recursive_dir_creation<E, F> (fs: &F, mut ancestors: &mut Ancestors<'_>) -> R
     Ok(transaction)
 }
 
+//Creates directory and its missing ancestors, unless the transaction already does
+pub fn ensure_directory<E: Entry, F: ReadableFileSystem<Item=E>>(fs: &F, directory: &Path, transaction: &mut AtomicTransaction) -> Result<(), DomainError> {
+    let mut missing = Vec::new();
+    for ancestor in directory.ancestors() {
+        if transaction.creates(ancestor) || fs.status(ancestor)?.exists() {
+            break;
+        }
+        missing.push(ancestor.to_path_buf());
+    }
+    for ancestor in missing.into_iter().rev() {
+        transaction.add(Atomic::CreateEmptyDirectory(ancestor));
+    }
+    Ok(())
+}
+
 pub fn atomize<E: Entry, F: ReadableFileSystem<Item=E>>(event: &CreateEvent, fs: &F, guard: &mut dyn Guard) -> Result<AtomicTransaction, DomainError> {
     let entry = fs.status(event.path())?;
     let mut transaction = AtomicTransaction::default();
@@ -125,7 +141,7 @@ pub fn atomize<E: Entry, F: ReadableFileSystem<Item=E>>(event: &CreateEvent, fs:
         },
         Kind::File => {
             if entry.exists() {
-                if guard.authorize(Capability::Overwrite, event.overwrite(), event.path())? {
+                if guard.authorize_entry(Capability::Overwrite, event.overwrite(), event.path(), entry.is_dir())?.is_allowed() {
                     if event.recursive() {
                         transaction.merge(recursive_dir_creation(fs, &mut ancestors)?);
                     }
@@ -0,0 +1,154 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    env::{ current_dir, var_os },
+    fmt::{ Display, Formatter, Result as FmtResult },
+    path::Path
+};
+
+use serde::{ Serialize, Deserialize };
+
+use crate::{
+    errors::DomainError,
+    tools::{ normalize, canonical }
+};
+
+/*
+Targets removed or moved away only when explicitly asked for, whatever the guard.
+The filesystem root is always refused, like rm --preserve-root.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Danger {
+    Root,
+    Home,
+    MountPoint,
+    WorkingDirectory
+}
+
+#[cfg(unix)]
+fn is_mount_point(path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (path.symlink_metadata(), path.join("..").metadata()) {
+        (Ok(metadata), Ok(parent)) => metadata.is_dir() && metadata.dev() != parent.dev(),
+        _ => false
+    }
+}
+
+#[cfg(not(unix))]
+fn is_mount_point(_path: &Path) -> bool {
+    false
+}
+
+impl Danger {
+    /*
+    The working directory is the one of the session emitting the event, the one of the process when not known.
+    Only the top of a removal or a move is classified, its children cannot be more dangerous.
+    A link itself is what gets removed or moved, so the last component of path is not resolved.
+    */
+    pub fn classify(path: &Path, cwd: Option<&Path>) -> Option<Danger> {
+        let path = normalize(path);
+        let path = match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => canonical(parent).join(name),
+            _ => path
+        };
+        let path = path.as_path();
+        let cwd = match cwd {
+            Some(cwd) => Some(canonical(cwd)),
+            None => current_dir().ok().map(|cwd| canonical(cwd.as_path()))
+        };
+        let home = var_os("HOME").map(|home| canonical(Path::new(&home)));
+
+        if path.parent().is_none() {
+            Some(Danger::Root)
+        } else if matches!(home, Some(home) if home.starts_with(path)) {
+            Some(Danger::Home)
+        } else if matches!(cwd, Some(cwd) if cwd.starts_with(path)) {
+            Some(Danger::WorkingDirectory)
+        } else if is_mount_point(path) {
+            Some(Danger::MountPoint)
+        } else {
+            None
+        }
+    }
+
+    //Nothing but the root stays refused once dangerous targets are explicitly allowed
+    pub fn check(path: &Path, cwd: Option<&Path>, allowed: bool) -> Result<(), DomainError> {
+        match Self::classify(path, cwd) {
+            Some(Danger::Root) => Err(DomainError::DangerousTarget(path.to_path_buf(), Danger::Root)),
+            Some(danger) if !allowed => Err(DomainError::DangerousTarget(path.to_path_buf(), danger)),
+            _ => Ok(())
+        }
+    }
+}
+
+impl Display for Danger {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{}",
+            match self {
+                Danger::Root => "the filesystem root",
+                Danger::Home => "the home directory or one of its ancestors",
+                Danger::MountPoint => "a mount point",
+                Danger::WorkingDirectory => "the working directory or one of its ancestors"
+            }
+        )
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    use crate::sample::Samples;
+
+    #[test]
+    fn classify_dangerous_targets() {
+        let chroot = Samples::init_simple_chroot("danger_classify_dangerous_targets");
+
+        assert_eq!(Danger::classify(Path::new("/"), None), Some(Danger::Root));
+        assert_eq!(Danger::classify(current_dir().unwrap().as_path(), None), Some(Danger::WorkingDirectory));
+        assert_eq!(Danger::classify(chroot.join("RDIR").as_path(), None), None);
+        assert_eq!(Danger::classify(chroot.join("RDIR").as_path(), Some(chroot.join("RDIR/NESTED").as_path())), Some(Danger::WorkingDirectory));
+        assert_eq!(Danger::classify(chroot.join("RDIR3/../RDIR").as_path(), Some(chroot.join("RDIR/NESTED").as_path())), Some(Danger::WorkingDirectory));
+        assert_eq!(Danger::classify(chroot.join("RDIR/..").as_path(), Some(chroot.join("RDIR").as_path())), Some(Danger::WorkingDirectory));
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(chroot.join("RDIR"), chroot.join("LINK")).unwrap();
+            assert_eq!(Danger::classify(chroot.join("RDIR").as_path(), Some(chroot.join("LINK/NESTED").as_path())), Some(Danger::WorkingDirectory));
+            //Removing the link leaves the working directory in place
+            assert_eq!(Danger::classify(chroot.join("LINK").as_path(), Some(chroot.join("RDIR").as_path())), None);
+        }
+        if let Some(home) = var_os("HOME").map(PathBuf::from).filter(|home| home.parent().is_some()) {
+            assert_eq!(Danger::classify(home.as_path(), None), Some(Danger::Home));
+        }
+        #[cfg(target_os = "linux")]
+        assert_eq!(Danger::classify(Path::new("/proc"), None), Some(Danger::MountPoint));
+
+        assert!(matches!(Danger::check(Path::new("/"), None, true), Err(DomainError::DangerousTarget(_, Danger::Root))));
+        assert!(Danger::check(current_dir().unwrap().as_path(), None, true).is_ok());
+        assert!(Danger::check(chroot.join("RDIR").as_path(), None, false).is_ok());
+    }
+}
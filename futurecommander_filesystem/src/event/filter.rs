@@ -0,0 +1,345 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    convert::TryFrom,
+    fmt::{ Display, Formatter, Result as FmtResult },
+    fs::read_to_string,
+    path::{ Path, PathBuf }
+};
+
+use serde::{ Serialize, Deserialize };
+
+use glob::{ Pattern as Glob, MatchOptions };
+use regex::Regex;
+
+use crate::{
+    errors::DomainError,
+    event::is_false
+};
+
+const IGNORE_FILES : [&str; 2] = [".fcignore", ".gitignore"];
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Glob(Glob),
+    Regex(Regex)
+}
+
+/*
+Glob by default, regex when prefixed by "re:".
+Like in .gitignore, a glob without separator matches the name at any depth,
+a leading separator anchors it and a trailing separator restricts it to directories.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(try_from = "String", into = "String")]
+pub struct Pattern {
+    text: String,
+    anchored: bool,
+    directory_only: bool,
+    matcher: Matcher
+}
+
+impl Pattern {
+    pub fn new(text: &str) -> Result<Pattern, DomainError> {
+        let invalid = |reason: String| DomainError::InvalidPattern(text.to_string(), reason);
+
+        if let Some(expression) = text.strip_prefix("re:") {
+            return Ok(Pattern {
+                text: text.to_string(),
+                anchored: true,
+                directory_only: false,
+                matcher: Matcher::Regex(Regex::new(expression).map_err(|error| invalid(error.to_string()))?)
+            });
+        }
+
+        let directory_only = text.ends_with('/');
+        let glob = text.trim_end_matches('/');
+        let anchored = glob.contains('/');
+        let glob = glob.trim_start_matches('/');
+        if glob.is_empty() {
+            return Err(invalid("empty pattern".to_string()));
+        }
+
+        Ok(Pattern {
+            text: text.to_string(),
+            anchored,
+            directory_only,
+            matcher: Matcher::Glob(Glob::new(glob).map_err(|error| invalid(error.to_string()))?)
+        })
+    }
+
+    pub fn as_str(&self) -> &str { self.text.as_str() }
+
+    //Relative path from the directory the pattern applies to
+    pub fn matches(&self, relative: &Path, is_dir: bool) -> bool {
+        if self.directory_only && ! is_dir {
+            return false;
+        }
+
+        match &self.matcher {
+            Matcher::Regex(regex) => regex.is_match(relative.to_string_lossy().as_ref()),
+            Matcher::Glob(glob) => {
+                let options = MatchOptions {
+                    require_literal_separator: true,
+                    ..MatchOptions::new()
+                };
+                if self.anchored {
+                    glob.matches_path_with(relative, options)
+                } else {
+                    relative.file_name()
+                        .map(|name| glob.matches_with(name.to_string_lossy().as_ref(), options))
+                        .unwrap_or(false)
+                }
+            }
+        }
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Pattern) -> bool {
+        self.text == other.text
+    }
+}
+
+impl TryFrom<String> for Pattern {
+    type Error = DomainError;
+
+    fn try_from(text: String) -> Result<Pattern, DomainError> {
+        Pattern::new(text.as_str())
+    }
+}
+
+impl From<Pattern> for String {
+    fn from(pattern: Pattern) -> String {
+        pattern.text
+    }
+}
+
+/*
+Decides which children are visited when an event recurses.
+The top level entry of an event is never filtered.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Filter {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    include: Vec<Pattern>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    exclude: Vec<Pattern>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    ignore_files: bool,
+    //Runtime state of a recursion : event root, and rules read from ignore files along the way
+    #[serde(skip)]
+    root: Option<PathBuf>,
+    #[serde(skip)]
+    ignored: Vec<IgnoreRule>
+}
+
+//Pattern read from an ignore file, relative to the directory holding it
+#[derive(Debug, Clone, PartialEq)]
+struct IgnoreRule {
+    directory: PathBuf,
+    negated: bool,
+    pattern: Pattern
+}
+
+impl Filter {
+    pub fn new(include: Vec<Pattern>, exclude: Vec<Pattern>, ignore_files: bool) -> Filter {
+        Filter {
+            include,
+            exclude,
+            ignore_files,
+            ..Filter::default()
+        }
+    }
+
+    pub fn include(&self) -> &[Pattern] { &self.include }
+    pub fn exclude(&self) -> &[Pattern] { &self.exclude }
+    pub fn ignore_files(&self) -> bool { self.ignore_files }
+
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty() && ! self.ignore_files
+    }
+
+    //Filter applying to the children of directory, real holds its content if any
+    pub fn descend(&self, directory: &Path, real: Option<&Path>) -> Filter {
+        let mut filter = self.clone();
+        if filter.root.is_none() {
+            filter.root = Some(directory.to_path_buf());
+        }
+
+        if self.ignore_files {
+            if let Some(real) = real {
+                for name in IGNORE_FILES.iter() {
+                    if let Ok(content) = read_to_string(real.join(name)) {
+                        for line in content.lines().map(str::trim) {
+                            if line.is_empty() || line.starts_with('#') {
+                                continue;
+                            }
+                            //A leading "!" re-includes what an earlier rule excluded, "\!" and "\#" are literal
+                            let (negated, line) = match line.strip_prefix('!') {
+                                Some(negated) => (true, negated),
+                                None => (false, line.strip_prefix('\\').unwrap_or(line))
+                            };
+                            if let Ok(pattern) = Pattern::new(line) {
+                                filter.ignored.push(IgnoreRule { directory: directory.to_path_buf(), negated, pattern });
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        filter
+    }
+
+    pub fn accepts(&self, path: &Path, is_dir: bool) -> bool {
+        let relative = match self.root.as_ref().and_then(|root| path.strip_prefix(root).ok()) {
+            Some(relative) => relative,
+            None => return true
+        };
+
+        if self.exclude.iter().any(|pattern| pattern.matches(relative, is_dir)) {
+            return false;
+        }
+
+        //Like in .gitignore, the last matching rule wins and deeper files come last
+        let ignored = self.ignored.iter()
+            .rev()
+            .find(|rule|
+                path.strip_prefix(&rule.directory)
+                    .map(|relative| rule.pattern.matches(relative, is_dir))
+                    .unwrap_or(false)
+            )
+            .map(|rule| ! rule.negated)
+            .unwrap_or(false);
+        if ignored {
+            return false;
+        }
+
+        //Directories are traversed so that included files can be reached
+        is_dir || self.include.is_empty() || self.include.iter().any(|pattern| pattern.matches(relative, is_dir))
+    }
+}
+
+impl Display for Filter {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        for pattern in self.include.iter() {
+            write!(f, " --include {}", pattern.as_str())?;
+        }
+        for pattern in self.exclude.iter() {
+            write!(f, " --exclude {}", pattern.as_str())?;
+        }
+        if self.ignore_files {
+            write!(f, " --ignore-files")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{
+        fs::write
+    };
+
+    use crate::{
+        sample::Samples
+    };
+
+    fn patterns(texts: &[&str]) -> Vec<Pattern> {
+        texts.iter().map(|text| Pattern::new(text).unwrap()).collect()
+    }
+
+    #[test]
+    fn glob_patterns() {
+        let name = Pattern::new("*.o").unwrap();
+        assert!(name.matches(Path::new("main.o"), false));
+        assert!(name.matches(Path::new("src/deep/main.o"), false));
+
+        let anchored = Pattern::new("/src/*.rs").unwrap();
+        assert!(anchored.matches(Path::new("src/main.rs"), false));
+        assert!(!anchored.matches(Path::new("src/bin/main.rs"), false));
+
+        let directory = Pattern::new("target/").unwrap();
+        assert!(directory.matches(Path::new("target"), true));
+        assert!(directory.matches(Path::new("sub/target"), true));
+        assert!(!directory.matches(Path::new("target"), false));
+    }
+
+    #[test]
+    fn regex_patterns() {
+        let pattern = Pattern::new("re:^build/.*\\.log$").unwrap();
+        assert!(pattern.matches(Path::new("build/out.log"), false));
+        assert!(!pattern.matches(Path::new("src/build/out.log"), false));
+
+        match Pattern::new("re:(") {
+            Err(DomainError::InvalidPattern(text, _)) => assert_eq!(text, "re:("),
+            _ => panic!("invalid regex should be refused")
+        }
+    }
+
+    #[test]
+    fn include_and_exclude() {
+        let filter = Filter::new(patterns(&["*.rs"]), patterns(&["node_modules/"]), false)
+            .descend(Path::new("/project"), None);
+
+        assert!(filter.accepts(Path::new("/project/src"), true));
+        assert!(filter.accepts(Path::new("/project/src/lib.rs"), false));
+        assert!(!filter.accepts(Path::new("/project/README.md"), false));
+        assert!(!filter.accepts(Path::new("/project/web/node_modules"), true));
+    }
+
+    #[test]
+    fn ignore_files() {
+        let chroot = Samples::init_simple_chroot("filter_ignore_files");
+        write(chroot.join(".gitignore"), "RFILE*\n").unwrap();
+        write(chroot.join("RDIR/.gitignore"), "# build output\nRFILEA\n!RFILEB\n").unwrap();
+
+        let filter = Filter::new(vec![], vec![], true)
+            .descend(chroot.as_path(), Some(chroot.as_path()))
+            .descend(chroot.join("RDIR").as_path(), Some(chroot.join("RDIR").as_path()));
+
+        assert!(!filter.accepts(chroot.join("RDIR/RFILEA").as_path(), false));
+        assert!(filter.accepts(chroot.join("RDIR/RFILEB").as_path(), false));
+        assert!(!filter.accepts(chroot.join("RDIR2/RFILEA").as_path(), false));
+    }
+
+    #[test]
+    fn ignore_files_negation_last_match_wins() {
+        let chroot = Samples::init_simple_chroot("filter_ignore_files_negation");
+        write(chroot.join("RDIR/.gitignore"), "RFILE*\n!RFILEB\nRFILEB\n!RFILEA\n").unwrap();
+
+        let filter = Filter::new(vec![], vec![], true)
+            .descend(chroot.join("RDIR").as_path(), Some(chroot.join("RDIR").as_path()));
+
+        assert!(filter.accepts(chroot.join("RDIR/RFILEA").as_path(), false));
+        assert!(!filter.accepts(chroot.join("RDIR/RFILEB").as_path(), false));
+    }
+
+    #[test]
+    fn serialize_as_strings() {
+        let filter = Filter::new(patterns(&["*.rs"]), patterns(&["re:~$", "target/"]), true);
+        let json = serde_json::to_string(&filter).unwrap();
+        assert_eq!(json, "{\"include\":[\"*.rs\"],\"exclude\":[\"re:~$\",\"target/\"],\"ignore_files\":true}");
+        assert_eq!(serde_json::from_str::<Filter>(json.as_str()).unwrap(), filter);
+    }
+}
@@ -0,0 +1,162 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::path::{ Path, PathBuf };
+
+use serde::{ Serialize, Deserialize };
+
+use crate::{
+    errors::DomainError,
+    capability::Guard,
+    port::{
+        Entry,
+        ReadableFileSystem,
+        AtomicTransaction,
+        Atomic
+    }
+};
+
+/*
+Gives the file at source a second name at destination, like ln does without -s.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LinkEvent {
+    #[serde(with = "crate::lossless")]
+    source: PathBuf,
+    #[serde(with = "crate::lossless")]
+    destination: PathBuf
+}
+
+impl LinkEvent {
+    pub fn new(source: &Path, destination: &Path) -> LinkEvent {
+        LinkEvent {
+            source: source.to_path_buf(),
+            destination: destination.to_path_buf()
+        }
+    }
+
+    pub fn source(&self) -> &Path { self.source.as_path() }
+    pub fn destination(&self) -> &Path { self.destination.as_path() }
+}
+
+pub fn atomize<E: Entry, F: ReadableFileSystem<Item=E>>(event: &LinkEvent, fs: &F, _guard: &mut dyn Guard) -> Result<AtomicTransaction, DomainError> {
+    let source = fs.status(event.source())?;
+    let destination = fs.status(event.destination())?;
+
+    if !source.exists() {
+        return Err(DomainError::SourceDoesNotExists(source.to_path()));
+    }
+    if source.is_dir() {
+        return Err(DomainError::LinkToDirectory(source.to_path()));
+    }
+    if destination.exists() {
+        return Err(DomainError::OverwriteNotAllowed(destination.to_path()));
+    }
+
+    let mut transaction = AtomicTransaction::default();
+    transaction.add(Atomic::HardLink {
+        source: source.to_path(),
+        destination: destination.to_path()
+    });
+    Ok(transaction)
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(all(test, unix))]
+mod real_tests {
+    use super::*;
+
+    use std::{
+        fs::write,
+        os::unix::fs::MetadataExt
+    };
+
+    use crate::{
+        sample::Samples,
+        port::FileSystemAdapter,
+        infrastructure::RealFileSystem,
+        capability::ZealedGuard
+    };
+
+    #[test]
+    fn link_operation_file() {
+        let chroot = Samples::init_simple_chroot("link_operation_file");
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+
+        atomize(
+            &LinkEvent::new(chroot.join("RDIR/RFILEA").as_path(), chroot.join("LINKED").as_path()),
+            &fs,
+            &mut ZealedGuard
+        ).unwrap()
+         .apply(&mut fs)
+         .unwrap();
+
+        write(chroot.join("LINKED"), "changed").unwrap();
+        assert_eq!(chroot.join("RDIR/RFILEA").metadata().unwrap().ino(), chroot.join("LINKED").metadata().unwrap().ino());
+        assert_eq!(std::fs::read_to_string(chroot.join("RDIR/RFILEA")).unwrap(), "changed");
+    }
+
+    #[test]
+    fn link_operation_refuses_directories_and_existing_destinations() {
+        let chroot = Samples::init_simple_chroot("link_operation_refuses");
+        let fs = FileSystemAdapter(RealFileSystem::default());
+
+        assert!(matches!(
+            atomize(&LinkEvent::new(chroot.join("RDIR").as_path(), chroot.join("LINKED").as_path()), &fs, &mut ZealedGuard),
+            Err(DomainError::LinkToDirectory(_))
+        ));
+        assert!(matches!(
+            atomize(&LinkEvent::new(chroot.join("RDIR/RFILEA").as_path(), chroot.join("RDIR/RFILEB").as_path()), &fs, &mut ZealedGuard),
+            Err(DomainError::OverwriteNotAllowed(_))
+        ));
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod virtual_tests {
+    use super::*;
+
+    use crate::{
+        sample::Samples,
+        port::FileSystemAdapter,
+        infrastructure::VirtualFileSystem,
+        capability::ZealedGuard
+    };
+
+    #[test]
+    fn virtual_link_operation_file() {
+        let chroot = Samples::init_simple_chroot("virtual_link_operation_file");
+        let mut fs = FileSystemAdapter(VirtualFileSystem::default());
+
+        atomize(
+            &LinkEvent::new(chroot.join("RDIR/RFILEA").as_path(), chroot.join("LINKED").as_path()),
+            &fs,
+            &mut ZealedGuard
+        ).unwrap()
+         .apply(&mut fs)
+         .unwrap();
+
+        let linked = fs.status(chroot.join("LINKED").as_path()).unwrap();
+        assert!(linked.exists());
+        assert!(linked.is_file());
+        assert_eq!(linked.source(), Some(chroot.join("RDIR/RFILEA").as_path()));
+        assert!(!chroot.join("LINKED").exists());
+    }
+}
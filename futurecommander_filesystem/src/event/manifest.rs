@@ -0,0 +1,401 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    collections::{ BTreeMap, HashSet },
+    ffi::OsString,
+    path::{ Path, PathBuf }
+};
+
+use serde::{ Serialize, Deserialize };
+
+use crate::{
+    Kind,
+    errors::DomainError,
+    capability::RegistrarGuard,
+    tools::normalize,
+    port::{
+        Entry,
+        ReadableFileSystem
+    },
+    event::{
+        is_false,
+        Listener,
+        Delayer,
+        FileSystemEvent,
+        CreateEvent,
+        CopyEvent,
+        RemoveEvent,
+        SyncComparison
+    }
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ManifestEntry {
+    #[serde(with = "crate::lossless")]
+    path: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "crate::lossless::option")]
+    from: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    directory: bool
+}
+
+impl ManifestEntry {
+    pub fn new(path: &Path, from: Option<&Path>, directory: bool) -> ManifestEntry {
+        ManifestEntry {
+            path: path.to_path_buf(),
+            from: from.map(Path::to_path_buf),
+            directory
+        }
+    }
+
+    pub fn path(&self) -> &Path { self.path.as_path() }
+    pub fn from(&self) -> Option<&Path> { self.from.as_deref() }
+    pub fn directory(&self) -> bool { self.directory }
+}
+
+/*
+Desired layout of the root directory.
+Entry paths are relative to the root, while sources and a relative root are relative to the manifest directory.
+An entry without source is an empty directory or file which only has to exist.
+With prune, anything under the root which is neither described nor an ancestor of a described entry is removed,
+and so is anything in a copied directory which is not in its source.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Manifest {
+    #[serde(with = "crate::lossless")]
+    root: PathBuf,
+    #[serde(default, skip_serializing_if = "is_false")]
+    prune: bool,
+    #[serde(default)]
+    entries: Vec<ManifestEntry>
+}
+
+impl Manifest {
+    pub fn new(root: &Path, prune: bool) -> Manifest {
+        Manifest {
+            root: root.to_path_buf(),
+            prune,
+            entries: Vec::new()
+        }
+    }
+
+    pub fn add(&mut self, entry: ManifestEntry) {
+        self.entries.push(entry);
+    }
+
+    pub fn root(&self) -> &Path { self.root.as_path() }
+    pub fn prune(&self) -> bool { self.prune }
+    pub fn entries(&self) -> &[ManifestEntry] { &self.entries }
+
+    pub fn from_toml(content: &str) -> Result<Manifest, DomainError> {
+        Ok(toml::from_str(content)?)
+    }
+
+    pub fn from_yaml(content: &str) -> Result<Manifest, DomainError> {
+        Ok(serde_yaml::from_str(content)?)
+    }
+
+    /*
+    Every event is emitted as soon as it is computed, so the next decisions are taken against the view it produces.
+    Returns the staged events in order.
+    */
+    pub fn reconcile<E, L>(&self, cwd: &Path, listener: &mut L) -> Result<Vec<FileSystemEvent>, DomainError>
+        where E: Entry,
+              L: Listener + Delayer + ReadableFileSystem<Item=E> {
+
+        let root = normalize(&cwd.join(self.root()));
+        let mut targets : BTreeMap<PathBuf, &ManifestEntry> = BTreeMap::new();
+        for entry in self.entries.iter() {
+            let target = normalize(&root.join(entry.path()));
+            if ! target.starts_with(root.as_path()) {
+                return Err(DomainError::OutsideOfRoot(target, root));
+            }
+            targets.insert(target, entry);
+        }
+
+        let mut reconciliation = Reconciliation {
+            listener,
+            prune: self.prune(),
+            described: targets.keys().cloned().collect(),
+            staged: Vec::new()
+        };
+
+        if self.prune() {
+            reconciliation.prune(root.as_path(), &targets)?;
+        }
+
+        for (target, entry) in targets.iter() {
+            match entry.from() {
+                Some(from) => reconciliation.copy(normalize(&cwd.join(from)).as_path(), target)?,
+                None => reconciliation.create(target, if entry.directory() { Kind::Directory } else { Kind::File })?
+            }
+        }
+
+        Ok(reconciliation.staged)
+    }
+}
+
+struct Reconciliation<'a, L> {
+    listener: &'a mut L,
+    prune: bool,
+    described: Vec<PathBuf>,
+    staged: Vec<FileSystemEvent>
+}
+
+impl <'a, E, L> Reconciliation<'a, L>
+    where E: Entry,
+          L: Listener + Delayer + ReadableFileSystem<Item=E> {
+
+    fn stage(&mut self, event: FileSystemEvent) -> Result<(), DomainError> {
+        let guard = self.listener.emit(&event, RegistrarGuard::default())?;
+        self.listener.delay(event.clone(), guard);
+        self.staged.push(event);
+        Ok(())
+    }
+
+    fn ensure_parent(&mut self, target: &Path) -> Result<(), DomainError> {
+        if let Some(parent) = target.parent() {
+            if ! self.listener.status(parent)?.exists() {
+                self.stage(FileSystemEvent::Create(CreateEvent::new(parent, Kind::Directory, true, false)))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn prune(&mut self, directory: &Path, targets: &BTreeMap<PathBuf, &ManifestEntry>) -> Result<(), DomainError> {
+        for child in self.listener.read_dir(directory)?.sort().into_iter() {
+            let path = child.to_path();
+            match targets.get(&path) {
+                Some(entry) if entry.directory() && child.is_dir() => self.prune(path.as_path(), targets)?,
+                Some(_) => {},
+                //Content of copied directories is described by their source, it is pruned along the copy
+                None if targets.iter().any(|(target, entry)| entry.from().is_some() && path.starts_with(target)) => {},
+                None if child.is_dir() && targets.keys().any(|target| target.starts_with(path.as_path())) =>
+                    self.prune(path.as_path(), targets)?,
+                None => self.stage(FileSystemEvent::Remove(RemoveEvent::new(path.as_path(), true)))?
+            }
+        }
+        Ok(())
+    }
+
+    fn create(&mut self, target: &Path, kind: Kind) -> Result<(), DomainError> {
+        let status = self.listener.status(target)?;
+        let satisfied = match kind {
+            Kind::Directory => status.is_dir(),
+            _ => status.is_file()
+        };
+
+        if ! satisfied {
+            if status.exists() {
+                self.stage(FileSystemEvent::Remove(RemoveEvent::new(target, true)))?;
+            }
+            self.ensure_parent(target)?;
+            self.stage(FileSystemEvent::Create(CreateEvent::new(target, kind, false, false)))?;
+        }
+        Ok(())
+    }
+
+    fn copy(&mut self, source: &Path, target: &Path) -> Result<(), DomainError> {
+        let source_status = self.listener.status(source)?;
+        if ! source_status.exists() {
+            return Err(DomainError::SourceDoesNotExists(source.to_path_buf()));
+        }
+
+        let status = self.listener.status(target)?;
+        if source_status.is_dir() && status.is_dir() {
+            let children = self.listener.read_dir(source)?.sort();
+            if self.prune {
+                let names : HashSet<OsString> = children.iter().filter_map(|child| child.name().map(OsString::from)).collect();
+                for extraneous in self.listener.read_dir(target)?.sort().into_iter() {
+                    let path = extraneous.to_path();
+                    if ! extraneous.name().is_some_and(|name| names.contains(name))
+                        && ! self.described.iter().any(|described| described.starts_with(path.as_path())) {
+                        self.stage(FileSystemEvent::Remove(RemoveEvent::new(path.as_path(), true)))?;
+                    }
+                }
+            }
+
+            for child in children.into_iter() {
+                self.copy(child.path(), target.join(child.name().unwrap()).as_path())?;
+            }
+            return Ok(());
+        }
+
+        if source_status.is_file() && status.is_file() {
+            if SyncComparison::Content.unchanged(&source_status, &status) {
+                return Ok(());
+            }
+            return self.stage(FileSystemEvent::Copy(CopyEvent::new(source, target, false, true)));
+        }
+
+        if status.exists() {
+            self.stage(FileSystemEvent::Remove(RemoveEvent::new(target, true)))?;
+        }
+        self.ensure_parent(target)?;
+        self.stage(FileSystemEvent::Copy(CopyEvent::new(source, target, false, false)))
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{
+        fs::write
+    };
+
+    use crate::{
+        sample::Samples,
+        Container
+    };
+
+    fn render(events: &[FileSystemEvent]) -> Vec<String> {
+        events.iter().map(FileSystemEvent::to_string).collect()
+    }
+
+    #[test]
+    fn reconcile_missing_entries() {
+        let chroot = Samples::init_simple_chroot("reconcile_missing_entries");
+        let mut container = Container::new();
+
+        let manifest = Manifest::from_toml(r#"
+root = "SITE"
+
+[[entries]]
+path = "assets/A"
+from = "RDIR/RFILEA"
+
+[[entries]]
+path = "cache"
+directory = true
+
+[[entries]]
+path = "copy"
+from = "RDIR"
+"#).unwrap();
+
+        let staged = manifest.reconcile(chroot.as_path(), &mut container).unwrap();
+        let site = chroot.join("SITE");
+        assert_eq!(
+            render(&staged),
+            vec![
+                format!("mkdir -r {}", site.join("assets").to_string_lossy()),
+                format!("cp {} {}", chroot.join("RDIR/RFILEA").to_string_lossy(), site.join("assets/A").to_string_lossy()),
+                format!("mkdir {}", site.join("cache").to_string_lossy()),
+                format!("cp {} {}", chroot.join("RDIR").to_string_lossy(), site.join("copy").to_string_lossy()),
+            ]
+        );
+        assert!(container.status(site.join("copy/RFILEB").as_path()).unwrap().is_file());
+
+        //Already reconciled
+        assert!(manifest.reconcile(chroot.as_path(), &mut container).unwrap().is_empty());
+    }
+
+    #[test]
+    fn reconcile_existing_entries() {
+        let chroot = Samples::init_simple_chroot("reconcile_existing_entries");
+        write(chroot.join("RDIR2/RFILEA"), "changed").unwrap();
+        let mut container = Container::new();
+
+        let mut manifest = Manifest::new(Path::new("RDIR2"), true);
+        manifest.add(ManifestEntry::new(Path::new("."), Some(Path::new("RDIR")), false));
+        manifest.add(ManifestEntry::new(Path::new("RFILEC"), None, true));
+
+        let staged = manifest.reconcile(chroot.as_path(), &mut container).unwrap();
+        let site = chroot.join("RDIR2");
+        assert_eq!(
+            render(&staged),
+            vec![
+                format!("cp -o {} {}", chroot.join("RDIR/RFILEA").to_string_lossy(), site.join("RFILEA").to_string_lossy()),
+                format!("cp {} {}", chroot.join("RDIR/RFILEB").to_string_lossy(), site.join("RFILEB").to_string_lossy()),
+                format!("rm -r {}", site.join("RFILEC").to_string_lossy()),
+                format!("mkdir {}", site.join("RFILEC").to_string_lossy()),
+            ]
+        );
+    }
+
+    #[test]
+    fn prune_undescribed_entries() {
+        let chroot = Samples::init_simple_chroot("prune_undescribed_entries");
+        let mut container = Container::new();
+
+        let yaml = r#"
+root: .
+prune: true
+entries:
+  - path: RDIR/RFILEB
+"#;
+        let staged = Manifest::from_yaml(yaml).unwrap().reconcile(chroot.as_path(), &mut container).unwrap();
+
+        let mut removed = render(&staged);
+        removed.sort();
+        assert_eq!(
+            removed,
+            vec![
+                format!("rm -r {}", chroot.join("RDIR/RFILEA").to_string_lossy()),
+                format!("rm -r {}", chroot.join("RDIR2").to_string_lossy()),
+                format!("rm -r {}", chroot.join("RDIR3").to_string_lossy()),
+            ]
+        );
+        assert!(container.status(chroot.join("RDIR/RFILEB").as_path()).unwrap().exists());
+    }
+
+    #[test]
+    fn prune_copied_directories_against_their_source() {
+        let chroot = Samples::init_simple_chroot("prune_copied_directories_against_their_source");
+        write(chroot.join("RDIR2/EXTRA"), "extra").unwrap();
+        let mut container = Container::new();
+
+        let mut manifest = Manifest::new(Path::new("RDIR2"), true);
+        manifest.add(ManifestEntry::new(Path::new("."), Some(Path::new("RDIR")), false));
+
+        let staged = manifest.reconcile(chroot.as_path(), &mut container).unwrap();
+        let site = chroot.join("RDIR2");
+        assert_eq!(
+            render(&staged),
+            vec![
+                format!("rm -r {}", site.join("EXTRA").to_string_lossy()),
+                format!("rm -r {}", site.join("RFILEC").to_string_lossy()),
+                format!("cp -o {} {}", chroot.join("RDIR/RFILEA").to_string_lossy(), site.join("RFILEA").to_string_lossy()),
+                format!("cp {} {}", chroot.join("RDIR/RFILEB").to_string_lossy(), site.join("RFILEB").to_string_lossy()),
+            ]
+        );
+        assert!(!container.status(chroot.join("RDIR2/EXTRA").as_path()).unwrap().exists());
+
+        //Already reconciled
+        assert!(manifest.reconcile(chroot.as_path(), &mut container).unwrap().is_empty());
+    }
+
+    #[test]
+    fn refuse_entries_outside_of_root() {
+        let chroot = Samples::init_simple_chroot("refuse_entries_outside_of_root");
+        let mut manifest = Manifest::new(Path::new("RDIR"), false);
+        manifest.add(ManifestEntry::new(Path::new("../RDIR2/RFILEC"), None, false));
+
+        match manifest.reconcile(chroot.as_path(), &mut Container::new()) {
+            Err(DomainError::OutsideOfRoot(path, root)) => {
+                assert_eq!(path, chroot.join("RDIR2/RFILEC"));
+                assert_eq!(root, chroot.join("RDIR"));
+            },
+            _ => panic!("entry outside of root should be refused")
+        }
+    }
+}
@@ -0,0 +1,438 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    convert::TryFrom,
+    fmt::{ Display, Formatter, Result as FmtResult },
+    path::{ Path, PathBuf },
+    str::FromStr
+};
+
+use serde::{ Serialize, Deserialize };
+
+use crate::{
+    errors::{ DomainError },
+    event::is_false,
+    capability::{
+        Guard,
+        Capability
+    },
+    port::{
+        Entry,
+        ReadableFileSystem,
+        Atomic,
+        AtomicTransaction,
+        Metadata
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operator {
+    Add,
+    Remove,
+    Set
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Clause {
+    who: u32,
+    actions: Vec<(Operator, String)>
+}
+
+/*
+Permissions as chmod takes them, an octal mode or comma separated clauses like u+rwX,go-w.
+X grants execution to directories and to files someone can already execute, no user means everyone.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(try_from = "String", into = "String")]
+pub struct Mode {
+    text: String,
+    octal: Option<u32>,
+    clauses: Vec<Clause>
+}
+
+impl Mode {
+    pub fn new(text: &str) -> Result<Mode, DomainError> {
+        let invalid = || DomainError::InvalidMode(text.to_string());
+
+        if !text.is_empty() && text.chars().all(|c| c.is_digit(8)) {
+            return match u32::from_str_radix(text, 8) {
+                Ok(octal) if octal <= 0o7777 => Ok(Mode { text: text.to_string(), octal: Some(octal), clauses: Vec::new() }),
+                _ => Err(invalid())
+            };
+        }
+
+        let mut clauses = Vec::new();
+        for clause in text.split(',') {
+            let operators = clause.find(['+', '-', '=']).ok_or_else(invalid)?;
+            let mut who = 0;
+            for c in clause[..operators].chars() {
+                who |= match c {
+                    'u' => 0o4700,
+                    'g' => 0o2070,
+                    'o' => 0o1007,
+                    'a' => 0o7777,
+                    _ => return Err(invalid())
+                };
+            }
+
+            let mut actions : Vec<(Operator, String)> = Vec::new();
+            for c in clause[operators..].chars() {
+                match c {
+                    '+' => actions.push((Operator::Add, String::new())),
+                    '-' => actions.push((Operator::Remove, String::new())),
+                    '=' => actions.push((Operator::Set, String::new())),
+                    'r' | 'w' | 'x' | 'X' | 's' | 't' => actions.last_mut().ok_or_else(invalid)?.1.push(c),
+                    _ => return Err(invalid())
+                }
+            }
+
+            clauses.push(Clause { who: if who == 0 { 0o7777 } else { who }, actions });
+        }
+
+        Ok(Mode { text: text.to_string(), octal: None, clauses })
+    }
+
+    pub fn as_str(&self) -> &str { self.text.as_str() }
+
+    //Mode an entry ends with, from the one it has
+    pub fn apply(&self, current: u32, directory: bool) -> u32 {
+        if let Some(octal) = self.octal {
+            return octal;
+        }
+
+        let mut mode = current & 0o7777;
+        for clause in self.clauses.iter() {
+            for (operator, permissions) in clause.actions.iter() {
+                let mut bits = 0;
+                for permission in permissions.chars() {
+                    bits |= match permission {
+                        'r' => 0o444,
+                        'w' => 0o222,
+                        'x' => 0o111,
+                        'X' if directory || current & 0o111 != 0 => 0o111,
+                        's' => 0o6000,
+                        't' => 0o1000,
+                        _ => 0
+                    };
+                }
+                bits &= clause.who;
+                mode = match operator {
+                    Operator::Add => mode | bits,
+                    Operator::Remove => mode & !bits,
+                    Operator::Set => (mode & !clause.who) | bits
+                };
+            }
+        }
+        mode
+    }
+}
+
+impl PartialEq for Mode {
+    fn eq(&self, other: &Mode) -> bool {
+        self.text == other.text
+    }
+}
+
+impl Display for Mode {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}", self.text)
+    }
+}
+
+impl FromStr for Mode {
+    type Err = DomainError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Mode::new(s.trim())
+    }
+}
+
+impl TryFrom<String> for Mode {
+    type Error = DomainError;
+
+    fn try_from(text: String) -> Result<Mode, DomainError> {
+        Mode::new(text.as_str())
+    }
+}
+
+impl From<Mode> for String {
+    fn from(mode: Mode) -> String {
+        mode.text
+    }
+}
+
+/*
+Permissions, ownership and modification time changes, like chmod, chown and touch -d.
+Only what is given changes, recursively through directories without following linked ones.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MetadataEvent {
+    #[serde(with = "crate::lossless")]
+    path: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mode: Option<Mode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    owner: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    group: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    modified: Option<i64>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    recursive: bool
+}
+
+impl MetadataEvent {
+    pub fn new(path: &Path, recursive: bool) -> MetadataEvent {
+        MetadataEvent {
+            path: path.to_path_buf(),
+            mode: None,
+            owner: None,
+            group: None,
+            modified: None,
+            recursive
+        }
+    }
+
+    pub fn with_mode(mut self, mode: Option<Mode>) -> MetadataEvent {
+        self.mode = mode;
+        self
+    }
+
+    pub fn with_ownership(mut self, owner: Option<u32>, group: Option<u32>) -> MetadataEvent {
+        self.owner = owner;
+        self.group = group;
+        self
+    }
+
+    //Seconds since the epoch
+    pub fn with_modified(mut self, modified: Option<i64>) -> MetadataEvent {
+        self.modified = modified;
+        self
+    }
+
+    pub fn path(&self) -> &Path { self.path.as_path() }
+    pub fn mode(&self) -> Option<&Mode> { self.mode.as_ref() }
+    pub fn owner(&self) -> Option<u32> { self.owner }
+    pub fn group(&self) -> Option<u32> { self.group }
+    pub fn modified(&self) -> Option<i64> { self.modified }
+    pub fn recursive(&self) -> bool { self.recursive }
+}
+
+pub fn atomize<E: Entry, F: ReadableFileSystem<Item=E>>(event: &MetadataEvent, fs: &F, guard: &mut dyn Guard) -> Result<AtomicTransaction, DomainError> {
+    let mut transaction = AtomicTransaction::default();
+    change(event, event.path(), fs, guard, &mut transaction)?;
+    Ok(transaction)
+}
+
+fn change<E: Entry, F: ReadableFileSystem<Item=E>>(event: &MetadataEvent, path: &Path, fs: &F, guard: &mut dyn Guard, transaction: &mut AtomicTransaction) -> Result<(), DomainError> {
+    let entry = fs.status(path)?;
+
+    if !entry.exists() {
+        return Err(DomainError::DoesNotExists(path.to_path_buf()))
+    }
+
+    //Links met on the way only get their own ownership, like chown -R does, chmod -R and touch leave them alone
+    if entry.is_symlink() && path != event.path() {
+        if event.owner().is_some() || event.group().is_some() {
+            transaction.add(Atomic::SetMetadata {
+                path: entry.to_path(),
+                metadata: Metadata::default().with_owner(event.owner()).with_group(event.group())
+            });
+        }
+        return Ok(());
+    }
+
+    let current = fs.metadata(path)?.unwrap_or_default();
+    let metadata = Metadata::default()
+        .with_mode(event.mode().map(|mode| mode.apply(current.mode().unwrap_or(0), entry.is_dir())))
+        .with_owner(event.owner())
+        .with_group(event.group())
+        .with_modified(event.modified());
+    //The link given is followed, like chmod, chown and touch do
+    let target = match entry.source() {
+        Some(source) if entry.is_symlink() => source.canonicalize()?,
+        _ => entry.to_path()
+    };
+    let atomic = Atomic::SetMetadata { path: target, metadata };
+
+    if !entry.is_dir() || !event.recursive()
+        || !guard.authorize_entry(Capability::Recursive, event.recursive(), path, entry.is_dir())?.is_allowed()
+        || (entry.is_symlink() && !guard.authorize_entry(Capability::FollowSymlinks, false, path, entry.is_dir())?.is_allowed()) {
+        transaction.add(atomic);
+        return Ok(());
+    }
+
+    //A directory its owner could no longer read and traverse is changed once its children are
+    let last = matches!(metadata.mode(), Some(mode) if mode & 0o500 != 0o500);
+    if !last {
+        transaction.add(atomic.clone());
+    }
+    for child in fs.read_dir(path)?.iter() {
+        change(event, child.path(), fs, guard, transaction)?;
+    }
+    if last {
+        transaction.add(atomic);
+    }
+    Ok(())
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_apply_modes() {
+        assert_eq!(Mode::new("750").unwrap().apply(0o644, false), 0o750);
+        assert_eq!(Mode::new("u+x,go-w").unwrap().apply(0o664, false), 0o744);
+        assert_eq!(Mode::new("a=r").unwrap().apply(0o777, true), 0o444);
+        assert_eq!(Mode::new("+X").unwrap().apply(0o644, false), 0o644);
+        assert_eq!(Mode::new("+X").unwrap().apply(0o644, true), 0o755);
+        assert_eq!(Mode::new("go=").unwrap().apply(0o644, false), 0o600);
+
+        assert!(Mode::new("").is_err());
+        assert!(Mode::new("17777").is_err());
+        assert!(Mode::new("u+q").is_err());
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(all(test, unix))]
+mod real_tests {
+    use super::*;
+
+    use crate::{
+        sample::Samples,
+        port::FileSystemAdapter,
+        infrastructure::RealFileSystem,
+        capability::ZealedGuard
+    };
+
+    #[test]
+    fn recursive_chmod() {
+        let chroot = Samples::init_simple_chroot("metadata_recursive_chmod");
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+
+        atomize(
+            &MetadataEvent::new(chroot.join("RDIR").as_path(), true)
+                .with_mode(Some(Mode::new("go-rwx").unwrap()))
+                .with_modified(Some(1_000_000_000)),
+            &fs,
+            &mut ZealedGuard
+        ).unwrap()
+         .apply(&mut fs)
+         .unwrap();
+
+        let directory = Metadata::read(chroot.join("RDIR").as_path()).unwrap();
+        let file = Metadata::read(chroot.join("RDIR/RFILEA").as_path()).unwrap();
+        assert_eq!(directory.mode().unwrap() & 0o077, 0);
+        assert_eq!(file.mode().unwrap() & 0o077, 0);
+        assert_eq!(file.modified(), Some(1_000_000_000));
+        assert_ne!(Metadata::read(chroot.join("RDIR2").as_path()).unwrap().mode().unwrap() & 0o077, 0);
+    }
+
+    #[test]
+    fn recursive_chmod_leaves_links_alone() {
+        let chroot = Samples::init_simple_chroot("metadata_recursive_chmod_leaves_links_alone");
+        std::os::unix::fs::symlink(chroot.join("RDIR2"), chroot.join("RDIR/LINK")).unwrap();
+        std::os::unix::fs::symlink(chroot.join("RDIR2/RFILEC"), chroot.join("RDIR/FILELINK")).unwrap();
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+        let before = Metadata::read(chroot.join("RDIR2/RFILEC").as_path()).unwrap();
+
+        atomize(
+            &MetadataEvent::new(chroot.join("RDIR").as_path(), true)
+                .with_mode(Some(Mode::new("go-rwx").unwrap()))
+                .with_modified(Some(1_000_000_000)),
+            &fs,
+            &mut ZealedGuard
+        ).unwrap()
+         .apply(&mut fs)
+         .unwrap();
+
+        assert_eq!(Metadata::read(chroot.join("RDIR/RFILEA").as_path()).unwrap().mode().unwrap() & 0o077, 0);
+        assert_eq!(Metadata::read(chroot.join("RDIR2/RFILEC").as_path()).unwrap(), before);
+        assert_ne!(Metadata::read(chroot.join("RDIR2").as_path()).unwrap().mode().unwrap() & 0o077, 0);
+    }
+
+    #[test]
+    fn touch_unreadable_file() {
+        let chroot = Samples::init_simple_chroot("metadata_touch_unreadable_file");
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+        Metadata::default().with_mode(Some(0o200)).write(chroot.join("RDIR/RFILEA").as_path()).unwrap();
+
+        atomize(
+            &MetadataEvent::new(chroot.join("RDIR/RFILEA").as_path(), false).with_modified(Some(1_000_000_000)),
+            &fs,
+            &mut ZealedGuard
+        ).unwrap()
+         .apply(&mut fs)
+         .unwrap();
+
+        assert_eq!(Metadata::read(chroot.join("RDIR/RFILEA").as_path()).unwrap().modified(), Some(1_000_000_000));
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod virtual_tests {
+    use super::*;
+
+    use crate::{
+        sample::Samples,
+        port::FileSystemAdapter,
+        infrastructure::VirtualFileSystem,
+        capability::ZealedGuard
+    };
+
+    #[test]
+    fn virtual_chmod_is_staged() {
+        let chroot = Samples::init_simple_chroot("virtual_metadata_chmod");
+        let mut fs = FileSystemAdapter(VirtualFileSystem::default());
+        let before = Metadata::read(chroot.join("RDIR/RFILEA").as_path()).unwrap();
+
+        atomize(
+            &MetadataEvent::new(chroot.join("RDIR/RFILEA").as_path(), false)
+                .with_mode(Some(Mode::new("600").unwrap()))
+                .with_ownership(Some(4242), None),
+            &fs,
+            &mut ZealedGuard
+        ).unwrap()
+         .apply(&mut fs)
+         .unwrap();
+
+        let staged = fs.metadata(chroot.join("RDIR/RFILEA").as_path()).unwrap().unwrap();
+        assert_eq!(staged.mode(), Some(0o600));
+        assert_eq!(staged.owner(), Some(4242));
+        assert_eq!(staged.group(), before.group());
+        assert_eq!(Metadata::read(chroot.join("RDIR/RFILEA").as_path()).unwrap(), before);
+    }
+
+    #[test]
+    fn virtual_chmod_of_missing_entry() {
+        let chroot = Samples::init_simple_chroot("virtual_metadata_missing");
+        let fs = FileSystemAdapter(VirtualFileSystem::default());
+
+        assert!(atomize(
+            &MetadataEvent::new(chroot.join("NOPE").as_path(), false).with_mode(Some(Mode::new("600").unwrap())),
+            &fs,
+            &mut ZealedGuard
+        ).is_err());
+    }
+}
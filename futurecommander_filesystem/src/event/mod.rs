@@ -18,7 +18,8 @@
  */
 
 use std::{
-    fmt::Debug
+    fmt::{ Debug, Display, Formatter, Result as FmtResult },
+    path::{ Path, PathBuf }
 };
 
 use serde::{Serialize, Deserialize};
@@ -27,21 +28,47 @@ mod copy;
 mod create;
 mod mov;
 mod remove;
+mod rename;
+mod sync;
+mod metadata;
+mod link;
+mod plan;
+mod manifest;
+mod filter;
+mod conflict;
+mod trash;
+mod backup;
+mod danger;
 
 pub mod capability;
 
+use self::conflict::DEFAULT_RENAME_TEMPLATE;
+
 pub use self::{
     copy::CopyEvent,
     create::CreateEvent,
     mov::MoveEvent,
-    remove::RemoveEvent
+    remove::RemoveEvent,
+    rename::{ RenameEvent, RenameRule },
+    sync::{ SyncEvent, SyncComparison },
+    metadata::{ MetadataEvent, Mode },
+    link::LinkEvent,
+    plan::{ Plan, PlanOperation },
+    manifest::{ Manifest, ManifestEntry },
+    filter::{ Filter, Pattern },
+    conflict::OverwritePolicy,
+    trash::{ Trash, TrashedEntry },
+    backup::Backup,
+    danger::Danger
 };
 
 use crate::{
+    Kind,
     errors::DomainError,
     capability::{
         Guard,
-        RegistrarGuard
+        RegistrarGuard,
+        Impact
     },
     port::{
         Entry,
@@ -50,6 +77,8 @@ use crate::{
     }
 };
 
+fn is_false(value: &bool) -> bool { !*value }
+
 pub trait Listener {
     fn emit(&mut self, event: &FileSystemEvent, guard: RegistrarGuard) -> Result<RegistrarGuard, DomainError>;
 }
@@ -63,16 +92,201 @@ pub enum FileSystemEvent {
     Create(create::CreateEvent),
     Copy(copy::CopyEvent),
     Move(mov::MoveEvent),
-    Remove(remove::RemoveEvent)
+    Remove(remove::RemoveEvent),
+    Rename(rename::RenameEvent),
+    Sync(sync::SyncEvent),
+    Metadata(metadata::MetadataEvent),
+    Link(link::LinkEvent)
 }
 
 impl FileSystemEvent {
+    //Nothing is done when the guard refuses the impact of the whole event
     pub fn atomize<E: Entry, F: ReadableFileSystem<Item=E>>(&self, fs: &F, guard: &mut dyn Guard) -> Result<AtomicTransaction, DomainError> {
-        match self {
+        let transaction = match self {
             FileSystemEvent::Create(event) => create::atomize(event, fs, guard),
             FileSystemEvent::Copy(event) => copy::atomize(event, fs, guard),
             FileSystemEvent::Move(event) => mov::atomize(event, fs, guard),
             FileSystemEvent::Remove(event) => remove::atomize(event, fs, guard),
+            FileSystemEvent::Rename(event) => rename::atomize(event, fs, guard),
+            FileSystemEvent::Sync(event) => sync::atomize(event, fs, guard),
+            FileSystemEvent::Metadata(event) => metadata::atomize(event, fs, guard),
+            FileSystemEvent::Link(event) => link::atomize(event, fs, guard),
+        }?;
+
+        if guard.measures_impact()
+            && !guard.authorize_impact(&Impact::measure(&transaction, fs), self.target())?.is_allowed() {
+            return Ok(AtomicTransaction::default());
+        }
+        Ok(transaction)
+    }
+
+    //Main path written by the event
+    pub fn target(&self) -> &Path {
+        match self {
+            FileSystemEvent::Create(event) => event.path(),
+            FileSystemEvent::Copy(event) => event.destination(),
+            FileSystemEvent::Move(event) => event.destination(),
+            FileSystemEvent::Remove(event) => event.path(),
+            FileSystemEvent::Rename(event) => event.path(),
+            FileSystemEvent::Sync(event) => event.destination(),
+            FileSystemEvent::Metadata(event) => event.path(),
+            FileSystemEvent::Link(event) => event.destination()
+        }
+    }
+
+    //Paths written when applied, in place or as a destination
+    pub fn targets(&self) -> Vec<PathBuf> {
+        let targets : Vec<&Path> = match self {
+            FileSystemEvent::Create(event) => vec![event.path()],
+            FileSystemEvent::Copy(event) => match event.backup() {
+                Backup::Directory(directory) => vec![event.destination(), directory.as_path()],
+                _ => vec![event.destination()]
+            },
+            FileSystemEvent::Move(event) => match event.backup() {
+                Backup::Directory(directory) => vec![event.source(), event.destination(), directory.as_path()],
+                _ => vec![event.source(), event.destination()]
+            },
+            FileSystemEvent::Remove(event) => vec![event.path()],
+            FileSystemEvent::Rename(event) => vec![event.path()],
+            FileSystemEvent::Sync(event) => vec![event.destination()],
+            FileSystemEvent::Metadata(event) => vec![event.path()],
+            FileSystemEvent::Link(event) => vec![event.destination()]
+        };
+
+        let mut targets : Vec<PathBuf> = targets.into_iter().map(Path::to_path_buf).collect();
+        //Removed entries go to the trash of their device
+        if let FileSystemEvent::Remove(event) = self {
+            if let Some(trash) = event.trash() {
+                targets.push(trash.for_path(event.path()).root().to_path_buf());
+            }
         }
+        targets
     }
-}
\ No newline at end of file
+}
+
+impl Display for FileSystemEvent {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            FileSystemEvent::Create(event) => write!(
+                f,
+                "{}{}{} {}",
+                match event.kind() {
+                    Kind::Directory => "mkdir",
+                    Kind::File => "touch",
+                    Kind::Unknown => "create"
+                },
+                if event.recursive() { " -r" } else { "" },
+                if event.overwrite() { " -o" } else { "" },
+                event.path().to_string_lossy()
+            ),
+            FileSystemEvent::Copy(event) => write!(
+                f,
+                "cp{}{}{}{}{}{}{} {} {}",
+                if event.merge() { " -m" } else { "" },
+                match event.overwrite_policy() {
+                    OverwritePolicy::Never => String::new(),
+                    OverwritePolicy::Always => " -o".to_string(),
+                    policy => format!(" --overwrite-policy {}", policy)
+                },
+                if event.keep_both() { " -k" } else { "" },
+                match event.rename_template() {
+                    DEFAULT_RENAME_TEMPLATE => String::new(),
+                    template => format!(" --rename-template {}", template)
+                },
+                match event.backup() {
+                    Backup::None => String::new(),
+                    Backup::Directory(directory) => format!(" --backup-dir {}", directory.to_string_lossy()),
+                    backup => format!(" --backup {}", backup)
+                },
+                if event.follow_symlinks() { " --follow-symlinks" } else { "" },
+                event.filter(),
+                event.source().to_string_lossy(),
+                event.destination().to_string_lossy()
+            ),
+            FileSystemEvent::Move(event) => write!(
+                f,
+                "mv{}{}{}{}{}{}{}{}{} {} {}",
+                if event.merge() { " -m" } else { "" },
+                match event.overwrite_policy() {
+                    OverwritePolicy::Never => String::new(),
+                    OverwritePolicy::Always => " -o".to_string(),
+                    policy => format!(" --overwrite-policy {}", policy)
+                },
+                if event.keep_both() { " -k" } else { "" },
+                match event.rename_template() {
+                    DEFAULT_RENAME_TEMPLATE => String::new(),
+                    template => format!(" --rename-template {}", template)
+                },
+                match event.backup() {
+                    Backup::None => String::new(),
+                    Backup::Directory(directory) => format!(" --backup-dir {}", directory.to_string_lossy()),
+                    backup => format!(" --backup {}", backup)
+                },
+                if event.follow_symlinks() { " --follow-symlinks" } else { "" },
+                if event.cross_device() { " --cross-device" } else { "" },
+                if event.allow_dangerous() { " --allow-dangerous" } else { "" },
+                event.filter(),
+                event.source().to_string_lossy(),
+                event.destination().to_string_lossy()
+            ),
+            FileSystemEvent::Remove(event) => write!(
+                f,
+                "rm{}{}{}{}{} {}",
+                if event.recursive() { " -r" } else { "" },
+                if event.trash().is_some() { " --trash" } else { "" },
+                if event.follow_symlinks() { " --follow-symlinks" } else { "" },
+                if event.allow_dangerous() { " --allow-dangerous" } else { "" },
+                event.filter(),
+                event.path().to_string_lossy()
+            ),
+            FileSystemEvent::Rename(event) => write!(
+                f,
+                "rename {} {}",
+                event.path().to_string_lossy(),
+                event.name().to_string_lossy()
+            ),
+            FileSystemEvent::Sync(event) => write!(
+                f,
+                "sync{}{}{} {} {}",
+                if event.delete() { " -d" } else { "" },
+                if event.skip_unchanged() { " -u" } else { "" },
+                match event.comparison() {
+                    SyncComparison::SizeAndTime => "",
+                    SyncComparison::Content => " -c"
+                },
+                event.source().to_string_lossy(),
+                event.destination().to_string_lossy()
+            ),
+            FileSystemEvent::Metadata(event) => {
+                let recursive = if event.recursive() { " -R" } else { "" };
+                let mut commands = Vec::new();
+                if let Some(mode) = event.mode() {
+                    commands.push(format!("chmod{} {}", recursive, mode));
+                }
+                match (event.owner(), event.group()) {
+                    (Some(owner), Some(group)) => commands.push(format!("chown{} {}:{}", recursive, owner, group)),
+                    (Some(owner), None) => commands.push(format!("chown{} {}", recursive, owner)),
+                    (None, Some(group)) => commands.push(format!("chown{} :{}", recursive, group)),
+                    (None, None) => {}
+                }
+                if let Some(modified) = event.modified() {
+                    commands.push(format!("touch{} -d @{}", recursive, modified));
+                }
+                write!(
+                    f,
+                    "{}",
+                    commands.iter()
+                        .map(|command| format!("{} {}", command, event.path().to_string_lossy()))
+                        .collect::<Vec<String>>()
+                        .join(" && ")
+                )
+            },
+            FileSystemEvent::Link(event) => write!(
+                f,
+                "ln {} {}",
+                event.source().to_string_lossy(),
+                event.destination().to_string_lossy()
+            )
+        }
+    }
+}
@@ -25,9 +25,17 @@ use serde::{ Serialize, Deserialize };
 
 use crate::{
     errors::{ DomainError },
+    event::{
+        is_false,
+        Filter,
+        Backup,
+        Danger,
+        conflict::{ self, DEFAULT_RENAME_TEMPLATE, OverwritePolicy }
+    },
     capability::{
         Guard,
-        Capability
+        Capability,
+        Authorization
     },
     port::{
         Entry,
@@ -39,35 +47,194 @@ use crate::{
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct MoveEvent {
+    #[serde(with = "crate::lossless")]
     source: PathBuf,
+    #[serde(with = "crate::lossless")]
     destination: PathBuf,
     merge: bool,
-    overwrite: bool, //To honour overwrite or merge error, we should crawl recursively the entire vfs children of dst ...
+    overwrite: OverwritePolicy, //To honour overwrite or merge error, we should crawl recursively the entire vfs children of dst ...
+    #[serde(default, skip_serializing_if = "Filter::is_empty")]
+    filter: Filter,
+    #[serde(default, skip_serializing_if = "is_false")]
+    keep_both: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rename_template: Option<String>,
+    #[serde(default, skip_serializing_if = "Backup::is_none")]
+    backup: Backup,
+    #[serde(default, skip_serializing_if = "is_false")]
+    follow_symlinks: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    cross_device: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    allow_dangerous: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "crate::lossless::option")]
+    working_directory: Option<PathBuf>
 }
 
 impl MoveEvent {
-    pub fn new(source: &Path, destination: &Path, merge: bool, overwrite: bool) -> MoveEvent {
+    pub fn new(source: &Path, destination: &Path, merge: bool, overwrite: impl Into<OverwritePolicy>) -> MoveEvent {
         MoveEvent {
             source: source.to_path_buf(),
             destination: destination.to_path_buf(),
             merge,
-            overwrite
+            overwrite: overwrite.into(),
+            filter: Filter::default(),
+            keep_both: false,
+            rename_template: None,
+            backup: Backup::None,
+            follow_symlinks: false,
+            cross_device: false,
+            allow_dangerous: false,
+            working_directory: None
+        }
+    }
+
+    pub fn with_filter(mut self, filter: Filter) -> MoveEvent {
+        self.filter = filter;
+        self
+    }
+
+    pub fn with_keep_both(mut self, keep_both: bool) -> MoveEvent {
+        self.keep_both = keep_both;
+        self
+    }
+
+    pub fn with_rename_template(mut self, template: Option<&str>) -> MoveEvent {
+        self.rename_template = template.map(str::to_string);
+        self
+    }
+
+    pub fn with_backup(mut self, backup: Backup) -> MoveEvent {
+        self.backup = backup;
+        self
+    }
+
+    pub fn with_follow_symlinks(mut self, follow_symlinks: bool) -> MoveEvent {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    pub fn with_cross_device(mut self, cross_device: bool) -> MoveEvent {
+        self.cross_device = cross_device;
+        self
+    }
+
+    //Home, working directory and mount points, never the root
+    pub fn with_allow_dangerous(mut self, allow_dangerous: bool) -> MoveEvent {
+        self.allow_dangerous = allow_dangerous;
+        self
+    }
+
+    //Working directory of the session, guarded like the home directory
+    pub fn with_working_directory(mut self, cwd: &Path) -> MoveEvent {
+        self.working_directory = Some(cwd.to_path_buf());
+        self
+    }
+
+    fn child(&self, source: &Path, destination: &Path, filter: Filter) -> MoveEvent {
+        MoveEvent {
+            source: source.to_path_buf(),
+            destination: destination.to_path_buf(),
+            filter,
+            ..self.clone()
         }
     }
 
     pub fn source(&self) -> &Path { self.source.as_path() }
     pub fn destination(&self) -> &Path { self.destination.as_path() }
     pub fn merge(&self) -> bool { self.merge }
-    pub fn overwrite(&self) -> bool { self.overwrite }
+    pub fn overwrite(&self) -> bool { self.overwrite.allowed() }
+    pub fn overwrite_policy(&self) -> OverwritePolicy { self.overwrite }
+    pub fn filter(&self) -> &Filter { &self.filter }
+    pub fn keep_both(&self) -> bool { self.keep_both }
+    pub fn rename_template(&self) -> &str { self.rename_template.as_deref().unwrap_or(DEFAULT_RENAME_TEMPLATE) }
+    pub fn backup(&self) -> &Backup { &self.backup }
+    pub fn follow_symlinks(&self) -> bool { self.follow_symlinks }
+    pub fn cross_device(&self) -> bool { self.cross_device }
+    pub fn allow_dangerous(&self) -> bool { self.allow_dangerous }
+    pub fn working_directory(&self) -> Option<&Path> { self.working_directory.as_deref() }
+}
+
+//Renaming fails between devices, the move then degrades to copy and remove
+#[cfg(unix)]
+fn crosses_device(source: &Path, destination: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    let device = source.symlink_metadata().map(|metadata| metadata.dev());
+    let landing = destination.ancestors().find_map(|ancestor| ancestor.metadata().ok()).map(|metadata| metadata.dev());
+    match (device, landing) {
+        (Ok(device), Some(landing)) => device != landing,
+        _ => false
+    }
+}
+
+#[cfg(not(unix))]
+fn crosses_device(_source: &Path, _destination: &Path) -> bool {
+    false
+}
+
+//Moves accepted children, the source directory stays in place unless all of them left
+fn filtered<E: Entry, F: ReadableFileSystem<Item=E>>(event: &MoveEvent, source: &E, destination: &E, fs: &F, guard: &mut dyn Guard) -> Result<AtomicTransaction, DomainError> {
+    let mut transaction = AtomicTransaction::default();
+    let filter = event.filter().descend(source.path(), source.source());
+    let children = fs.read_dir(source.path())?;
+
+    for child in children.iter() {
+        if !filter.accepts(child.path(), child.is_dir()) {
+            continue;
+        }
+        transaction.merge(
+            mov(
+                &event.child(
+                    child.path(),
+                    destination.path()
+                        .join(child.name().unwrap())
+                        .as_path(),
+                    filter.clone()
+                ),
+                fs,
+                guard
+            )?
+        );
+    }
+
+    if children.iter().all(|child| transaction.vacates(child.path())) {
+        transaction.add(Atomic::RemoveEmptyDirectory(source.to_path()));
+    }
+    Ok(transaction)
 }
 
 pub fn atomize<E: Entry, F: ReadableFileSystem<Item=E>>(event: &MoveEvent, fs: &F, guard: &mut dyn Guard) -> Result<AtomicTransaction, DomainError> {
+    Danger::check(event.source(), event.working_directory(), event.allow_dangerous())?;
+    mov(event, fs, guard)
+}
+
+fn mov<E: Entry, F: ReadableFileSystem<Item=E>>(event: &MoveEvent, fs: &F, guard: &mut dyn Guard) -> Result<AtomicTransaction, DomainError> {
     let source = fs.status(event.source())?;
 
     if !source.exists() {
         return Err(DomainError::SourceDoesNotExists(event.source().to_path_buf()))
     }
 
+    //Children of a linked directory are reached through the link
+    if source.is_dir() && source.is_symlink()
+        && !guard.authorize_entry(Capability::FollowSymlinks, event.follow_symlinks(), event.source(), source.is_dir())?.is_allowed() {
+        return Ok(AtomicTransaction::default());
+    }
+
+    //Once granted, children do not ask again
+    let granted;
+    let event = match source.source() {
+        Some(real) if crosses_device(real, event.destination()) => {
+            if !guard.authorize_entry(Capability::CrossDevice, event.cross_device(), event.destination(), source.is_dir())?.is_allowed() {
+                return Ok(AtomicTransaction::default());
+            }
+            granted = event.clone().with_cross_device(true);
+            &granted
+        },
+        _ => event
+    };
+
     let mut transaction = AtomicTransaction::default();
     let destination = fs.status(event.destination())?;
 
@@ -78,41 +245,67 @@ pub fn atomize<E: Entry, F: ReadableFileSystem<Item=E>>(event: &MoveEvent, fs: &
     if destination.exists() {
         if source.is_dir() {
             if destination.is_dir() {
-                if guard.authorize(Capability::Merge, event.merge(), event.destination())? {
-                    for child in fs.read_dir(source.path())? {
+                match conflict::resolve(guard, Capability::Merge, event.merge(), event.keep_both(), event.destination(), destination.is_dir())? {
+                    Authorization::Allow => if event.filter().is_empty() && !event.overwrite_policy().is_conditional() {
+                        for child in fs.read_dir(source.path())? {
+                            transaction.merge(
+                                mov(
+                                    &event.child(
+                                        child.path(),
+                                        destination.path()
+                                            .join(child.name().unwrap())
+                                            .as_path(),
+                                        Filter::default()
+                                    ),
+                                    fs, 
+                                    guard
+                                )?
+                            );
+                        }
+                        transaction.add(Atomic::RemoveEmptyDirectory(source.to_path()));
+                    } else {
+                        transaction.merge(filtered(event, &source, &destination, fs, guard)?);
+                    },
+                    Authorization::Rename => {
+                        let renamed = conflict::keep_both(fs, destination.path(), true, event.rename_template())?;
                         transaction.merge(
-                            atomize(
-                                &MoveEvent::new(
-                                    child.path(),
-                                    destination.path()
-                                        .join(child.name().unwrap())
-                                        .as_path(),
-                                    event.merge(),
-                                    event.overwrite()
-                                ),
-                                fs, 
-                                guard
-                            )?
+                            mov(&event.child(source.path(), renamed.as_path(), event.filter().clone()), fs, guard)?
                         );
-                    }
-                    transaction.add(Atomic::RemoveEmptyDirectory(source.to_path()));
+                    },
+                    Authorization::Skip => {}
                 }
             } else {
                 return Err(DomainError::MergeFileWithDirectory(source.to_path(), destination.to_path()));
             }
         } else if source.is_file() {
             if destination.is_file() {
-                if guard.authorize(Capability::Overwrite, event.overwrite(), event.destination())? {
-                    transaction.add(Atomic::RemoveFile(destination.to_path()));
-                    transaction.add(Atomic::MoveFileToFile {
-                        source: source.to_path(),
-                        destination: destination.to_path()
-                    });
+                //Up to date destinations are left alone, without asking the guard
+                if event.overwrite_policy().applies(&source, &destination) {
+                    match conflict::resolve(guard, Capability::Overwrite, event.overwrite(), event.keep_both(), event.destination(), destination.is_dir())? {
+                        Authorization::Allow => {
+                            event.backup().atomize(fs, destination.path(), &mut transaction)?;
+                            transaction.add(Atomic::MoveFileToFile {
+                                source: source.to_path(),
+                                destination: destination.to_path()
+                            });
+                        },
+                        Authorization::Rename => {
+                            transaction.add(Atomic::MoveFileToFile {
+                                source: source.to_path(),
+                                destination: conflict::keep_both(fs, destination.path(), false, event.rename_template())?
+                            });
+                        },
+                        Authorization::Skip => {}
+                    }
                 }
             } else {
                 return Err(DomainError::OverwriteDirectoryWithFile(source.to_path(), destination.to_path()))
             }
         }
+    } else if source.is_dir() && !event.filter().is_empty() {
+        //A bound directory would bring every child along
+        transaction.add(Atomic::CreateEmptyDirectory(destination.to_path()));
+        transaction.merge(filtered(event, &source, &destination, fs, guard)?);
     } else if source.is_dir() {
         transaction.add(Atomic::BindDirectoryToDirectory {
             source: source.to_path(),
@@ -120,14 +313,13 @@ pub fn atomize<E: Entry, F: ReadableFileSystem<Item=E>>(event: &MoveEvent, fs: &
         });
         for child in fs.read_maintained(source.path())? {
             transaction.merge(
-                atomize(
-                    &MoveEvent::new(
+                mov(
+                    &event.child(
                         child.path(),
                         destination.path()
                             .join(child.name().unwrap())
                             .as_path(),
-                        event.merge(),
-                        event.overwrite()
+                        Filter::default()
                     ),
                     fs, guard
                 )?
@@ -148,6 +340,8 @@ pub fn atomize<E: Entry, F: ReadableFileSystem<Item=E>>(event: &MoveEvent, fs: &
 mod real_tests {
     use super::*;
 
+    use std::time::SystemTime;
+
     use crate::{
         sample::Samples,
         port::{
@@ -158,7 +352,8 @@ mod real_tests {
         },
         capability::{
             ZealedGuard
-        }
+        },
+        event::{ Filter, Pattern, OverwritePolicy }
     };
 
     #[test]
@@ -270,6 +465,115 @@ mod real_tests {
             chroot.join("RDIR2/RFILEA").metadata().unwrap().len()
         )
     }
+
+    #[test]
+    fn move_operation_dir_filtered_keeps_source(){
+        let chroot = Samples::init_simple_chroot("move_operation_dir_filtered_keeps_source");
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+
+        atomize(
+            &MoveEvent::new(
+                chroot.join("RDIR").as_path(),
+                chroot.join("MOVED").as_path(),
+                false,
+                false
+            ).with_filter(Filter::new(vec![], vec![Pattern::new("re:B$").unwrap()], false)),
+            &fs,
+            &mut ZealedGuard
+        ).unwrap()
+         .apply(&mut fs)
+         .unwrap();
+
+        assert!(!chroot.join("RDIR/RFILEA").exists());
+        assert!(chroot.join("RDIR/RFILEB").exists());
+        assert!(chroot.join("MOVED/RFILEA").exists());
+        assert!(!chroot.join("MOVED/RFILEB").exists());
+    }
+
+    #[test]
+    fn move_operation_dir_merge_filtered_vacates_source(){
+        let chroot = Samples::init_simple_chroot("move_operation_dir_merge_filtered_vacates_source");
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+
+        atomize(
+            &MoveEvent::new(
+                chroot.join("RDIR").as_path(),
+                chroot.join("RDIR3").as_path(),
+                true,
+                false
+            ).with_filter(Filter::new(vec![], vec![Pattern::new("*.tmp").unwrap()], false)),
+            &fs,
+            &mut ZealedGuard
+        ).unwrap()
+         .apply(&mut fs)
+         .unwrap();
+
+        assert!(!chroot.join("RDIR").exists());
+        assert!(chroot.join("RDIR3/RFILEA").exists());
+        assert!(chroot.join("RDIR3/RFILEB").exists());
+    }
+
+    #[test]
+    fn move_operation_dir_merge_keep_both(){
+        let chroot = Samples::init_simple_chroot("move_operation_dir_merge_keep_both");
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+
+        atomize(
+            &MoveEvent::new(
+                chroot.join("RDIR").as_path(),
+                chroot.join("RDIR2").as_path(),
+                true,
+                false
+            ).with_keep_both(true),
+            &fs,
+            &mut ZealedGuard
+        ).unwrap()
+         .apply(&mut fs)
+         .unwrap();
+
+        assert!(!chroot.join("RDIR").exists());
+        assert!(chroot.join("RDIR2/RFILEA").exists());
+        assert!(chroot.join("RDIR2/RFILEA (1)").exists());
+        assert!(chroot.join("RDIR2/RFILEB").exists());
+    }
+
+    #[test]
+    fn move_operation_dir_merge_if_newer_keeps_outdated_source(){
+        let chroot = Samples::init_simple_chroot("move_operation_dir_merge_if_newer_keeps_outdated_source");
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+        std::fs::File::options()
+            .write(true)
+            .open(chroot.join("RDIR/RFILEA"))
+            .unwrap()
+            .set_modified(SystemTime::UNIX_EPOCH)
+            .unwrap();
+
+        atomize(
+            &MoveEvent::new(
+                chroot.join("RDIR").as_path(),
+                chroot.join("RDIR2").as_path(),
+                true,
+                OverwritePolicy::IfNewer
+            ),
+            &fs,
+            &mut ZealedGuard
+        ).unwrap()
+         .apply(&mut fs)
+         .unwrap();
+
+        assert!(chroot.join("RDIR/RFILEA").exists());
+        assert!(!chroot.join("RDIR/RFILEB").exists());
+        assert!(chroot.join("RDIR2/RFILEB").exists());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn move_operation_detects_cross_device(){
+        let chroot = Samples::init_simple_chroot("move_operation_detects_cross_device");
+
+        assert!(!crosses_device(chroot.join("RDIR/RFILEA").as_path(), chroot.join("RDIR2/NEW/RFILEA").as_path()));
+        assert!(crosses_device(Path::new("/proc/version"), chroot.join("version").as_path()));
+    }
 }
 
 
@@ -289,7 +593,8 @@ mod virtual_tests {
         },
         capability::{
             ZealedGuard
-        }
+        },
+        event::{ Filter, Pattern }
     };
 
     #[test]
@@ -387,4 +692,28 @@ mod virtual_tests {
         assert!(fs.as_inner().virtual_state().unwrap().is_virtual(samples_path.join("A/C").as_path()).unwrap());
         assert!(fs.as_inner().virtual_state().unwrap().is_file(samples_path.join("A/C").as_path()).unwrap());
     }
+
+    #[test]
+    fn virtual_move_operation_directory_filtered(){
+        let chroot = Samples::init_simple_chroot("virtual_move_operation_directory_filtered");
+        let mut fs = FileSystemAdapter(VirtualFileSystem::default());
+
+        atomize(
+            &MoveEvent::new(
+                chroot.join("RDIR").as_path(),
+                chroot.join("MOVED").as_path(),
+                false,
+                false
+            ).with_filter(Filter::new(vec![Pattern::new("RFILEA").unwrap()], vec![], false)),
+            &fs,
+            &mut ZealedGuard
+        ).unwrap()
+         .apply(&mut fs)
+         .unwrap();
+
+        assert!(fs.status(chroot.join("MOVED/RFILEA").as_path()).unwrap().exists());
+        assert!(!fs.status(chroot.join("MOVED/RFILEB").as_path()).unwrap().exists());
+        assert!(!fs.status(chroot.join("RDIR/RFILEA").as_path()).unwrap().exists());
+        assert!(fs.status(chroot.join("RDIR/RFILEB").as_path()).unwrap().exists());
+    }
 }
@@ -0,0 +1,566 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    ffi::OsString,
+    path::{ Path, PathBuf }
+};
+
+use serde::{ Serialize, Deserialize };
+
+use crate::{
+    Kind,
+    errors::DomainError,
+    tools::normalize,
+    event::{
+        is_false,
+        FileSystemEvent,
+        CreateEvent,
+        CopyEvent,
+        MoveEvent,
+        RemoveEvent,
+        RenameEvent,
+        SyncEvent,
+        SyncComparison,
+        MetadataEvent,
+        LinkEvent,
+        Mode,
+        Filter,
+        Trash,
+        Backup,
+        OverwritePolicy,
+        conflict::DEFAULT_RENAME_TEMPLATE
+    }
+};
+
+/*
+Human writable counterpart of FileSystemEvent, paths may be relative to the plan base.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PlanOperation {
+    Mkdir {
+        #[serde(with = "crate::lossless")]
+        path: PathBuf,
+        #[serde(default, skip_serializing_if = "is_false")]
+        recursive: bool
+    },
+    Touch {
+        #[serde(with = "crate::lossless")]
+        path: PathBuf,
+        #[serde(default, skip_serializing_if = "is_false")]
+        recursive: bool,
+        #[serde(default, skip_serializing_if = "is_false")]
+        overwrite: bool
+    },
+    Copy {
+        #[serde(with = "crate::lossless")]
+        from: PathBuf,
+        #[serde(with = "crate::lossless")]
+        to: PathBuf,
+        #[serde(default, skip_serializing_if = "is_false")]
+        merge: bool,
+        #[serde(default, skip_serializing_if = "OverwritePolicy::is_never")]
+        overwrite: OverwritePolicy,
+        #[serde(default, skip_serializing_if = "is_false")]
+        keep_both: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rename_template: Option<String>,
+        #[serde(default, skip_serializing_if = "Backup::is_none")]
+        backup: Backup,
+        #[serde(default, skip_serializing_if = "is_false")]
+        follow_symlinks: bool,
+        #[serde(flatten)]
+        filter: Filter
+    },
+    Move {
+        #[serde(with = "crate::lossless")]
+        from: PathBuf,
+        #[serde(with = "crate::lossless")]
+        to: PathBuf,
+        #[serde(default, skip_serializing_if = "is_false")]
+        merge: bool,
+        #[serde(default, skip_serializing_if = "OverwritePolicy::is_never")]
+        overwrite: OverwritePolicy,
+        #[serde(default, skip_serializing_if = "is_false")]
+        keep_both: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        rename_template: Option<String>,
+        #[serde(default, skip_serializing_if = "Backup::is_none")]
+        backup: Backup,
+        #[serde(default, skip_serializing_if = "is_false")]
+        follow_symlinks: bool,
+        #[serde(default, skip_serializing_if = "is_false")]
+        cross_device: bool,
+        #[serde(default, skip_serializing_if = "is_false")]
+        allow_dangerous: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none", with = "crate::lossless::option")]
+        working_directory: Option<PathBuf>,
+        #[serde(flatten)]
+        filter: Filter
+    },
+    Remove {
+        #[serde(with = "crate::lossless")]
+        path: PathBuf,
+        #[serde(default, skip_serializing_if = "is_false")]
+        recursive: bool,
+        #[serde(default, skip_serializing_if = "is_false")]
+        trash: bool,
+        //Left out for the home trash, so that it stays the one of whoever imports the plan
+        #[serde(default, skip_serializing_if = "Option::is_none", with = "crate::lossless::option")]
+        trash_root: Option<PathBuf>,
+        #[serde(default, skip_serializing_if = "is_false")]
+        follow_symlinks: bool,
+        #[serde(default, skip_serializing_if = "is_false")]
+        allow_dangerous: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none", with = "crate::lossless::option")]
+        working_directory: Option<PathBuf>,
+        #[serde(flatten)]
+        filter: Filter
+    },
+    Rename {
+        #[serde(with = "crate::lossless")]
+        path: PathBuf,
+        #[serde(with = "crate::lossless::name")]
+        name: OsString
+    },
+    Sync {
+        #[serde(with = "crate::lossless")]
+        from: PathBuf,
+        #[serde(with = "crate::lossless")]
+        to: PathBuf,
+        #[serde(default, skip_serializing_if = "is_false")]
+        delete: bool,
+        #[serde(default, skip_serializing_if = "is_false")]
+        skip_unchanged: bool,
+        #[serde(default)]
+        comparison: SyncComparison
+    },
+    Link {
+        #[serde(with = "crate::lossless")]
+        from: PathBuf,
+        #[serde(with = "crate::lossless")]
+        to: PathBuf
+    },
+    Metadata {
+        #[serde(with = "crate::lossless")]
+        path: PathBuf,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        mode: Option<Mode>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        owner: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        group: Option<u32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        modified: Option<i64>,
+        #[serde(default, skip_serializing_if = "is_false")]
+        recursive: bool
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Plan {
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "crate::lossless::option")]
+    base: Option<PathBuf>,
+    #[serde(default)]
+    operations: Vec<PlanOperation>
+}
+
+impl Plan {
+    pub fn new(base: Option<&Path>) -> Plan {
+        Plan {
+            base: base.map(Path::to_path_buf),
+            operations: Vec::new()
+        }
+    }
+
+    pub fn base(&self) -> Option<&Path> { self.base.as_deref() }
+    pub fn operations(&self) -> &[PlanOperation] { &self.operations }
+
+    fn relative(&self, path: &Path) -> PathBuf {
+        match self.base() {
+            Some(base) => match path.strip_prefix(base) {
+                Ok(relative) if relative.as_os_str().is_empty() => PathBuf::from("."),
+                Ok(relative) => relative.to_path_buf(),
+                Err(_) => path.to_path_buf()
+            },
+            None => path.to_path_buf()
+        }
+    }
+
+    pub fn add(&mut self, event: &FileSystemEvent) -> Result<(), DomainError> {
+        let operation = match event {
+            FileSystemEvent::Create(event) => match event.kind() {
+                Kind::Directory => PlanOperation::Mkdir {
+                    path: self.relative(event.path()),
+                    recursive: event.recursive()
+                },
+                Kind::File => PlanOperation::Touch {
+                    path: self.relative(event.path()),
+                    recursive: event.recursive(),
+                    overwrite: event.overwrite()
+                },
+                Kind::Unknown => return Err(DomainError::CreateUnknown(event.path().to_path_buf()))
+            },
+            FileSystemEvent::Copy(event) => PlanOperation::Copy {
+                from: self.relative(event.source()),
+                to: self.relative(event.destination()),
+                merge: event.merge(),
+                overwrite: event.overwrite_policy(),
+                keep_both: event.keep_both(),
+                rename_template: match event.rename_template() {
+                    DEFAULT_RENAME_TEMPLATE => None,
+                    template => Some(template.to_string())
+                },
+                backup: event.backup().clone(),
+                follow_symlinks: event.follow_symlinks(),
+                filter: event.filter().clone()
+            },
+            FileSystemEvent::Move(event) => PlanOperation::Move {
+                from: self.relative(event.source()),
+                to: self.relative(event.destination()),
+                merge: event.merge(),
+                overwrite: event.overwrite_policy(),
+                keep_both: event.keep_both(),
+                rename_template: match event.rename_template() {
+                    DEFAULT_RENAME_TEMPLATE => None,
+                    template => Some(template.to_string())
+                },
+                backup: event.backup().clone(),
+                follow_symlinks: event.follow_symlinks(),
+                cross_device: event.cross_device(),
+                allow_dangerous: event.allow_dangerous(),
+                working_directory: event.working_directory().map(|cwd| self.relative(cwd)),
+                filter: event.filter().clone()
+            },
+            FileSystemEvent::Remove(event) => PlanOperation::Remove {
+                path: self.relative(event.path()),
+                recursive: event.recursive(),
+                trash: event.trash().is_some(),
+                trash_root: event.trash()
+                    .filter(|trash| Trash::home().ok().as_ref() != Some(*trash))
+                    .map(|trash| trash.root().to_path_buf()),
+                follow_symlinks: event.follow_symlinks(),
+                allow_dangerous: event.allow_dangerous(),
+                working_directory: event.working_directory().map(|cwd| self.relative(cwd)),
+                filter: event.filter().clone()
+            },
+            FileSystemEvent::Rename(event) => PlanOperation::Rename {
+                path: self.relative(event.path()),
+                name: event.name().to_os_string()
+            },
+            FileSystemEvent::Sync(event) => PlanOperation::Sync {
+                from: self.relative(event.source()),
+                to: self.relative(event.destination()),
+                delete: event.delete(),
+                skip_unchanged: event.skip_unchanged(),
+                comparison: event.comparison()
+            },
+            FileSystemEvent::Link(event) => PlanOperation::Link {
+                from: self.relative(event.source()),
+                to: self.relative(event.destination())
+            },
+            FileSystemEvent::Metadata(event) => PlanOperation::Metadata {
+                path: self.relative(event.path()),
+                mode: event.mode().cloned(),
+                owner: event.owner(),
+                group: event.group(),
+                modified: event.modified(),
+                recursive: event.recursive()
+            }
+        };
+        self.operations.push(operation);
+        Ok(())
+    }
+
+    /*
+    A relative base is resolved against cwd ( usually the directory holding the plan file ),
+    then relative operation paths are resolved against the base.
+    Trashing removals without trash root use the home trash.
+    */
+    pub fn events(&self, cwd: &Path) -> Result<Vec<FileSystemEvent>, DomainError> {
+        let base = match self.base() {
+            Some(base) => normalize(&cwd.join(base)),
+            None => cwd.to_path_buf()
+        };
+        let resolve = |path: &Path| normalize(&base.join(path));
+
+        self.operations.iter().map(|operation| Ok(match operation {
+            PlanOperation::Mkdir { path, recursive } => FileSystemEvent::Create(
+                CreateEvent::new(resolve(path).as_path(), Kind::Directory, *recursive, false)
+            ),
+            PlanOperation::Touch { path, recursive, overwrite } => FileSystemEvent::Create(
+                CreateEvent::new(resolve(path).as_path(), Kind::File, *recursive, *overwrite)
+            ),
+            PlanOperation::Copy { from, to, merge, overwrite, keep_both, rename_template, backup, follow_symlinks, filter } => FileSystemEvent::Copy(
+                CopyEvent::new(resolve(from).as_path(), resolve(to).as_path(), *merge, *overwrite)
+                    .with_keep_both(*keep_both)
+                    .with_rename_template(rename_template.as_deref())
+                    .with_backup(backup.clone())
+                    .with_follow_symlinks(*follow_symlinks)
+                    .with_filter(filter.clone())
+            ),
+            PlanOperation::Move { from, to, merge, overwrite, keep_both, rename_template, backup, follow_symlinks, cross_device, allow_dangerous, working_directory, filter } => {
+                let event = MoveEvent::new(resolve(from).as_path(), resolve(to).as_path(), *merge, *overwrite)
+                    .with_keep_both(*keep_both)
+                    .with_rename_template(rename_template.as_deref())
+                    .with_backup(backup.clone())
+                    .with_follow_symlinks(*follow_symlinks)
+                    .with_cross_device(*cross_device)
+                    .with_allow_dangerous(*allow_dangerous)
+                    .with_filter(filter.clone());
+                FileSystemEvent::Move(match working_directory {
+                    Some(cwd) => event.with_working_directory(resolve(cwd).as_path()),
+                    None => event
+                })
+            },
+            PlanOperation::Remove { path, recursive, trash, trash_root, follow_symlinks, allow_dangerous, working_directory, filter } => {
+                let mut event = RemoveEvent::new(resolve(path).as_path(), *recursive)
+                    .with_follow_symlinks(*follow_symlinks)
+                    .with_allow_dangerous(*allow_dangerous)
+                    .with_filter(filter.clone());
+                if let Some(cwd) = working_directory {
+                    event = event.with_working_directory(resolve(cwd).as_path());
+                }
+                FileSystemEvent::Remove(match (trash, trash_root) {
+                    (_, Some(root)) => event.with_trash(Trash::new(root.as_path())),
+                    (true, None) => event.with_trash(Trash::home()?),
+                    (false, None) => event
+                })
+            },
+            PlanOperation::Rename { path, name } => FileSystemEvent::Rename(
+                RenameEvent::new(resolve(path).as_path(), name.as_os_str())
+            ),
+            PlanOperation::Sync { from, to, delete, skip_unchanged, comparison } => FileSystemEvent::Sync(
+                SyncEvent::new(resolve(from).as_path(), resolve(to).as_path(), *delete)
+                    .with_skip_unchanged(*skip_unchanged)
+                    .with_comparison(*comparison)
+            ),
+            PlanOperation::Link { from, to } => FileSystemEvent::Link(
+                LinkEvent::new(resolve(from).as_path(), resolve(to).as_path())
+            ),
+            PlanOperation::Metadata { path, mode, owner, group, modified, recursive } => FileSystemEvent::Metadata(
+                MetadataEvent::new(resolve(path).as_path(), *recursive)
+                    .with_mode(mode.clone())
+                    .with_ownership(*owner, *group)
+                    .with_modified(*modified)
+            )
+        })).collect()
+    }
+
+    pub fn from_toml(content: &str) -> Result<Plan, DomainError> {
+        Ok(toml::from_str(content)?)
+    }
+
+    pub fn to_toml(&self) -> Result<String, DomainError> {
+        Ok(toml::to_string(self)?)
+    }
+
+    pub fn from_yaml(content: &str) -> Result<Plan, DomainError> {
+        Ok(serde_yaml::from_str(content)?)
+    }
+
+    pub fn to_yaml(&self) -> Result<String, DomainError> {
+        Ok(serde_yaml::to_string(self)?)
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::ffi::OsStr;
+
+    use crate::event::Pattern;
+
+    fn events() -> Vec<FileSystemEvent> {
+        vec![
+            FileSystemEvent::Create(CreateEvent::new(Path::new("/srv/data/NEW"), Kind::Directory, true, false)),
+            FileSystemEvent::Copy(
+                CopyEvent::new(Path::new("/srv/data/A"), Path::new("/srv/data/NEW/A"), true, false)
+                    .with_filter(Filter::new(vec![], vec![Pattern::new("target/").unwrap()], true))
+            ),
+            FileSystemEvent::Move(
+                MoveEvent::new(Path::new("/srv/data/B"), Path::new("/elsewhere/B"), false, OverwritePolicy::IfNewer)
+                    .with_keep_both(true)
+                    .with_rename_template(Some("{name}.{n}{ext}"))
+                    .with_backup(Backup::Numbered)
+                    .with_follow_symlinks(true)
+                    .with_cross_device(true)
+                    .with_allow_dangerous(true)
+            ),
+            FileSystemEvent::Sync(
+                SyncEvent::new(Path::new("/srv/data/A"), Path::new("/srv/mirror"), true)
+                    .with_comparison(SyncComparison::Content)
+            ),
+            FileSystemEvent::Rename(RenameEvent::new(Path::new("/srv/data/NEW/A"), OsStr::new("C"))),
+            FileSystemEvent::Remove(RemoveEvent::new(Path::new("/srv/data"), true).with_follow_symlinks(true)),
+            FileSystemEvent::Link(LinkEvent::new(Path::new("/srv/data/A/F"), Path::new("/srv/data/B/F"))),
+            FileSystemEvent::Metadata(
+                MetadataEvent::new(Path::new("/srv/data/NEW"), true)
+                    .with_mode(Some(Mode::new("u+rwX,go-w").unwrap()))
+                    .with_ownership(Some(1000), None)
+            )
+        ]
+    }
+
+    fn render(events: &[FileSystemEvent]) -> Vec<String> {
+        events.iter().map(FileSystemEvent::to_string).collect()
+    }
+
+    fn plan() -> Plan {
+        let mut plan = Plan::new(Some(Path::new("/srv/data")));
+        for event in events().iter() {
+            plan.add(event).unwrap();
+        }
+        plan
+    }
+
+    #[test]
+    fn paths_are_relative_to_base() {
+        let plan = plan();
+        assert_eq!(
+            plan.operations()[1],
+            PlanOperation::Copy {
+                from: PathBuf::from("A"),
+                to: PathBuf::from("NEW/A"),
+                merge: true,
+                overwrite: OverwritePolicy::Never,
+                keep_both: false,
+                rename_template: None,
+                backup: Backup::None,
+                follow_symlinks: false,
+                filter: Filter::new(vec![], vec![Pattern::new("target/").unwrap()], true)
+            }
+        );
+        assert_eq!(
+            plan.operations()[2],
+            PlanOperation::Move {
+                from: PathBuf::from("B"),
+                to: PathBuf::from("/elsewhere/B"),
+                merge: false,
+                overwrite: OverwritePolicy::IfNewer,
+                keep_both: true,
+                rename_template: Some("{name}.{n}{ext}".to_string()),
+                backup: Backup::Numbered,
+                follow_symlinks: true,
+                cross_device: true,
+                allow_dangerous: true,
+                working_directory: None,
+                filter: Filter::default()
+            }
+        );
+        assert_eq!(
+            plan.operations()[4],
+            PlanOperation::Rename { path: PathBuf::from("NEW/A"), name: OsString::from("C") }
+        );
+        assert_eq!(
+            plan.operations()[5],
+            PlanOperation::Remove {
+                path: PathBuf::from("."),
+                recursive: true,
+                trash: false,
+                trash_root: None,
+                follow_symlinks: true,
+                allow_dangerous: false,
+                working_directory: None,
+                filter: Filter::default()
+            }
+        );
+    }
+
+    #[test]
+    fn toml_round_trip() {
+        let toml = plan().to_toml().unwrap();
+        let loaded = Plan::from_toml(toml.as_str()).unwrap();
+
+        assert_eq!(loaded, plan());
+        assert_eq!(render(&loaded.events(Path::new("/")).unwrap()), render(&events()));
+    }
+
+    #[test]
+    fn yaml_round_trip() {
+        let yaml = plan().to_yaml().unwrap();
+        let loaded = Plan::from_yaml(yaml.as_str()).unwrap();
+
+        assert_eq!(loaded, plan());
+        assert_eq!(render(&loaded.events(Path::new("/")).unwrap()), render(&events()));
+    }
+
+    #[test]
+    fn round_trip_keeps_trash_and_working_directory() {
+        let events = vec![
+            FileSystemEvent::Remove(
+                RemoveEvent::new(Path::new("/srv/data/A"), true)
+                    .with_trash(Trash::new(Path::new("/srv/.Trash-1000")))
+                    .with_working_directory(Path::new("/srv/data"))
+            ),
+            FileSystemEvent::Move(
+                MoveEvent::new(Path::new("/srv/data/B"), Path::new("/srv/data/C"), false, false)
+                    .with_working_directory(Path::new("/home/user"))
+            )
+        ];
+        let mut plan = Plan::new(Some(Path::new("/srv/data")));
+        for event in events.iter() {
+            plan.add(event).unwrap();
+        }
+
+        let loaded = Plan::from_toml(plan.to_toml().unwrap().as_str()).unwrap();
+        assert_eq!(loaded, plan);
+        assert_eq!(
+            serde_json::to_string(&loaded.events(Path::new("/")).unwrap()).unwrap(),
+            serde_json::to_string(&events).unwrap()
+        );
+    }
+
+    #[test]
+    fn read_hand_written_plans() {
+        let toml = Plan::from_toml(r#"
+base = "data"
+
+[[operations]]
+copy = { from = "A", to = "../backup/A", merge = true, exclude = ["target/", "node_modules/"] }
+
+[[operations]]
+remove = { path = "A/tmp", recursive = true }
+
+[[operations]]
+metadata = { path = "A", mode = "750", group = 100, recursive = true }
+"#).unwrap();
+
+        let yaml = Plan::from_yaml(r#"
+base: data
+operations:
+  - copy: { from: A, to: ../backup/A, merge: true, exclude: [target/, node_modules/] }
+  - remove: { path: A/tmp, recursive: true }
+  - metadata: { path: A, mode: "750", group: 100, recursive: true }
+"#).unwrap();
+
+        assert_eq!(toml, yaml);
+        assert_eq!(
+            render(&toml.events(Path::new("/srv")).unwrap()),
+            vec![
+                "cp -m --exclude target/ --exclude node_modules/ /srv/data/A /srv/backup/A",
+                "rm -r /srv/data/A/tmp",
+                "chmod -R 750 /srv/data/A && chown -R :100 /srv/data/A"
+            ]
+        );
+    }
+}
@@ -26,6 +26,7 @@ use serde::{ Serialize, Deserialize };
 
 use crate::{
     errors::{ DomainError },
+    event::{ is_false, Filter, Trash, Danger },
     capability::{
         Guard,
         Capability
@@ -40,55 +41,165 @@ use crate::{
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct RemoveEvent {
+    #[serde(with = "crate::lossless")]
     path: PathBuf,
-    recursive: bool
+    recursive: bool,
+    #[serde(default, skip_serializing_if = "Filter::is_empty")]
+    filter: Filter,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    trash: Option<Trash>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    follow_symlinks: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    allow_dangerous: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "crate::lossless::option")]
+    working_directory: Option<PathBuf>
 }
 
 impl RemoveEvent {
     pub fn new(path: &Path, recursive: bool) -> RemoveEvent {
         RemoveEvent {
             path: path.to_path_buf(),
-            recursive
+            recursive,
+            filter: Filter::default(),
+            trash: None,
+            follow_symlinks: false,
+            allow_dangerous: false,
+            working_directory: None
         }
     }
 
+    pub fn with_filter(mut self, filter: Filter) -> RemoveEvent {
+        self.filter = filter;
+        self
+    }
+
+    //Entries are moved into the trash instead of being permanently deleted
+    pub fn with_trash(mut self, trash: Trash) -> RemoveEvent {
+        self.trash = Some(trash);
+        self
+    }
+
+    pub fn with_follow_symlinks(mut self, follow_symlinks: bool) -> RemoveEvent {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    //Home, working directory and mount points, never the root
+    pub fn with_allow_dangerous(mut self, allow_dangerous: bool) -> RemoveEvent {
+        self.allow_dangerous = allow_dangerous;
+        self
+    }
+
+    //Working directory of the session, guarded like the home directory
+    pub fn with_working_directory(mut self, cwd: &Path) -> RemoveEvent {
+        self.working_directory = Some(cwd.to_path_buf());
+        self
+    }
+
     pub fn path(&self) -> &Path { self.path.as_path() }
     pub fn recursive(&self) -> bool { self.recursive }
+    pub fn follow_symlinks(&self) -> bool { self.follow_symlinks }
+    pub fn allow_dangerous(&self) -> bool { self.allow_dangerous }
+    pub fn filter(&self) -> &Filter { &self.filter }
+    pub fn trash(&self) -> Option<&Trash> { self.trash.as_ref() }
+    pub fn working_directory(&self) -> Option<&Path> { self.working_directory.as_deref() }
 }
 
 pub fn atomize<E: Entry, F: ReadableFileSystem<Item=E>>(event: &RemoveEvent, fs: &F, guard: &mut dyn Guard) -> Result<AtomicTransaction, DomainError> {
-    let entry = fs.status(event.path())?;
+    Danger::check(event.path(), event.working_directory(), event.allow_dangerous())?;
     let mut transaction = AtomicTransaction::default();
+    remove(event, Capability::Delete.default_granted(), fs, guard, &mut transaction)?;
+    Ok(transaction)
+}
+
+//Delete is granted to path by default only when requested, like extraneous entries of a sync, returns whether path is gone
+pub(crate) fn atomize_requested<E: Entry, F: ReadableFileSystem<Item=E>>(event: &RemoveEvent, requested: bool, fs: &F, guard: &mut dyn Guard, transaction: &mut AtomicTransaction) -> Result<bool, DomainError> {
+    Danger::check(event.path(), event.working_directory(), event.allow_dangerous())?;
+    remove(event, requested, fs, guard, transaction)
+}
+
+//Children share the transaction so that trashed entries never get the same name, returns whether path is gone
+fn remove<E: Entry, F: ReadableFileSystem<Item=E>>(event: &RemoveEvent, granted: bool, fs: &F, guard: &mut dyn Guard, transaction: &mut AtomicTransaction) -> Result<bool, DomainError> {
+    let entry = fs.status(event.path())?;
 
     if !entry.exists() {
         return Err(DomainError::DoesNotExists(event.path().to_path_buf()))
     }
 
-    if entry.is_file() {
+    //Granted by default, only policies restrict it
+    if !guard.authorize_entry(Capability::Delete, granted, event.path(), entry.is_dir())?.is_allowed() {
+        return Ok(false);
+    }
+
+    //A linked directory is unlinked like rm -r does, removing its children would remove them from where the link points
+    let linked = entry.is_dir() && entry.is_symlink();
+    if linked && event.follow_symlinks()
+        && !guard.authorize_entry(Capability::FollowSymlinks, event.follow_symlinks(), event.path(), entry.is_dir())?.is_allowed() {
+        return Ok(false);
+    }
+
+    //Holding no data, the link itself is unlinked even when trashing, moving it to the trash would move its children
+    if linked && !event.follow_symlinks() {
         transaction.add(Atomic::RemoveFile(entry.path().to_path_buf()));
+    } else if entry.is_file() {
+        match event.trash() {
+            Some(trash) => trash.atomize(&entry, fs, guard, transaction)?,
+            None => transaction.add(Atomic::RemoveFile(entry.path().to_path_buf()))
+        }
     } else if entry.is_dir() {
         let children = fs.read_dir(entry.path())?;
 
-        if children.is_empty() {
-            transaction.add(Atomic::RemoveEmptyDirectory(entry.path().to_path_buf()))
-        } else if guard.authorize(Capability::Recursive, event.recursive(), event.path())? {
-            for child in children {
-                transaction.merge(
-                    atomize(
-                        &RemoveEvent::new(
-                            child.path(),
-                            true
-                        ),
-                        fs, 
-                        guard
-                    )?
-                )
+        //A followed link is unlinked once its target is emptied, rmdir refuses links
+        if children.is_empty() && linked {
+            transaction.add(Atomic::RemoveFile(entry.path().to_path_buf()));
+        } else if children.is_empty() {
+            match event.trash() {
+                Some(trash) => trash.atomize(&entry, fs, guard, transaction)?,
+                None => transaction.add(Atomic::RemoveEmptyDirectory(entry.path().to_path_buf()))
             }
-            transaction.add(Atomic::RemoveEmptyDirectory(entry.path().to_path_buf()))
+        } else if guard.authorize_entry(Capability::Recursive, event.recursive(), event.path(), entry.is_dir())?.is_allowed() {
+            let filter = event.filter().descend(entry.path(), entry.source());
+            if let (Some(trash), true, false) = (event.trash(), filter.is_empty(), linked) {
+                trash.atomize(&entry, fs, guard, transaction)?;
+                return Ok(true);
+            }
+
+            //Excluded or kept children keep their directory, trashed children leave it empty
+            let mut vacated = true;
+            for child in children.iter() {
+                if !filter.accepts(child.path(), child.is_dir()) {
+                    vacated = false;
+                    continue;
+                }
+                vacated &= remove(
+                    &RemoveEvent {
+                        path: child.path().to_path_buf(),
+                        recursive: true,
+                        filter: filter.clone(),
+                        trash: event.trash.clone(),
+                        follow_symlinks: event.follow_symlinks,
+                        allow_dangerous: event.allow_dangerous,
+                        working_directory: event.working_directory.clone()
+                    },
+                    Capability::Delete.default_granted(),
+                    fs,
+                    guard,
+                    transaction
+                )?;
+            }
+            if vacated && linked {
+                transaction.add(Atomic::RemoveFile(entry.path().to_path_buf()))
+            } else if vacated {
+                transaction.add(Atomic::RemoveEmptyDirectory(entry.path().to_path_buf()))
+            }
+            return Ok(vacated);
+        } else {
+            return Ok(false);
         }
     }
 
-    Ok(transaction)
+    Ok(true)
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -105,8 +216,17 @@ mod real_tests {
             RealFileSystem
         },
         capability::{
-            ZealedGuard
-        }
+            ZealedGuard,
+            BlindGuard,
+            QuietGuard,
+            Policy,
+            PolicyRule,
+            PolicyAction,
+            PolicyGuard,
+            Thresholds,
+            ThresholdGuard
+        },
+        event::{ Filter, Pattern, Trash, FileSystemEvent }
     };
 
     #[test]
@@ -165,6 +285,153 @@ mod real_tests {
 
         assert!(!chroot.join("RDIR").exists());
     }
+
+    #[test]
+    fn remove_operation_directory_filtered() {
+        let chroot = Samples::init_simple_chroot("remove_operation_directory_filtered");
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+
+        atomize(
+            &RemoveEvent::new(
+                chroot.as_path(),
+                true
+            ).with_filter(Filter::new(vec![], vec![Pattern::new("/RDIR/RFILEB").unwrap()], false)),
+            &fs,
+            &mut ZealedGuard
+        ).unwrap()
+         .apply(&mut fs)
+         .unwrap();
+
+        assert!(chroot.join("RDIR/RFILEB").exists());
+        assert!(!chroot.join("RDIR/RFILEA").exists());
+        assert!(!chroot.join("RDIR2").exists());
+        assert!(!chroot.join("RDIR3").exists());
+    }
+
+    #[test]
+    fn remove_operation_trash() {
+        let chroot = Samples::init_simple_chroot("remove_operation_trash");
+        let trash = Trash::new(Samples::init_empty_chroot("remove_operation_trash_bin").join("Trash").as_path());
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+
+        atomize(
+            &RemoveEvent::new(chroot.join("RDIR2").as_path(), true).with_trash(trash.clone()),
+            &fs,
+            &mut ZealedGuard
+        ).unwrap()
+         .apply(&mut fs)
+         .unwrap();
+
+        atomize(
+            &RemoveEvent::new(chroot.join("RDIR").as_path(), true)
+                .with_filter(Filter::new(vec![], vec![Pattern::new("RFILEB").unwrap()], false))
+                .with_trash(trash.clone()),
+            &fs,
+            &mut ZealedGuard
+        ).unwrap()
+         .apply(&mut fs)
+         .unwrap();
+
+        assert!(!chroot.join("RDIR2").exists());
+        assert!(trash.files().join("RDIR2/RFILEC").is_file());
+        assert!(trash.info().join("RDIR2.trashinfo").is_file());
+        assert!(chroot.join("RDIR/RFILEB").exists());
+        assert!(!chroot.join("RDIR/RFILEA").exists());
+        assert!(trash.files().join("RFILEA").is_file());
+        assert_eq!(trash.list().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn remove_operation_policy() {
+        let chroot = Samples::init_simple_chroot("remove_operation_policy");
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+        let policy = Policy::new(vec![
+            PolicyRule::new(Pattern::new("RFILEB").unwrap(), vec![Capability::Delete], PolicyAction::Ask),
+            PolicyRule::new(Pattern::new(format!("{}/RDIR2/**", chroot.to_string_lossy()).as_str()).unwrap(), vec![Capability::Delete], PolicyAction::Deny)
+        ]);
+        let mut guard = PolicyGuard::new(policy, Box::new(BlindGuard)).with_ask(Box::new(QuietGuard));
+
+        atomize(&RemoveEvent::new(chroot.join("RDIR").as_path(), true), &fs, &mut guard).unwrap()
+            .apply(&mut fs)
+            .unwrap();
+        assert!(chroot.join("RDIR/RFILEB").exists());
+        assert!(!chroot.join("RDIR/RFILEA").exists());
+
+        assert!(matches!(
+            atomize(&RemoveEvent::new(chroot.join("RDIR2").as_path(), true), &fs, &mut guard),
+            Err(DomainError::PolicyDenied(Capability::Delete, _))
+        ));
+    }
+
+    #[test]
+    fn remove_operation_beyond_thresholds() {
+        let chroot = Samples::init_simple_chroot("remove_operation_beyond_thresholds");
+        let fs = FileSystemAdapter(RealFileSystem::default());
+        let mut guard = ThresholdGuard::new(Thresholds::new(None, None, Some(2)), Box::new(BlindGuard));
+
+        //Only whole events are measured
+        atomize(&RemoveEvent::new(chroot.join("RDIR").as_path(), true), &fs, &mut guard).unwrap();
+        assert!(matches!(
+            FileSystemEvent::Remove(RemoveEvent::new(chroot.join("RDIR").as_path(), true)).atomize(&fs, &mut guard),
+            Err(DomainError::BulkNotAllowed(_))
+        ));
+
+        let mut guard = guard.with_confirm(Box::new(BlindGuard));
+        assert_eq!(
+            FileSystemEvent::Remove(RemoveEvent::new(chroot.join("RDIR").as_path(), true)).atomize(&fs, &mut guard).unwrap().iter().count(),
+            3
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn remove_operation_does_not_follow_symlinks() {
+        let chroot = Samples::init_simple_chroot("remove_operation_does_not_follow_symlinks");
+        std::os::unix::fs::symlink(chroot.join("RDIR2"), chroot.join("RDIR/LINK")).unwrap();
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+
+        atomize(&RemoveEvent::new(chroot.join("RDIR").as_path(), true), &fs, &mut ZealedGuard).unwrap()
+            .apply(&mut fs)
+            .unwrap();
+        assert!(!chroot.join("RDIR").exists());
+        assert!(chroot.join("RDIR2/RFILEC").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn remove_operation_follows_symlinks_and_unlinks() {
+        let chroot = Samples::init_simple_chroot("remove_operation_follows_symlinks_and_unlinks");
+        std::os::unix::fs::symlink(chroot.join("RDIR2"), chroot.join("RDIR/LINK")).unwrap();
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+
+        atomize(&RemoveEvent::new(chroot.join("RDIR").as_path(), true).with_follow_symlinks(true), &fs, &mut ZealedGuard).unwrap()
+            .apply(&mut fs)
+            .unwrap();
+        assert!(!chroot.join("RDIR").exists());
+        assert!(chroot.join("RDIR2").is_dir());
+        assert!(!chroot.join("RDIR2/RFILEC").exists());
+        assert!(!chroot.join("RDIR2/RFILEA").exists());
+    }
+
+    #[test]
+    fn remove_operation_refuses_dangerous_targets() {
+        let fs = FileSystemAdapter(RealFileSystem::default());
+
+        assert!(matches!(
+            atomize(&RemoveEvent::new(Path::new("/"), true).with_allow_dangerous(true), &fs, &mut BlindGuard),
+            Err(DomainError::DangerousTarget(_, Danger::Root))
+        ));
+        assert!(matches!(
+            atomize(&RemoveEvent::new(std::env::current_dir().unwrap().as_path(), true), &fs, &mut BlindGuard),
+            Err(DomainError::DangerousTarget(_, Danger::WorkingDirectory))
+        ));
+
+        let chroot = Samples::init_simple_chroot("remove_operation_refuses_dangerous_targets");
+        assert!(matches!(
+            atomize(&RemoveEvent::new(chroot.join("RDIR2").as_path(), true).with_working_directory(chroot.join("RDIR2/NESTED").as_path()), &fs, &mut BlindGuard),
+            Err(DomainError::DangerousTarget(_, Danger::WorkingDirectory))
+        ));
+    }
 }
 
 
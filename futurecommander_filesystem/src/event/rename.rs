@@ -0,0 +1,422 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    collections::{ BTreeMap, BTreeSet },
+    ffi::{ OsStr, OsString },
+    path::{ Path, PathBuf, MAIN_SEPARATOR },
+    str::FromStr
+};
+
+use serde::{ Serialize, Deserialize };
+
+use regex::{ Regex, bytes::Regex as BytesRegex };
+
+use crate::{
+    lossless,
+    errors::DomainError,
+    event::{
+        MoveEvent,
+        mov
+    },
+    capability::Guard,
+    port::{
+        Entry,
+        ReadableFileSystem,
+        AtomicTransaction
+    }
+};
+
+/*
+Changes the last component of path, the entry stays in its directory.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RenameEvent {
+    #[serde(with = "crate::lossless")]
+    path: PathBuf,
+    #[serde(with = "crate::lossless::name")]
+    name: OsString
+}
+
+impl RenameEvent {
+    pub fn new(path: &Path, name: &OsStr) -> RenameEvent {
+        RenameEvent {
+            path: path.to_path_buf(),
+            name: name.to_os_string()
+        }
+    }
+
+    pub fn path(&self) -> &Path { self.path.as_path() }
+    pub fn name(&self) -> &OsStr { self.name.as_os_str() }
+    pub fn destination(&self) -> PathBuf { self.path.with_file_name(self.name.as_os_str()) }
+}
+
+pub fn validate_name(name: &OsStr) -> Result<(), DomainError> {
+    let bytes = lossless::bytes(name);
+    if bytes.is_empty() || name == "." || name == ".." || bytes.contains(&(MAIN_SEPARATOR as u8)) || bytes.contains(&0) {
+        return Err(DomainError::InvalidName(lossless::escape(name)));
+    }
+    Ok(())
+}
+
+pub fn atomize<E: Entry, F: ReadableFileSystem<Item=E>>(event: &RenameEvent, fs: &F, guard: &mut dyn Guard) -> Result<AtomicTransaction, DomainError> {
+    validate_name(event.name())?;
+
+    let destination = event.destination();
+    if destination == event.path() {
+        return Ok(AtomicTransaction::default());
+    }
+    if fs.status(destination.as_path())?.exists() {
+        return Err(DomainError::RenameCollision(event.path().to_path_buf(), destination));
+    }
+
+    mov::atomize(&MoveEvent::new(event.path(), destination.as_path(), false, false), fs, guard)
+}
+
+/*
+Computes new names for a batch rename, either a sed like substitution s/REGEX/REPLACEMENT/[g]
+where the replacement may use $1 or ${name} captures, or a template where {name} is the name without extension,
+{ext} the extension with its dot and {n} a counter starting at 1, optionally zero padded like {n:03}.
+*/
+#[derive(Debug, Clone)]
+pub enum RenameRule {
+    Substitution {
+        regex: BytesRegex,
+        replacement: String,
+        global: bool
+    },
+    Template(String)
+}
+
+impl RenameRule {
+    fn substitution(rule: &str) -> Result<RenameRule, DomainError> {
+        let invalid = |reason: &str| DomainError::InvalidPattern(rule.to_string(), reason.to_string());
+
+        let mut chars = rule.chars().skip(1);
+        let delimiter = chars.next().ok_or_else(|| invalid("missing delimiter"))?;
+        let mut parts = vec![String::new()];
+        let mut escaped = false;
+        for c in chars {
+            if escaped {
+                if c != delimiter {
+                    parts.last_mut().unwrap().push('\\');
+                }
+                parts.last_mut().unwrap().push(c);
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == delimiter {
+                parts.push(String::new());
+            } else {
+                parts.last_mut().unwrap().push(c);
+            }
+        }
+
+        if parts.len() != 3 {
+            return Err(invalid("expected s/REGEX/REPLACEMENT/"));
+        }
+        let global = match parts[2].as_str() {
+            "" => false,
+            "g" => true,
+            _ => return Err(invalid("only the g flag is supported"))
+        };
+
+        Ok(RenameRule::Substitution {
+            regex: BytesRegex::new(parts[0].as_str()).map_err(|error| invalid(error.to_string().as_str()))?,
+            replacement: parts[1].clone(),
+            global
+        })
+    }
+
+    fn placeholders() -> Regex {
+        Regex::new(r"\{([^{}]*)\}").unwrap()
+    }
+
+    fn template(rule: &str) -> Result<RenameRule, DomainError> {
+        let counter = Regex::new(r"^n(:\d+)?$").unwrap();
+        for captures in Self::placeholders().captures_iter(rule) {
+            match &captures[1] {
+                "name" | "ext" => {},
+                placeholder if counter.is_match(placeholder) => {},
+                placeholder => return Err(DomainError::InvalidPattern(rule.to_string(), format!("unknown placeholder {{{}}}", placeholder)))
+            }
+        }
+        Ok(RenameRule::Template(rule.to_string()))
+    }
+
+    //Directories have no extension, bytes which are not UTF-8 are kept as they are
+    pub fn apply(&self, name: &OsStr, is_dir: bool, n: usize) -> Result<OsString, DomainError> {
+        let bytes = lossless::bytes(name);
+        let renamed = match self {
+            RenameRule::Substitution { regex, replacement, global: true } => regex.replace_all(&bytes, replacement.as_bytes()).to_vec(),
+            RenameRule::Substitution { regex, replacement, global: false } => regex.replace(&bytes, replacement.as_bytes()).to_vec(),
+            RenameRule::Template(template) => {
+                let path = Path::new(name);
+                let (stem, ext) = if is_dir {
+                    (bytes, Vec::new())
+                } else {
+                    let mut ext = Vec::new();
+                    if let Some(extension) = path.extension() {
+                        ext.push(b'.');
+                        ext.append(&mut lossless::bytes(extension));
+                    }
+                    (path.file_stem().map(lossless::bytes).unwrap_or_default(), ext)
+                };
+
+                let mut renamed = Vec::new();
+                let mut last = 0;
+                for captures in Self::placeholders().captures_iter(template) {
+                    let (placeholder, counter) = (captures.get(0).unwrap(), &captures[1]);
+                    renamed.extend_from_slice(&template.as_bytes()[last..placeholder.start()]);
+                    match counter {
+                        "name" => renamed.extend_from_slice(&stem),
+                        "ext" => renamed.extend_from_slice(&ext),
+                        counter => renamed.extend_from_slice(
+                            match counter.strip_prefix("n:") {
+                                Some(width) => format!("{:0width$}", n, width = width.parse().unwrap_or(0)),
+                                None => n.to_string()
+                            }.as_bytes()
+                        )
+                    }
+                    last = placeholder.end();
+                }
+                renamed.extend_from_slice(&template.as_bytes()[last..]);
+                renamed
+            }
+        };
+        lossless::from_bytes(renamed)
+    }
+
+    /*
+    Renames of directory children sorted by name, unchanged names are left out.
+    Two children renamed to the same name, or onto a child which stays in place, are refused before anything is staged.
+    Renames are ordered so that a child is renamed away before another one takes its name.
+    */
+    pub fn batch<E: Entry, F: ReadableFileSystem<Item=E>>(&self, fs: &F, directory: &Path) -> Result<Vec<RenameEvent>, DomainError> {
+        let entry = fs.status(directory)?;
+        if !entry.exists() {
+            return Err(DomainError::DoesNotExists(directory.to_path_buf()));
+        }
+
+        let mut children : Vec<(OsString, bool)> = fs.read_dir(directory)?
+            .iter()
+            .filter_map(|child| child.name().map(|name| (name.to_os_string(), child.is_dir())))
+            .collect();
+        children.sort();
+
+        let mut renamed : BTreeMap<OsString, OsString> = BTreeMap::new();
+        let mut targets : BTreeMap<OsString, OsString> = BTreeMap::new();
+        for (n, (name, is_dir)) in children.iter().enumerate() {
+            let new_name = self.apply(name, *is_dir, n + 1)?;
+            validate_name(new_name.as_os_str())?;
+            if new_name == *name {
+                continue;
+            }
+            if let Some(other) = targets.insert(new_name.clone(), name.clone()) {
+                return Err(DomainError::RenameCollision(directory.join(other), directory.join(new_name)));
+            }
+            renamed.insert(name.clone(), new_name);
+        }
+
+        let names : BTreeSet<&OsString> = children.iter().map(|(name, _)| name).collect();
+        for (name, new_name) in renamed.iter() {
+            if names.contains(new_name) && !renamed.contains_key(new_name) {
+                return Err(DomainError::RenameCollision(directory.join(name), directory.join(new_name)));
+            }
+        }
+
+        let mut events = Vec::new();
+        while !renamed.is_empty() {
+            let ready = renamed.iter()
+                .find(|(_, new_name)| !renamed.contains_key(*new_name))
+                .map(|(name, new_name)| (name.clone(), new_name.clone()));
+
+            match ready {
+                Some((name, new_name)) => {
+                    renamed.remove(&name);
+                    events.push(RenameEvent::new(directory.join(name.as_os_str()).as_path(), new_name.as_os_str()));
+                },
+                //Only cycles like a swap are left
+                None => {
+                    let (name, new_name) = renamed.iter().next().unwrap();
+                    return Err(DomainError::RenameCollision(directory.join(name), directory.join(new_name)));
+                }
+            }
+        }
+        Ok(events)
+    }
+}
+
+impl FromStr for RenameRule {
+    type Err = DomainError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with("s/") || s.starts_with("s|") || s.starts_with("s#") {
+            Self::substitution(s)
+        } else {
+            Self::template(s)
+        }
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substitution_rule() {
+        let rule : RenameRule = r"s/IMG_(\d+)/photo-$1/".parse().unwrap();
+        assert_eq!(rule.apply(OsStr::new("IMG_0042.jpg"), false, 1).unwrap(), "photo-0042.jpg");
+        assert_eq!(rule.apply(OsStr::new("notes.txt"), false, 2).unwrap(), "notes.txt");
+
+        let rule : RenameRule = "s|a|b|g".parse().unwrap();
+        assert_eq!(rule.apply(OsStr::new("banana"), false, 1).unwrap(), "bbnbnb");
+
+        let rule : RenameRule = r"s/\//-/".parse().unwrap();
+        assert_eq!(rule.apply(OsStr::new("a/b"), false, 1).unwrap(), "a-b");
+
+        assert!("s/IMG/photo".parse::<RenameRule>().is_err());
+        assert!("s/IMG/photo/x".parse::<RenameRule>().is_err());
+        assert!("s/(/photo/".parse::<RenameRule>().is_err());
+    }
+
+    #[test]
+    fn template_rule() {
+        let rule : RenameRule = "{n:03}-{name}{ext}".parse().unwrap();
+        assert_eq!(rule.apply(OsStr::new("IMG_0042.jpg"), false, 7).unwrap(), "007-IMG_0042.jpg");
+        assert_eq!(rule.apply(OsStr::new("holidays.2019"), true, 12).unwrap(), "012-holidays.2019");
+
+        let rule : RenameRule = "{name}_{n}".parse().unwrap();
+        assert_eq!(rule.apply(OsStr::new("archive.tar.gz"), false, 1).unwrap(), "archive.tar_1");
+
+        assert!("{n}-{title}".parse::<RenameRule>().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rules_keep_bytes_which_are_not_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+        let name = OsStr::from_bytes(b"caf\xe9.txt");
+
+        let rule : RenameRule = r"s/\.txt$/.md/".parse().unwrap();
+        assert_eq!(rule.apply(name, false, 1).unwrap().as_os_str(), OsStr::from_bytes(b"caf\xe9.md"));
+
+        let rule : RenameRule = "{n}-{name}{ext}".parse().unwrap();
+        assert_eq!(rule.apply(name, false, 2).unwrap().as_os_str(), OsStr::from_bytes(b"2-caf\xe9.txt"));
+    }
+
+    #[test]
+    fn refuse_invalid_names() {
+        assert!(validate_name(OsStr::new("photo.jpg")).is_ok());
+        assert!(validate_name(OsStr::new("")).is_err());
+        assert!(validate_name(OsStr::new("..")).is_err());
+        assert!(validate_name(OsStr::new("a/b")).is_err());
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod real_tests {
+    use super::*;
+
+    use std::fs::{ write, read_to_string };
+
+    use crate::{
+        sample::Samples,
+        port::{
+            FileSystemAdapter
+        },
+        infrastructure::{
+            RealFileSystem
+        },
+        capability::{
+            ZealedGuard
+        }
+    };
+
+    #[test]
+    fn rename_operation_file() {
+        let chroot = Samples::init_simple_chroot("rename_operation_file");
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+
+        atomize(
+            &RenameEvent::new(chroot.join("RDIR/RFILEA").as_path(), OsStr::new("RENAMED")),
+            &fs,
+            &mut ZealedGuard
+        ).unwrap()
+         .apply(&mut fs)
+         .unwrap();
+
+        assert!(!chroot.join("RDIR/RFILEA").exists());
+        assert!(chroot.join("RDIR/RENAMED").is_file());
+    }
+
+    #[test]
+    fn rename_operation_refuse_existing_name() {
+        let chroot = Samples::init_simple_chroot("rename_operation_refuse_existing_name");
+        let fs = FileSystemAdapter(RealFileSystem::default());
+
+        match atomize(&RenameEvent::new(chroot.join("RDIR/RFILEA").as_path(), OsStr::new("RFILEB")), &fs, &mut ZealedGuard) {
+            Err(DomainError::RenameCollision(path, destination)) => {
+                assert_eq!(path, chroot.join("RDIR/RFILEA"));
+                assert_eq!(destination, chroot.join("RDIR/RFILEB"));
+            },
+            _ => panic!("renaming onto an existing entry must be refused")
+        }
+    }
+
+    #[test]
+    fn batch_orders_chained_renames() {
+        let chroot = Samples::init_empty_chroot("rename_batch_orders_chained_renames");
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+        write(chroot.join("0"), "zero").unwrap();
+        write(chroot.join("1"), "one").unwrap();
+
+        let events = "{n}".parse::<RenameRule>().unwrap().batch(&fs, chroot.as_path()).unwrap();
+        assert_eq!(events[0].path(), chroot.join("1").as_path());
+        assert_eq!(events[1].path(), chroot.join("0").as_path());
+
+        for event in events.iter() {
+            atomize(event, &fs, &mut ZealedGuard).unwrap().apply(&mut fs).unwrap();
+        }
+        assert_eq!(read_to_string(chroot.join("1")).unwrap(), "zero");
+        assert_eq!(read_to_string(chroot.join("2")).unwrap(), "one");
+        assert!(!chroot.join("0").exists());
+    }
+
+    #[test]
+    fn batch_detects_collisions() {
+        let chroot = Samples::init_simple_chroot("rename_batch_detects_collisions");
+        let fs = FileSystemAdapter(RealFileSystem::default());
+
+        match "s/RFILE[AB]/RFILE/".parse::<RenameRule>().unwrap().batch(&fs, chroot.join("RDIR").as_path()) {
+            Err(DomainError::RenameCollision(path, destination)) => {
+                assert_eq!(path, chroot.join("RDIR/RFILEA"));
+                assert_eq!(destination, chroot.join("RDIR/RFILE"));
+            },
+            _ => panic!("two entries renamed to the same name must be refused")
+        }
+
+        match "s/RFILEA/RFILEB/".parse::<RenameRule>().unwrap().batch(&fs, chroot.join("RDIR").as_path()) {
+            Err(DomainError::RenameCollision(_, destination)) => assert_eq!(destination, chroot.join("RDIR/RFILEB")),
+            _ => panic!("renaming onto an entry staying in place must be refused")
+        }
+    }
+}
@@ -0,0 +1,499 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    collections::HashSet,
+    ffi::OsString,
+    fs::metadata,
+    path:: { Path, PathBuf },
+    time::{ SystemTime, UNIX_EPOCH }
+};
+
+use serde::{ Serialize, Deserialize };
+
+use crate::{
+    errors::{ DomainError },
+    tools::same_content,
+    capability::{
+        Guard,
+        Capability
+    },
+    port::{
+        Entry,
+        ReadableFileSystem,
+        Metadata,
+        Atomic,
+        AtomicTransaction
+    },
+    event::{
+        is_false,
+        copy::{ self, CopyEvent },
+        remove::{ self, RemoveEvent }
+    }
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncComparison {
+    #[default]
+    SizeAndTime,
+    Content
+}
+
+impl SyncComparison {
+    //Entries without real content, like virtually created files, are always considered changed
+    pub fn unchanged<E: Entry>(self, source: &E, destination: &E) -> bool {
+        let (source, destination) = match (source.source(), destination.source()) {
+            (Some(source), Some(destination)) => (source, destination),
+            _ => return false
+        };
+
+        if source == destination {
+            return true;
+        }
+
+        match self {
+            //Like the quick check of rsync, to the second. Copies made by a sync keep the time of their source
+            SyncComparison::SizeAndTime => match (metadata(source), metadata(destination)) {
+                (Ok(source), Ok(destination)) =>
+                    source.len() == destination.len()
+                        && match (source.modified().map(seconds), destination.modified().map(seconds)) {
+                            (Ok(Some(source)), Ok(Some(destination))) => source == destination,
+                            _ => false
+                        },
+                _ => false
+            },
+            SyncComparison::Content => same_content(source, destination).unwrap_or(false)
+        }
+    }
+}
+
+/*
+Makes destination an exact mirror of source, like rsync --delete.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SyncEvent {
+    #[serde(with = "crate::lossless")]
+    source: PathBuf,
+    #[serde(with = "crate::lossless")]
+    destination: PathBuf,
+    delete: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    skip_unchanged: bool,
+    #[serde(default)]
+    comparison: SyncComparison
+}
+
+impl SyncEvent {
+    pub fn new(source: &Path, destination: &Path, delete: bool) -> SyncEvent {
+        SyncEvent {
+            source: source.to_path_buf(),
+            destination: destination.to_path_buf(),
+            delete,
+            skip_unchanged: false,
+            comparison: SyncComparison::default()
+        }
+    }
+
+    pub fn with_skip_unchanged(mut self, skip_unchanged: bool) -> SyncEvent {
+        self.skip_unchanged = skip_unchanged;
+        self
+    }
+
+    pub fn with_comparison(mut self, comparison: SyncComparison) -> SyncEvent {
+        self.comparison = comparison;
+        self
+    }
+
+    pub fn source(&self) -> &Path { self.source.as_path() }
+    pub fn destination(&self) -> &Path { self.destination.as_path() }
+    pub fn delete(&self) -> bool { self.delete }
+    pub fn skip_unchanged(&self) -> bool { self.skip_unchanged }
+    pub fn comparison(&self) -> SyncComparison { self.comparison }
+
+    fn child(&self, source: &Path, destination: &Path) -> SyncEvent {
+        SyncEvent {
+            source: source.to_path_buf(),
+            destination: destination.to_path_buf(),
+            ..self.clone()
+        }
+    }
+}
+
+//Delete is asked once, by the removal itself, returns whether target is gone
+fn delete<E: Entry, F: ReadableFileSystem<Item=E>>(event: &SyncEvent, target: &E, fs: &F, guard: &mut dyn Guard, transaction: &mut AtomicTransaction) -> Result<bool, DomainError> {
+    remove::atomize_requested(&RemoveEvent::new(target.path(), true), event.delete(), fs, guard, transaction)
+}
+
+fn seconds(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH).ok().map(|elapsed| elapsed.as_secs())
+}
+
+//Every copied file gets the modification time of its source, so that the next sync finds it unchanged
+pub fn atomize<E: Entry, F: ReadableFileSystem<Item=E>>(event: &SyncEvent, fs: &F, guard: &mut dyn Guard) -> Result<AtomicTransaction, DomainError> {
+    let mut transaction = mirror(event, fs, guard)?;
+    let mut times = Vec::new();
+    for atomic in transaction.iter() {
+        if let Atomic::CopyFileToFile { source, destination } = atomic {
+            if let Some(modified) = fs.metadata(source.as_path())?.and_then(|metadata| metadata.modified()) {
+                times.push(Atomic::SetMetadata {
+                    path: destination.clone(),
+                    metadata: Metadata::default().with_modified(Some(modified))
+                });
+            }
+        }
+    }
+    for atomic in times {
+        transaction.add(atomic);
+    }
+    Ok(transaction)
+}
+
+fn mirror<E: Entry, F: ReadableFileSystem<Item=E>>(event: &SyncEvent, fs: &F, guard: &mut dyn Guard) -> Result<AtomicTransaction, DomainError> {
+    let source = fs.status(event.source())?;
+
+    if !source.exists() {
+        return Err(DomainError::SourceDoesNotExists(event.source().to_path_buf()))
+    }
+
+    let mut transaction = AtomicTransaction::default();
+    let destination = fs.status(event.destination())?;
+
+    if source.is_dir() && destination.is_contained_by(&source) {
+        return Err(DomainError::CopyIntoItSelf(source.to_path(), destination.to_path()));
+    }
+
+    if destination.exists() && source.is_dir() == destination.is_dir() {
+        if source.is_dir() {
+            let mut names : HashSet<OsString> = HashSet::new();
+            for child in fs.read_dir(source.path())? {
+                let name = child.name().unwrap().to_os_string();
+                transaction.merge(
+                    mirror(
+                        &event.child(child.path(), destination.path().join(&name).as_path()),
+                        fs,
+                        guard
+                    )?
+                );
+                names.insert(name);
+            }
+
+            for extraneous in fs.read_dir(destination.path())? {
+                if ! names.contains(extraneous.name().unwrap()) {
+                    delete(event, &extraneous, fs, guard, &mut transaction)?;
+                }
+            }
+        } else if ! (event.skip_unchanged() && event.comparison().unchanged(&source, &destination))
+            && guard.authorize_entry(Capability::Overwrite, true, event.destination(), destination.is_dir())?.is_allowed() {
+            transaction.add(Atomic::RemoveFile(destination.to_path()));
+            transaction.add(Atomic::CopyFileToFile {
+                source: source.to_path(),
+                destination: destination.to_path()
+            });
+        }
+        return Ok(transaction);
+    }
+
+    if ! destination.exists() {
+        return copy::atomize(&CopyEvent::new(source.path(), destination.path(), false, false), fs, guard);
+    }
+
+    //A file takes the place of a directory or the other way around, destination is considered gone from now on
+    if !delete(event, &destination, fs, guard, &mut transaction)? {
+        return Ok(transaction);
+    }
+
+    if source.is_dir() {
+        transaction.add(Atomic::BindDirectoryToDirectory {
+            source: source.to_path(),
+            destination: destination.to_path()
+        });
+        for child in fs.read_maintained(source.path())? {
+            transaction.merge(
+                copy::atomize(
+                    &CopyEvent::new(child.path(), destination.path().join(child.name().unwrap()).as_path(), false, false),
+                    fs,
+                    guard
+                )?
+            );
+        }
+    } else {
+        transaction.add(Atomic::CopyFileToFile {
+            source: source.to_path(),
+            destination: destination.to_path()
+        });
+    }
+    Ok(transaction)
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod real_tests {
+    use super::*;
+
+    use std::{
+        fs::{ write, create_dir, read_to_string }
+    };
+
+    use crate::{
+        sample::Samples,
+        port::{
+            FileSystemAdapter
+        },
+        infrastructure::{
+            RealFileSystem
+        },
+        capability::{
+            ZealedGuard,
+            QuietGuard,
+            Authorization
+        }
+    };
+
+    #[test]
+    fn sync_operation_mirror(){
+        let chroot = Samples::init_simple_chroot("sync_operation_mirror");
+        create_dir(chroot.join("RDIR2/NESTED")).unwrap();
+        write(chroot.join("RDIR2/NESTED/EXTRA"), "extra").unwrap();
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+
+        atomize(
+            &SyncEvent::new(
+                chroot.join("RDIR").as_path(),
+                chroot.join("RDIR2").as_path(),
+                true
+            ),
+            &fs,
+            &mut ZealedGuard
+        ).unwrap()
+         .apply(&mut fs)
+         .unwrap();
+
+        assert!(chroot.join("RDIR2/RFILEA").exists());
+        assert!(chroot.join("RDIR2/RFILEB").exists());
+        assert!(!chroot.join("RDIR2/RFILEC").exists());
+        assert!(!chroot.join("RDIR2/NESTED").exists());
+        assert_eq!(
+            read_to_string(chroot.join("RDIR/RFILEA")).unwrap(),
+            read_to_string(chroot.join("RDIR2/RFILEA")).unwrap()
+        );
+    }
+
+    #[test]
+    fn sync_operation_keep_extraneous(){
+        let chroot = Samples::init_simple_chroot("sync_operation_keep_extraneous");
+        let fs = FileSystemAdapter(RealFileSystem::default());
+        let event = SyncEvent::new(
+            chroot.join("RDIR").as_path(),
+            chroot.join("RDIR2").as_path(),
+            false
+        );
+
+        match atomize(&event, &fs, &mut ZealedGuard) {
+            Err(DomainError::DeleteNotAllowed(path)) => assert_eq!(path, chroot.join("RDIR2/RFILEC")),
+            _ => panic!("zealed guard should refuse deletion")
+        }
+
+        let transaction = atomize(&event, &fs, &mut QuietGuard).unwrap();
+        assert!(!transaction.iter().any(|atomic| matches!(atomic, Atomic::RemoveFile(path) if path.ends_with("RFILEC"))));
+    }
+
+    #[test]
+    fn sync_operation_skip_unchanged(){
+        let chroot = Samples::init_simple_chroot("sync_operation_skip_unchanged");
+        write(chroot.join("RDIR2/RFILEA"), read_to_string(chroot.join("RDIR/RFILEA")).unwrap()).unwrap();
+        let fs = FileSystemAdapter(RealFileSystem::default());
+
+        let event = SyncEvent::new(
+            chroot.join("RDIR/RFILEA").as_path(),
+            chroot.join("RDIR2/RFILEA").as_path(),
+            false
+        ).with_skip_unchanged(true);
+
+        assert!(
+            atomize(&event.with_comparison(SyncComparison::Content), &fs, &mut ZealedGuard).unwrap()
+                .iter()
+                .next()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn sync_operation_skip_applied(){
+        let chroot = Samples::init_simple_chroot("sync_operation_skip_applied");
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+        let event = SyncEvent::new(
+            chroot.join("RDIR").as_path(),
+            chroot.join("RDIR2").as_path(),
+            true
+        ).with_skip_unchanged(true);
+
+        atomize(&event, &fs, &mut ZealedGuard).unwrap()
+            .apply(&mut fs)
+            .unwrap();
+
+        assert!(atomize(&event, &fs, &mut ZealedGuard).unwrap().iter().next().is_none());
+    }
+
+    #[test]
+    fn sync_operation_mirror_same_size_edit(){
+        let chroot = Samples::init_simple_chroot("sync_operation_mirror_same_size_edit");
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+        let event = SyncEvent::new(
+            chroot.join("RDIR").as_path(),
+            chroot.join("RDIR2").as_path(),
+            true
+        ).with_skip_unchanged(true);
+
+        atomize(&event, &fs, &mut ZealedGuard).unwrap()
+            .apply(&mut fs)
+            .unwrap();
+
+        //Written after its source, with the same size
+        let edited = "x".repeat(read_to_string(chroot.join("RDIR/RFILEA")).unwrap().len());
+        write(chroot.join("RDIR2/RFILEA"), edited.as_str()).unwrap();
+        Metadata::default().with_modified(Some(4_000_000_000)).write(chroot.join("RDIR2/RFILEA").as_path()).unwrap();
+
+        atomize(&event, &fs, &mut ZealedGuard).unwrap()
+            .apply(&mut fs)
+            .unwrap();
+        assert_eq!(
+            read_to_string(chroot.join("RDIR/RFILEA")).unwrap(),
+            read_to_string(chroot.join("RDIR2/RFILEA")).unwrap()
+        );
+    }
+
+    #[derive(Serialize, Deserialize, Debug, Default)]
+    struct CountingGuard {
+        deletes: Vec<PathBuf>
+    }
+
+    #[typetag::serde]
+    impl Guard for CountingGuard {
+        fn authorize(&mut self, capability: Capability, _default: bool, target: &Path) -> Result<Authorization, DomainError> {
+            if capability == Capability::Delete {
+                self.deletes.push(target.to_path_buf());
+            }
+            Ok(Authorization::Allow)
+        }
+    }
+
+    #[test]
+    fn sync_operation_asks_delete_once(){
+        let chroot = Samples::init_simple_chroot("sync_operation_asks_delete_once");
+        let fs = FileSystemAdapter(RealFileSystem::default());
+        let mut guard = CountingGuard::default();
+
+        atomize(&SyncEvent::new(chroot.join("RDIR").as_path(), chroot.join("RDIR2").as_path(), false), &fs, &mut guard).unwrap();
+        assert_eq!(guard.deletes, vec![chroot.join("RDIR2/RFILEC")]);
+    }
+
+    #[test]
+    fn sync_operation_replace_kind(){
+        let chroot = Samples::init_simple_chroot("sync_operation_replace_kind");
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+
+        atomize(
+            &SyncEvent::new(
+                chroot.join("RDIR/RFILEA").as_path(),
+                chroot.join("RDIR2").as_path(),
+                true
+            ),
+            &fs,
+            &mut ZealedGuard
+        ).unwrap()
+         .apply(&mut fs)
+         .unwrap();
+
+        assert!(chroot.join("RDIR2").is_file());
+
+        atomize(
+            &SyncEvent::new(
+                chroot.join("RDIR").as_path(),
+                chroot.join("RDIR2").as_path(),
+                true
+            ),
+            &fs,
+            &mut ZealedGuard
+        ).unwrap()
+         .apply(&mut fs)
+         .unwrap();
+
+        assert!(chroot.join("RDIR2/RFILEB").is_file());
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod virtual_tests {
+    use super::*;
+
+    use crate::{
+        sample::Samples,
+        port::{
+            FileSystemAdapter
+        },
+        infrastructure::{
+            VirtualFileSystem
+        },
+        capability::{
+            ZealedGuard
+        }
+    };
+
+    #[test]
+    fn virtual_sync_operation_mirror(){
+        let chroot = Samples::init_simple_chroot("virtual_sync_operation_mirror");
+        let mut fs = FileSystemAdapter(VirtualFileSystem::default());
+
+        atomize(
+            &SyncEvent::new(
+                chroot.join("RDIR").as_path(),
+                chroot.join("RDIR2").as_path(),
+                true
+            ),
+            &fs,
+            &mut ZealedGuard
+        ).unwrap()
+         .apply(&mut fs)
+         .unwrap();
+
+        assert!(fs.status(chroot.join("RDIR2/RFILEA").as_path()).unwrap().is_file());
+        assert!(fs.status(chroot.join("RDIR2/RFILEB").as_path()).unwrap().is_file());
+        assert!(!fs.status(chroot.join("RDIR2/RFILEC").as_path()).unwrap().exists());
+    }
+
+    #[test]
+    fn virtual_sync_operation_skip_virtual_copy(){
+        let chroot = Samples::init_simple_chroot("virtual_sync_operation_skip_virtual_copy");
+        let mut fs = FileSystemAdapter(VirtualFileSystem::default());
+        let event = SyncEvent::new(
+            chroot.join("RDIR").as_path(),
+            chroot.join("MIRROR").as_path(),
+            true
+        ).with_skip_unchanged(true);
+
+        atomize(&event, &fs, &mut ZealedGuard).unwrap()
+            .apply(&mut fs)
+            .unwrap();
+
+        //Virtual copies still refer to their source
+        assert!(atomize(&event, &fs, &mut ZealedGuard).unwrap().iter().next().is_none());
+    }
+}
@@ -0,0 +1,412 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    env::var_os,
+    ffi::{ OsStr, OsString },
+    fs::{ read_dir, read_to_string },
+    path::{ Path, PathBuf }
+};
+
+use serde::{ Serialize, Deserialize };
+
+use chrono::Local;
+
+use crate::{
+    lossless,
+    errors::DomainError,
+    event::{
+        FileSystemEvent,
+        MoveEvent,
+        RemoveEvent,
+        mov,
+        create::ensure_directory,
+        conflict::{ numbered, DEFAULT_RENAME_TEMPLATE }
+    },
+    capability::Guard,
+    port::{
+        Entry,
+        ReadableFileSystem,
+        Atomic,
+        AtomicTransaction
+    }
+};
+
+const INFO_EXTENSION : &str = ".trashinfo";
+
+/*
+Trash of the freedesktop.org trash specification, the home trash unless resolved for another device.
+A trashed entry is moved into files and described by a .trashinfo file with the same name in info,
+so it can be restored by any desktop file manager.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(transparent)]
+pub struct Trash {
+    #[serde(with = "crate::lossless")]
+    root: PathBuf
+}
+
+impl Trash {
+    pub fn new(root: &Path) -> Trash {
+        Trash {
+            root: root.to_path_buf()
+        }
+    }
+
+    //$XDG_DATA_HOME/Trash, $XDG_DATA_HOME defaulting to ~/.local/share
+    pub fn home() -> Result<Trash, DomainError> {
+        match (var_os("XDG_DATA_HOME").map(PathBuf::from), var_os("HOME").map(PathBuf::from)) {
+            (Some(data_home), _) if data_home.is_absolute() => Ok(Trash::new(data_home.join("Trash").as_path())),
+            (_, Some(home)) if home.is_absolute() => Ok(Trash::new(home.join(".local/share/Trash").as_path())),
+            _ => Err(DomainError::TrashUnavailable)
+        }
+    }
+
+    pub fn root(&self) -> &Path { self.root.as_path() }
+    pub fn files(&self) -> PathBuf { self.root.join("files") }
+    pub fn info(&self) -> PathBuf { self.root.join("info") }
+
+    fn info_file(&self, name: &OsStr) -> PathBuf {
+        let mut file = name.to_os_string();
+        file.push(INFO_EXTENSION);
+        self.info().join(file)
+    }
+
+    /*
+    Entries on another device than the home trash go to the trash of the top directory of their mount,
+    $topdir/.Trash/$uid when the administrator provided a sticky $topdir/.Trash, $topdir/.Trash-$uid otherwise.
+    */
+    pub fn for_path(&self, path: &Path) -> Trash {
+        let device = match path.ancestors().find_map(device) {
+            Some(device) => device,
+            None => return self.clone()
+        };
+        if self.root.ancestors().find_map(self::device) == Some(device) {
+            return self.clone();
+        }
+
+        //Entries not created yet belong to the device of their nearest existing ancestor
+        let topdir = match path.ancestors().take_while(|ancestor| self::device(ancestor).is_none_or(|other| other == device)).last() {
+            Some(topdir) => topdir,
+            None => return self.clone()
+        };
+        let shared = topdir.join(".Trash");
+        if is_sticky_directory(shared.as_path()) {
+            Trash::new(shared.join(uid().to_string()).as_path())
+        } else {
+            Trash::new(topdir.join(format!(".Trash-{}", uid())).as_path())
+        }
+    }
+
+    fn info_content(original: &Path) -> String {
+        format!(
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            escape(original),
+            Local::now().format("%Y-%m-%dT%H:%M:%S")
+        )
+    }
+
+    /*
+    Creates the trash directories when needed, then writes the .trashinfo file before moving the entry,
+    as the specification requires. Names already taken, even by this transaction, get a counter.
+    */
+    pub fn atomize<E: Entry, F: ReadableFileSystem<Item=E>>(&self, entry: &E, fs: &F, guard: &mut dyn Guard, transaction: &mut AtomicTransaction) -> Result<(), DomainError> {
+        let trash = self.for_path(entry.path());
+        ensure_directory(fs, trash.files().as_path(), transaction)?;
+        ensure_directory(fs, trash.info().as_path(), transaction)?;
+
+        let trashed = trash.files().join(entry.name().unwrap_or_default());
+        let mut n : u64 = 0;
+        let destination = loop {
+            let candidate = if n == 0 { trashed.clone() } else { numbered(trashed.as_path(), entry.is_dir(), DEFAULT_RENAME_TEMPLATE, n)? };
+            let info = trash.info_file(candidate.file_name().unwrap_or_default());
+            if !fs.status(candidate.as_path())?.exists()
+                && !fs.status(info.as_path())?.exists()
+                && !transaction.creates(candidate.as_path())
+                && !transaction.creates(info.as_path()) {
+                transaction.add(Atomic::CreateFileWithContent {
+                    path: info,
+                    content: Self::info_content(entry.path())
+                });
+                break candidate;
+            }
+            n += 1;
+        };
+
+        //Dangerous targets were refused by the removal, against the working directory of its session
+        transaction.merge(mov::atomize(&MoveEvent::new(entry.path(), destination.as_path(), false, false).with_allow_dangerous(true), fs, guard)?);
+        Ok(())
+    }
+
+    //Entries already in the real trash, by deletion date
+    pub fn list(&self) -> Result<Vec<TrashedEntry>, DomainError> {
+        let mut entries = Vec::new();
+        if !self.info().is_dir() {
+            return Ok(entries);
+        }
+
+        for info in read_dir(self.info())? {
+            let info = info?.path();
+            let name = match info.file_name().map(lossless::bytes) {
+                Some(name) if name.ends_with(INFO_EXTENSION.as_bytes()) =>
+                    lossless::from_bytes(name[..name.len() - INFO_EXTENSION.len()].to_vec())?,
+                _ => continue
+            };
+
+            let content = read_to_string(info.as_path())?;
+            let field = |key: &str| content.lines()
+                .find_map(|line| line.strip_prefix(key).and_then(|line| line.strip_prefix('=')))
+                .map(str::to_string);
+
+            //Paths in the trash of a top directory may be relative to it
+            if let Some(path) = field("Path") {
+                entries.push(TrashedEntry {
+                    trash: self.clone(),
+                    original: self.topdir().join(unescape(path.as_str())?),
+                    deletion_date: field("DeletionDate").unwrap_or_default(),
+                    name
+                });
+            }
+        }
+        entries.sort_by(|a, b| a.deletion_date.cmp(&b.deletion_date).then(a.name.cmp(&b.name)));
+        Ok(entries)
+    }
+
+    fn topdir(&self) -> &Path {
+        let parent = self.root.parent().unwrap_or(self.root.as_path());
+        match parent.file_name() {
+            Some(name) if name == ".Trash" => parent.parent().unwrap_or(parent),
+            _ => parent
+        }
+    }
+
+    //Trashes of the mounted file systems which already hold something, after this one
+    pub fn known(&self) -> Vec<Trash> {
+        let mut trashes = vec![self.clone()];
+        for topdir in mount_points() {
+            for trash in [topdir.join(".Trash").join(uid().to_string()), topdir.join(format!(".Trash-{}", uid()))] {
+                if trash.join("info").is_dir() && !trashes.iter().any(|known| known.root() == trash.as_path()) {
+                    trashes.push(Trash::new(trash.as_path()));
+                }
+            }
+        }
+        trashes
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrashedEntry {
+    trash: Trash,
+    name: OsString,
+    original: PathBuf,
+    deletion_date: String
+}
+
+impl TrashedEntry {
+    pub fn trash(&self) -> &Trash { &self.trash }
+    pub fn name(&self) -> &OsStr { self.name.as_os_str() }
+    pub fn original(&self) -> &Path { self.original.as_path() }
+    pub fn deletion_date(&self) -> &str { self.deletion_date.as_str() }
+
+    //Events putting the entry back where it was deleted
+    pub fn restore(&self) -> Vec<FileSystemEvent> {
+        vec![
+            FileSystemEvent::Move(MoveEvent::new(self.trash.files().join(self.name()).as_path(), self.original(), false, false)),
+            FileSystemEvent::Remove(RemoveEvent::new(self.trash.info_file(self.name()).as_path(), false))
+        ]
+    }
+}
+
+#[cfg(unix)]
+fn device(path: &Path) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    path.symlink_metadata().ok().map(|metadata| metadata.dev())
+}
+
+#[cfg(not(unix))]
+fn device(_path: &Path) -> Option<u64> {
+    None
+}
+
+#[cfg(unix)]
+fn uid() -> u32 {
+    unsafe { libc::getuid() }
+}
+
+#[cfg(not(unix))]
+fn uid() -> u32 {
+    0
+}
+
+#[cfg(unix)]
+fn is_sticky_directory(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.symlink_metadata()
+        .map(|metadata| metadata.is_dir() && metadata.permissions().mode() & 0o1000 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_sticky_directory(_path: &Path) -> bool {
+    false
+}
+
+//Mount points listed by the kernel, none where it does not tell
+fn mount_points() -> Vec<PathBuf> {
+    read_to_string("/proc/self/mounts")
+        .map(|mounts| mounts.lines()
+            .filter_map(|line| line.split(' ').nth(1))
+            .map(unescape_mount_point)
+            .collect()
+        )
+        .unwrap_or_default()
+}
+
+//Octal escapes of /proc/self/mounts, like \040 for a space
+fn unescape_mount_point(escaped: &str) -> PathBuf {
+    let bytes = escaped.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], escaped.get(i + 1..i + 4).and_then(|octal| u8::from_str_radix(octal, 8).ok())) {
+            (b'\\', Some(byte)) => {
+                decoded.push(byte);
+                i += 4;
+            },
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    lossless::from_bytes(decoded).map(PathBuf::from).unwrap_or_else(|_| PathBuf::from(escaped))
+}
+
+//Percent encoding of the raw bytes of the trashinfo Path key, separators are kept
+fn escape(path: &Path) -> String {
+    lossless::bytes(path.as_os_str())
+        .into_iter()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => (byte as char).to_string(),
+            byte => format!("%{:02X}", byte)
+        })
+        .collect()
+}
+
+fn unescape(path: &str) -> Result<PathBuf, DomainError> {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], path.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok())) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            },
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    lossless::from_bytes(decoded).map(PathBuf::from)
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        sample::Samples,
+        port::{
+            FileSystemAdapter
+        },
+        infrastructure::{
+            RealFileSystem,
+            VirtualFileSystem
+        },
+        capability::{
+            ZealedGuard
+        }
+    };
+
+    #[test]
+    fn escape_trashinfo_path() {
+        let path = Path::new("/home/user/My Files/été 100%.txt");
+        assert_eq!(escape(path), "/home/user/My%20Files/%C3%A9t%C3%A9%20100%25.txt");
+        assert_eq!(unescape(escape(path).as_str()).unwrap(), path);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn escape_trashinfo_path_which_is_not_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+        let path = Path::new(OsStr::from_bytes(b"/home/user/caf\xe9.txt"));
+        assert_eq!(escape(path), "/home/user/caf%E9.txt");
+        assert_eq!(unescape(escape(path).as_str()).unwrap(), path);
+    }
+
+    #[test]
+    fn unescape_mount_points() {
+        assert_eq!(unescape_mount_point("/media/My\\040Disk"), Path::new("/media/My Disk"));
+    }
+
+    #[test]
+    fn home_trash_for_same_device() {
+        let chroot = Samples::init_simple_chroot("trash_home_trash_for_same_device");
+        let trash = Trash::new(chroot.join("Trash").as_path());
+        assert_eq!(trash.for_path(chroot.join("RDIR/RFILEA").as_path()), trash);
+        assert_eq!(trash.for_path(chroot.join("NEW/FILE").as_path()), trash);
+    }
+
+    #[test]
+    fn trash_and_list_entries() {
+        let chroot = Samples::init_simple_chroot("trash_trash_and_list_entries");
+        let trash = Trash::new(chroot.join("Trash").as_path());
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+
+        let mut transaction = AtomicTransaction::default();
+        for path in ["RDIR/RFILEA", "RDIR2/RFILEA", "RDIR3"].iter() {
+            let entry = fs.status(chroot.join(path).as_path()).unwrap();
+            trash.atomize(&entry, &fs, &mut ZealedGuard, &mut transaction).unwrap();
+        }
+        transaction.apply(&mut fs).unwrap();
+
+        assert!(!chroot.join("RDIR/RFILEA").exists());
+        assert!(chroot.join("Trash/files/RFILEA").is_file());
+        assert!(chroot.join("Trash/files/RFILEA (1)").is_file());
+        assert!(chroot.join("Trash/files/RDIR3").is_dir());
+
+        let info = read_to_string(chroot.join("Trash/info/RFILEA (1).trashinfo")).unwrap();
+        assert!(info.starts_with(format!("[Trash Info]\nPath={}\nDeletionDate=", escape(chroot.join("RDIR2/RFILEA").as_path())).as_str()));
+
+        let entries = trash.list().unwrap();
+        assert_eq!(entries.len(), 3);
+        let restored = entries.iter().find(|entry| entry.name() == "RDIR3").unwrap();
+        assert_eq!(restored.original(), chroot.join("RDIR3").as_path());
+
+        let mut vfs = FileSystemAdapter(VirtualFileSystem::default());
+        for event in restored.restore() {
+            event.atomize(&vfs, &mut ZealedGuard).unwrap().apply(&mut vfs).unwrap();
+        }
+        assert!(vfs.status(chroot.join("RDIR3").as_path()).unwrap().is_dir());
+        assert!(!vfs.status(chroot.join("Trash/info/RDIR3.trashinfo").as_path()).unwrap().exists());
+    }
+}
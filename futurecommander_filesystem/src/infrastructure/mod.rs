@@ -25,7 +25,8 @@ pub use self::real::RealFileSystem;
 mod virt;
 pub use self::virt::{
     VirtualFileSystem,
-    entry_status::VirtualStatus
+    entry_status::VirtualStatus,
+    changes::{ Change, ChangeKind, Changes }
 };
 
 pub use futurecommander_representation::VirtualState;
@@ -53,6 +53,10 @@ impl Entry for EntryAdapter<&Path> {
     fn exists(&self) -> bool { self.0.exists() }
 
     fn is_virtual(&self) -> bool { false }
+
+    fn source(&self) -> Option<&Path> {
+        if self.exists() { Some(self.0) } else { None }
+    }
 }
 
 impl Entry for EntryAdapter<PathBuf> {
@@ -69,6 +73,10 @@ impl Entry for EntryAdapter<PathBuf> {
     fn exists(&self) -> bool { self.0.exists() }
 
     fn is_virtual(&self) -> bool { false }
+
+    fn source(&self) -> Option<&Path> {
+        if self.exists() { Some(self.0.as_path()) } else { None }
+    }
 }
 
 
@@ -110,6 +118,7 @@ mod tests {
         assert_eq!(f.to_path(), f_path.clone());
         assert_eq!(f.path(), f_path.as_path());
         assert_eq!(f.name(), Some(OsStr::new("F")));
+        assert_eq!(f.source(), Some(f_path.as_path()));
         assert_eq!(f.as_inner(), &f_path.as_path());
         assert_eq!(f.into_inner(), f_path.as_path());
     }
@@ -128,6 +137,7 @@ mod tests {
         assert_eq!(z.to_path(), z_path.clone());
         assert_eq!(z.path(), z_path.as_path());
         assert_eq!(z.name(), Some(OsStr::new("Z")));
+        assert_eq!(z.source(), None);
         assert_eq!(z.as_inner(), &z_path.as_path());
         assert_eq!(z.into_inner(), z_path.as_path());
     }
@@ -163,6 +173,7 @@ mod tests {
         assert_eq!(f.to_path(), f_path);
         assert_eq!(f.path(), f_path.as_path());
         assert_eq!(f.name(), Some(OsStr::new("F")));
+        assert_eq!(f.source(), Some(f_path.as_path()));
         assert_eq!(f.as_inner(), &f_path.as_path());
         assert_eq!(f.into_inner(), f_path.as_path());
     }
@@ -181,6 +192,7 @@ mod tests {
         assert_eq!(z.to_path(), z_path);
         assert_eq!(z.path(), z_path.as_path());
         assert_eq!(z.name(), Some(OsStr::new("Z")));
+        assert_eq!(z.source(), None);
         assert_eq!(z.as_inner(), &z_path.as_path());
         assert_eq!(z.into_inner(), z_path.as_path());
     }
@@ -15,7 +15,8 @@ use crate::{
     },
     infrastructure::real::{
         RealFileSystem
-    }
+    },
+    lock::LOCK_FILE
 };
 impl ReadableFileSystem for FileSystemAdapter<RealFileSystem> {
     type Item = EntryAdapter<PathBuf>;
@@ -33,7 +34,11 @@ impl ReadableFileSystem for FileSystemAdapter<RealFileSystem> {
         let mut entry_collection = EntryCollection::new();
 
         for result in path.read_dir()? {
-            entry_collection.add(EntryAdapter(result?.path()));
+            let result = result?;
+            //Lock files belong to whoever applies, never to the tree
+            if result.file_name() != LOCK_FILE {
+                entry_collection.add(EntryAdapter(result.path()));
+            }
         }
 
         Ok(entry_collection)
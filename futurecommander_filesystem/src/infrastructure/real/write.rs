@@ -27,15 +27,19 @@ use std::{
         File,
         create_dir,
         rename,
+        hard_link,
         remove_file,
-        remove_dir
+        remove_dir,
+        symlink_metadata,
+        write
     }
 };
 
 use crate::{
     port::{
         WriteableFileSystem,
-        FileSystemAdapter
+        FileSystemAdapter,
+        Metadata
     },
     infrastructure::{
         errors::{ InfrastructureError },
@@ -127,6 +131,12 @@ impl WriteableFileSystem for FileSystemAdapter<RealFileSystem> {
         Ok(())
     }
 
+    fn create_file_with_content(&mut self, path: &Path, content: &str) -> Result<(), InfrastructureError> {
+        self.safe_parent(path)?;
+        write(path, content)?;
+        Ok(())
+    }
+
     fn copy_file_to_file(&mut self, source: &Path, destination: &Path) -> Result<(), InfrastructureError>{
         self.safe_file_translation(source, destination)?;
         self._copy_file(source, destination, &|_|{})?;
@@ -134,6 +144,16 @@ impl WriteableFileSystem for FileSystemAdapter<RealFileSystem> {
     }
 
     fn move_file_to_file(&mut self, source: &Path, destination: &Path) -> Result<(), InfrastructureError>{
+        //A link is moved itself, whatever it points to
+        if symlink_metadata(source).is_ok_and(|metadata| metadata.file_type().is_symlink()) {
+            self.safe_parent(destination)?;
+            if destination.exists() && ! destination.is_file() {
+                return Err(InfrastructureError::DestinationIsNotAFile(destination.to_path_buf()));
+            }
+            rename(source, destination)?;
+            return Ok(());
+        }
+
         self.safe_file_translation(source, destination)?;
         match rename(source, destination) {
             Err(error) => {
@@ -163,6 +183,17 @@ impl WriteableFileSystem for FileSystemAdapter<RealFileSystem> {
         self.create_empty_directory(destination)
     }
 
+    fn hard_link(&mut self, source: &Path, destination: &Path) -> Result<(), InfrastructureError> {
+        self.safe_file_translation(source, destination)?;
+
+        if destination.exists() {
+            return Err(InfrastructureError::DestinationAlreadyExists(destination.to_path_buf()));
+        }
+
+        hard_link(source, destination)?;
+        Ok(())
+    }
+
     fn remove_file(&mut self, path: &Path) -> Result<(), InfrastructureError> {
         if ! path.exists() {
             return Err(InfrastructureError::PathDoesNotExists(path.to_path_buf()));
@@ -183,6 +214,14 @@ impl WriteableFileSystem for FileSystemAdapter<RealFileSystem> {
         remove_dir(path)?;
         Ok(())
     }
+
+    fn set_metadata(&mut self, path: &Path, metadata: &Metadata) -> Result<(), InfrastructureError> {
+        if ! path.exists() {
+            return Err(InfrastructureError::PathDoesNotExists(path.to_path_buf()));
+        }
+        metadata.write(path)?;
+        Ok(())
+    }
 }
 
 
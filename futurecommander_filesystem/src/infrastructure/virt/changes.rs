@@ -0,0 +1,327 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    collections::HashSet,
+    fmt::{ Display, Formatter, Result as FmtResult },
+    fs::{ read_dir, symlink_metadata },
+    path::{ Path, PathBuf },
+    slice::Iter
+};
+
+use serde::Serialize;
+
+use futurecommander_representation::{
+    VirtualDelta,
+    errors::RepresentationError
+};
+
+use crate::{
+    Kind,
+    infrastructure::virt::VirtualFileSystem
+};
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Replaced,
+    Moved,
+    Modified
+}
+
+impl Display for ChangeKind {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{}",
+            match self {
+                ChangeKind::Added => "added",
+                ChangeKind::Removed => "removed",
+                ChangeKind::Replaced => "replaced",
+                ChangeKind::Moved => "moved",
+                ChangeKind::Modified => "modified"
+            }
+        )
+    }
+}
+
+/*
+A path which differs from the real file system once applied.
+Bytes are the real content written at path, or freed for a removal.
+*/
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Change {
+    kind: ChangeKind,
+    #[serde(with = "crate::lossless")]
+    path: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none", with = "crate::lossless::option")]
+    source: Option<PathBuf>,
+    directory: bool,
+    bytes: u64
+}
+
+impl Change {
+    pub fn kind(&self) -> ChangeKind { self.kind }
+    pub fn path(&self) -> &Path { self.path.as_path() }
+    pub fn source(&self) -> Option<&Path> { self.source.as_deref() }
+    pub fn is_dir(&self) -> bool { self.directory }
+    pub fn bytes(&self) -> u64 { self.bytes }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Default)]
+pub struct Changes {
+    changes: Vec<Change>
+}
+
+impl Changes {
+    pub fn iter(&self) -> Iter<'_, Change> { self.changes.iter() }
+    pub fn is_empty(&self) -> bool { self.changes.is_empty() }
+
+    pub fn count(&self, kind: ChangeKind) -> usize {
+        self.changes.iter().filter(|change| change.kind == kind).count()
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.changes.iter().map(|change| change.bytes).sum()
+    }
+
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+}
+
+//Real size of path, recursively for directories
+fn size(path: &Path) -> u64 {
+    match symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => read_dir(path)
+            .map(|children| children.filter_map(Result::ok).map(|child| size(child.path().as_path())).sum())
+            .unwrap_or(0),
+        Ok(metadata) => metadata.len(),
+        Err(_) => 0
+    }
+}
+
+//Whether path is removed along with one of its ancestors
+fn removed_with_ancestor(add: &VirtualDelta, sub: &VirtualDelta, path: &Path) -> Result<bool, RepresentationError> {
+    for ancestor in path.ancestors().skip(1) {
+        if sub.get(ancestor)?.is_some() && add.get(ancestor)?.is_none() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+impl VirtualFileSystem {
+    //Real bytes path holds once applied, files of an added directory being its real children left and added ones
+    fn added_size(&self, identity: &Path, source: Option<&Path>, directory: bool) -> Result<u64, RepresentationError> {
+        if !directory {
+            return Ok(source.map(size).unwrap_or(0));
+        }
+
+        let mut bytes = 0;
+        if let Some(children) = source.and_then(|source| read_dir(source).ok()) {
+            for child in children.filter_map(Result::ok) {
+                let child_identity = identity.join(child.file_name());
+                if self.sub.get(child_identity.as_path())?.is_some() || self.add.get(child_identity.as_path())?.is_some() {
+                    continue;
+                }
+                let child_source = child.path();
+                let is_dir = symlink_metadata(child_source.as_path()).map(|metadata| metadata.is_dir()).unwrap_or(false);
+                bytes += self.added_size(child_identity.as_path(), Some(child_source.as_path()), is_dir)?;
+            }
+        }
+
+        if let Some(children) = self.add.children(identity) {
+            for child in children.iter() {
+                bytes += self.added_size(child.as_identity(), child.as_source(), child.as_kind() == &Kind::Directory)?;
+            }
+        }
+        Ok(bytes)
+    }
+
+    /*
+    Added entries replace the real entry at their path, and were moved when their source is subtracted.
+    An added directory is reported once with the bytes of its files, like a removed one.
+    Subtractions which are neither replaced nor moved are removals, reported once for a removed directory,
+    subtractions inside added entries only shape what gets added.
+    Staged metadata only makes a change of its own on paths not changed otherwise.
+    */
+    pub fn changes(&self) -> Result<Changes, RepresentationError> {
+        let mut changes = Vec::new();
+        let mut moved = HashSet::new();
+
+        for child in self.add.hierarchy.values().flat_map(|children| children.iter()) {
+            if self.add.is_virtual(child.as_identity().parent().unwrap_or(child.as_identity()))? {
+                continue;
+            }
+
+            let source = child.to_source();
+            let from_removed = match source.as_deref() {
+                Some(source) => self.sub.get(source)?.is_some() && self.add.get(source)?.is_none(),
+                None => false
+            };
+
+            let kind = if symlink_metadata(child.as_identity()).is_ok() {
+                ChangeKind::Replaced
+            } else if from_removed {
+                ChangeKind::Moved
+            } else {
+                ChangeKind::Added
+            };
+
+            if from_removed {
+                moved.insert(source.clone().unwrap_or_default());
+            }
+
+            let directory = child.as_kind() == &Kind::Directory;
+            changes.push(Change {
+                kind,
+                path: child.to_identity(),
+                bytes: self.added_size(child.as_identity(), source.as_deref(), directory)?,
+                directory,
+                source
+            });
+        }
+
+        for child in self.sub.hierarchy.values().flat_map(|children| children.iter()) {
+            let identity = child.as_identity();
+            if self.add.get(identity)?.is_some()
+                || moved.contains(identity)
+                || self.add.is_virtual(identity)?
+                || removed_with_ancestor(&self.add, &self.sub, identity)? {
+                continue;
+            }
+
+            changes.push(Change {
+                kind: ChangeKind::Removed,
+                path: child.to_identity(),
+                source: None,
+                directory: child.as_kind() == &Kind::Directory,
+                bytes: size(identity)
+            });
+        }
+
+        for (path, _metadata) in self.metadata_changes() {
+            if changes.iter().any(|change| path.starts_with(change.path.as_path())) {
+                continue;
+            }
+
+            changes.push(Change {
+                kind: ChangeKind::Modified,
+                path: path.to_path_buf(),
+                source: None,
+                directory: match self.add.get(path)? {
+                    Some(added) => added.as_kind() == &Kind::Directory,
+                    None => path.is_dir()
+                },
+                bytes: 0
+            });
+        }
+
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(Changes { changes })
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::write;
+
+    use crate::{
+        sample::Samples,
+        port::{ FileSystemAdapter, WriteableFileSystem, Metadata }
+    };
+
+    #[test]
+    fn summarize_changes() {
+        let chroot = Samples::init_simple_chroot("virtual_summarize_changes");
+        write(chroot.join("RDIR/RFILEA"), "0123456789").unwrap();
+        let mut fs = FileSystemAdapter(VirtualFileSystem::default());
+
+        fs.create_empty_directory(chroot.join("NEW").as_path()).unwrap();
+        fs.move_file_to_file(chroot.join("RDIR/RFILEA").as_path(), chroot.join("MOVED").as_path()).unwrap();
+        fs.remove_file(chroot.join("RDIR2/RFILEA").as_path()).unwrap();
+        fs.copy_file_to_file(chroot.join("MOVED").as_path(), chroot.join("RDIR2/RFILEA").as_path()).unwrap();
+        fs.remove_file(chroot.join("RDIR/RFILEB").as_path()).unwrap();
+        fs.remove_empty_directory(chroot.join("RDIR").as_path()).unwrap();
+
+        let changes = fs.as_inner().changes().unwrap();
+        let summary : Vec<(ChangeKind, PathBuf)> = changes.iter().map(|change| (change.kind(), change.path().to_path_buf())).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (ChangeKind::Moved, chroot.join("MOVED")),
+                (ChangeKind::Added, chroot.join("NEW")),
+                (ChangeKind::Removed, chroot.join("RDIR")),
+                (ChangeKind::Replaced, chroot.join("RDIR2/RFILEA"))
+            ]
+        );
+        assert_eq!(changes.count(ChangeKind::Removed), 1);
+        assert_eq!(changes.iter().find(|change| change.kind() == ChangeKind::Moved).unwrap().bytes(), 10);
+        assert!(changes.iter().find(|change| change.kind() == ChangeKind::Removed).unwrap().is_dir());
+    }
+
+    #[test]
+    fn summarize_added_directory_once() {
+        let chroot = Samples::init_simple_chroot("virtual_summarize_added_directory_once");
+        write(chroot.join("RDIR/RFILEA"), "0123456789").unwrap();
+        write(chroot.join("RDIR/RFILEB"), "").unwrap();
+        write(chroot.join("RDIR2/RFILEC"), "01234").unwrap();
+        let mut fs = FileSystemAdapter(VirtualFileSystem::default());
+
+        fs.bind_directory_to_directory(chroot.join("RDIR").as_path(), chroot.join("COPIED").as_path()).unwrap();
+        fs.copy_file_to_file(chroot.join("RDIR2/RFILEC").as_path(), chroot.join("COPIED/RFILEC").as_path()).unwrap();
+        fs.remove_file(chroot.join("COPIED/RFILEB").as_path()).unwrap();
+        fs.set_metadata(chroot.join("COPIED/RFILEA").as_path(), &Metadata::default().with_mode(Some(0o600))).unwrap();
+
+        let changes = fs.as_inner().changes().unwrap();
+        let summary : Vec<(ChangeKind, PathBuf, u64)> = changes.iter().map(|change| (change.kind(), change.path().to_path_buf(), change.bytes())).collect();
+        assert_eq!(summary, vec![(ChangeKind::Added, chroot.join("COPIED"), 15)]);
+        assert_eq!(changes.bytes(), 15);
+    }
+
+    #[test]
+    fn summarize_metadata_changes() {
+        let chroot = Samples::init_simple_chroot("virtual_summarize_metadata_changes");
+        let mut fs = FileSystemAdapter(VirtualFileSystem::default());
+        let metadata = Metadata::default().with_mode(Some(0o700));
+
+        fs.create_empty_directory(chroot.join("NEW").as_path()).unwrap();
+        fs.set_metadata(chroot.join("NEW").as_path(), &metadata).unwrap();
+        fs.set_metadata(chroot.join("RDIR").as_path(), &metadata).unwrap();
+        fs.set_metadata(chroot.join("RDIR2/RFILEA").as_path(), &metadata).unwrap();
+        fs.remove_file(chroot.join("RDIR2/RFILEA").as_path()).unwrap();
+
+        let changes = fs.as_inner().changes().unwrap();
+        let summary : Vec<(ChangeKind, PathBuf, bool)> = changes.iter().map(|change| (change.kind(), change.path().to_path_buf(), change.is_dir())).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (ChangeKind::Added, chroot.join("NEW"), true),
+                (ChangeKind::Modified, chroot.join("RDIR"), true),
+                (ChangeKind::Removed, chroot.join("RDIR2/RFILEA"), false)
+            ]
+        );
+    }
+}
@@ -141,6 +141,10 @@ impl Entry for EntryAdapter<VirtualStatus> {
             | VirtualState::Removed => false
         }
     }
+
+    fn source(&self) -> Option<&Path> {
+        self.0.as_existing_virtual().and_then(|identity| identity.as_source())
+    }
 }
 
 #[cfg(not(tarpaulin_include))]
@@ -192,6 +196,7 @@ mod tests {
         assert_eq!(a.to_path(), a_path);
         assert_eq!(a.path(), a_path.as_path());
         assert_eq!(a.name(), Some(OsStr::new("MOCK")));
+        assert_eq!(a.source(), Some(a_path.as_path()));
         assert_eq!(a.into_inner(), a_status);
     }
 
@@ -222,6 +227,7 @@ mod tests {
     fn entry_adapter_virtual_status_removed() {
         let a = _mock_virtual_status_entry(VirtualState::Removed, Kind::Unknown);
         assert!(!a.exists());
+        assert_eq!(a.source(), None);
         assert!(!a.is_dir());
         assert!(!a.is_file());
         assert!(!a.is_virtual());
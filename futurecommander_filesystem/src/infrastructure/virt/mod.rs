@@ -20,29 +20,83 @@
 pub mod entry_status;
 pub mod read;
 pub mod write;
+pub mod changes;
+
+use std::{
+    collections::BTreeMap,
+    path::{ Path, PathBuf }
+};
 
 use futurecommander_representation::{
     VirtualDelta,
+    PathComparison,
     errors::RepresentationError
 };
 
-#[derive(Debug, Default)]
+use crate::port::Metadata;
+
+#[derive(Debug, Default, Clone)]
 pub struct VirtualFileSystem {
     add: VirtualDelta,
-    sub: VirtualDelta
+    sub: VirtualDelta,
+    //Staged metadata changes alongside the deltas, by folded identity
+    metadata: BTreeMap<PathBuf, (PathBuf, Metadata)>
 }
 
 impl VirtualFileSystem {
     pub fn reset(&mut self) {
-        self.add = VirtualDelta::default();
-        self.sub = VirtualDelta::default();
+        self.add = VirtualDelta::new(self.comparison());
+        self.sub = VirtualDelta::new(self.comparison());
+        self.metadata.clear();
+    }
+
+    pub fn comparison(&self) -> PathComparison {
+        self.add.comparison
+    }
+
+    //Staged identities folded together by the new comparison collide, leaving the state untouched
+    pub fn set_comparison(&mut self, comparison: PathComparison) -> Result<(), RepresentationError> {
+        let add = self.add.rekey(comparison)?;
+        let sub = self.sub.rekey(comparison)?;
+        self.add = add;
+        self.sub = sub;
+        self.metadata = self.metadata.values()
+            .map(|(identity, metadata)| (comparison.key(identity), (identity.clone(), *metadata)))
+            .collect();
+        Ok(())
     }
 
     pub fn has_addition(&self) -> bool { !self.add.is_empty() }
 
     pub fn has_subtraction(&self) -> bool { !self.sub.is_empty() }
 
-    pub fn is_empty(&self) -> bool { ! self.has_addition() && ! self.has_subtraction() }
+    pub fn has_metadata(&self) -> bool { !self.metadata.is_empty() }
+
+    pub fn is_empty(&self) -> bool { ! self.has_addition() && ! self.has_subtraction() && ! self.has_metadata() }
+
+    pub fn staged_metadata(&self, path: &Path) -> Option<&Metadata> {
+        self.metadata.get(&self.comparison().key(path)).map(|(_identity, metadata)| metadata)
+    }
+
+    //Staged values are replaced one by one by those of metadata
+    pub fn stage_metadata(&mut self, path: &Path, metadata: &Metadata) {
+        let staged = self.staged_metadata(path).map_or(*metadata, |staged| staged.overridden_by(metadata));
+        self.metadata.insert(self.comparison().key(path), (path.to_path_buf(), staged));
+    }
+
+    pub fn take_metadata(&mut self, path: &Path) -> Option<Metadata> {
+        self.metadata.remove(&self.comparison().key(path)).map(|(_identity, metadata)| metadata)
+    }
+
+    //Path and everything below it
+    pub fn discard_metadata(&mut self, path: &Path) {
+        let key = self.comparison().key(path);
+        self.metadata.retain(|staged, _| !staged.starts_with(key.as_path()));
+    }
+
+    pub fn metadata_changes(&self) -> impl Iterator<Item = (&Path, &Metadata)> {
+        self.metadata.values().map(|(identity, metadata)| (identity.as_path(), metadata))
+    }
 
     pub fn mut_add_state(&mut self) -> &mut VirtualDelta {
         &mut self.add
@@ -69,10 +123,6 @@ impl VirtualFileSystem {
 mod test {
     use super::*;
 
-    use std::{
-        path::{ Path }
-    };
-
     use crate::{
         Kind,
     };
@@ -94,4 +144,45 @@ mod test {
         assert!(!vfs.has_addition());
         assert!(!vfs.has_subtraction());
     }
+
+    #[test]
+    fn set_comparison_keeps_state_on_collision() {
+        let mut vfs = VirtualFileSystem::default();
+        let comparison = PathComparison::exact().with_case_insensitive(true);
+
+        vfs.mut_add_state().attach(Path::new("/virtual"), None, Kind::File).unwrap();
+        vfs.mut_add_state().attach(Path::new("/VIRTUAL"), None, Kind::File).unwrap();
+        assert!(vfs.set_comparison(comparison).is_err());
+        assert!(vfs.comparison().is_exact());
+
+        vfs.mut_add_state().detach(Path::new("/VIRTUAL")).unwrap();
+        vfs.set_comparison(comparison).unwrap();
+        assert!(vfs.add_state().get(Path::new("/Virtual")).unwrap().is_some());
+
+        vfs.reset();
+        assert_eq!(vfs.comparison(), comparison);
+    }
+
+    #[test]
+    fn stage_metadata_along_the_deltas() {
+        let mut vfs = VirtualFileSystem::default();
+        let mode = Metadata::default().with_mode(Some(0o700));
+        let owner = Metadata::default().with_owner(Some(1000));
+
+        vfs.stage_metadata(Path::new("/A"), &mode);
+        vfs.stage_metadata(Path::new("/A"), &owner);
+        vfs.stage_metadata(Path::new("/A/B"), &owner);
+        vfs.stage_metadata(Path::new("/AB"), &owner);
+        assert!(!vfs.is_empty());
+        assert_eq!(vfs.staged_metadata(Path::new("/A")), Some(&mode.with_owner(Some(1000))));
+
+        vfs.set_comparison(PathComparison::exact().with_case_insensitive(true)).unwrap();
+        assert!(vfs.staged_metadata(Path::new("/a/b")).is_some());
+
+        vfs.discard_metadata(Path::new("/a"));
+        assert_eq!(vfs.metadata_changes().map(|(path, _)| path).collect::<Vec<&Path>>(), vec![Path::new("/AB")]);
+
+        vfs.reset();
+        assert!(vfs.is_empty());
+    }
 }
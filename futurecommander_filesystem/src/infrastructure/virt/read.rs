@@ -17,7 +17,7 @@
  * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use std::{ path::{ Path } };
+use std::{ path::{ Path, PathBuf } };
 
 use futurecommander_representation::{
     VirtualPath,
@@ -34,15 +34,35 @@ use crate::{
         FileSystemAdapter,
         EntryAdapter,
         EntryCollection,
-        Entry
+        Entry,
+        Metadata
     },
     infrastructure::virt::{
         VirtualFileSystem,
         entry_status::{ VirtualStatus }
-    }
+    },
+    lock::LOCK_FILE
 };
 
 impl FileSystemAdapter<VirtualFileSystem> {
+    //Real entry the comparison folds path into, path itself when it exists as is
+    fn real_match(&self, path: &Path) -> Option<PathBuf> {
+        if path.exists() {
+            return Some(path.to_path_buf());
+        }
+        let comparison = self.0.comparison();
+        if comparison.is_exact() {
+            return None;
+        }
+
+        let name = Path::new(path.file_name()?);
+        self.real_match(path.parent()?)?
+            .read_dir().ok()?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .find(|sibling| matches!(sibling.file_name(), Some(other) if comparison.matches(Path::new(other), name)))
+    }
+
     fn virtual_unknown(&self, path: &Path) -> Result<VirtualPath, QueryError>{
         match VirtualPath::from(
             path.to_path_buf(),
@@ -63,7 +83,7 @@ impl FileSystemAdapter<VirtualFileSystem> {
         } else {
             match self.0.add_state().get(path)? {//IN ADD AND NOT IN SUB
                 Some(virtual_identity) =>
-                    if path.exists() {
+                    if self.real_match(path).is_some() {
                         Ok(VirtualStatus::new(VirtualState::Replaced, virtual_identity.clone()))
                     } else {
                         Ok(VirtualStatus::new(VirtualState::ExistsVirtually, virtual_identity.clone()))
@@ -96,14 +116,14 @@ impl FileSystemAdapter<VirtualFileSystem> {
     fn status_real(&self, path: &Path) -> Result<VirtualStatus, QueryError> {
         if self.0.sub_state().is_virtual(path)? {
             Ok(VirtualStatus::new(VirtualState::Removed, self.virtual_unknown(path)?))
-        } else if path.exists() {
+        } else if let Some(real_path) = self.real_match(path) {
             Ok(
                 VirtualStatus::new(
                     VirtualState::Exists,
                     VirtualPath::from(
                         path.to_path_buf(),
-                        Some(path.to_path_buf()),
-                        if path.is_dir()
+                        Some(real_path.clone()),
+                        if real_path.is_dir()
                         { Kind::Directory }
                         else { Kind::File }
                     )?
@@ -139,6 +159,7 @@ impl ReadableFileSystem for FileSystemAdapter<VirtualFileSystem> {
                 Ok(results) => {
                     for result in results {
                         match result {
+                            Ok(result) if result.file_name() == LOCK_FILE => {},
                             Ok(result) => {
                                 let result_path = result.path();
                                 let mut virtual_identity = VirtualPath::from_path(result.path().as_path())?
@@ -165,7 +186,12 @@ impl ReadableFileSystem for FileSystemAdapter<VirtualFileSystem> {
         }
 
         if let Some(to_add_children) = self.0.add_state().children(directory.as_identity()) {
+            let comparison = self.0.comparison();
             for child in to_add_children.iter() {
+                //Replacing a real entry spelled another way
+                if !comparison.is_exact() && entry_collection.iter().any(|entry| comparison.matches(entry.path(), child.as_identity())) {
+                    continue;
+                }
                 if ! self.0.sub_state().is_virtual(child.as_identity())? {
                     entry_collection.add(self.status(child.as_identity())?)
                 }
@@ -197,6 +223,23 @@ impl ReadableFileSystem for FileSystemAdapter<VirtualFileSystem> {
                 }).collect()
         )
     }
+
+    //Staged values over those of the real entry holding the content, entries created virtually only have staged ones
+    fn metadata(&self, path: &Path) -> Result<Option<Metadata>, QueryError> {
+        let entry = self.status(path)?;
+        if !entry.exists() {
+            return Ok(None);
+        }
+
+        let real = match entry.source() {
+            Some(source) => Metadata::read(source)?,
+            None => Metadata::default()
+        };
+        Ok(Some(match self.0.staged_metadata(path) {
+            Some(staged) => real.overridden_by(staged),
+            None => real
+        }))
+    }
 }
 
 
@@ -31,7 +31,8 @@ use crate::{
         ReadableFileSystem,
         FileSystemAdapter,
         Entry,
-        EntryAdapter
+        EntryAdapter,
+        Metadata
     },
     infrastructure::{
         errors::{
@@ -71,6 +72,7 @@ impl FileSystemAdapter<VirtualFileSystem> where Self : ReadableFileSystem {
                 return Err(InfrastructureError::PathDoesNotExists(path.to_path_buf()))
             ,
         }
+        self.0.discard_metadata(path);
         Ok(())
     }
 
@@ -86,6 +88,8 @@ impl FileSystemAdapter<VirtualFileSystem> where Self : ReadableFileSystem {
     }
 
     fn create(&mut self, new_identity: VirtualPath, destination: EntryAdapter<VirtualStatus>) -> Result<(), InfrastructureError>{
+        //A new entry does not keep what was staged for the one it replaces
+        self.0.discard_metadata(new_identity.as_identity());
         let state = destination.as_inner().state();
         match destination.into_inner() {
             VirtualStatus{ state: VirtualState::Exists, identity }
@@ -143,6 +147,11 @@ impl WriteableFileSystem for FileSystemAdapter<VirtualFileSystem> {
         )
     }
 
+    //Virtual files have no content
+    fn create_file_with_content(&mut self, path: &Path, _content: &str) -> Result<(), InfrastructureError> {
+        self.create_empty_file(path)
+    }
+
     fn copy_file_to_file(&mut self, src: &Path, dst: &Path) -> Result<(), InfrastructureError>{
         let source = self.status(src)?;
         let destination = self.status(dst)?;
@@ -172,9 +181,47 @@ impl WriteableFileSystem for FileSystemAdapter<VirtualFileSystem> {
         )
     }
 
+    //Staged metadata follows the file, as a rename keeps it. A link is moved itself, whatever it points to
     fn move_file_to_file(&mut self, source: &Path, destination: &Path) -> Result<(), InfrastructureError>{
-        self.copy_file_to_file(source, destination)?;
+        let staged = self.0.staged_metadata(source).copied();
+        let link = self.status(source)?;
+        if link.exists() && link.is_symlink() {
+            let target = self.status(destination)?;
+            self.safe_parent(destination)?;
+            if target.exists() && ! target.is_file() {
+                return Err(InfrastructureError::DestinationIsNotAFile(target.to_path()));
+            }
+
+            let identity = link.as_inner().as_virtual();
+            self.create(
+                VirtualPath::from(
+                    target.to_path(),
+                    identity.to_source(),
+                    identity.to_kind()
+                )?,
+                target
+            )?;
+        } else {
+            self.copy_file_to_file(source, destination)?;
+        }
         self.remove(source)?;
+        if let Some(staged) = staged {
+            self.0.stage_metadata(destination, &staged);
+        }
+        Ok(())
+    }
+
+    //A link shows the content and staged metadata of its source, the inode is only shared once applied
+    fn hard_link(&mut self, source: &Path, destination: &Path) -> Result<(), InfrastructureError> {
+        if self.status(destination)?.exists() {
+            return Err(InfrastructureError::DestinationAlreadyExists(destination.to_path_buf()));
+        }
+
+        let staged = self.0.staged_metadata(source).copied();
+        self.copy_file_to_file(source, destination)?;
+        if let Some(staged) = staged {
+            self.0.stage_metadata(destination, &staged);
+        }
         Ok(())
     }
 
@@ -241,4 +288,13 @@ impl WriteableFileSystem for FileSystemAdapter<VirtualFileSystem> {
         self.remove(path)
     }
 
+    fn set_metadata(&mut self, path: &Path, metadata: &Metadata) -> Result<(), InfrastructureError> {
+        if ! self.status(path)?.exists() {
+            return Err(InfrastructureError::PathDoesNotExists(path.to_path_buf()));
+        }
+
+        self.0.stage_metadata(path, metadata);
+        Ok(())
+    }
+
 }
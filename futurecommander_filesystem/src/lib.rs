@@ -23,8 +23,11 @@ mod port;
 mod infrastructure;
 mod errors;
 mod container;
+mod recovery;
+mod lock;
+mod naming;
 
-pub use futurecommander_representation::Kind;
+pub use futurecommander_representation::{ Kind, PathComparison, Normalization };
 
 pub use self::{
     errors::{ DomainError, QueryError },
@@ -39,16 +42,24 @@ pub use self::{
         WriteableFileSystem,
         EntryAdapter,
         EntryCollection,
-        SerializableEntry
+        SerializableEntry,
+        ShellScript,
+        Metadata
     },
     event::*,
-    container::Container
+    container::Container,
+    recovery::{ Recovery, RecoverySession, Retention },
+    lock::{ Locks, LockOwner, LOCK_FILE },
+    naming::{ Portability, NameIssue, NameWarning, NameValidator }
 };
 
 //Mainly for testing
 pub use self::infrastructure::VirtualState;
 
+pub use self::infrastructure::{ Change, ChangeKind, Changes };
+
 pub mod tools;
+pub mod lossless;
 
 #[cfg(not(tarpaulin_include))]
 pub mod sample;
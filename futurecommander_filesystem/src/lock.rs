@@ -0,0 +1,345 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    env::var,
+    fmt::{ Display, Formatter, Result as FmtResult },
+    fs::{ OpenOptions, read_dir, read_to_string, remove_file },
+    io::{ ErrorKind, Write },
+    path::{ Path, PathBuf },
+    process
+};
+
+use serde::{ Serialize, Deserialize };
+
+use chrono::Local;
+
+use crate::{
+    errors::DomainError,
+    event::FileSystemEvent
+};
+
+pub const LOCK_FILE : &str = ".futurecommander.lock";
+
+fn hostname() -> String {
+    match var("HOSTNAME") {
+        Ok(host) if !host.trim().is_empty() => host.trim().to_string(),
+        _ => read_to_string("/etc/hostname")
+            .map(|host| host.trim().to_string())
+            .ok()
+            .filter(|host| !host.is_empty())
+            .unwrap_or_else(|| "localhost".to_string())
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn is_alive(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn is_alive(_pid: u32) -> bool {
+    true
+}
+
+//Written in the lock file so that other operators know who to talk to
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LockOwner {
+    pid: u32,
+    host: String,
+    started: String
+}
+
+impl LockOwner {
+    pub fn current() -> LockOwner {
+        LockOwner {
+            pid: process::id(),
+            host: hostname(),
+            started: Local::now().format("%Y-%m-%dT%H:%M:%S").to_string()
+        }
+    }
+
+    pub fn pid(&self) -> u32 { self.pid }
+    pub fn host(&self) -> &str { self.host.as_str() }
+    pub fn started(&self) -> &str { self.started.as_str() }
+
+    pub fn is_current(&self) -> bool {
+        self.pid == process::id() && self.host == hostname()
+    }
+
+    //Only a process of this host can be known as gone
+    pub fn is_stale(&self) -> bool {
+        self.host == hostname() && !is_alive(self.pid)
+    }
+}
+
+impl Display for LockOwner {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "pid {} on {} since {}", self.pid, self.host, self.started)
+    }
+}
+
+/*
+Advisory lock files taken in the directories an operation changes.
+A lock in a directory covers its whole tree, locks held by this process are reentrant
+and stale locks are taken over. Every lock file created is removed on release or drop.
+Only the ancestors of a root are checked, unless the locks are kept for a session:
+the tree of a root is then walked once for foreign locks, when it gets locked.
+*/
+#[derive(Debug, Default)]
+pub struct Locks {
+    held: Vec<PathBuf>,
+    session: bool
+}
+
+impl Locks {
+    pub fn session() -> Locks {
+        Locks {
+            held: Vec::new(),
+            session: true
+        }
+    }
+
+    //Nearest existing directories containing what the events write, without those already covered by another one
+    pub fn roots<'a, I: IntoIterator<Item=&'a FileSystemEvent>>(events: I) -> Vec<PathBuf> {
+        let mut roots : Vec<PathBuf> = events.into_iter()
+            .flat_map(|event| event.targets())
+            .filter_map(|target| target.ancestors().skip(1).find(|ancestor| ancestor.is_dir()).map(Path::to_path_buf))
+            .collect();
+
+        roots.sort();
+        roots.dedup();
+        let covered : Vec<PathBuf> = roots.iter()
+            .filter(|root| roots.iter().any(|other| other != *root && root.starts_with(other)))
+            .cloned()
+            .collect();
+        roots.retain(|root| !covered.contains(root));
+        roots
+    }
+
+    pub fn owner(directory: &Path) -> Result<Option<LockOwner>, DomainError> {
+        match read_to_string(directory.join(LOCK_FILE)) {
+            Ok(content) => Ok(Some(serde_json::from_str(content.as_str())?)),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(DomainError::from(error))
+        }
+    }
+
+    //First foreign lock held in the tree of directory, stale ones being taken over on the way. Linked directories are not walked
+    fn nested_owner(directory: &Path) -> Result<Option<(PathBuf, LockOwner)>, DomainError> {
+        let children = match read_dir(directory) {
+            Ok(children) => children,
+            Err(_) => return Ok(None)
+        };
+        for child in children.filter_map(Result::ok) {
+            if !child.file_type().map(|file_type| file_type.is_dir()).unwrap_or(false) {
+                continue;
+            }
+            let child = child.path();
+            match Self::owner(child.as_path())? {
+                Some(owner) if owner.is_current() => {},
+                Some(owner) if owner.is_stale() => remove_file(child.join(LOCK_FILE))?,
+                Some(owner) => return Ok(Some((child, owner))),
+                None => {}
+            }
+            if let Some(nested) = Self::nested_owner(child.as_path())? {
+                return Ok(Some(nested));
+            }
+        }
+        Ok(None)
+    }
+
+    //Locks taken below root after it was created see it as an ancestor, those taken before are refused
+    fn try_lock(root: &Path, nested: bool) -> Result<Option<PathBuf>, DomainError> {
+        for directory in root.ancestors() {
+            match Self::owner(directory)? {
+                Some(owner) if owner.is_current() => return Ok(None),
+                Some(owner) if owner.is_stale() => remove_file(directory.join(LOCK_FILE))?,
+                Some(owner) => return Err(DomainError::Locked(directory.to_path_buf(), owner)),
+                None => {}
+            }
+        }
+
+        let path = root.join(LOCK_FILE);
+        let mut file = match OpenOptions::new().write(true).create_new(true).open(path.as_path()) {
+            Ok(file) => file,
+            //Another process won the race
+            Err(error) if error.kind() == ErrorKind::AlreadyExists => return match Self::owner(root)? {
+                Some(owner) => Err(DomainError::Locked(root.to_path_buf(), owner)),
+                None => Err(DomainError::from(error))
+            },
+            Err(error) => return Err(DomainError::from(error))
+        };
+        file.write_all(serde_json::to_string(&LockOwner::current())?.as_bytes())?;
+        if !nested {
+            return Ok(Some(path));
+        }
+
+        match Self::nested_owner(root) {
+            Ok(None) => Ok(Some(path)),
+            Ok(Some((directory, owner))) => {
+                remove_file(path)?;
+                Err(DomainError::Locked(directory, owner))
+            },
+            Err(error) => {
+                remove_file(path)?;
+                Err(error)
+            }
+        }
+    }
+
+    //Every root is locked, or none. Held locks a new root covers are released, they must not stay in its tree
+    pub fn acquire(&mut self, roots: &[PathBuf]) -> Result<(), DomainError> {
+        let mut acquired = Locks::default();
+        for root in roots {
+            if let Some(path) = Self::try_lock(root.as_path(), self.session)? {
+                acquired.held.push(path);
+            }
+        }
+
+        for path in acquired.held.iter() {
+            let root = path.parent().unwrap_or(path);
+            let (covered, kept) = self.held.drain(..).partition(|held| held != path && held.starts_with(root));
+            self.held = kept;
+            Locks { held: covered, session: false }.release();
+        }
+        self.held.append(&mut acquired.held);
+        Ok(())
+    }
+
+    pub fn release(&mut self) {
+        for path in self.held.drain(..) {
+            let _ = remove_file(path);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.held.is_empty()
+    }
+}
+
+impl Drop for Locks {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::write;
+
+    use crate::{
+        sample::Samples,
+        event::{ CopyEvent, RemoveEvent }
+    };
+
+    #[test]
+    fn roots_cover_written_directories() {
+        let chroot = Samples::init_simple_chroot("lock_roots_cover_written_directories");
+        let events = [
+            FileSystemEvent::Copy(CopyEvent::new(chroot.join("RDIR/RFILEA").as_path(), chroot.join("RDIR2/NEW/RFILEA").as_path(), false, false)),
+            FileSystemEvent::Remove(RemoveEvent::new(chroot.join("RDIR3").as_path(), true)),
+            FileSystemEvent::Remove(RemoveEvent::new(chroot.join("RDIR/RFILEB").as_path(), false))
+        ];
+
+        assert_eq!(Locks::roots(events.iter()), vec![chroot.clone()]);
+        assert_eq!(Locks::roots(events[..1].iter()), vec![chroot.join("RDIR2")]);
+    }
+
+    #[test]
+    fn lock_is_exclusive_until_released() {
+        let chroot = Samples::init_simple_chroot("lock_is_exclusive_until_released");
+        let foreign = LockOwner {
+            pid: process::id(),
+            host: "elsewhere".to_string(),
+            started: "2019-10-07T10:20:30".to_string()
+        };
+        write(chroot.join("RDIR").join(LOCK_FILE), serde_json::to_string(&foreign).unwrap()).unwrap();
+
+        let mut locks = Locks::default();
+        match locks.acquire(&[chroot.join("RDIR2"), chroot.join("RDIR")]) {
+            Err(DomainError::Locked(directory, owner)) => {
+                assert_eq!(directory, chroot.join("RDIR"));
+                assert_eq!(owner, foreign);
+            },
+            _ => panic!("a lock held on another host must be respected")
+        }
+        assert!(!chroot.join("RDIR2").join(LOCK_FILE).exists());
+
+        remove_file(chroot.join("RDIR").join(LOCK_FILE)).unwrap();
+        locks.acquire(std::slice::from_ref(&chroot)).unwrap();
+        assert!(Locks::owner(chroot.as_path()).unwrap().unwrap().is_current());
+
+        //Reentrant for this process
+        let mut nested = Locks::default();
+        nested.acquire(&[chroot.join("RDIR")]).unwrap();
+        assert!(nested.is_empty());
+
+        drop(locks);
+        assert!(!chroot.join(LOCK_FILE).exists());
+    }
+
+    #[test]
+    fn refuse_lock_over_nested_foreign_lock() {
+        let chroot = Samples::init_simple_chroot("lock_refuse_lock_over_nested_foreign_lock");
+        let foreign = LockOwner {
+            pid: process::id(),
+            host: "elsewhere".to_string(),
+            started: "2019-10-07T10:20:30".to_string()
+        };
+        write(chroot.join("RDIR2").join(LOCK_FILE), serde_json::to_string(&foreign).unwrap()).unwrap();
+
+        //Only the ancestors are checked outside of a session
+        let mut locks = Locks::default();
+        locks.acquire(std::slice::from_ref(&chroot)).unwrap();
+        locks.release();
+
+        let mut locks = Locks::session();
+        match locks.acquire(std::slice::from_ref(&chroot)) {
+            Err(DomainError::Locked(directory, owner)) => {
+                assert_eq!(directory, chroot.join("RDIR2"));
+                assert_eq!(owner, foreign);
+            },
+            _ => panic!("a lock held below the root must be respected")
+        }
+        assert!(!chroot.join(LOCK_FILE).exists());
+        assert!(locks.is_empty());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn take_over_stale_lock() {
+        let chroot = Samples::init_simple_chroot("lock_take_over_stale_lock");
+        let gone = LockOwner {
+            pid: u32::MAX,
+            host: hostname(),
+            started: "2019-10-07T10:20:30".to_string()
+        };
+        assert!(gone.is_stale());
+        write(chroot.join(LOCK_FILE), serde_json::to_string(&gone).unwrap()).unwrap();
+
+        let mut locks = Locks::default();
+        locks.acquire(&[chroot.join("RDIR")]).unwrap();
+        assert!(!chroot.join(LOCK_FILE).exists());
+        assert!(chroot.join("RDIR").join(LOCK_FILE).exists());
+    }
+}
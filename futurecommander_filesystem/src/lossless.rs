@@ -0,0 +1,242 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    ffi::{ OsStr, OsString },
+    fmt::Write,
+    path::{ Path, PathBuf }
+};
+
+use serde::{ Serializer, Deserializer, Deserialize, de::Error };
+
+use crate::errors::DomainError;
+
+/*
+Lossless text form of paths for state files and output.
+Valid UTF-8 paths are written as they are, others get the marker followed by the path with \\ and \xHH escapes.
+A UTF-8 path which starts with the marker is escaped too, so decoding is never ambiguous.
+*/
+pub const MARKER: &str = "bytes:";
+
+#[cfg(unix)]
+pub(crate) fn bytes(path: &OsStr) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_bytes().to_vec()
+}
+
+#[cfg(not(unix))]
+pub(crate) fn bytes(path: &OsStr) -> Vec<u8> {
+    path.to_string_lossy().as_bytes().to_vec()
+}
+
+#[cfg(unix)]
+pub(crate) fn from_bytes(bytes: Vec<u8>) -> Result<OsString, DomainError> {
+    use std::os::unix::ffi::OsStringExt;
+    Ok(OsString::from_vec(bytes))
+}
+
+#[cfg(not(unix))]
+pub(crate) fn from_bytes(bytes: Vec<u8>) -> Result<OsString, DomainError> {
+    String::from_utf8(bytes)
+        .map(OsString::from)
+        .map_err(|error| DomainError::InvalidEncodedPath(String::from_utf8_lossy(error.as_bytes()).to_string()))
+}
+
+/*
+Readable form without the marker, meant for listings.
+*/
+pub fn escape(path: &OsStr) -> String {
+    let mut escaped = String::new();
+    for chunk in bytes(path).utf8_chunks() {
+        escaped.push_str(chunk.valid().replace('\\', "\\\\").as_str());
+        for byte in chunk.invalid() {
+            let _ = write!(escaped, "\\x{:02x}", byte);
+        }
+    }
+    escaped
+}
+
+pub fn encode(path: &Path) -> String {
+    match path.to_str() {
+        Some(text) if !text.starts_with(MARKER) => text.to_string(),
+        _ => format!("{}{}", MARKER, escape(path.as_os_str()))
+    }
+}
+
+pub fn decode(text: &str) -> Result<PathBuf, DomainError> {
+    let escaped = match text.strip_prefix(MARKER) {
+        Some(escaped) => escaped,
+        None => return Ok(PathBuf::from(text))
+    };
+
+    let invalid = || DomainError::InvalidEncodedPath(text.to_string());
+    let mut decoded = Vec::with_capacity(escaped.len());
+    let mut chars = escaped.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('\\') => decoded.push(b'\\'),
+                Some('x') => {
+                    let hex : String = chars.by_ref().take(2).collect();
+                    if hex.len() != 2 {
+                        return Err(invalid());
+                    }
+                    decoded.push(u8::from_str_radix(hex.as_str(), 16).map_err(|_| invalid())?);
+                },
+                _ => return Err(invalid())
+            },
+            c => decoded.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes())
+        }
+    }
+
+    Ok(PathBuf::from(from_bytes(decoded)?))
+}
+
+pub fn serialize<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(encode(path).as_str())
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PathBuf, D::Error> {
+    decode(String::deserialize(deserializer)?.as_str()).map_err(D::Error::custom)
+}
+
+//File names, encoded like paths
+pub mod name {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(name: &OsStr, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(encode(Path::new(name)).as_str())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<OsString, D::Error> {
+        decode(String::deserialize(deserializer)?.as_str())
+            .map(PathBuf::into_os_string)
+            .map_err(D::Error::custom)
+    }
+}
+
+pub mod option {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(path: &Option<PathBuf>, serializer: S) -> Result<S::Ok, S::Error> {
+        match path {
+            Some(path) => serializer.serialize_some(encode(path).as_str()),
+            None => serializer.serialize_none()
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<PathBuf>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(text) => decode(text.as_str()).map(Some).map_err(D::Error::custom),
+            None => Ok(None)
+        }
+    }
+}
+
+pub mod vec {
+    use super::*;
+    use serde::ser::SerializeSeq;
+
+    pub fn serialize<S: Serializer>(paths: &[PathBuf], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(paths.len()))?;
+        for path in paths {
+            seq.serialize_element(encode(path).as_str())?;
+        }
+        seq.end()
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<PathBuf>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|text| decode(text.as_str()).map_err(D::Error::custom))
+            .collect()
+    }
+}
+
+pub mod map {
+    use super::*;
+    use std::collections::HashMap;
+    use serde::{ Serialize, ser::SerializeMap };
+
+    pub fn serialize<V: Serialize, S: Serializer>(paths: &HashMap<PathBuf, V>, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(paths.len()))?;
+        for (path, value) in paths {
+            map.serialize_entry(encode(path).as_str(), value)?;
+        }
+        map.end()
+    }
+
+    pub fn deserialize<'de, V: Deserialize<'de>, D: Deserializer<'de>>(deserializer: D) -> Result<HashMap<PathBuf, V>, D::Error> {
+        HashMap::<String, V>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(text, value)| decode(text.as_str()).map(|path| (path, value)).map_err(D::Error::custom))
+            .collect()
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde::{ Serialize, Deserialize };
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Paths {
+        #[serde(with = "crate::lossless")]
+        path: PathBuf,
+        #[serde(with = "crate::lossless::option")]
+        from: Option<PathBuf>,
+        #[serde(with = "crate::lossless::vec")]
+        held: Vec<PathBuf>
+    }
+
+    #[cfg(unix)]
+    fn latin1() -> PathBuf {
+        use std::os::unix::ffi::OsStrExt;
+        PathBuf::from(OsStr::from_bytes(b"/shares/caf\xe9\\menu"))
+    }
+
+    #[test]
+    fn utf8_paths_are_kept_as_they_are() {
+        assert_eq!(encode(Path::new("/A/é b")), "/A/é b");
+        assert_eq!(decode("/A/é b").unwrap(), PathBuf::from("/A/é b"));
+        assert_eq!(encode(Path::new("bytes:A")), "bytes:bytes:A");
+        assert_eq!(decode("bytes:bytes:A").unwrap(), PathBuf::from("bytes:A"));
+        assert!(decode("bytes:caf\\xe").is_err());
+        assert!(decode("bytes:caf\\e9").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn non_utf8_paths_round_trip() {
+        let path = latin1();
+        assert_eq!(encode(path.as_path()), "bytes:/shares/caf\\xe9\\\\menu");
+        assert_eq!(escape(path.file_name().unwrap()), "caf\\xe9\\\\menu");
+        assert_eq!(decode(encode(path.as_path()).as_str()).unwrap(), path);
+
+        let paths = Paths { path: path.clone(), from: Some(path.clone()), held: vec![path.clone(), PathBuf::from("/A")] };
+        let json = serde_json::to_string(&paths).unwrap();
+        assert_eq!(serde_json::from_str::<Paths>(json.as_str()).unwrap(), paths);
+        let toml = toml::to_string(&paths).unwrap();
+        assert_eq!(toml::from_str::<Paths>(toml.as_str()).unwrap(), paths);
+        let yaml = serde_yaml::to_string(&paths).unwrap();
+        assert_eq!(serde_yaml::from_str::<Paths>(yaml.as_str()).unwrap(), paths);
+    }
+}
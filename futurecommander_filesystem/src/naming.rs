@@ -0,0 +1,376 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    fmt::{ Display, Formatter, Result as FmtResult },
+    path::{ Path, PathBuf },
+    str::FromStr
+};
+
+use serde::{ Serialize, Deserialize };
+
+use futurecommander_representation::{ PathComparison, Normalization };
+
+use crate::{
+    errors::DomainError,
+    port::{
+        Entry,
+        ReadableFileSystem,
+        Atomic,
+        AtomicTransaction
+    }
+};
+
+const WINDOWS_RESERVED : [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL",
+    "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9"
+];
+
+//Filesystem the names must be valid on, native being the one under the tree
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Portability {
+    Native,
+    Posix,
+    Windows,
+    MacOs
+}
+
+impl Portability {
+    //Longest component and path
+    fn limits(self) -> (usize, usize) {
+        match self {
+            Portability::Native | Portability::Posix => (255, 4096),
+            Portability::Windows => (255, 260),
+            Portability::MacOs => (255, 1024)
+        }
+    }
+
+    //How the filesystem tells names apart, HFS+ storing them decomposed
+    pub fn comparison(self) -> PathComparison {
+        match self {
+            Portability::Native | Portability::Posix => PathComparison::exact(),
+            Portability::Windows => PathComparison::exact().with_case_insensitive(true),
+            Portability::MacOs => PathComparison::exact().with_case_insensitive(true).with_normalization(Normalization::Nfd)
+        }
+    }
+
+    fn forbids(self, character: char) -> bool {
+        match self {
+            Portability::Native => character == '\0',
+            Portability::Posix => !(character.is_ascii_alphanumeric() || character == '.' || character == '_' || character == '-'),
+            Portability::Windows => character.is_control() || "<>:\"\\|?*".contains(character),
+            Portability::MacOs => character == '\0' || character == ':'
+        }
+    }
+}
+
+impl FromStr for Portability {
+    type Err = DomainError;
+
+    fn from_str(profile: &str) -> Result<Portability, DomainError> {
+        match profile.to_lowercase().as_str() {
+            "native" => Ok(Portability::Native),
+            "posix" => Ok(Portability::Posix),
+            "windows" => Ok(Portability::Windows),
+            "macos" => Ok(Portability::MacOs),
+            _ => Err(DomainError::InvalidPortability(profile.to_string()))
+        }
+    }
+}
+
+impl Display for Portability {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{}",
+            match self {
+                Portability::Native => "native",
+                Portability::Posix => "posix",
+                Portability::Windows => "windows",
+                Portability::MacOs => "macos"
+            }
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum NameIssue {
+    Reserved,
+    TrailingDotOrSpace,
+    LeadingHyphen,
+    NotUnicode,
+    ForbiddenCharacter(char),
+    ComponentTooLong(usize, usize),
+    PathTooLong(usize, usize),
+    CaseCollision(#[serde(with = "crate::lossless")] PathBuf)
+}
+
+impl Display for NameIssue {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            NameIssue::Reserved => write!(f, "reserved name"),
+            NameIssue::TrailingDotOrSpace => write!(f, "trailing dot or space"),
+            NameIssue::LeadingHyphen => write!(f, "leading hyphen"),
+            NameIssue::NotUnicode => write!(f, "not valid unicode"),
+            NameIssue::ForbiddenCharacter(character) => write!(f, "forbidden character {:?}", character),
+            NameIssue::ComponentTooLong(length, max) => write!(f, "name of {} characters, at most {}", length, max),
+            NameIssue::PathTooLong(length, max) => write!(f, "path of {} characters, at most {}", length, max),
+            NameIssue::CaseCollision(other) => write!(f, "only differs by case from {}", other.to_string_lossy())
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct NameWarning {
+    #[serde(with = "crate::lossless")]
+    path: PathBuf,
+    portability: Portability,
+    issue: NameIssue
+}
+
+impl NameWarning {
+    pub fn path(&self) -> &Path { self.path.as_path() }
+    pub fn portability(&self) -> Portability { self.portability }
+    pub fn issue(&self) -> &NameIssue { &self.issue }
+}
+
+impl Display for NameWarning {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{} is not {} portable : {}", self.path.to_string_lossy(), self.portability, self.issue)
+    }
+}
+
+impl From<NameWarning> for DomainError {
+    fn from(warning: NameWarning) -> Self {
+        DomainError::NotPortable(warning.path, warning.portability, warning.issue)
+    }
+}
+
+/*
+Checks names an operation puts in place, children of bound directories included.
+Strict validation refuses the operation, otherwise issues are only reported.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct NameValidator {
+    portability: Portability,
+    strict: bool
+}
+
+impl NameValidator {
+    pub fn new(portability: Portability) -> NameValidator {
+        NameValidator {
+            portability,
+            strict: false
+        }
+    }
+
+    pub fn with_strict(mut self, strict: bool) -> NameValidator {
+        self.strict = strict;
+        self
+    }
+
+    pub fn portability(&self) -> Portability { self.portability }
+    pub fn is_strict(&self) -> bool { self.strict }
+
+    //Issues of the last component and of the whole path, collisions aside
+    pub fn issues(&self, path: &Path) -> Vec<NameIssue> {
+        let mut issues = Vec::new();
+        let (component_max, path_max) = self.portability.limits();
+        let name = match path.file_name() {
+            Some(name) => name,
+            None => return issues
+        };
+
+        match name.to_str() {
+            Some(name) => {
+                if self.portability == Portability::Windows {
+                    let stem = name.split('.').next().unwrap_or(name).trim_end();
+                    if WINDOWS_RESERVED.iter().any(|reserved| reserved.eq_ignore_ascii_case(stem)) {
+                        issues.push(NameIssue::Reserved);
+                    }
+                    if name.ends_with('.') || name.ends_with(' ') {
+                        issues.push(NameIssue::TrailingDotOrSpace);
+                    }
+                }
+                if self.portability == Portability::Posix && name.starts_with('-') {
+                    issues.push(NameIssue::LeadingHyphen);
+                }
+                if let Some(character) = name.chars().find(|character| self.portability.forbids(*character)) {
+                    issues.push(NameIssue::ForbiddenCharacter(character));
+                }
+            },
+            None if self.portability != Portability::Native => issues.push(NameIssue::NotUnicode),
+            None => {}
+        }
+
+        //Bytes on the native filesystem, characters elsewhere
+        let length = |text: &std::ffi::OsStr| match self.portability {
+            Portability::Native => text.len(),
+            _ => text.to_string_lossy().chars().count()
+        };
+        if length(name) > component_max {
+            issues.push(NameIssue::ComponentTooLong(length(name), component_max));
+        }
+        if length(path.as_os_str()) > path_max {
+            issues.push(NameIssue::PathTooLong(length(path.as_os_str()), path_max));
+        }
+        issues
+    }
+
+    fn created<E: Entry, F: ReadableFileSystem<Item=E>>(transaction: &AtomicTransaction, fs: &F) -> Result<Vec<PathBuf>, DomainError> {
+        let mut created = Vec::new();
+        for atomic in transaction.iter() {
+            match atomic {
+                Atomic::CreateEmptyDirectory(path)
+                | Atomic::CreateEmptyFile(path)
+                | Atomic::CreateFileWithContent { path, .. }
+                | Atomic::CopyFileToFile { destination: path, .. }
+                | Atomic::MoveFileToFile { destination: path, .. }
+                | Atomic::HardLink { destination: path, .. } => created.push(path.to_path_buf()),
+                Atomic::BindDirectoryToDirectory { source, destination } => {
+                    created.push(destination.to_path_buf());
+                    Self::bound(source, destination, fs, &mut created)?;
+                },
+                _ => {}
+            }
+        }
+        Ok(created)
+    }
+
+    fn bound<E: Entry, F: ReadableFileSystem<Item=E>>(source: &Path, destination: &Path, fs: &F, created: &mut Vec<PathBuf>) -> Result<(), DomainError> {
+        for child in fs.read_dir(source)? {
+            if let Some(name) = child.name() {
+                created.push(destination.join(name));
+                if child.is_dir() {
+                    Self::bound(child.path(), destination.join(name).as_path(), fs, created)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn collision<E: Entry, F: ReadableFileSystem<Item=E>>(&self, path: &Path, transaction: &AtomicTransaction, others: &[PathBuf], fs: &F) -> Option<PathBuf> {
+        let comparison = self.portability.comparison();
+        let (parent, name) = match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => (parent, Path::new(name)),
+            _ => return None
+        };
+        let siblings = fs.read_dir(parent)
+            .map(|children| children.into_iter().map(|child| child.to_path()).collect())
+            .unwrap_or_else(|_| Vec::new());
+
+        siblings.iter()
+            .filter(|sibling| !transaction.vacates(sibling))
+            .chain(others.iter().filter(|other| other.parent() == Some(parent)))
+            .find(|sibling| {
+                sibling.as_path() != path
+                    && matches!(sibling.file_name(), Some(other) if comparison.matches(Path::new(other), name))
+            })
+            .cloned()
+    }
+
+    //Reported issues, the first one is an error when strict
+    pub fn validate<E: Entry, F: ReadableFileSystem<Item=E>>(&self, transaction: &AtomicTransaction, fs: &F) -> Result<Vec<NameWarning>, DomainError> {
+        let created = Self::created(transaction, fs)?;
+        let mut warnings = Vec::new();
+
+        for (index, path) in created.iter().enumerate() {
+            let mut issues = self.issues(path);
+            if !self.portability.comparison().is_exact() {
+                if let Some(other) = self.collision(path, transaction, &created[..index], fs) {
+                    issues.push(NameIssue::CaseCollision(other));
+                }
+            }
+
+            for issue in issues {
+                let warning = NameWarning {
+                    path: path.to_path_buf(),
+                    portability: self.portability,
+                    issue
+                };
+                if self.strict {
+                    return Err(DomainError::from(warning));
+                }
+                warnings.push(warning);
+            }
+        }
+        Ok(warnings)
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        sample::Samples,
+        port::FileSystemAdapter,
+        infrastructure::RealFileSystem
+    };
+
+    #[test]
+    fn name_issues_per_profile() {
+        let windows = NameValidator::new(Portability::Windows);
+        assert_eq!(windows.issues(Path::new("/tree/con.txt")), vec![NameIssue::Reserved]);
+        assert_eq!(windows.issues(Path::new("/tree/LPT1")), vec![NameIssue::Reserved]);
+        assert_eq!(windows.issues(Path::new("/tree/notes. ")), vec![NameIssue::TrailingDotOrSpace]);
+        assert_eq!(windows.issues(Path::new("/tree/a:b")), vec![NameIssue::ForbiddenCharacter(':')]);
+        assert!(windows.issues(Path::new("/tree/console.txt")).is_empty());
+
+        let posix = NameValidator::new(Portability::Posix);
+        assert_eq!(posix.issues(Path::new("/tree/-rf")), vec![NameIssue::LeadingHyphen]);
+        assert_eq!(posix.issues(Path::new("/tree/été")), vec![NameIssue::ForbiddenCharacter('é')]);
+
+        let macos = NameValidator::new(Portability::MacOs);
+        assert_eq!(macos.issues(Path::new("/tree/a:b")), vec![NameIssue::ForbiddenCharacter(':')]);
+        assert!(macos.issues(Path::new("/tree/con")).is_empty());
+
+        let native = NameValidator::new(Portability::Native);
+        let long = "a".repeat(256);
+        assert_eq!(native.issues(Path::new("/tree").join(long.as_str()).as_path()), vec![NameIssue::ComponentTooLong(256, 255)]);
+        assert_eq!(
+            windows.issues(Path::new("/tree").join("a".repeat(200)).join("b".repeat(70)).as_path()),
+            vec![NameIssue::PathTooLong(277, 260)]
+        );
+    }
+
+    #[test]
+    fn validate_transaction() {
+        let chroot = Samples::init_simple_chroot("naming_validate_transaction");
+        let fs = FileSystemAdapter(RealFileSystem::default());
+        let mut transaction = AtomicTransaction::default();
+        transaction.add(Atomic::CopyFileToFile { source: chroot.join("RDIR/RFILEA"), destination: chroot.join("RDIR/rfileb") });
+        transaction.add(Atomic::BindDirectoryToDirectory { source: chroot.join("RDIR"), destination: chroot.join("AUX") });
+
+        let warnings = NameValidator::new(Portability::Windows).validate(&transaction, &fs).unwrap();
+        assert_eq!(
+            warnings.iter().map(NameWarning::issue).cloned().collect::<Vec<NameIssue>>(),
+            vec![NameIssue::CaseCollision(chroot.join("RDIR/RFILEB")), NameIssue::Reserved]
+        );
+        assert!(NameValidator::new(Portability::Native).validate(&transaction, &fs).unwrap().is_empty());
+
+        match NameValidator::new(Portability::Windows).with_strict(true).validate(&transaction, &fs) {
+            Err(DomainError::NotPortable(path, Portability::Windows, NameIssue::CaseCollision(_))) => assert_eq!(path, chroot.join("RDIR/rfileb")),
+            _ => panic!("strict validation must refuse the first issue")
+        }
+    }
+}
@@ -18,37 +18,63 @@
  */
 
 use std::{
-    path    ::{ PathBuf },
+    path    ::{ Path, PathBuf },
+    slice   ::{ Iter },
     vec     ::{ IntoIter }
 };
 
+use serde::{ Serialize, Deserialize };
+
 use crate::{
     port    ::{
-        WriteableFileSystem
+        WriteableFileSystem,
+        Metadata
     },
     infrastructure::{ errors:: InfrastructureError }
 
 };
 
-#[derive(Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum Atomic {
-    CreateEmptyDirectory(PathBuf),
-    CreateEmptyFile(PathBuf),
+    CreateEmptyDirectory(#[serde(with = "crate::lossless")] PathBuf),
+    CreateEmptyFile(#[serde(with = "crate::lossless")] PathBuf),
+    CreateFileWithContent {
+        #[serde(with = "crate::lossless")]
+        path: PathBuf,
+        content: String
+    },
     BindDirectoryToDirectory {
+        #[serde(with = "crate::lossless")]
         source: PathBuf,
+        #[serde(with = "crate::lossless")]
         destination: PathBuf
     },
     CopyFileToFile {
+        #[serde(with = "crate::lossless")]
         source: PathBuf,
+        #[serde(with = "crate::lossless")]
         destination: PathBuf
     },
     MoveFileToFile {
+        #[serde(with = "crate::lossless")]
+        source: PathBuf,
+        #[serde(with = "crate::lossless")]
+        destination: PathBuf
+    },
+    HardLink {
+        #[serde(with = "crate::lossless")]
         source: PathBuf,
+        #[serde(with = "crate::lossless")]
         destination: PathBuf
     },
-    RemoveFile(PathBuf),
-    RemoveEmptyDirectory(PathBuf),
-    RemoveMaintainedEmptyDirectory(PathBuf)
+    RemoveFile(#[serde(with = "crate::lossless")] PathBuf),
+    RemoveEmptyDirectory(#[serde(with = "crate::lossless")] PathBuf),
+    RemoveMaintainedEmptyDirectory(#[serde(with = "crate::lossless")] PathBuf),
+    SetMetadata {
+        #[serde(with = "crate::lossless")]
+        path: PathBuf,
+        metadata: Metadata
+    }
 }
 
 impl Atomic {
@@ -57,12 +83,15 @@ impl Atomic {
         match self {
             CreateEmptyDirectory(path) => fs.create_empty_directory(path.as_path()),
             CreateEmptyFile(path) => fs.create_empty_file(path.as_path()),
+            CreateFileWithContent { path, content } => fs.create_file_with_content(path.as_path(), content.as_str()),
             BindDirectoryToDirectory { source, destination } => fs.bind_directory_to_directory(source.as_path(), destination.as_path()),
             CopyFileToFile { source, destination } => fs.copy_file_to_file(source.as_path(), destination.as_path()),
             MoveFileToFile { source, destination } => fs.move_file_to_file(source.as_path(), destination.as_path()),
+            HardLink { source, destination } => fs.hard_link(source.as_path(), destination.as_path()),
             RemoveFile(path) => fs.remove_file(path.as_path()),
             RemoveEmptyDirectory(path) => fs.remove_empty_directory(path.as_path()),
-            RemoveMaintainedEmptyDirectory(path) => fs.remove_maintained_empty_directory(path.as_path())
+            RemoveMaintainedEmptyDirectory(path) => fs.remove_maintained_empty_directory(path.as_path()),
+            SetMetadata { path, metadata } => fs.set_metadata(path.as_path(), &metadata)
         }
     }
 }
@@ -84,9 +113,44 @@ impl AtomicTransaction {
 
     pub fn merge(&mut self, transaction: AtomicTransaction) {
         for atomic in transaction {
+            //Directories ensured by several children are only created once
+            if let Atomic::CreateEmptyDirectory(path) = &atomic {
+                if self.creates(path) && !self.vacates(path) {
+                    continue;
+                }
+            }
             self.add(atomic);
         }
     }
+
+    pub fn iter(&self) -> Iter<'_, Atomic> {
+        self.0.iter()
+    }
+
+    //Whether the transaction puts something at path once applied
+    pub fn creates(&self, path: &Path) -> bool {
+        self.0.iter().any(|atomic| match atomic {
+            Atomic::CreateEmptyDirectory(created)
+            | Atomic::CreateEmptyFile(created)
+            | Atomic::CreateFileWithContent { path: created, .. }
+            | Atomic::BindDirectoryToDirectory { destination: created, .. }
+            | Atomic::CopyFileToFile { destination: created, .. }
+            | Atomic::MoveFileToFile { destination: created, .. }
+            | Atomic::HardLink { destination: created, .. } => created == path,
+            _ => false
+        })
+    }
+
+    //Whether the transaction leaves nothing at path once applied
+    pub fn vacates(&self, path: &Path) -> bool {
+        self.0.iter().any(|atomic| match atomic {
+            Atomic::MoveFileToFile { source, .. } => source == path,
+            Atomic::RemoveFile(removed)
+            | Atomic::RemoveEmptyDirectory(removed)
+            | Atomic::RemoveMaintainedEmptyDirectory(removed) => removed == path,
+            _ => false
+        })
+    }
 }
 
 impl IntoIterator for AtomicTransaction {
@@ -42,6 +42,15 @@ pub trait Entry {
     fn is_file(&self) -> bool;
     fn exists(&self) -> bool;
     fn is_virtual(&self) -> bool;
+    //Real path holding the content, if any
+    fn source(&self) -> Option<&Path>;
+    //Whether the content is reached through a symbolic link, is_dir and is_file follow it
+    fn is_symlink(&self) -> bool {
+        match self.source().map(Path::symlink_metadata) {
+            Some(Ok(metadata)) => metadata.file_type().is_symlink(),
+            _ => false
+        }
+    }
     fn is_contained_by(&self, other: &dyn Entry) -> bool {
         for ancestor in self.path().ancestors() {
             if other.path() == ancestor {
@@ -25,7 +25,8 @@ use crate::{
     errors::{ QueryError },
     port::{
         Entry,
-        EntryCollection
+        EntryCollection,
+        Metadata
     },
     infrastructure::errors::InfrastructureError
 };
@@ -49,20 +50,39 @@ pub trait ReadableFileSystem {
     fn read_maintained(&self, path: &Path) -> Result<EntryCollection<Self::Item>,QueryError> {
         self.read_dir(path)
     }
+    //Metadata path will have, None when it does not exist
+    fn metadata(&self, path: &Path) -> Result<Option<Metadata>, QueryError> {
+        let entry = self.status(path)?;
+        match entry.source() {
+            Some(source) if entry.exists() => Ok(Some(Metadata::read(source)?)),
+            _ => Ok(None)
+        }
+    }
+    //Identity shared by the real names of a file linked more than once, None for entries which are not real
+    fn hard_link_identity(&self, path: &Path) -> Result<Option<(u64, u64)>, QueryError> {
+        let entry = self.status(path)?;
+        match entry.source() {
+            Some(source) if entry.exists() && !entry.is_virtual() => Ok(Metadata::hard_link_identity(source)?),
+            _ => Ok(None)
+        }
+    }
 }
 
 pub trait WriteableFileSystem: ReadableFileSystem {
     //Write API Interface
     fn create_empty_directory(&mut self, path: &Path) -> Result<(), InfrastructureError>;
     fn create_empty_file(&mut self, path: &Path) -> Result<(), InfrastructureError>;
+    fn create_file_with_content(&mut self, path: &Path, content: &str) -> Result<(), InfrastructureError>;
     fn copy_file_to_file(&mut self, source: &Path, destination: &Path) -> Result<(), InfrastructureError>;
     fn move_file_to_file(&mut self, source: &Path, destination: &Path) -> Result<(), InfrastructureError>;
     fn bind_directory_to_directory(&mut self, source: &Path, destination: &Path) -> Result<(), InfrastructureError>;
+    fn hard_link(&mut self, source: &Path, destination: &Path) -> Result<(), InfrastructureError>;
     fn remove_file(&mut self, path: &Path) -> Result<(), InfrastructureError>;
     fn remove_empty_directory(&mut self, path: &Path) -> Result<(), InfrastructureError>;
     fn remove_maintained_empty_directory(&mut self, path: &Path) -> Result<(), InfrastructureError> {
         self.remove_empty_directory(path)
     }
+    fn set_metadata(&mut self, path: &Path, metadata: &Metadata) -> Result<(), InfrastructureError>;
 }
 
 pub trait FileSystemTransaction<F: WriteableFileSystem> {
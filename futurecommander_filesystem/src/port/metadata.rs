@@ -0,0 +1,245 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    fs::{ self, Permissions },
+    io,
+    path::Path,
+    time::{ Duration, SystemTime, UNIX_EPOCH }
+};
+
+use serde::{ Serialize, Deserialize };
+
+use filetime::{ FileTime, set_file_mtime };
+
+/*
+Permissions, ownership and modification time of an entry, each one only when known or changed.
+Modification time is in seconds since the epoch.
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Metadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mode: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    owner: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    group: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    modified: Option<i64>
+}
+
+impl Metadata {
+    pub fn with_mode(mut self, mode: Option<u32>) -> Metadata {
+        self.mode = mode.map(|mode| mode & 0o7777);
+        self
+    }
+
+    pub fn with_owner(mut self, owner: Option<u32>) -> Metadata {
+        self.owner = owner;
+        self
+    }
+
+    pub fn with_group(mut self, group: Option<u32>) -> Metadata {
+        self.group = group;
+        self
+    }
+
+    pub fn with_modified(mut self, modified: Option<i64>) -> Metadata {
+        self.modified = modified;
+        self
+    }
+
+    pub fn mode(&self) -> Option<u32> { self.mode }
+    pub fn owner(&self) -> Option<u32> { self.owner }
+    pub fn group(&self) -> Option<u32> { self.group }
+    pub fn modified(&self) -> Option<i64> { self.modified }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Metadata::default()
+    }
+
+    //Values of other replace those of self
+    pub fn overridden_by(&self, other: &Metadata) -> Metadata {
+        Metadata {
+            mode: other.mode.or(self.mode),
+            owner: other.owner.or(self.owner),
+            group: other.group.or(self.group),
+            modified: other.modified.or(self.modified)
+        }
+    }
+
+    //Only keeps the values other changes
+    pub fn restricted_to(&self, other: &Metadata) -> Metadata {
+        Metadata {
+            mode: other.mode.and(self.mode),
+            owner: other.owner.and(self.owner),
+            group: other.group.and(self.group),
+            modified: other.modified.and(self.modified)
+        }
+    }
+
+    //Links are followed, like chmod, chown and touch do
+    pub fn read(path: &Path) -> io::Result<Metadata> {
+        let metadata = fs::metadata(path)?;
+        let modified = match metadata.modified()?.duration_since(UNIX_EPOCH) {
+            Ok(elapsed) => elapsed.as_secs() as i64,
+            Err(error) => -(error.duration().as_secs() as i64)
+        };
+        Ok(Self::read_ownership(&metadata).with_modified(Some(modified)))
+    }
+
+    #[cfg(unix)]
+    fn read_ownership(metadata: &fs::Metadata) -> Metadata {
+        use std::os::unix::fs::MetadataExt;
+        Metadata::default()
+            .with_mode(Some(metadata.mode()))
+            .with_owner(Some(metadata.uid()))
+            .with_group(Some(metadata.gid()))
+    }
+
+    #[cfg(not(unix))]
+    fn read_ownership(metadata: &fs::Metadata) -> Metadata {
+        Metadata::default()
+            .with_mode(Some(if metadata.permissions().readonly() { 0o444 } else { 0o644 }))
+    }
+
+    //Device and inode shared by the names of a regular file linked more than once, links are not followed
+    #[cfg(unix)]
+    pub fn hard_link_identity(path: &Path) -> io::Result<Option<(u64, u64)>> {
+        use std::os::unix::fs::MetadataExt;
+        let metadata = fs::symlink_metadata(path)?;
+        if metadata.is_file() && metadata.nlink() > 1 {
+            Ok(Some((metadata.dev(), metadata.ino())))
+        } else {
+            Ok(None)
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn hard_link_identity(_path: &Path) -> io::Result<Option<(u64, u64)>> {
+        Ok(None)
+    }
+
+    //A link only gets its own ownership, its target is left alone. Times are set by path, without reading the file
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        if self.owner.is_some() || self.group.is_some() {
+            Self::write_ownership(path, self.owner, self.group)?;
+        }
+        if fs::symlink_metadata(path)?.file_type().is_symlink() {
+            return Ok(());
+        }
+        if let Some(mode) = self.mode {
+            fs::set_permissions(path, Self::permissions(path, mode)?)?;
+        }
+        if let Some(time) = self.modified_time() {
+            set_file_mtime(path, FileTime::from_system_time(time))?;
+        }
+        Ok(())
+    }
+
+    //Ownership goes first, changing it may clear the setuid and setgid bits
+    #[cfg(unix)]
+    fn write_ownership(path: &Path, owner: Option<u32>, group: Option<u32>) -> io::Result<()> {
+        std::os::unix::fs::lchown(path, owner, group)
+    }
+
+    #[cfg(not(unix))]
+    fn write_ownership(_path: &Path, _owner: Option<u32>, _group: Option<u32>) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "ownership cannot be changed on this platform"))
+    }
+
+    #[cfg(unix)]
+    fn permissions(_path: &Path, mode: u32) -> io::Result<Permissions> {
+        use std::os::unix::fs::PermissionsExt;
+        Ok(Permissions::from_mode(mode))
+    }
+
+    #[cfg(not(unix))]
+    fn permissions(path: &Path, mode: u32) -> io::Result<Permissions> {
+        let mut permissions = fs::metadata(path)?.permissions();
+        permissions.set_readonly(mode & 0o222 == 0);
+        Ok(permissions)
+    }
+
+    //Same as ls -l, directories get a d
+    pub fn mode_string(mode: u32, directory: bool) -> String {
+        let mut rendered = String::from(if directory { "d" } else { "-" });
+        for (shift, special, set, unset) in [(6, 0o4000, 's', 'S'), (3, 0o2000, 's', 'S'), (0, 0o1000, 't', 'T')].iter() {
+            let bits = (mode >> shift) & 0o7;
+            rendered.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+            rendered.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+            rendered.push(match (bits & 0o1 != 0, mode & special != 0) {
+                (true, true) => *set,
+                (false, true) => *unset,
+                (true, false) => 'x',
+                (false, false) => '-'
+            });
+        }
+        rendered
+    }
+
+    pub fn modified_time(&self) -> Option<SystemTime> {
+        self.modified.map(|modified| if modified < 0 {
+            UNIX_EPOCH - Duration::from_secs(modified.unsigned_abs())
+        } else {
+            UNIX_EPOCH + Duration::from_secs(modified as u64)
+        })
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::sample::Samples;
+
+    #[test]
+    fn override_and_restrict() {
+        let real = Metadata::default().with_mode(Some(0o100644)).with_owner(Some(1000)).with_group(Some(100)).with_modified(Some(10));
+        let staged = Metadata::default().with_mode(Some(0o755));
+
+        assert_eq!(real.mode(), Some(0o644));
+        assert_eq!(real.overridden_by(&staged), real.with_mode(Some(0o755)));
+        assert_eq!(real.restricted_to(&staged), staged.with_mode(Some(0o644)));
+        assert!(real.restricted_to(&Metadata::default()).is_empty());
+    }
+
+    #[test]
+    fn render_mode() {
+        assert_eq!(Metadata::mode_string(0o755, true), "drwxr-xr-x");
+        assert_eq!(Metadata::mode_string(0o4640, false), "-rwSr-----");
+        assert_eq!(Metadata::mode_string(0o1777, true), "drwxrwxrwt");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_then_read() {
+        let chroot = Samples::init_simple_chroot("metadata_write_then_read");
+        let path = chroot.join("RDIR/RFILEA");
+
+        Metadata::default().with_mode(Some(0o600)).with_modified(Some(86_400)).write(path.as_path()).unwrap();
+        Metadata::default().with_modified(Some(-86_400)).write(chroot.join("RDIR").as_path()).unwrap();
+
+        let read = Metadata::read(path.as_path()).unwrap();
+        assert_eq!(read.mode(), Some(0o600));
+        assert_eq!(read.modified(), Some(86_400));
+        assert_eq!(Metadata::read(chroot.join("RDIR").as_path()).unwrap().modified(), Some(-86_400));
+    }
+}
@@ -21,12 +21,16 @@ mod entry;
 mod entry_collection;
 mod filesystem;
 mod atomic;
+mod metadata;
 mod serializable;
+mod shell_script;
 
 pub use self::{
     entry_collection::{ EntryCollection },
     entry::{ EntryAdapter, Entry },
     filesystem::{ FileSystemAdapter, WriteableFileSystem, ReadableFileSystem, FileSystemTransaction },
     atomic::{ AtomicTransaction, Atomic },
-    serializable::SerializableEntry
+    metadata::Metadata,
+    serializable::SerializableEntry,
+    shell_script::ShellScript
 };
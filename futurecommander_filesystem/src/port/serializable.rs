@@ -17,9 +17,12 @@
  * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
  */
 
+use std::path::Path;
+
 use serde::{ Serialize, Deserialize };
 
 use crate::{
+    lossless,
     port::{
         Entry
     }
@@ -38,9 +41,7 @@ impl Eq for SerializableEntry {}
 impl SerializableEntry {
     pub fn from(entry: &dyn Entry) -> Self {
         SerializableEntry {
-            name: if let Some(s) = entry.name() {
-                Some(s.to_string_lossy().to_string())
-            } else { None },
+            name: entry.name().map(|name| lossless::encode(Path::new(name))),
             is_dir: entry.is_dir(),
             is_file: entry.is_file(),
             is_virtual: entry.is_virtual()
@@ -0,0 +1,332 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    fmt::{ Display, Formatter, Result as FmtResult },
+    path::{ Path }
+};
+
+use chrono::{ Utc, TimeZone };
+
+use crate::{
+    lossless,
+    port::{
+        Atomic,
+        AtomicTransaction,
+        Metadata
+    }
+};
+
+/*
+POSIX shell rendering of atomic transactions.
+Every line checks whether its work is already done, so the script can be re-run safely after a partial failure.
+*/
+#[derive(Debug, Default)]
+pub struct ShellScript {
+    lines: Vec<String>
+}
+
+impl ShellScript {
+    pub fn quote(path: &Path) -> String {
+        match path.to_str() {
+            Some(text) => Self::quote_text(text),
+            None => Self::quote_bytes(path)
+        }
+    }
+
+    // Bytes which are not UTF-8 are written as octal escapes through printf, the only portable way
+    fn quote_bytes(path: &Path) -> String {
+        let mut quoted = String::new();
+        for chunk in lossless::bytes(path.as_os_str()).utf8_chunks() {
+            if !chunk.valid().is_empty() {
+                quoted.push_str(Self::quote_text(chunk.valid()).as_str());
+            }
+            if !chunk.invalid().is_empty() {
+                quoted.push_str(
+                    format!(
+                        "\"$(printf '{}')\"",
+                        chunk.invalid().iter().map(|byte| format!("\\{:03o}", byte)).collect::<String>()
+                    ).as_str()
+                );
+            }
+        }
+        quoted
+    }
+
+    fn quote_text(text: &str) -> String {
+        format!("'{}'", text.replace('\'', "'\\''"))
+    }
+
+    fn comment(text: &str) -> String {
+        format!(
+            "# {}",
+            text.chars()
+                .map(|c| if c.is_control() { '?' } else { c })
+                .collect::<String>()
+        )
+    }
+
+    fn refuse_overwrite(destination: &str) -> String {
+        format!("{{ echo 'refusing to overwrite' {} >&2; exit 1; }}", destination)
+    }
+
+    fn copy(&mut self, source: &Path, destination: &Path, overwrite: bool) {
+        let (source, destination) = (Self::quote(source), Self::quote(destination));
+        // A re-run finds an identical destination, or no source anymore once it was moved away
+        if overwrite {
+            self.lines.push(format!("[ ! -e {0} ] || cmp -s -- {0} {1} || {{ rm -f -- {1} && cp -- {0} {1}; }}", source, destination));
+        } else {
+            self.lines.push(format!("[ -e {1} ] && ! cmp -s -- {0} {1} && {2}", source, destination, Self::refuse_overwrite(&destination)));
+            self.lines.push(format!("[ -e {1} ] || cp -- {0} {1}", source, destination));
+        }
+    }
+
+    // Re-running finds the destination already linked to the source
+    fn link(&mut self, source: &Path, destination: &Path) {
+        let (source, destination) = (Self::quote(source), Self::quote(destination));
+        self.lines.push(format!("[ -e {1} ] && [ ! {0} -ef {1} ] && {2}", source, destination, Self::refuse_overwrite(&destination)));
+        self.lines.push(format!("[ -e {1} ] || ln -- {0} {1}", source, destination));
+    }
+
+    fn mov(&mut self, source: &Path, destination: &Path, overwrite: bool) {
+        let (source, destination) = (Self::quote(source), Self::quote(destination));
+        // A re-run may bring the source back through an earlier copy, an identical destination is then already done
+        if !overwrite {
+            self.lines.push(format!("[ -e {0} ] && [ -e {1} ] && ! cmp -s -- {0} {1} && {2}", source, destination, Self::refuse_overwrite(&destination)));
+        }
+        self.lines.push(format!("[ ! -e {0} ] || mv -f -- {0} {1}", source, destination));
+    }
+
+    // Ownership first, changing it may clear the setuid and setgid bits
+    fn metadata(&mut self, path: &Path, metadata: &Metadata) {
+        let path = Self::quote(path);
+        match (metadata.owner(), metadata.group()) {
+            (Some(owner), Some(group)) => self.lines.push(format!("chown -- {}:{} {}", owner, group, path)),
+            (Some(owner), None) => self.lines.push(format!("chown -- {} {}", owner, path)),
+            (None, Some(group)) => self.lines.push(format!("chgrp -- {} {}", group, path)),
+            (None, None) => {}
+        }
+        if let Some(mode) = metadata.mode() {
+            self.lines.push(format!("chmod -- {:04o} {}", mode, path));
+        }
+        if let Some(modified) = metadata.modified().and_then(|modified| Utc.timestamp_opt(modified, 0).single()) {
+            self.lines.push(format!("TZ=UTC0 touch -c -m -t {} -- {}", modified.format("%Y%m%d%H%M.%S"), path));
+        }
+    }
+
+    fn atomic(&mut self, atomic: &Atomic) {
+        match atomic {
+            Atomic::CreateEmptyDirectory(path)
+            | Atomic::BindDirectoryToDirectory { destination: path, .. } =>
+                self.lines.push(format!("[ -d {0} ] || mkdir -- {0}", Self::quote(path))),
+            Atomic::CreateEmptyFile(path) =>
+                self.lines.push(format!("[ -e {0} ] || touch -- {0}", Self::quote(path))),
+            Atomic::CreateFileWithContent { path, content } =>
+                self.lines.push(format!("[ -e {0} ] || printf '%s' {1} > {0}", Self::quote(path), Self::quote_text(content))),
+            Atomic::CopyFileToFile { source, destination } => self.copy(source, destination, false),
+            Atomic::MoveFileToFile { source, destination } => self.mov(source, destination, false),
+            Atomic::HardLink { source, destination } => self.link(source, destination),
+            Atomic::RemoveFile(path) =>
+                self.lines.push(format!("rm -f -- {}", Self::quote(path))),
+            Atomic::RemoveEmptyDirectory(path)
+            | Atomic::RemoveMaintainedEmptyDirectory(path) =>
+                self.lines.push(format!("[ ! -d {0} ] || rmdir -- {0}", Self::quote(path))),
+            Atomic::SetMetadata { path, metadata } => self.metadata(path, metadata)
+        }
+    }
+
+    pub fn add(&mut self, description: &str, transaction: &AtomicTransaction) {
+        self.lines.push(String::new());
+        self.lines.push(Self::comment(description));
+
+        let mut atomics = transaction.iter().peekable();
+        while let Some(atomic) = atomics.next() {
+            // An authorized overwrite is atomized as a removal immediately followed by the translation,
+            // it must be rendered as one step to keep the script re-runnable.
+            if let Atomic::RemoveFile(removed) = atomic {
+                match atomics.peek() {
+                    Some(Atomic::CopyFileToFile { source, destination }) if destination == removed => {
+                        self.copy(source, destination, true);
+                        atomics.next();
+                        continue;
+                    },
+                    Some(Atomic::MoveFileToFile { source, destination }) if destination == removed => {
+                        self.mov(source, destination, true);
+                        atomics.next();
+                        continue;
+                    },
+                    _ => {}
+                }
+            }
+            self.atomic(atomic);
+        }
+    }
+}
+
+impl Display for ShellScript {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        writeln!(f, "#!/bin/sh")?;
+        writeln!(f, "# Generated by futurecommander, review it before running it.")?;
+        writeln!(f, "set -e")?;
+        for line in self.lines.iter() {
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{
+        path::PathBuf
+    };
+
+    #[test]
+    fn quote_escapes_single_quotes() {
+        assert_eq!(ShellScript::quote(Path::new("/tmp/it's here")), "'/tmp/it'\\''s here'");
+        assert_eq!(ShellScript::quote(Path::new("/tmp/$HOME `x`")), "'/tmp/$HOME `x`'");
+    }
+
+    #[test]
+    fn change_metadata() {
+        let mut transaction = AtomicTransaction::default();
+        transaction.add(Atomic::SetMetadata {
+            path: PathBuf::from("/A"),
+            metadata: Metadata::default().with_mode(Some(0o750)).with_group(Some(100)).with_modified(Some(1_000_000_000))
+        });
+
+        let mut script = ShellScript::default();
+        script.add("chmod 750 /A", &transaction);
+
+        //Whatever the timezone of the machine rendering or running it
+        assert!(script.to_string().ends_with("chgrp -- 100 '/A'\nchmod -- 0750 '/A'\nTZ=UTC0 touch -c -m -t 200109090146.40 -- '/A'\n"));
+    }
+
+    #[test]
+    fn link_is_re_runnable() {
+        let mut transaction = AtomicTransaction::default();
+        transaction.add(Atomic::HardLink { source: PathBuf::from("/A"), destination: PathBuf::from("/B") });
+
+        let mut script = ShellScript::default();
+        script.add("ln /A /B", &transaction);
+
+        assert!(script.to_string().ends_with(
+            "[ -e '/B' ] && [ ! '/A' -ef '/B' ] && { echo 'refusing to overwrite' '/B' >&2; exit 1; }\n[ -e '/B' ] || ln -- '/A' '/B'\n"
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn quote_keeps_bytes_which_are_not_utf8() {
+        use std::{ ffi::OsStr, os::unix::ffi::OsStrExt };
+        assert_eq!(
+            ShellScript::quote(Path::new(OsStr::from_bytes(b"/tmp/caf\xe9's"))),
+            "'/tmp/caf'\"$(printf '\\351')\"''\\''s'"
+        );
+    }
+
+    #[test]
+    fn guard_copy_without_overwrite() {
+        let mut transaction = AtomicTransaction::default();
+        transaction.add(Atomic::CopyFileToFile {
+            source: PathBuf::from("/A"),
+            destination: PathBuf::from("/B")
+        });
+
+        let mut script = ShellScript::default();
+        script.add("cp /A /B", &transaction);
+
+        let rendered = script.to_string();
+        assert!(rendered.starts_with("#!/bin/sh\n"));
+        assert!(rendered.contains("set -e\n"));
+        assert!(rendered.contains("# cp /A /B\n"));
+        assert!(rendered.contains("[ -e '/B' ] && ! cmp -s -- '/A' '/B' && { echo 'refusing to overwrite' '/B' >&2; exit 1; }\n"));
+        assert!(rendered.contains("[ -e '/B' ] || cp -- '/A' '/B'\n"));
+    }
+
+    #[test]
+    fn fold_authorized_overwrite() {
+        let mut transaction = AtomicTransaction::default();
+        transaction.add(Atomic::RemoveFile(PathBuf::from("/B")));
+        transaction.add(Atomic::MoveFileToFile {
+            source: PathBuf::from("/A"),
+            destination: PathBuf::from("/B")
+        });
+
+        let mut script = ShellScript::default();
+        script.add("mv -o /A /B", &transaction);
+
+        let rendered = script.to_string();
+        assert!(rendered.contains("[ ! -e '/A' ] || mv -f -- '/A' '/B'\n"));
+        assert!(!rendered.contains("rm -f -- '/B'"));
+        assert!(!rendered.contains("exit 1"));
+    }
+
+    #[test]
+    fn fold_authorized_copy_overwrite() {
+        let mut transaction = AtomicTransaction::default();
+        transaction.add(Atomic::RemoveFile(PathBuf::from("/B")));
+        transaction.add(Atomic::CopyFileToFile {
+            source: PathBuf::from("/A"),
+            destination: PathBuf::from("/B")
+        });
+
+        let mut script = ShellScript::default();
+        script.add("cp -o /A /B", &transaction);
+
+        let rendered = script.to_string();
+        assert!(rendered.contains("[ ! -e '/A' ] || cmp -s -- '/A' '/B' || { rm -f -- '/B' && cp -- '/A' '/B'; }\n"));
+        assert!(!rendered.contains("\nrm -f -- '/B'\n"));
+    }
+
+    #[test]
+    fn write_content_once() {
+        let mut transaction = AtomicTransaction::default();
+        transaction.add(Atomic::CreateFileWithContent {
+            path: PathBuf::from("/info/A.trashinfo"),
+            content: "[Trash Info]\nPath=/it's\n".to_string()
+        });
+
+        let mut script = ShellScript::default();
+        script.add("rm --trash /it's", &transaction);
+
+        assert!(script.to_string().contains("[ -e '/info/A.trashinfo' ] || printf '%s' '[Trash Info]\nPath=/it'\\''s\n' > '/info/A.trashinfo'\n"));
+    }
+
+    #[test]
+    fn directories_are_idempotent() {
+        let mut transaction = AtomicTransaction::default();
+        transaction.add(Atomic::BindDirectoryToDirectory {
+            source: PathBuf::from("/A"),
+            destination: PathBuf::from("/B")
+        });
+        transaction.add(Atomic::RemoveMaintainedEmptyDirectory(PathBuf::from("/A")));
+
+        let mut script = ShellScript::default();
+        script.add("mv\nrm -rf /", &transaction);
+
+        let rendered = script.to_string();
+        assert!(rendered.contains("# mv?rm -rf /\n"));
+        assert!(rendered.contains("[ -d '/B' ] || mkdir -- '/B'\n"));
+        assert!(rendered.contains("[ ! -d '/A' ] || rmdir -- '/A'\n"));
+    }
+}
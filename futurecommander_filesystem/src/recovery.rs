@@ -0,0 +1,503 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    collections::BTreeMap,
+    env::var_os,
+    fs::{ create_dir_all, read_dir, read_to_string, remove_dir_all, write, symlink_metadata },
+    path::{ Path, PathBuf, Component },
+    time::{ Duration, SystemTime }
+};
+
+use serde::{ Serialize, Deserialize };
+
+use chrono::Local;
+
+use crate::{
+    errors::DomainError,
+    port::{
+        Entry,
+        ReadableFileSystem,
+        WriteableFileSystem,
+        FileSystemAdapter,
+        Atomic,
+        AtomicTransaction
+    },
+    infrastructure::VirtualFileSystem
+};
+
+const JOURNAL : &str = "journal.json";
+const STASH : &str = "stash";
+
+/*
+How long reverting stays possible, in number of sessions and in days since they were applied.
+*/
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Retention {
+    sessions: Option<usize>,
+    days: Option<u64>
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Retention {
+            sessions: Some(10),
+            days: Some(30)
+        }
+    }
+}
+
+impl Retention {
+    pub fn new(sessions: Option<usize>, days: Option<u64>) -> Retention {
+        Retention { sessions, days }
+    }
+
+    pub fn sessions(&self) -> Option<usize> { self.sessions }
+    pub fn days(&self) -> Option<u64> { self.days }
+}
+
+//Size and modification time of a file the session created, to tell whether it changed since
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+struct Fingerprint {
+    size: u64,
+    modified: Option<SystemTime>
+}
+
+impl Fingerprint {
+    fn of(path: &Path) -> Option<Fingerprint> {
+        symlink_metadata(path).ok().map(|metadata| Fingerprint {
+            size: metadata.len(),
+            modified: metadata.modified().ok()
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct Record {
+    created: String,
+    inverse: Vec<Atomic>,
+    //By index of the inverse removing the file
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    fingerprints: BTreeMap<usize, Fingerprint>
+}
+
+/*
+Directory where every recoverable session keeps a journal, the inverse of each applied atomic in order,
+and a stash holding the files the session removed or overwrote.
+*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recovery {
+    root: PathBuf,
+    retention: Retention
+}
+
+impl Recovery {
+    pub fn new(root: &Path) -> Recovery {
+        Recovery {
+            root: root.to_path_buf(),
+            retention: Retention::default()
+        }
+    }
+
+    //$XDG_STATE_HOME/futurecommander/recovery, $XDG_STATE_HOME defaulting to ~/.local/state
+    pub fn home() -> Result<Recovery, DomainError> {
+        match (var_os("XDG_STATE_HOME").map(PathBuf::from), var_os("HOME").map(PathBuf::from)) {
+            (Some(state_home), _) if state_home.is_absolute() => Ok(Recovery::new(state_home.join("futurecommander/recovery").as_path())),
+            (_, Some(home)) if home.is_absolute() => Ok(Recovery::new(home.join(".local/state/futurecommander/recovery").as_path())),
+            _ => Err(DomainError::RecoveryUnavailable)
+        }
+    }
+
+    pub fn with_retention(mut self, retention: Retention) -> Recovery {
+        self.retention = retention;
+        self
+    }
+
+    pub fn root(&self) -> &Path { self.root.as_path() }
+    pub fn retention(&self) -> Retention { self.retention }
+
+    //Session names come from the user, they must not escape the root
+    fn session_directory(&self, session: &str) -> Result<PathBuf, DomainError> {
+        let mut components = Path::new(session).components();
+        match (components.next(), components.next()) {
+            (Some(Component::Normal(_)), None) if self.root.join(session).join(JOURNAL).is_file() => Ok(self.root.join(session)),
+            _ => Err(DomainError::UnknownSession(session.to_string()))
+        }
+    }
+
+    fn record(&self, session: &str) -> Result<Record, DomainError> {
+        let content = read_to_string(self.session_directory(session)?.join(JOURNAL))?;
+        Ok(serde_json::from_str(content.as_str())?)
+    }
+
+    pub(crate) fn start(&self) -> Result<Journal, DomainError> {
+        let timestamp = Local::now().format("%Y%m%d-%H%M%S").to_string();
+        let mut name = timestamp.clone();
+        let mut n : u64 = 0;
+        while self.root.join(name.as_str()).exists() {
+            n += 1;
+            name = format!("{}-{}", timestamp, n);
+        }
+
+        let journal = Journal {
+            directory: self.root.join(name.as_str()),
+            name,
+            record: Record {
+                created: Local::now().format("%Y-%m-%dT%H:%M:%S").to_string(),
+                ..Record::default()
+            }
+        };
+        create_dir_all(journal.stash())?;
+        journal.save()?;
+        Ok(journal)
+    }
+
+    //Oldest first
+    pub fn sessions(&self) -> Result<Vec<RecoverySession>, DomainError> {
+        let mut sessions = Vec::new();
+        if !self.root.is_dir() {
+            return Ok(sessions);
+        }
+
+        for directory in read_dir(self.root.as_path())? {
+            let directory = directory?.path();
+            let name = match directory.file_name().and_then(|name| name.to_str()) {
+                Some(name) if directory.join(JOURNAL).is_file() => name.to_string(),
+                _ => continue
+            };
+
+            let record = self.record(name.as_str())?;
+            sessions.push(RecoverySession {
+                name,
+                created: record.created,
+                operations: record.inverse.len()
+            });
+        }
+        sessions.sort_by(|a, b| a.created.cmp(&b.created).then(a.name.cmp(&b.name)));
+        Ok(sessions)
+    }
+
+    //Inverse of the session, last applied atomic first
+    pub fn inverse(&self, session: &str) -> Result<AtomicTransaction, DomainError> {
+        let mut inverse = self.record(session)?.inverse;
+        inverse.reverse();
+        Ok(AtomicTransaction(inverse))
+    }
+
+    /*
+    The inverse is rehearsed against a virtual file system first, so that a real state which changed
+    since the session was applied, like a created file edited since, is reported before anything is touched.
+    The revert is itself journaled as a new session, removed files are stashed and a failure halfway can be reverted,
+    the reverted session is only discarded once it fully succeeded. Returns the name of the new session.
+    */
+    pub fn revert<F: WriteableFileSystem>(&self, session: &str, fs: &mut F) -> Result<String, DomainError> {
+        let record = self.record(session)?;
+
+        let mut simulation = FileSystemAdapter(VirtualFileSystem::default());
+        for (index, atomic) in record.inverse.iter().enumerate().rev() {
+            match atomic {
+                Atomic::CreateEmptyDirectory(path) | Atomic::MoveFileToFile { destination: path, .. }
+                    if simulation.status(path.as_path())?.exists() => {
+                    return Err(DomainError::RevertConflict(path.clone()));
+                },
+                Atomic::RemoveFile(path) => {
+                    if let Some(fingerprint) = record.fingerprints.get(&index) {
+                        let entry = simulation.status(path.as_path())?;
+                        if entry.source().and_then(Fingerprint::of).as_ref() != Some(fingerprint) {
+                            return Err(DomainError::RevertConflict(path.clone()));
+                        }
+                    }
+                },
+                _ => {}
+            }
+            atomic.clone().apply(&mut simulation)?;
+        }
+
+        let mut journal = self.start()?;
+        journal.apply(self.inverse(session)?, fs)?;
+        self.discard(session)?;
+        Ok(journal.name().to_string())
+    }
+
+    pub fn discard(&self, session: &str) -> Result<(), DomainError> {
+        remove_dir_all(self.session_directory(session)?)?;
+        Ok(())
+    }
+
+    //Drops the sessions beyond the retention policy, returns their names
+    pub fn expire(&self) -> Result<Vec<String>, DomainError> {
+        let sessions = self.sessions()?;
+        let count = sessions.len();
+        let mut expired = Vec::new();
+
+        for (i, session) in sessions.into_iter().enumerate() {
+            let too_many = self.retention.sessions.is_some_and(|kept| count - i > kept);
+            let too_old = match self.retention.days {
+                Some(days) => self.root.join(session.name()).join(JOURNAL).metadata()?.modified()?
+                    .elapsed()
+                    .is_ok_and(|age| age > Duration::from_secs(days * 24 * 60 * 60)),
+                None => false
+            };
+
+            if too_many || too_old {
+                self.discard(session.name())?;
+                expired.push(session.name);
+            }
+        }
+        Ok(expired)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecoverySession {
+    name: String,
+    created: String,
+    operations: usize
+}
+
+impl RecoverySession {
+    pub fn name(&self) -> &str { self.name.as_str() }
+    pub fn created(&self) -> &str { self.created.as_str() }
+    pub fn operations(&self) -> usize { self.operations }
+}
+
+/*
+Applies atomics while recording their inverse. Removed files are moved into the stash instead,
+their inverse moves them back. Files moved out of a stash, when reverting, are moved back into this one.
+*/
+#[derive(Debug)]
+pub(crate) struct Journal {
+    name: String,
+    directory: PathBuf,
+    record: Record
+}
+
+impl Journal {
+    pub fn name(&self) -> &str { self.name.as_str() }
+
+    fn stash(&self) -> PathBuf { self.directory.join(STASH) }
+
+    fn save(&self) -> Result<(), DomainError> {
+        write(self.directory.join(JOURNAL), serde_json::to_string(&self.record)?)?;
+        Ok(())
+    }
+
+    //The journal is saved even when an atomic fails, so a partially applied session can be reverted
+    pub fn apply<F: WriteableFileSystem>(&mut self, transaction: AtomicTransaction, fs: &mut F) -> Result<(), DomainError> {
+        let result = transaction.into_iter().try_for_each(|atomic| self.record(atomic, fs));
+        self.save()?;
+        result
+    }
+
+    fn record<F: WriteableFileSystem>(&mut self, atomic: Atomic, fs: &mut F) -> Result<(), DomainError> {
+        let (applied, inverse) = match atomic {
+            Atomic::RemoveFile(path) => {
+                let stashed = self.stash().join(self.record.inverse.len().to_string());
+                (
+                    Atomic::MoveFileToFile { source: path.clone(), destination: stashed.clone() },
+                    Atomic::MoveFileToFile { source: stashed, destination: path }
+                )
+            },
+            Atomic::CreateEmptyDirectory(ref path)
+            | Atomic::BindDirectoryToDirectory { destination: ref path, .. } => {
+                let inverse = Atomic::RemoveEmptyDirectory(path.clone());
+                (atomic, inverse)
+            },
+            Atomic::CreateEmptyFile(ref path)
+            | Atomic::CreateFileWithContent { ref path, .. }
+            | Atomic::CopyFileToFile { destination: ref path, .. }
+            | Atomic::HardLink { destination: ref path, .. } => {
+                let path = path.clone();
+                atomic.apply(fs)?;
+                if let Some(fingerprint) = Fingerprint::of(path.as_path()) {
+                    self.record.fingerprints.insert(self.record.inverse.len(), fingerprint);
+                }
+                self.record.inverse.push(Atomic::RemoveFile(path));
+                return Ok(());
+            },
+            Atomic::MoveFileToFile { ref source, ref destination } if self.directory.parent().is_some_and(|root| source.starts_with(root)) => {
+                let stashed = self.stash().join(self.record.inverse.len().to_string());
+                let inverse = Atomic::MoveFileToFile { source: destination.clone(), destination: stashed };
+                (atomic, inverse)
+            },
+            Atomic::MoveFileToFile { ref source, ref destination } => {
+                let inverse = Atomic::MoveFileToFile { source: destination.clone(), destination: source.clone() };
+                (atomic, inverse)
+            },
+            Atomic::RemoveEmptyDirectory(ref path)
+            | Atomic::RemoveMaintainedEmptyDirectory(ref path) => {
+                let inverse = Atomic::CreateEmptyDirectory(path.clone());
+                (atomic, inverse)
+            },
+            //Values in place before, only those the atomic changes
+            Atomic::SetMetadata { ref path, metadata } => {
+                let previous = fs.metadata(path.as_path())?.unwrap_or_default().restricted_to(&metadata);
+                let inverse = Atomic::SetMetadata { path: path.clone(), metadata: previous };
+                (atomic, inverse)
+            }
+        };
+
+        applied.apply(fs)?;
+        self.record.inverse.push(inverse);
+        Ok(())
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::{
+        sample::Samples,
+        infrastructure::RealFileSystem,
+        capability::ZealedGuard,
+        event::{ FileSystemEvent, RemoveEvent }
+    };
+
+    #[test]
+    fn journal_stashes_removed_files() {
+        let chroot = Samples::init_simple_chroot("recovery_journal_stashes_removed_files");
+        let recovery = Recovery::new(chroot.join("RECOVERY").as_path());
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+        let content = read_to_string(chroot.join("RDIR/RFILEA")).unwrap();
+
+        let mut journal = recovery.start().unwrap();
+        journal.apply(
+            AtomicTransaction(vec![
+                Atomic::RemoveFile(chroot.join("RDIR/RFILEA")),
+                Atomic::CreateEmptyDirectory(chroot.join("NEW"))
+            ]),
+            &mut fs
+        ).unwrap();
+
+        assert!(!chroot.join("RDIR/RFILEA").exists());
+        assert_eq!(
+            recovery.inverse(journal.name()).unwrap().0,
+            vec![
+                Atomic::RemoveEmptyDirectory(chroot.join("NEW")),
+                Atomic::MoveFileToFile { source: journal.stash().join("0"), destination: chroot.join("RDIR/RFILEA") }
+            ]
+        );
+
+        let revert = recovery.revert(journal.name(), &mut fs).unwrap();
+        assert_eq!(read_to_string(chroot.join("RDIR/RFILEA")).unwrap(), content);
+        assert!(!chroot.join("NEW").exists());
+        assert_eq!(
+            recovery.sessions().unwrap().iter().map(|session| session.name().to_string()).collect::<Vec<String>>(),
+            vec![revert.clone()]
+        );
+
+        //Reverting the revert removes the file again, into the stash of the revert
+        recovery.revert(revert.as_str(), &mut fs).unwrap();
+        assert!(!chroot.join("RDIR/RFILEA").exists());
+        assert!(chroot.join("NEW").is_dir());
+    }
+
+    #[test]
+    fn revert_refuses_created_files_changed_since() {
+        let chroot = Samples::init_simple_chroot("recovery_revert_refuses_created_files_changed_since");
+        let recovery = Recovery::new(chroot.join("RECOVERY").as_path());
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+
+        let mut journal = recovery.start().unwrap();
+        journal.apply(
+            AtomicTransaction(vec![
+                Atomic::CopyFileToFile { source: chroot.join("RDIR/RFILEA"), destination: chroot.join("COPIED") }
+            ]),
+            &mut fs
+        ).unwrap();
+        write(chroot.join("COPIED"), "edited after apply").unwrap();
+
+        match recovery.revert(journal.name(), &mut fs) {
+            Err(DomainError::RevertConflict(path)) => assert_eq!(path, chroot.join("COPIED")),
+            _ => panic!("an edited file must not be reverted")
+        }
+        assert_eq!(read_to_string(chroot.join("COPIED")).unwrap(), "edited after apply");
+    }
+
+    #[test]
+    fn revert_stashes_removed_files() {
+        let chroot = Samples::init_simple_chroot("recovery_revert_stashes_removed_files");
+        let recovery = Recovery::new(chroot.join("RECOVERY").as_path());
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+
+        let mut journal = recovery.start().unwrap();
+        journal.apply(
+            AtomicTransaction(vec![
+                Atomic::CreateFileWithContent { path: chroot.join("CREATED"), content: "created".to_string() }
+            ]),
+            &mut fs
+        ).unwrap();
+
+        let revert = recovery.revert(journal.name(), &mut fs).unwrap();
+        assert!(!chroot.join("CREATED").exists());
+        assert_eq!(read_to_string(chroot.join("RECOVERY").join(revert).join(STASH).join("0")).unwrap(), "created");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn journal_stashes_linked_directories() {
+        let chroot = Samples::init_simple_chroot("recovery_journal_stashes_linked_directories");
+        std::os::unix::fs::symlink(chroot.join("RDIR2"), chroot.join("RDIR/LINK")).unwrap();
+        let recovery = Recovery::new(chroot.join("RECOVERY").as_path());
+        let mut fs = FileSystemAdapter(RealFileSystem::default());
+
+        let transaction = FileSystemEvent::Remove(RemoveEvent::new(chroot.join("RDIR").as_path(), true))
+            .atomize(&fs, &mut ZealedGuard)
+            .unwrap();
+        let mut journal = recovery.start().unwrap();
+        journal.apply(transaction, &mut fs).unwrap();
+
+        assert!(!chroot.join("RDIR").exists());
+        assert!(chroot.join("RDIR2/RFILEC").is_file());
+
+        recovery.revert(journal.name(), &mut fs).unwrap();
+        assert!(symlink_metadata(chroot.join("RDIR/LINK")).unwrap().file_type().is_symlink());
+        assert!(chroot.join("RDIR/LINK/RFILEC").is_file());
+        assert!(chroot.join("RDIR/RFILEA").is_file());
+    }
+
+    #[test]
+    fn refuse_unknown_session() {
+        let chroot = Samples::init_empty_chroot("recovery_refuse_unknown_session");
+        let recovery = Recovery::new(chroot.as_path());
+        recovery.start().unwrap();
+
+        for session in ["missing", "../recovery", ""].iter() {
+            match recovery.inverse(session) {
+                Err(DomainError::UnknownSession(_)) => {},
+                _ => panic!("{} must not be a session", session)
+            }
+        }
+    }
+
+    #[test]
+    fn expire_beyond_retention() {
+        let chroot = Samples::init_empty_chroot("recovery_expire_beyond_retention");
+        let recovery = Recovery::new(chroot.as_path()).with_retention(Retention::new(Some(2), None));
+
+        let names : Vec<String> = (0..3).map(|_| recovery.start().unwrap().name().to_string()).collect();
+        assert_eq!(recovery.expire().unwrap(), vec![names[0].clone()]);
+        assert_eq!(
+            recovery.sessions().unwrap().iter().map(|session| session.name().to_string()).collect::<Vec<String>>(),
+            names[1..].to_vec()
+        );
+    }
+}
@@ -17,7 +17,12 @@
  * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use std::path::{ Path, PathBuf, Component };
+use std::{
+    cmp::min,
+    fs::{ File, canonicalize },
+    io::{ self, BufRead, BufReader },
+    path::{ Path, PathBuf, Component }
+};
 
 pub fn absolute(cwd: &Path, path: &Path) -> PathBuf {
     normalize(&cwd.join(path) )
@@ -63,3 +68,41 @@ pub fn normalize(p: &Path) -> PathBuf {
 
     norm_path
 }
+
+//Symlinks and .. resolved, as far as the path exists
+pub fn canonical(path: &Path) -> PathBuf {
+    let path = normalize(path);
+    for ancestor in path.ancestors() {
+        if let Ok(resolved) = canonicalize(ancestor) {
+            return match path.strip_prefix(ancestor) {
+                Ok(rest) if !rest.as_os_str().is_empty() => resolved.join(rest),
+                _ => resolved
+            };
+        }
+    }
+    path
+}
+
+pub fn same_content(left: &Path, right: &Path) -> io::Result<bool> {
+    if left.metadata()?.len() != right.metadata()?.len() {
+        return Ok(false);
+    }
+
+    let mut left = BufReader::new(File::open(left)?);
+    let mut right = BufReader::new(File::open(right)?);
+    loop {
+        let length = {
+            let (left_buffer, right_buffer) = (left.fill_buf()?, right.fill_buf()?);
+            if left_buffer.is_empty() || right_buffer.is_empty() {
+                return Ok(left_buffer.is_empty() && right_buffer.is_empty());
+            }
+            let length = min(left_buffer.len(), right_buffer.len());
+            if left_buffer[..length] != right_buffer[..length] {
+                return Ok(false);
+            }
+            length
+        };
+        left.consume(length);
+        right.consume(length);
+    }
+}
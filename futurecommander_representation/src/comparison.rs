@@ -0,0 +1,172 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    fmt::{ Display, Formatter, Result as FmtResult },
+    path::{ Path, PathBuf, Component },
+    ffi::OsString,
+    str::FromStr
+};
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::errors::RepresentationError;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Normalization {
+    #[default]
+    None,
+    Nfc,
+    Nfd
+}
+
+/*
+How identities are told apart, byte for byte unless the target filesystem folds case
+or normalizes unicode, like exFAT, SMB shares or HFS+.
+Identities keep their spelling, only the comparison key is folded.
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PathComparison {
+    case_insensitive: bool,
+    normalization: Normalization
+}
+
+impl PathComparison {
+    pub fn exact() -> PathComparison {
+        PathComparison::default()
+    }
+
+    pub fn with_case_insensitive(mut self, case_insensitive: bool) -> PathComparison {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+
+    pub fn with_normalization(mut self, normalization: Normalization) -> PathComparison {
+        self.normalization = normalization;
+        self
+    }
+
+    pub fn is_case_insensitive(&self) -> bool { self.case_insensitive }
+    pub fn normalization(&self) -> Normalization { self.normalization }
+
+    pub fn is_exact(&self) -> bool {
+        *self == PathComparison::exact()
+    }
+
+    //Components which are not valid unicode are compared as they are
+    pub fn key(&self, path: &Path) -> PathBuf {
+        if self.is_exact() {
+            return path.to_path_buf();
+        }
+
+        path.components()
+            .map(|component| match component {
+                Component::Normal(name) => match name.to_str() {
+                    Some(name) => OsString::from(self.fold(name)),
+                    None => name.to_os_string()
+                },
+                other => other.as_os_str().to_os_string()
+            })
+            .collect()
+    }
+
+    pub fn matches(&self, left: &Path, right: &Path) -> bool {
+        self.key(left) == self.key(right)
+    }
+
+    fn fold(&self, name: &str) -> String {
+        let normalized : String = match self.normalization {
+            Normalization::None => name.to_string(),
+            Normalization::Nfc => name.nfc().collect(),
+            Normalization::Nfd => name.nfd().collect()
+        };
+        if self.case_insensitive {
+            normalized.to_lowercase()
+        } else {
+            normalized
+        }
+    }
+}
+
+//exact, or case-insensitive, nfc and nfd joined by +
+impl FromStr for PathComparison {
+    type Err = RepresentationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut comparison = PathComparison::exact();
+        for part in s.split('+') {
+            comparison = match part.trim().to_lowercase().as_str() {
+                "exact" => comparison,
+                "case-insensitive" => comparison.with_case_insensitive(true),
+                "nfc" => comparison.with_normalization(Normalization::Nfc),
+                "nfd" => comparison.with_normalization(Normalization::Nfd),
+                _ => return Err(RepresentationError::InvalidComparison(s.to_string()))
+            };
+        }
+        Ok(comparison)
+    }
+}
+
+impl Display for PathComparison {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let mut parts = Vec::new();
+        if self.case_insensitive {
+            parts.push("case-insensitive");
+        }
+        match self.normalization {
+            Normalization::None => {},
+            Normalization::Nfc => parts.push("nfc"),
+            Normalization::Nfd => parts.push("nfd")
+        }
+        if parts.is_empty() {
+            parts.push("exact");
+        }
+        write!(f, "{}", parts.join("+"))
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fold_case_and_normalization() {
+        let composed = Path::new("/Tree/Caf\u{e9}");
+        let decomposed = Path::new("/tree/cafe\u{301}");
+
+        assert!(!PathComparison::exact().matches(composed, decomposed));
+        assert!(!PathComparison::exact().with_case_insensitive(true).matches(composed, decomposed));
+        assert!(PathComparison::exact().with_case_insensitive(true).with_normalization(Normalization::Nfd).matches(composed, decomposed));
+        assert_eq!(
+            PathComparison::exact().with_normalization(Normalization::Nfc).key(decomposed),
+            PathBuf::from("/tree/caf\u{e9}")
+        );
+    }
+
+    #[test]
+    fn parse_and_display() {
+        let comparison : PathComparison = "case-insensitive+nfd".parse().unwrap();
+        assert!(comparison.is_case_insensitive());
+        assert_eq!(comparison.normalization(), Normalization::Nfd);
+        assert_eq!(comparison.to_string(), "case-insensitive+nfd");
+        assert_eq!(PathComparison::exact().to_string(), "exact");
+        assert!("case-folding".parse::<PathComparison>().is_err());
+    }
+}
@@ -27,6 +27,7 @@ use std::{
 
 use crate::{
     Kind,
+    PathComparison,
     errors::RepresentationError,
     VirtualChildren,
     VirtualPath
@@ -35,10 +36,40 @@ use crate::{
 #[derive(Debug, Clone, Default)]
 pub struct VirtualDelta {
     pub hierarchy: BTreeMap<PathBuf, VirtualChildren>,
-    pub detached: Vec<VirtualPath>
+    pub detached: Vec<VirtualPath>,
+    //Hierarchy keys and children are folded accordingly
+    pub comparison: PathComparison
 }
 
 impl VirtualDelta {
+    pub fn new(comparison: PathComparison) -> VirtualDelta {
+        VirtualDelta {
+            comparison,
+            ..VirtualDelta::default()
+        }
+    }
+
+    //Same identities under another comparison, those it folds together collide
+    pub fn rekey(&self, comparison: PathComparison) -> Result<VirtualDelta, RepresentationError> {
+        let mut result = VirtualDelta::new(comparison);
+        for child in self.hierarchy.values().flat_map(|children| children.iter()) {
+            result.attach_virtual(child)?;
+        }
+        Ok(result)
+    }
+
+    fn key(&self, identity: &Path) -> PathBuf {
+        self.comparison.key(identity)
+    }
+
+    fn parent_key(&self, identity: &Path) -> PathBuf {
+        self.key(Self::get_parent_or_root(identity).as_path())
+    }
+
+    fn lookup(&self, identity: &Path) -> Result<VirtualPath, RepresentationError> {
+        Ok(VirtualPath::from_path(identity)?.with_comparison(&self.comparison))
+    }
+
     pub fn attach_virtual(&mut self, virtual_path: &VirtualPath) -> Result<(), RepresentationError>{
         self.attach(
             virtual_path.as_identity(),
@@ -51,11 +82,12 @@ impl VirtualDelta {
        if self.get(identity)?.is_some() { Err(RepresentationError::AlreadyExists(identity.to_path_buf())) }
        else {
             let parent = Self::get_parent_or_root(identity);
+            let parent_key = self.key(parent.as_path());
 
-            if !self.hierarchy.contains_key(parent.as_path()) {
+            if !self.hierarchy.contains_key(parent_key.as_path()) {
                 // https://github.com/rust-lang/rust-clippy/issues/5595
                 #[allow(clippy::redundant_clone)]
-                self.hierarchy.insert(parent.to_path_buf(), VirtualChildren::default());
+                self.hierarchy.insert(parent_key.clone(), VirtualChildren::default());
             }
 
             if self.is_file(parent.as_path())? {
@@ -63,14 +95,13 @@ impl VirtualDelta {
             }
 
             if identity != Self::root_identity().as_path() {
+                let child = self.lookup(identity)?
+                    .with_source(source)
+                    .with_kind(kind);
                 self.hierarchy
-                    .get_mut(parent.as_path())
+                    .get_mut(parent_key.as_path())
                     .unwrap() //Assumed
-                    .insert(
-                        VirtualPath::from_path(identity)?
-                            .with_source(source)
-                            .with_kind(kind)
-                    );
+                    .insert(child);
             }
 
             Ok(())
@@ -80,18 +111,20 @@ impl VirtualDelta {
     pub fn detach(&mut self, identity: &Path) -> Result<(), RepresentationError> {
         if self.get(identity)?.is_some() {
             let parent = Self::get_parent_or_root(identity);
+            let child = self.lookup(identity)?;
 
-            self.hierarchy.get_mut(&parent)
+            self.hierarchy.get_mut(&self.parent_key(identity))
                 .unwrap()//TODO Assumed ? self.get has not the same behavior as hierarchy.get_mut
-                .remove(&VirtualPath::from_path(identity)?);
+                .remove(&child);
 
 
             if self.is_directory_empty(parent.as_path()) {
-                self.hierarchy.remove(&parent);
+                self.hierarchy.remove(&self.key(parent.as_path()));
             }
 
-            if self.hierarchy.contains_key(&identity.to_path_buf()) {
-                self.hierarchy.remove(identity);
+            let key = self.key(identity);
+            if self.hierarchy.contains_key(&key) {
+                self.hierarchy.remove(&key);
             }
             Ok(())
         } else { Err(RepresentationError::DoesNotExists(identity.to_path_buf())) }
@@ -120,16 +153,16 @@ impl VirtualDelta {
     }
 
     pub fn children(&self, parent: &Path) -> Option<&VirtualChildren> {
-        match self.hierarchy.get(parent) {
+        match self.hierarchy.get(&self.key(parent)) {
             Some(children) => Some(&children),
             None => None //No key parent
         }
     }
 
     pub fn get(&self, identity: &Path) -> Result<Option<&VirtualPath>, RepresentationError> {
-        match self.hierarchy.get(Self::get_parent_or_root(identity).as_path()) {
+        match self.hierarchy.get(self.parent_key(identity).as_path()) {
             Some(children) => {
-                match children.get(&VirtualPath::from_path(identity)?) {
+                match children.get(&self.lookup(identity)?) {
                     Some(child) => Ok(Some(&child)),
                     None => Ok(None) //No matching child
                 }
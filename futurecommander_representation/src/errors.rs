@@ -27,6 +27,7 @@ pub enum RepresentationError {
     DoesNotExists(PathBuf),
     IsNotADirectory(PathBuf),
     IsRelativePath(PathBuf),
+    InvalidComparison(String),
 }
 impl fmt::Display for RepresentationError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -36,6 +37,7 @@ impl fmt::Display for RepresentationError {
             RepresentationError::DoesNotExists(identity) => write!(f, "Identity {} does not exists", identity.as_os_str().to_string_lossy()),
             RepresentationError::IsNotADirectory(identity) => write!(f, "Identity {} is not a directory", identity.as_os_str().to_string_lossy()),
             RepresentationError::IsRelativePath(identity) => write!(f, "Path {} is relative", identity.as_os_str().to_string_lossy()),
+            RepresentationError::InvalidComparison(comparison) => write!(f, "Unknown path comparison {}, expected exact or case-insensitive, nfc and nfd joined by +", comparison),
         }
     }
 }
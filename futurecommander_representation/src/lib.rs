@@ -26,12 +26,14 @@ mod path;
 mod delta;
 mod state;
 mod kind;
+mod comparison;
 
 pub use self::{
     kind::Kind,
     path::VirtualPath,
     delta::VirtualDelta,
-    state::VirtualState
+    state::VirtualState,
+    comparison::{ PathComparison, Normalization }
 };
 
 use std::collections::{
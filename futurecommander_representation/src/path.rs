@@ -32,6 +32,7 @@ use std::{
 
 use crate::{
     Kind,
+    PathComparison,
     errors::RepresentationError
 };
 
@@ -40,7 +41,9 @@ use crate::{
 pub struct VirtualPath {
     pub identity: PathBuf,
     pub source: Option<PathBuf>,
-    pub kind: Kind
+    pub kind: Kind,
+    //Folded identity, when not compared byte for byte
+    key: Option<PathBuf>
 }
 
 impl Eq for VirtualPath {}
@@ -57,25 +60,25 @@ impl FromStr for VirtualPath {
 //Rely on PathBuf implementation for identify & order VirtualPaths over Iterators
 impl Ord for VirtualPath {
     fn cmp(&self, other: &VirtualPath) -> Ordering {
-        self.identity.cmp(&other.identity)
+        self.as_key().cmp(other.as_key())
     }
 }
 
 impl PartialOrd for VirtualPath {
     fn partial_cmp(&self, other: &VirtualPath) -> Option<Ordering> {
-        Some(self.identity.cmp(&other.identity))
+        Some(self.cmp(other))
     }
 }
 
 impl PartialEq for VirtualPath {
     fn eq(&self, other: &VirtualPath) -> bool {
-        self.identity.eq(&other.identity)
+        self.as_key().eq(other.as_key())
     }
 }
 
 impl Hash for VirtualPath {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.identity.hash(state);
+        self.as_key().hash(state);
     }
 }
 
@@ -101,6 +104,11 @@ impl VirtualPath {
         &self.kind
     }
 
+    //What equality, ordering and hashing rely on
+    pub fn as_key(&self) -> &Path {
+        self.key.as_deref().unwrap_or(self.identity.as_path())
+    }
+
     //Conversions / Copy
     pub fn to_identity(&self) -> PathBuf {
         self.identity.to_path_buf()
@@ -133,7 +141,8 @@ impl VirtualPath {
         VirtualPath {
             identity,
             source,
-            kind
+            kind,
+            key: None
         }
     }
 
@@ -171,33 +180,37 @@ impl VirtualPath {
     }
 
     pub fn with_new_source_parent(self, new_parent: &Path) -> VirtualPath  {
-        Self::_from(
-            self.identity,
-            match &self.source {
+        VirtualPath {
+            source: match &self.source {
                 Some(source) => Some(Self::replace_parent(source.as_path(), new_parent)),
                 None => None
             },
-            self.kind
-        )
+            ..self
+        }
     }
 
     pub fn with_source(self, new_source: Option<&Path>) -> VirtualPath  {
-        Self::_from(
-            self.identity,
-            match new_source {
+        VirtualPath {
+            source: match new_source {
                 Some(source) => Some(source.to_path_buf()),
                 None => None
             },
-            self.kind
-        )
+            ..self
+        }
     }
 
     pub fn with_kind(self, kind: Kind) -> VirtualPath  {
-        Self::_from(
-            self.identity,
-            self.source,
-            kind
-        )
+        VirtualPath {
+            kind,
+            ..self
+        }
+    }
+
+    pub fn with_comparison(self, comparison: &PathComparison) -> VirtualPath {
+        VirtualPath {
+            key: if comparison.is_exact() { None } else { Some(comparison.key(self.identity.as_path())) },
+            ..self
+        }
     }
 }
 
@@ -258,4 +271,17 @@ mod tests {
             calculate_hash(&vpath2)
         );
     }
+
+    #[test]
+    fn equal_through_comparison() {
+        let comparison = PathComparison::exact().with_case_insensitive(true);
+        let vpath1 = VirtualPath::from_str("/intentionally/virtual/Full/PATH").unwrap();
+        let vpath2 = VirtualPath::from_str("/intentionally/virtual/full/path").unwrap();
+        assert_ne!(vpath1, vpath2);
+
+        let vpath1 = vpath1.with_comparison(&comparison).with_kind(Kind::File);
+        let vpath2 = vpath2.with_comparison(&comparison);
+        assert_eq!(vpath1, vpath2);
+        assert_eq!(vpath1.as_identity(), Path::new("/intentionally/virtual/Full/PATH"));
+    }
 }
@@ -32,7 +32,10 @@ mod representation_integration {
     use crate::{
         Kind,
         VirtualPath,
-        VirtualDelta
+        VirtualDelta,
+        PathComparison,
+        Normalization,
+        errors::RepresentationError
     };
 
 
@@ -178,4 +181,27 @@ mod representation_integration {
 
         assert!(delta.get(Path::new("/C")).unwrap().is_none());
     }
+
+    #[test]
+    fn case_insensitive_delta_folds_identities() {
+        let comparison = PathComparison::exact().with_case_insensitive(true).with_normalization(Normalization::Nfc);
+        let mut delta = VirtualDelta::new(comparison);
+
+        delta.attach(Path::new("/Virtual"), None, Kind::Directory).unwrap();
+        delta.attach(Path::new("/virtual/Foo"), None, Kind::File).unwrap();
+        assert!(matches!(
+            delta.attach(Path::new("/VIRTUAL/foo"), None, Kind::File),
+            Err(RepresentationError::AlreadyExists(_))
+        ));
+        assert_eq!(delta.get(Path::new("/virtual/FOO")).unwrap().unwrap().as_identity(), Path::new("/virtual/Foo"));
+        assert!(delta.is_directory(Path::new("/VIRTUAL")).unwrap());
+
+        let exact = delta.rekey(PathComparison::exact()).unwrap();
+        assert!(exact.get(Path::new("/virtual/FOO")).unwrap().is_none());
+        assert!(exact.get(Path::new("/virtual/Foo")).unwrap().is_some());
+
+        delta.detach(Path::new("/VIRTUAL/FOO")).unwrap();
+        assert!(delta.get(Path::new("/virtual/Foo")).unwrap().is_none());
+        assert!(delta.is_directory_empty(Path::new("/virtual")));
+    }
 }
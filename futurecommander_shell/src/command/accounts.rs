@@ -0,0 +1,75 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    fs::read_to_string,
+    path::Path
+};
+
+const PASSWD: &str = "/etc/passwd";
+const GROUP: &str = "/etc/group";
+
+//Lines of /etc/passwd and /etc/group are name:password:id:..., missing databases resolve nothing
+fn entries(database: &Path) -> Vec<(String, u32)> {
+    read_to_string(database)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split(':');
+            let name = fields.next()?;
+            let id = fields.nth(1)?.parse().ok()?;
+            Some((name.to_string(), id))
+        })
+        .collect()
+}
+
+fn id(database: &str, name: &str) -> Option<u32> {
+    match name.parse() {
+        Ok(id) => Some(id),
+        Err(_) => entries(Path::new(database)).into_iter().find(|(entry, _)| entry == name).map(|(_, id)| id)
+    }
+}
+
+fn name(database: &str, id: u32) -> String {
+    entries(Path::new(database)).into_iter()
+        .find(|(_, entry)| *entry == id)
+        .map(|(name, _)| name)
+        .unwrap_or_else(|| id.to_string())
+}
+
+pub fn user_id(name: &str) -> Option<u32> { id(PASSWD, name) }
+pub fn group_id(name: &str) -> Option<u32> { id(GROUP, name) }
+pub fn user_name(id: u32) -> String { name(PASSWD, id) }
+pub fn group_name(id: u32) -> String { name(GROUP, id) }
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_root_and_numbers() {
+        assert_eq!(user_id("1234"), Some(1234));
+        assert_eq!(group_id("0"), Some(0));
+        assert_eq!(user_id("root"), Some(0));
+        assert_eq!(user_name(0), "root");
+        assert_eq!(user_name(4_000_000), "4000000");
+        assert_eq!(user_id("no such user"), None);
+    }
+}
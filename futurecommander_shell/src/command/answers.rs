@@ -0,0 +1,134 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use std::{
+    fs::File,
+    io::prelude::*,
+    path::{ Path, PathBuf }
+};
+
+use clap::ArgMatches;
+
+use futurecommander_filesystem::Container;
+
+use crate::command::{
+    Command,
+    errors::CommandError
+};
+
+pub struct AnswersCommand {}
+
+impl Command<AnswersCommand> {
+    pub fn initialize(cwd: &Path, args: &ArgMatches<'_>) -> Result<Command<InitializedAnswersCommand>, CommandError> {
+        Ok(
+            Command(
+                InitializedAnswersCommand {
+                    path: Self::extract_path_from_args(cwd, args, "path").ok(),
+                    overwrite: args.is_present("overwrite")
+                }
+            )
+        )
+    }
+}
+
+pub struct InitializedAnswersCommand {
+    pub path: Option<PathBuf>,
+    pub overwrite: bool
+}
+
+impl Command<InitializedAnswersCommand> {
+    pub fn execute<W: Write>(self, out: &mut W, container: &mut Container) -> Result<(), CommandError> {
+        let content = container.decisions().to_toml()?;
+
+        match self.0.path {
+            Some(path) => {
+                if ! self.0.overwrite && path.exists() {
+                    return Err(CommandError::AlreadyExists(path));
+                }
+                let mut file = File::create(path.as_path())?;
+                file.write_all(content.as_bytes())?;
+            },
+            None => write!(out, "{}", content)?
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    use futurecommander_filesystem::{
+        sample::Samples,
+        capability::{ AnswerFile, Capability, Authorization },
+        ReadableFileSystem,
+        Entry,
+        OverwritePolicy,
+        Backup,
+        Filter
+    };
+
+    use crate::command::{
+        AvailableGuard,
+        InitializedCopyCommand
+    };
+
+    #[test]
+    fn answers_replay_a_blind_session(){
+        let chroot = Samples::init_simple_chroot("answers_replay_a_blind_session");
+        let answers_path = chroot.join("answers.toml");
+        let mut container = Container::new();
+
+        Command(InitializedCopyCommand {
+            source: chroot.join("RDIR/RFILEA"),
+            destination: chroot.join("RDIR2"),
+            merge: false,
+            overwrite: OverwritePolicy::Never,
+            keep_both: false,
+            rename_template: None,
+            backup: Backup::None,
+            filter: Filter::default(),
+            follow_symlinks: false,
+            guard: AvailableGuard::Blind
+        }).execute(&mut container).unwrap();
+
+        Command(InitializedAnswersCommand {
+            path: Some(answers_path.clone()),
+            overwrite: false
+        }).execute(&mut Vec::new(), &mut container).unwrap();
+        let answers = AnswerFile::from_file(answers_path.as_path()).unwrap();
+        assert!(answers.decisions().iter().any(|decision| decision.path() == chroot.join("RDIR2/RFILEA") && decision.capability() == Capability::Overwrite));
+        assert_eq!(answers.answer(Capability::Overwrite, chroot.join("RDIR2/RFILEA").as_path()), Some(Authorization::Allow));
+
+        container.reset();
+        Command(InitializedCopyCommand {
+            source: chroot.join("RDIR/RFILEA"),
+            destination: chroot.join("RDIR2"),
+            merge: false,
+            overwrite: OverwritePolicy::Never,
+            keep_both: false,
+            rename_template: None,
+            backup: Backup::None,
+            filter: Filter::default(),
+            follow_symlinks: false,
+            guard: AvailableGuard::Answers(answers, Box::new(AvailableGuard::Zealed))
+        }).execute(&mut container).unwrap();
+        assert!(container.status(chroot.join("RDIR2/RFILEA").as_path()).unwrap().is_file());
+    }
+}
@@ -0,0 +1,158 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    io::Write,
+    path::{ Path, PathBuf }
+};
+
+use clap::ArgMatches;
+
+use futurecommander_filesystem::{
+    Container,
+    RenameRule,
+    FileSystemEvent,
+    lossless
+};
+
+use crate::command::{
+    Command,
+    errors::CommandError,
+    AvailableGuard
+};
+
+pub struct BatchRenameCommand {}
+
+impl Command<BatchRenameCommand> {
+    pub fn initialize(cwd: &Path, args: &ArgMatches<'_>) -> Result<Command<InitializedBatchRenameCommand>, CommandError> {
+        match args.value_of("rule") {
+            Some(rule) => Ok(
+                Command(InitializedBatchRenameCommand {
+                    directory: Self::extract_path_from_args(cwd, args, "directory")?,
+                    rule: rule.parse()?,
+                    dry_run: args.is_present("dry_run")
+                })
+            ),
+            None => Err(CommandError::ArgumentMissing("batch_rename".to_string(), "rule".to_string(), args.usage().to_string()))
+        }
+    }
+}
+
+pub struct InitializedBatchRenameCommand {
+    pub directory: PathBuf,
+    pub rule: RenameRule,
+    pub dry_run: bool
+}
+
+impl Command<InitializedBatchRenameCommand> {
+    pub fn execute<W: Write>(self, out: &mut W, container: &mut Container) -> Result<(), CommandError> {
+        //Collisions are detected over the whole batch, before anything is staged
+        let renames = self.0.rule.batch(container, self.0.directory.as_path())?;
+        if renames.is_empty() {
+            writeln!(out, "Nothing to rename")?;
+        }
+
+        for rename in renames.iter() {
+            writeln!(
+                out,
+                "{} -> {}",
+                lossless::escape(rename.path().file_name().unwrap_or_default()),
+                lossless::escape(rename.name())
+            )?;
+        }
+
+        //Nothing is staged when one of the renames fails
+        if ! self.0.dry_run {
+            container.emit_all(
+                renames.into_iter()
+                    .map(|rename| (FileSystemEvent::Rename(rename), AvailableGuard::Zealed.registrar()))
+                    .collect()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::write;
+
+    use futurecommander_filesystem::{
+        sample::Samples,
+        ReadableFileSystem,
+        Entry,
+        NameValidator,
+        Portability
+    };
+
+    #[test]
+    fn batch_rename_preview_and_stage(){
+        let chroot = Samples::init_empty_chroot("batch_rename_preview_and_stage");
+        write(chroot.join("IMG_12.jpg"), "").unwrap();
+        write(chroot.join("IMG_7.jpg"), "").unwrap();
+        write(chroot.join("notes.txt"), "").unwrap();
+        let mut container = Container::new();
+
+        let mut stdout = Vec::new();
+        Command(InitializedBatchRenameCommand {
+            directory: chroot.clone(),
+            rule: r"s/IMG_(\d+)/photo-$1/".parse().unwrap(),
+            dry_run: true
+        }).execute(&mut stdout, &mut container).unwrap();
+
+        assert_eq!(String::from_utf8(stdout).unwrap(), "IMG_12.jpg -> photo-12.jpg\nIMG_7.jpg -> photo-7.jpg\n");
+        assert!(container.is_empty());
+
+        let mut stdout = Vec::new();
+        Command(InitializedBatchRenameCommand {
+            directory: chroot.clone(),
+            rule: "{n:03}-{name}{ext}".parse().unwrap(),
+            dry_run: false
+        }).execute(&mut stdout, &mut container).unwrap();
+
+        assert!(container.status(chroot.join("001-IMG_12.jpg").as_path()).unwrap().is_file());
+        assert!(container.status(chroot.join("003-notes.txt").as_path()).unwrap().is_file());
+        assert!(!container.status(chroot.join("IMG_7.jpg").as_path()).unwrap().exists());
+    }
+
+    #[test]
+    fn batch_rename_stages_all_or_nothing(){
+        let chroot = Samples::init_empty_chroot("batch_rename_stages_all_or_nothing");
+        write(chroot.join("xa"), "").unwrap();
+        write(chroot.join("xnul"), "").unwrap();
+        let mut container = Container::new();
+        container.validate_names(Some(NameValidator::new(Portability::Windows).with_strict(true)));
+
+        let mut stdout = Vec::new();
+        assert!(
+            Command(InitializedBatchRenameCommand {
+                directory: chroot.clone(),
+                rule: "s/^x//".parse().unwrap(),
+                dry_run: false
+            }).execute(&mut stdout, &mut container).is_err()
+        );
+
+        assert!(container.is_empty());
+        assert_eq!(container.events().count(), 0);
+        assert!(container.status(chroot.join("xa").as_path()).unwrap().exists());
+    }
+}
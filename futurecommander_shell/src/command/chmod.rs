@@ -0,0 +1,113 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::path::{ Path, PathBuf };
+
+use clap::ArgMatches;
+
+use futurecommander_filesystem::{
+    Container,
+    MetadataEvent,
+    Mode,
+    Listener,
+    Delayer,
+    FileSystemEvent
+};
+
+use crate::command::{
+    Command,
+    errors::CommandError,
+    AvailableGuard
+};
+
+pub struct ChmodCommand {}
+
+impl Command<ChmodCommand> {
+    pub fn initialize(cwd: &Path, args: &ArgMatches<'_>) -> Result<Command<InitializedChmodCommand>, CommandError> {
+        let mode = match args.value_of("mode") {
+            Some(mode) => mode.parse()?,
+            None => return Err(CommandError::ArgumentMissing("chmod".to_string(), "mode".to_string(), args.usage().to_string()))
+        };
+
+        Ok(
+            Command(
+                InitializedChmodCommand {
+                    path: Self::extract_path_from_args(cwd, args, "path")?,
+                    mode,
+                    recursive: args.is_present("recursive"),
+                    guard: Self::extract_available_guard(cwd, args, "guard")?
+                }
+            )
+        )
+    }
+}
+
+pub struct InitializedChmodCommand {
+    pub path: PathBuf,
+    pub mode: Mode,
+    pub recursive: bool,
+    pub guard: AvailableGuard
+}
+
+impl Command<InitializedChmodCommand> {
+    pub fn execute(self, container: &mut Container) -> Result<(), CommandError> {
+        let event = FileSystemEvent::Metadata(
+            MetadataEvent::new(self.0.path.as_path(), self.0.recursive)
+                .with_mode(Some(self.0.mode))
+        );
+
+        let guard = container.emit(&event, self.0.guard.registrar())?;
+        container.delay(event, guard);
+        Ok(())
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    use futurecommander_filesystem::{
+        sample::Samples,
+        ReadableFileSystem,
+        Metadata
+    };
+
+    #[test]
+    fn chmod_recursively_then_apply(){
+        let chroot = Samples::init_simple_chroot("chmod_recursively_then_apply");
+        let mut container = Container::new();
+
+        Command(InitializedChmodCommand {
+            path: chroot.join("RDIR"),
+            mode: Mode::new("go-rwx").unwrap(),
+            recursive: true,
+            guard: AvailableGuard::Zealed
+        }).execute(&mut container).unwrap();
+
+        let staged = container.metadata(chroot.join("RDIR/RFILEA").as_path()).unwrap().unwrap();
+        assert_eq!(staged.mode().unwrap() & 0o077, 0);
+        assert_ne!(Metadata::read(chroot.join("RDIR/RFILEA").as_path()).unwrap().mode().unwrap() & 0o077, 0);
+
+        container.apply().unwrap();
+
+        assert_eq!(Metadata::read(chroot.join("RDIR/RFILEA").as_path()).unwrap().mode().unwrap() & 0o077, 0);
+        assert_eq!(Metadata::read(chroot.join("RDIR").as_path()).unwrap().mode().unwrap() & 0o077, 0);
+    }
+}
@@ -0,0 +1,138 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::path::{ Path, PathBuf };
+
+use clap::ArgMatches;
+
+use futurecommander_filesystem::{
+    Container,
+    MetadataEvent,
+    Listener,
+    Delayer,
+    FileSystemEvent
+};
+
+use crate::command::{
+    Command,
+    errors::CommandError,
+    AvailableGuard,
+    accounts
+};
+
+pub struct ChownCommand {}
+
+impl Command<ChownCommand> {
+    pub fn initialize(cwd: &Path, args: &ArgMatches<'_>) -> Result<Command<InitializedChownCommand>, CommandError> {
+        let (owner, group) = match args.value_of("owner") {
+            Some(ownership) => Self::ownership(ownership)?,
+            None => return Err(CommandError::ArgumentMissing("chown".to_string(), "owner".to_string(), args.usage().to_string()))
+        };
+
+        Ok(
+            Command(
+                InitializedChownCommand {
+                    path: Self::extract_path_from_args(cwd, args, "path")?,
+                    owner,
+                    group,
+                    recursive: args.is_present("recursive"),
+                    guard: Self::extract_available_guard(cwd, args, "guard")?
+                }
+            )
+        )
+    }
+
+    //OWNER, OWNER:GROUP or :GROUP, as names or numeric ids
+    pub fn ownership(text: &str) -> Result<(Option<u32>, Option<u32>), CommandError> {
+        let (owner, group) = match text.split_once(':') {
+            Some((owner, group)) => (owner, group),
+            None => (text, "")
+        };
+        if owner.is_empty() && group.is_empty() {
+            return Err(CommandError::UnknownAccount(text.to_string()));
+        }
+
+        let resolve = |name: &str, lookup: fn(&str) -> Option<u32>| -> Result<Option<u32>, CommandError> {
+            if name.is_empty() {
+                Ok(None)
+            } else {
+                lookup(name).map(Some).ok_or_else(|| CommandError::UnknownAccount(name.to_string()))
+            }
+        };
+        Ok((resolve(owner, accounts::user_id)?, resolve(group, accounts::group_id)?))
+    }
+}
+
+pub struct InitializedChownCommand {
+    pub path: PathBuf,
+    pub owner: Option<u32>,
+    pub group: Option<u32>,
+    pub recursive: bool,
+    pub guard: AvailableGuard
+}
+
+impl Command<InitializedChownCommand> {
+    pub fn execute(self, container: &mut Container) -> Result<(), CommandError> {
+        let event = FileSystemEvent::Metadata(
+            MetadataEvent::new(self.0.path.as_path(), self.0.recursive)
+                .with_ownership(self.0.owner, self.0.group)
+        );
+
+        let guard = container.emit(&event, self.0.guard.registrar())?;
+        container.delay(event, guard);
+        Ok(())
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futurecommander_filesystem::{
+        sample::Samples,
+        ReadableFileSystem
+    };
+
+    #[test]
+    fn parse_ownership(){
+        assert_eq!(Command::<ChownCommand>::ownership("1000").unwrap(), (Some(1000), None));
+        assert_eq!(Command::<ChownCommand>::ownership("1000:100").unwrap(), (Some(1000), Some(100)));
+        assert_eq!(Command::<ChownCommand>::ownership(":100").unwrap(), (None, Some(100)));
+        assert!(Command::<ChownCommand>::ownership(":").is_err());
+        assert!(Command::<ChownCommand>::ownership("no such user").is_err());
+    }
+
+    #[test]
+    fn chown_is_staged(){
+        let chroot = Samples::init_simple_chroot("chown_is_staged");
+        let mut container = Container::new();
+
+        Command(InitializedChownCommand {
+            path: chroot.join("RDIR"),
+            owner: Some(4242),
+            group: None,
+            recursive: true,
+            guard: AvailableGuard::Zealed
+        }).execute(&mut container).unwrap();
+
+        assert_eq!(container.metadata(chroot.join("RDIR/RFILEA").as_path()).unwrap().unwrap().owner(), Some(4242));
+        assert_ne!(container.metadata(chroot.join("RDIR2/RFILEA").as_path()).unwrap().unwrap().owner(), Some(4242));
+    }
+}
@@ -28,7 +28,10 @@ use futurecommander_filesystem::{
     Delayer,
     ReadableFileSystem,
     Entry,
-    FileSystemEvent
+    FileSystemEvent,
+    Filter,
+    OverwritePolicy,
+    Backup
 };
 
 use crate::{
@@ -51,8 +54,13 @@ impl Command<CopyCommand> {
                 source,
                 destination,
                 merge: args.is_present("merge"),
-                overwrite: args.is_present("overwrite"),
-                guard: Self::extract_available_guard(args, "guard")?
+                overwrite: Self::extract_overwrite_policy(args)?,
+                keep_both: args.is_present("keep_both"),
+                rename_template: args.value_of("rename_template").map(str::to_string),
+                backup: Self::extract_backup(cwd, args)?,
+                filter: Self::extract_filter(args)?,
+                follow_symlinks: args.is_present("follow_symlinks"),
+                guard: Self::extract_available_guard(cwd, args, "guard")?
             })
         )
     }
@@ -62,7 +70,12 @@ pub struct InitializedCopyCommand {
     pub source: PathBuf,
     pub destination: PathBuf,
     pub merge: bool,
-    pub overwrite: bool,
+    pub overwrite: OverwritePolicy,
+    pub keep_both: bool,
+    pub rename_template: Option<String>,
+    pub backup: Backup,
+    pub filter: Filter,
+    pub follow_symlinks: bool,
     pub guard: AvailableGuard
 }
 
@@ -85,7 +98,12 @@ impl Command<InitializedCopyCommand> {
                             .as_path(),
                         self.0.merge,
                         self.0.overwrite
-                    )                    
+                    )
+                    .with_keep_both(self.0.keep_both)
+                    .with_rename_template(self.0.rename_template.as_deref())
+                    .with_backup(self.0.backup.clone())
+                    .with_filter(self.0.filter.clone())
+                    .with_follow_symlinks(self.0.follow_symlinks)
                 } else if source.is_dir() {
                     return Err(CommandError::DirectoryIntoAFile(source.to_path(), destination.to_path()))
                 } else {
@@ -93,6 +111,11 @@ impl Command<InitializedCopyCommand> {
                 }
             } else {
                 CopyEvent::new(self.0.source.as_path(), self.0.destination.as_path(), self.0.merge, self.0.overwrite)
+                    .with_keep_both(self.0.keep_both)
+                    .with_rename_template(self.0.rename_template.as_deref())
+                    .with_backup(self.0.backup.clone())
+                    .with_filter(self.0.filter.clone())
+                    .with_follow_symlinks(self.0.follow_symlinks)
             }
         );
 
@@ -109,7 +132,8 @@ mod tests {
 
     use futurecommander_filesystem::{
         sample::Samples,
-        EntryAdapter
+        EntryAdapter,
+        Pattern
     };
 
     #[test]
@@ -121,7 +145,12 @@ mod tests {
             source: sample_path.join("B"),
             destination: sample_path.join("A"),
             merge: false,
-            overwrite: false,
+            overwrite: OverwritePolicy::Never,
+            keep_both: false,
+            rename_template: None,
+            backup: Backup::None,
+            filter: Filter::default(),
+            follow_symlinks: false,
             guard: AvailableGuard::Zealed
         });
 
@@ -131,7 +160,12 @@ mod tests {
             source: sample_path.join("A/B/D"),
             destination: sample_path.join("A"),
             merge: false,
-            overwrite: false,
+            overwrite: OverwritePolicy::Never,
+            keep_both: false,
+            rename_template: None,
+            backup: Backup::None,
+            filter: Filter::default(),
+            follow_symlinks: false,
             guard: AvailableGuard::Zealed
         });
 
@@ -154,7 +188,12 @@ mod tests {
             source: sample_path.join("B"),
             destination: sample_path.join("A"),
             merge: false,
-            overwrite: false,
+            overwrite: OverwritePolicy::Never,
+            keep_both: false,
+            rename_template: None,
+            backup: Backup::None,
+            filter: Filter::default(),
+            follow_symlinks: false,
             guard: AvailableGuard::Zealed
         });
 
@@ -164,7 +203,12 @@ mod tests {
             source: sample_path.join("F"),
             destination: sample_path.join("B"),
             merge: false,
-            overwrite: false,
+            overwrite: OverwritePolicy::Never,
+            keep_both: false,
+            rename_template: None,
+            backup: Backup::None,
+            filter: Filter::default(),
+            follow_symlinks: false,
             guard: AvailableGuard::Zealed
         });
 
@@ -174,7 +218,12 @@ mod tests {
             source: sample_path.join("B/F"),
             destination: sample_path.join("B/D/E"),
             merge: false,
-            overwrite: false,
+            overwrite: OverwritePolicy::Never,
+            keep_both: false,
+            rename_template: None,
+            backup: Backup::None,
+            filter: Filter::default(),
+            follow_symlinks: false,
             guard: AvailableGuard::Zealed
         });
 
@@ -194,7 +243,12 @@ mod tests {
             source: sample_path.join("B"),
             destination: sample_path.join("A"),
             merge: false,
-            overwrite: false,
+            overwrite: OverwritePolicy::Never,
+            keep_both: false,
+            rename_template: None,
+            backup: Backup::None,
+            filter: Filter::default(),
+            follow_symlinks: false,
             guard: AvailableGuard::Zealed
         });
         copy_b_to_a.execute(&mut container).unwrap();
@@ -203,7 +257,12 @@ mod tests {
             source: sample_path.join("A"),
             destination: sample_path.join("APRIME"),
             merge: false,
-            overwrite: false,
+            overwrite: OverwritePolicy::Never,
+            keep_both: false,
+            rename_template: None,
+            backup: Backup::None,
+            filter: Filter::default(),
+            follow_symlinks: false,
             guard: AvailableGuard::Zealed
         });
         copy_a_as_aprime.execute(&mut container).unwrap();
@@ -230,7 +289,12 @@ mod tests {
             source: sample_path.join("B"),
             destination: sample_path.join("A"),
             merge: false,
-            overwrite: false,
+            overwrite: OverwritePolicy::Never,
+            keep_both: false,
+            rename_template: None,
+            backup: Backup::None,
+            filter: Filter::default(),
+            follow_symlinks: false,
             guard: AvailableGuard::Zealed
         });
         copy_b_to_a.execute(&mut container).unwrap();
@@ -239,7 +303,12 @@ mod tests {
             source: sample_path.join("A"),
             destination: sample_path.join("APRIME"),
             merge: false,
-            overwrite: false,
+            overwrite: OverwritePolicy::Never,
+            keep_both: false,
+            rename_template: None,
+            backup: Backup::None,
+            filter: Filter::default(),
+            follow_symlinks: false,
             guard: AvailableGuard::Zealed
         });
         copy_a_as_aprime.execute(&mut container).unwrap();
@@ -248,7 +317,12 @@ mod tests {
             source: sample_path.join("APRIME"),
             destination: sample_path.join("ABETA"),
             merge: false,
-            overwrite: false,
+            overwrite: OverwritePolicy::Never,
+            keep_both: false,
+            rename_template: None,
+            backup: Backup::None,
+            filter: Filter::default(),
+            follow_symlinks: false,
             guard: AvailableGuard::Zealed
         });
         copy_aprime_as_abeta.execute(&mut container).unwrap();
@@ -257,7 +331,12 @@ mod tests {
             source: sample_path.join("ABETA"),
             destination: sample_path.join("A"),
             merge: false,
-            overwrite: false,
+            overwrite: OverwritePolicy::Never,
+            keep_both: false,
+            rename_template: None,
+            backup: Backup::None,
+            filter: Filter::default(),
+            follow_symlinks: false,
             guard: AvailableGuard::Zealed
         });
         copy_abeta_to_a.execute(&mut container).unwrap();
@@ -274,4 +353,78 @@ mod tests {
         assert!(collection_aprime_a_abeta_b_d.contains(&EntryAdapter(sample_path.join("A/ABETA/B/D/E").as_path())));
         assert!(collection_aprime_a_abeta_b_d.contains(&EntryAdapter(sample_path.join("A/ABETA/B/D/G").as_path())));
     }
+
+    #[test]
+    fn cp_with_exclude_filter(){
+        let sample_path = Samples::static_samples_path();
+        let mut container = Container::new();
+
+        Command(InitializedCopyCommand {
+            source: sample_path.join("B"),
+            destination: sample_path.join("Z"),
+            merge: false,
+            overwrite: OverwritePolicy::Never,
+            keep_both: false,
+            rename_template: None,
+            backup: Backup::None,
+            filter: Filter::new(vec![], vec![Pattern::new("E/").unwrap()], false),
+            follow_symlinks: false,
+            guard: AvailableGuard::Zealed
+        }).execute(&mut container).unwrap();
+
+        let collection = container.read_dir(sample_path.join("Z/D").as_path()).unwrap();
+        assert!(collection.contains(&EntryAdapter(sample_path.join("Z/D/G").as_path())));
+        assert!(!collection.contains(&EntryAdapter(sample_path.join("Z/D/E").as_path())));
+        assert!(container.status(sample_path.join("B/D/E").as_path()).unwrap().exists());
+    }
+
+    #[test]
+    fn cp_keep_both_renames_incoming_file(){
+        let sample_path = Samples::static_samples_path();
+        let mut container = Container::new();
+
+        for _ in 0..2 {
+            Command(InitializedCopyCommand {
+                source: sample_path.join("F"),
+                destination: sample_path.join("A"),
+                merge: false,
+                overwrite: OverwritePolicy::Never,
+                keep_both: true,
+                rename_template: None,
+                backup: Backup::None,
+                filter: Filter::default(),
+                follow_symlinks: false,
+                guard: AvailableGuard::Zealed
+            }).execute(&mut container).unwrap();
+        }
+
+        let collection = container.read_dir(sample_path.join("A").as_path()).unwrap();
+        assert!(collection.contains(&EntryAdapter(sample_path.join("A/F").as_path())));
+        assert!(collection.contains(&EntryAdapter(sample_path.join("A/F (1)").as_path())));
+    }
+
+    #[test]
+    fn cp_overwrite_keeps_numbered_backups(){
+        let sample_path = Samples::static_samples_path();
+        let mut container = Container::new();
+
+        for _ in 0..2 {
+            Command(InitializedCopyCommand {
+                source: sample_path.join("F"),
+                destination: sample_path.join("A"),
+                merge: false,
+                overwrite: OverwritePolicy::Always,
+                keep_both: false,
+                rename_template: None,
+                backup: Backup::Numbered,
+                filter: Filter::default(),
+                follow_symlinks: false,
+                guard: AvailableGuard::Zealed
+            }).execute(&mut container).unwrap();
+        }
+
+        let collection = container.read_dir(sample_path.join("A").as_path()).unwrap();
+        assert!(collection.contains(&EntryAdapter(sample_path.join("A/F").as_path())));
+        assert!(collection.contains(&EntryAdapter(sample_path.join("A/F.~1~").as_path())));
+    }
 }
@@ -45,7 +45,10 @@ pub enum CommandError {
     CwdIsInside(PathBuf),
     CustomError(String),
     DirectoryIntoAFile(PathBuf, PathBuf),
-    InvalidGuard(String)
+    InvalidGuard(String),
+    InvalidFormat(String),
+    UnknownAccount(String),
+    InvalidDate(String)
 }
 
 impl From<DomainError> for CommandError {
@@ -89,6 +92,9 @@ impl fmt::Display for CommandError {
             CommandError::DirectoryIntoAFile(src, dst) => write!(f, "Directory {} into a file {}", src.to_string_lossy(), dst.to_string_lossy()),
             CommandError::CustomError(custom_message) => write!(f, "Custom error message {}", custom_message),
             CommandError::InvalidGuard(guard) => write!(f, "Invalid guard {}", guard),
+            CommandError::InvalidFormat(format) => write!(f, "Invalid format {}", format),
+            CommandError::UnknownAccount(name) => write!(f, "Unknown user or group {}", name),
+            CommandError::InvalidDate(date) => write!(f, "Invalid date {}, expected @SECONDS, RFC 3339 or YYYY-MM-DD[ HH:MM[:SS]]", date),
         }
     }
 }
@@ -0,0 +1,203 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    fs::File,
+    io::prelude::*,
+    path::{ Path, PathBuf },
+    str::FromStr
+};
+
+use clap::ArgMatches;
+
+use futurecommander_filesystem::{ Container };
+
+use crate::command::{
+    Command,
+    errors::CommandError
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Shell,
+    Toml,
+    Yaml
+}
+
+impl FromStr for ExportFormat {
+    type Err = CommandError;
+
+    fn from_str(format: &str) -> Result<ExportFormat, CommandError> {
+        match format {
+            "sh" => Ok(ExportFormat::Shell),
+            "toml" => Ok(ExportFormat::Toml),
+            "yaml" | "yml" => Ok(ExportFormat::Yaml),
+            _ => Err(CommandError::InvalidFormat(format.to_string()))
+        }
+    }
+}
+
+pub struct ExportCommand {}
+
+impl Command<ExportCommand> {
+    pub fn initialize(cwd: &Path, args: &ArgMatches<'_>) -> Result<Command<InitializedExportCommand>, CommandError> {
+        Ok(
+            Command(
+                InitializedExportCommand {
+                    path: Self::extract_path_from_args(cwd, args, "path").ok(),
+                    format: args.value_of("format").unwrap_or("sh").parse()?,
+                    base: Self::extract_path_from_args(cwd, args, "base").ok(),
+                    overwrite: args.is_present("overwrite")
+                }
+            )
+        )
+    }
+}
+
+pub struct InitializedExportCommand {
+    pub path: Option<PathBuf>,
+    pub format: ExportFormat,
+    pub base: Option<PathBuf>,
+    pub overwrite: bool
+}
+
+impl Command<InitializedExportCommand> {
+    pub fn execute<W: Write>(self, out: &mut W, container: &mut Container) -> Result<(), CommandError> {
+        let content = match self.0.format {
+            ExportFormat::Shell => container.to_shell_script()?,
+            ExportFormat::Toml => container.to_plan(self.0.base.as_deref())?.to_toml()?,
+            ExportFormat::Yaml => container.to_plan(self.0.base.as_deref())?.to_yaml()?
+        };
+
+        match self.0.path {
+            Some(path) => {
+                if ! self.0.overwrite && path.exists() {
+                    return Err(CommandError::AlreadyExists(path));
+                }
+                let mut file = File::create(path.as_path())?;
+                file.write_all(content.as_bytes())?;
+            },
+            None => write!(out, "{}", content)?
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    use std::{
+        fs::read_to_string
+    };
+
+    use crate::{
+        command::{
+            InitializedCopyCommand,
+            AvailableGuard
+        },
+    };
+
+    use futurecommander_filesystem::{
+        sample::Samples,
+        Filter,
+        OverwritePolicy,
+        Backup
+    };
+
+    #[test]
+    fn can_export_queue_into_a_shell_script(){
+        let mut container = Container::new();
+        let sample_path = Samples::init_advanced_chroot("can_export_queue_into_a_shell_script");
+        let copy_command = Command(InitializedCopyCommand {
+            source: sample_path.join("A"),
+            destination: sample_path.join("APRIME"),
+            merge: false,
+            overwrite: OverwritePolicy::Never,
+            keep_both: false,
+            rename_template: None,
+            backup: Backup::None,
+            filter: Filter::default(),
+            follow_symlinks: false,
+            guard: AvailableGuard::Zealed
+        });
+
+        copy_command.execute(&mut container).unwrap();
+
+        let export_command = Command(InitializedExportCommand {
+            path: Some(sample_path.join("plan.sh")),
+            format: ExportFormat::Shell,
+            base: None,
+            overwrite: false
+        });
+
+        let mut stdout = Vec::new();
+        export_command.execute(&mut stdout, &mut container).unwrap();
+
+        let script = read_to_string(sample_path.join("plan.sh")).unwrap();
+        assert!(stdout.is_empty());
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.contains(
+            format!("[ -d '{0}' ] || mkdir -- '{0}'\n", sample_path.join("APRIME").to_string_lossy()).as_str()
+        ));
+        assert!(script.contains(
+            format!("[ -e '{1}' ] || cp -- '{0}' '{1}'\n", sample_path.join("A/C").to_string_lossy(), sample_path.join("APRIME/C").to_string_lossy()).as_str()
+        ));
+    }
+
+    #[test]
+    fn can_export_queue_into_a_relative_plan(){
+        let mut container = Container::new();
+        let sample_path = Samples::init_advanced_chroot("can_export_queue_into_a_relative_plan");
+        Command(InitializedCopyCommand {
+            source: sample_path.join("A"),
+            destination: sample_path.join("APRIME"),
+            merge: false,
+            overwrite: OverwritePolicy::Never,
+            keep_both: false,
+            rename_template: None,
+            backup: Backup::None,
+            filter: Filter::default(),
+            follow_symlinks: false,
+            guard: AvailableGuard::Zealed
+        }).execute(&mut container).unwrap();
+
+        let mut stdout = Vec::new();
+        Command(InitializedExportCommand {
+            path: None,
+            format: ExportFormat::Yaml,
+            base: Some(sample_path.clone()),
+            overwrite: false
+        }).execute(&mut stdout, &mut container).unwrap();
+
+        let plan = String::from_utf8(stdout).unwrap();
+        assert!(plan.contains("- copy:\n"));
+        assert!(plan.contains("from: A\n"));
+        assert!(plan.contains("to: APRIME\n"));
+    }
+
+    #[test]
+    fn refuse_unknown_format(){
+        match "bat".parse::<ExportFormat>() {
+            Err(CommandError::InvalidFormat(format)) => assert_eq!(format, "bat"),
+            _ => panic!("bat format should not be supported")
+        }
+    }
+}
@@ -18,7 +18,7 @@
  */
 
 use std::{
-    io::{ stdin },
+    any::Any,
     path::{ Path }
 };
 
@@ -30,49 +30,110 @@ use futurecommander_filesystem::{
         Capabilities,
         Guard,
         Capability,
+        Authorization,
         RegistrarGuard,
         ZealedGuard,
         BlindGuard,
-        QuietGuard
+        QuietGuard,
+        Policy,
+        PolicyGuard,
+        AnswerFile,
+        AnswerFileGuard,
+        Thresholds,
+        ThresholdGuard
     }
 };
 
-#[derive(Serialize, Deserialize, Default, Debug)]
+use crate::command::prompter::{
+    Prompter,
+    Question,
+    Answer,
+    TerminalPrompter,
+    PrompterFactory
+};
+
+fn terminal_prompter() -> Box<dyn Prompter> {
+    Box::new(TerminalPrompter)
+}
+
+//The prompter is not serialized, a loaded guard asks on the terminal until another one is attached
+#[derive(Serialize, Deserialize, Debug)]
 pub struct InteractiveGuard {
     skip_all: Capabilities,
-    allow_all: Capabilities
+    allow_all: Capabilities,
+    #[serde(default, skip_serializing_if = "Capabilities::is_empty")]
+    rename_all: Capabilities,
+    #[serde(skip, default = "terminal_prompter")]
+    prompter: Box<dyn Prompter>
+}
+
+impl Default for InteractiveGuard {
+    fn default() -> InteractiveGuard {
+        InteractiveGuard {
+            skip_all: Capabilities::default(),
+            allow_all: Capabilities::default(),
+            rename_all: Capabilities::default(),
+            prompter: terminal_prompter()
+        }
+    }
+}
+
+impl InteractiveGuard {
+    pub fn new(prompter: Box<dyn Prompter>) -> InteractiveGuard {
+        InteractiveGuard {
+            prompter,
+            ..InteractiveGuard::default()
+        }
+    }
+
+    pub fn with_prompter(mut self, prompter: Box<dyn Prompter>) -> InteractiveGuard {
+        self.prompter = prompter;
+        self
+    }
 }
 
 #[typetag::serde]
 impl Guard for InteractiveGuard {
-    fn authorize(&mut self, capability: Capability, default: bool, target: &Path) -> Result<bool, DomainError> {
+    fn authorize(&mut self, capability: Capability, default: bool, target: &Path) -> Result<Authorization, DomainError> {
         if self.skip_all.authorize(capability) {
-            return Ok(false)
+            return Ok(Authorization::Skip)
         }
 
-        if ! default && ! self.allow_all.authorize(capability) {
-            let mut input = String::new();
-            println!("Allow {} for target {} ?([skip]/skip_all/allow/allow_all/cancel) : ", capability, target.to_string_lossy());
-            stdin().read_line(&mut input)?;
-
-            match input.trim() {
-                "skip" => Ok(false),
-                "allow" => Ok(true),
-                "skip_all" => {
-                    self.skip_all = self.skip_all + capability;
-                    Ok(false)
-                },
-                "allow_all" => {
-                    self.allow_all = self.allow_all + capability;
-                    Ok(true)
-                },
-                "cancel" =>
-                    Err(DomainError::UserCancelled)
-                ,
-                _ => Ok(false)
-            }
-        } else {
-            Ok(true)
+        //An explicit grant on the command line wins over an earlier rename_all
+        if default || self.allow_all.authorize(capability) {
+            return Ok(Authorization::Allow)
+        }
+
+        if self.rename_all.authorize(capability) {
+            return Ok(Authorization::Rename)
+        }
+
+        match self.prompter.ask(&Question::new(capability, target, capability.can_rename()))? {
+            Answer::Skip => Ok(Authorization::Skip),
+            Answer::Allow => Ok(Authorization::Allow),
+            Answer::SkipAll => {
+                self.skip_all = self.skip_all + capability;
+                Ok(Authorization::Skip)
+            },
+            Answer::AllowAll => {
+                self.allow_all = self.allow_all + capability;
+                Ok(Authorization::Allow)
+            },
+            Answer::Rename if capability.can_rename() => Ok(Authorization::Rename),
+            Answer::RenameAll if capability.can_rename() => {
+                self.rename_all = self.rename_all + capability;
+                Ok(Authorization::Rename)
+            },
+            Answer::Cancel =>
+                Err(DomainError::UserCancelled)
+            ,
+            _ => Ok(Authorization::Skip)
+        }
+    }
+
+    fn attach(&mut self, attachment: &dyn Any) {
+        if let Some(factory) = attachment.downcast_ref::<PrompterFactory>() {
+            self.prompter = factory.prompter();
         }
     }
 }
@@ -81,21 +142,65 @@ pub enum AvailableGuard {
     Zealed,
     Blind,
     Quiet,
-    Interactive
+    Interactive,
+    //Rules enforced over the wrapped guard, asking interactively
+    Policy(Policy, Box<AvailableGuard>),
+    //Recorded answers replayed before asking the wrapped guard
+    Answers(AnswerFile, Box<AvailableGuard>),
+    //Events beyond thresholds are refused, or asked when the wrapped guard is interactive
+    Thresholds(Thresholds, Box<AvailableGuard>)
 }
 
 impl AvailableGuard {
-    pub fn registrar(&self) -> RegistrarGuard {
+    pub fn guard(&self) -> Box<dyn Guard> {
+        match self {
+            AvailableGuard::Zealed => Box::new(ZealedGuard),
+            AvailableGuard::Blind => Box::new(BlindGuard),
+            AvailableGuard::Quiet => Box::new(QuietGuard),
+            AvailableGuard::Interactive => Box::new(InteractiveGuard::default()),
+            AvailableGuard::Policy(policy, inner) => Box::new(
+                PolicyGuard::new(policy.clone(), inner.guard())
+                    .with_ask(Box::new(InteractiveGuard::default()))
+            ),
+            AvailableGuard::Answers(answers, inner) => Box::new(AnswerFileGuard::new(answers.clone(), inner.guard())),
+            AvailableGuard::Thresholds(thresholds, inner) => {
+                let guard = ThresholdGuard::new(*thresholds, inner.guard());
+                if inner.is_interactive() {
+                    Box::new(guard.with_confirm(Box::new(InteractiveGuard::default())))
+                } else {
+                    Box::new(guard)
+                }
+            }
+        }
+    }
+
+    pub fn is_interactive(&self) -> bool {
         match self {
-            AvailableGuard::Zealed => RegistrarGuard::from(Box::new(ZealedGuard)),
-            AvailableGuard::Blind => RegistrarGuard::from(Box::new(BlindGuard)),
-            AvailableGuard::Quiet => RegistrarGuard::from(Box::new(QuietGuard)),
-            AvailableGuard::Interactive => RegistrarGuard::from(Box::new(InteractiveGuard::default())),
+            AvailableGuard::Interactive => true,
+            AvailableGuard::Zealed | AvailableGuard::Blind | AvailableGuard::Quiet => false,
+            AvailableGuard::Policy(_, inner)
+            | AvailableGuard::Answers(_, inner)
+            | AvailableGuard::Thresholds(_, inner) => inner.is_interactive()
         }
     }
 
+    pub fn registrar(&self) -> RegistrarGuard {
+        RegistrarGuard::from(self.guard())
+    }
+
+    //policy alone wraps the default guard, policy:GUARD wraps GUARD
     pub fn available(s: &str) -> bool {
-        vec!["interactive", "zealed", "quiet", "blind"].contains(&s)
+        let guards = ["interactive", "zealed", "quiet", "blind"];
+        match s.strip_prefix("policy") {
+            Some("") => true,
+            Some(inner) => inner.strip_prefix(':').is_some_and(|inner| guards.contains(&inner)),
+            None => guards.contains(&s)
+        }
+    }
+
+    //Rules are read from Policy::home
+    pub fn policy(inner: AvailableGuard) -> Result<AvailableGuard, DomainError> {
+        Ok(AvailableGuard::Policy(Policy::from_file(Policy::home()?.as_path())?, Box::new(inner)))
     }
 }
 
@@ -116,3 +221,80 @@ impl Default for AvailableGuard {
         AvailableGuard::Interactive
     }
 }
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{
+        sync::mpsc::{ channel, Sender },
+        thread
+    };
+
+    use futurecommander_filesystem::{
+        sample::Samples,
+        Container,
+        Listener,
+        Delayer,
+        ReadableFileSystem,
+        Entry,
+        RemoveEvent,
+        FileSystemEvent
+    };
+
+    use crate::command::prompter::{ ScriptedPrompter, ChannelPrompter };
+
+    #[test]
+    fn interactive_guard_remembers_answers_for_all(){
+        let mut guard = InteractiveGuard::new(Box::new(ScriptedPrompter::new(vec![Answer::AllowAll, Answer::RenameAll, Answer::Rename])));
+        let target = Path::new("/virtual/directory");
+
+        assert!(guard.authorize(Capability::Recursive, false, target).unwrap().is_allowed());
+        assert!(guard.authorize(Capability::Recursive, false, target).unwrap().is_allowed());
+        assert_eq!(guard.authorize(Capability::Merge, false, target).unwrap(), Authorization::Rename);
+        assert_eq!(guard.authorize(Capability::Merge, false, target).unwrap(), Authorization::Rename);
+        assert!(guard.authorize(Capability::Merge, true, target).unwrap().is_allowed());
+        assert_eq!(guard.authorize(Capability::Delete, false, target).unwrap(), Authorization::Skip);
+        assert!(guard.authorize(Capability::Delete, true, target).unwrap().is_allowed());
+    }
+
+    #[test]
+    fn interactive_guard_asks_through_channel(){
+        let (questions, asked) = channel::<(Question, Sender<Answer>)>();
+        let frontend = thread::spawn(move || {
+            let (question, answer) = asked.recv().unwrap();
+            answer.send(Answer::Cancel).unwrap();
+            question
+        });
+
+        let mut guard = InteractiveGuard::new(Box::new(ChannelPrompter::new(questions)));
+        let target = Path::new("/virtual/file");
+        assert!(matches!(guard.authorize(Capability::Overwrite, false, target), Err(DomainError::UserCancelled)));
+        assert_eq!(frontend.join().unwrap(), Question::new(Capability::Overwrite, target, true));
+    }
+
+    #[test]
+    fn loaded_interactive_guard_asks_through_attached_prompter(){
+        let chroot = Samples::init_simple_chroot("interactive_guard_loaded_with_attached_prompter");
+        let event = FileSystemEvent::Remove(RemoveEvent::new(chroot.join("RDIR").as_path(), false));
+
+        let mut saved = Container::new();
+        let guard = InteractiveGuard::new(Box::new(ScriptedPrompter::new(vec![Answer::Skip])));
+        let guard = saved.emit(&event, RegistrarGuard::from(Box::new(guard))).unwrap();
+        saved.delay(event, guard);
+
+        let (questions, asked) = channel::<(Question, Sender<Answer>)>();
+        let frontend = thread::spawn(move || {
+            let (question, answer) = asked.recv().unwrap();
+            answer.send(Answer::Allow).unwrap();
+            question
+        });
+
+        let mut loaded = Container::new();
+        loaded.attach_to_guards(PrompterFactory::new(ChannelPrompter::new(questions)));
+        loaded.emit_json(saved.to_json().unwrap()).unwrap();
+        assert_eq!(frontend.join().unwrap(), Question::new(Capability::Recursive, chroot.join("RDIR").as_path(), false));
+        assert!(!loaded.status(chroot.join("RDIR").as_path()).unwrap().exists());
+    }
+}
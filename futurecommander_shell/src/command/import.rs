@@ -24,7 +24,7 @@ use std::{
 
 use clap::ArgMatches;
 
-use futurecommander_filesystem::{ Container };
+use futurecommander_filesystem::{ Container, Plan };
 
 use crate::command::{
     Command,
@@ -56,7 +56,14 @@ impl Command<InitializedImportCommand> {
             return Err(CommandError::DoesNotExists(self.0.path));
         }
 
-        container.emit_json(read_to_string(self.0.path.as_path())?)?;
+        let content = read_to_string(self.0.path.as_path())?;
+        //Relative plan paths are resolved from the directory holding the plan
+        let plan_directory = self.0.path.parent().unwrap_or_else(|| Path::new("/"));
+        match self.0.path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => container.emit_plan(&Plan::from_toml(content.as_str())?, plan_directory)?,
+            Some("yaml") | Some("yml") => container.emit_plan(&Plan::from_yaml(content.as_str())?, plan_directory)?,
+            _ => container.emit_json(content)?
+        }
         Ok(())
     }
 }
@@ -74,10 +81,17 @@ mod tests {
         },
     };
 
+    use std::{
+        fs::write
+    };
+
     use futurecommander_filesystem::{
         sample::Samples,
         ReadableFileSystem,
-        Entry
+        Entry,
+        Filter,
+        OverwritePolicy,
+        Backup
     };
 
     #[test]
@@ -88,7 +102,12 @@ mod tests {
             source: sample_path.join("A"),
             destination: sample_path.join("APRIME"),
             merge: false,
-            overwrite: false,
+            overwrite: OverwritePolicy::Never,
+            keep_both: false,
+            rename_template: None,
+            backup: Backup::None,
+            filter: Filter::default(),
+            follow_symlinks: false,
             guard: AvailableGuard::Zealed
         });
 
@@ -114,4 +133,21 @@ mod tests {
         assert!(b_stat.exists());
         assert!(b_stat.is_dir());
     }
+
+    #[test]
+    fn can_import_a_hand_written_plan(){
+        let sample_path = Samples::init_advanced_chroot("can_import_a_hand_written_plan");
+        write(
+            sample_path.join("plan.toml"),
+            "[[operations]]\ncopy = { from = \"A\", to = \"APRIME\" }\n\n[[operations]]\nremove = { path = \"F\" }\n"
+        ).unwrap();
+
+        let mut container = Container::new();
+        Command(InitializedImportCommand {
+            path: sample_path.join("plan.toml")
+        }).execute(&mut container).unwrap();
+
+        assert!(container.status(sample_path.join("APRIME/C").as_path()).unwrap().exists());
+        assert!(!container.status(sample_path.join("F").as_path()).unwrap().exists());
+    }
 }
@@ -0,0 +1,106 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::path::{ Path, PathBuf };
+
+use clap::ArgMatches;
+
+use futurecommander_filesystem::{
+    Container,
+    LinkEvent,
+    Listener,
+    Delayer,
+    FileSystemEvent
+};
+
+use crate::command::{
+    Command,
+    errors::CommandError,
+    AvailableGuard
+};
+
+pub struct LinkCommand {}
+
+impl Command<LinkCommand> {
+    pub fn initialize(cwd: &Path, args: &ArgMatches<'_>) -> Result<Command<InitializedLinkCommand>, CommandError> {
+        Ok(
+            Command(
+                InitializedLinkCommand {
+                    source: Self::extract_path_from_args(cwd, args, "source")?,
+                    destination: Self::extract_path_from_args(cwd, args, "destination")?,
+                    guard: Self::extract_available_guard(cwd, args, "guard")?
+                }
+            )
+        )
+    }
+}
+
+pub struct InitializedLinkCommand {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    pub guard: AvailableGuard
+}
+
+impl Command<InitializedLinkCommand> {
+    pub fn execute(self, container: &mut Container) -> Result<(), CommandError> {
+        let event = FileSystemEvent::Link(
+            LinkEvent::new(self.0.source.as_path(), self.0.destination.as_path())
+        );
+
+        let guard = container.emit(&event, self.0.guard.registrar())?;
+        container.delay(event, guard);
+        Ok(())
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    use std::os::unix::fs::MetadataExt;
+
+    use futurecommander_filesystem::{
+        sample::Samples,
+        ReadableFileSystem,
+        Entry
+    };
+
+    #[test]
+    fn link_then_apply(){
+        let chroot = Samples::init_simple_chroot("link_then_apply");
+        let mut container = Container::new();
+
+        Command(InitializedLinkCommand {
+            source: chroot.join("RDIR/RFILEA"),
+            destination: chroot.join("RDIR2/LINKED"),
+            guard: AvailableGuard::Zealed
+        }).execute(&mut container).unwrap();
+
+        assert!(container.status(chroot.join("RDIR2/LINKED").as_path()).unwrap().is_virtual());
+        assert!(!chroot.join("RDIR2/LINKED").exists());
+
+        container.apply().unwrap();
+
+        assert_eq!(
+            chroot.join("RDIR/RFILEA").metadata().unwrap().ino(),
+            chroot.join("RDIR2/LINKED").metadata().unwrap().ino()
+        );
+    }
+}
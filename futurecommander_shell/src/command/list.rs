@@ -22,18 +22,22 @@ use std::{
     path::{ Path, PathBuf }
 };
 
+use chrono::{ Local, TimeZone };
 use clap::ArgMatches;
 
 use futurecommander_filesystem::{
     ReadableFileSystem,
     Container,
-    Entry
+    Entry,
+    Metadata,
+    lossless
 };
 
 use crate::command::{
     Command,
     errors::CommandError,
-    add_red_color
+    add_red_color,
+    accounts
 };
 
 pub struct ListCommand {}
@@ -44,7 +48,8 @@ impl Command<ListCommand> {
             Command(
                 InitializedListCommand {
                     path:
-                        Self::extract_path_from_args(cwd, args, "path").unwrap_or_else(|_| cwd.to_path_buf())
+                        Self::extract_path_from_args(cwd, args, "path").unwrap_or_else(|_| cwd.to_path_buf()),
+                    long: args.is_present("long")
                 }
             )
         )
@@ -52,18 +57,37 @@ impl Command<ListCommand> {
 }
 
 pub struct InitializedListCommand {
-    pub path: PathBuf
+    pub path: PathBuf,
+    pub long: bool
 }
 
-
-
 impl Command<InitializedListCommand> {
+    //Permissions, ownership and modification time the entry will have after apply
+    fn long_format(metadata: Metadata, directory: bool) -> String {
+        format!(
+            "{} {:<8} {:<8} {}",
+            Metadata::mode_string(metadata.mode().unwrap_or(0), directory),
+            metadata.owner().map(accounts::user_name).unwrap_or_else(|| "-".to_string()),
+            metadata.group().map(accounts::group_name).unwrap_or_else(|| "-".to_string()),
+            metadata.modified()
+                .and_then(|modified| Local.timestamp_opt(modified, 0).single())
+                .map(|modified| modified.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_else(|| "-".repeat(16))
+        )
+    }
+
     pub fn execute<W : Write>(self, out: &mut W, container: &mut Container) -> Result<(), CommandError> {
         let collection = container.read_dir(self.0.path.as_path())?;
         if ! collection.is_empty() {
             for child in collection.sort().into_iter() {
                 let output = format!(
-                    "{}    {}",
+                    "{}{}    {}",
+                   if self.0.long {
+                       let metadata = container.metadata(child.path())?.unwrap_or_default();
+                       format!("{} ", Self::long_format(metadata, child.is_dir()))
+                   } else {
+                       String::new()
+                   },
                    if child.is_dir() {
                        "Directory"
                    } else if child.is_file() {
@@ -71,7 +95,7 @@ impl Command<InitializedListCommand> {
                    } else {
                        "Unknown  "
                    },
-                    child.name().unwrap().to_string_lossy(),
+                    lossless::escape(child.name().unwrap()),
 
                 );
                 writeln!(
@@ -91,3 +115,70 @@ impl Command<InitializedListCommand> {
     }
 }
 
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{
+        ffi::OsStr,
+        fs::write
+    };
+
+    use futurecommander_filesystem::sample::Samples;
+
+    use crate::VirtualHelper;
+
+    #[cfg(unix)]
+    #[test]
+    fn list_and_complete_names_which_are_not_utf8(){
+        use std::os::unix::ffi::OsStrExt;
+
+        let chroot = Samples::init_empty_chroot("list_and_complete_names_which_are_not_utf8");
+        write(chroot.join(OsStr::from_bytes(b"caf\xe9")), "menu").unwrap();
+        let mut container = Container::new();
+
+        let mut stdout = Vec::new();
+        Command(InitializedListCommand {
+            path: chroot.clone(),
+            long: false
+        }).execute(&mut stdout, &mut container).unwrap();
+
+        assert_eq!(String::from_utf8(stdout).unwrap(), "File         caf\\xe9\n");
+
+        let candidates = VirtualHelper::new(&container, chroot.clone()).path_candidates("bytes:caf");
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].display, "bytes:caf\\xe9");
+        assert_eq!(
+            lossless::decode(candidates[0].display.as_str()).unwrap(),
+            PathBuf::from(OsStr::from_bytes(b"caf\xe9"))
+        );
+    }
+
+    #[test]
+    fn long_listing_shows_future_metadata(){
+        let chroot = Samples::init_simple_chroot("long_listing_shows_future_metadata");
+        let mut container = Container::new();
+
+        Command(crate::command::InitializedChmodCommand {
+            path: chroot.join("RDIR/RFILEA"),
+            mode: "640".parse().unwrap(),
+            recursive: false,
+            guard: crate::command::AvailableGuard::Zealed
+        }).execute(&mut container).unwrap();
+
+        let mut stdout = Vec::new();
+        Command(InitializedListCommand {
+            path: chroot.join("RDIR"),
+            long: true
+        }).execute(&mut stdout, &mut container).unwrap();
+
+        let output = String::from_utf8(stdout).unwrap();
+        let lines : Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("-rw-r----- "));
+        assert!(lines[0].ends_with("File         RFILEA"));
+        assert!(lines[1].ends_with("File         RFILEB"));
+    }
+}
@@ -0,0 +1,93 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use std::io::Write;
+
+use clap::ArgMatches;
+
+use futurecommander_filesystem::Container;
+
+use crate::command::{
+    Command,
+    errors::CommandError
+};
+
+pub struct LockCommand {}
+
+impl Command<LockCommand> {
+    pub fn initialize(args: &ArgMatches<'_>) -> Result<Command<InitializedLockCommand>, CommandError> {
+        Ok(
+            Command(InitializedLockCommand {
+                release: args.is_present("release")
+            })
+        )
+    }
+}
+
+pub struct InitializedLockCommand {
+    pub release: bool
+}
+
+impl Command<InitializedLockCommand> {
+    pub fn execute<W: Write>(self, out: &mut W, container: &mut Container) -> Result<(), CommandError> {
+        if self.0.release {
+            container.unlock_session();
+            writeln!(out, "Session unlocked")?;
+        } else {
+            container.lock_session()?;
+            writeln!(out, "Session locked, directories stay locked from the moment an operation changes them")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futurecommander_filesystem::{
+        sample::Samples,
+        FileSystemEvent,
+        CreateEvent,
+        Kind,
+        Listener,
+        Delayer,
+        LOCK_FILE
+    };
+
+    use crate::command::AvailableGuard;
+
+    #[test]
+    fn lock_session_until_released(){
+        let chroot = Samples::init_simple_chroot("lock_session_until_released");
+        let mut container = Container::new();
+
+        Command(InitializedLockCommand { release: false }).execute(&mut Vec::new(), &mut container).unwrap();
+        let event = FileSystemEvent::Create(CreateEvent::new(chroot.join("RDIR/NEW").as_path(), Kind::File, false, false));
+        let guard = container.emit(&event, AvailableGuard::Zealed.registrar()).unwrap();
+        container.delay(event, guard);
+        assert!(container.is_session_locked());
+        assert!(chroot.join("RDIR").join(LOCK_FILE).exists());
+
+        let mut stdout = Vec::new();
+        Command(InitializedLockCommand { release: true }).execute(&mut stdout, &mut container).unwrap();
+        assert_eq!(String::from_utf8(stdout).unwrap(), "Session unlocked\n");
+        assert!(!chroot.join("RDIR").join(LOCK_FILE).exists());
+    }
+}
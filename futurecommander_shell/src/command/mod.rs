@@ -29,15 +29,36 @@ pub use self::list::{ InitializedListCommand, ListCommand };
 pub mod mov;
 pub use self::mov::{ InitializedMoveCommand, MoveCommand };
 
+pub mod link;
+pub use self::link::{ InitializedLinkCommand, LinkCommand };
+
+pub mod sync;
+pub use self::sync::{ InitializedSyncCommand, SyncCommand };
+
 pub mod new_directory;
 pub use self::new_directory::{ InitializedNewDirectoryCommand, NewDirectoryCommand };
 
 pub mod new_file;
 pub use self::new_file::{ InitializedNewFileCommand, NewFileCommand };
 
+pub mod chmod;
+pub use self::chmod::{ InitializedChmodCommand, ChmodCommand };
+
+pub mod chown;
+pub use self::chown::{ InitializedChownCommand, ChownCommand };
+
 pub mod remove;
 pub use self::remove::{ InitializedRemoveCommand, RemoveCommand };
 
+pub mod rename;
+pub use self::rename::{ InitializedRenameCommand, RenameCommand };
+
+pub mod batch_rename;
+pub use self::batch_rename::{ InitializedBatchRenameCommand, BatchRenameCommand };
+
+pub mod trash;
+pub use self::trash::{ InitializedTrashCommand, TrashCommand, TrashAction };
+
 pub mod tree;
 pub use self::tree::{ InitializedTreeCommand, TreeCommand };
 
@@ -47,19 +68,58 @@ pub use self::save::{ InitializedSaveCommand, SaveCommand };
 pub mod import;
 pub use self::import::{ InitializedImportCommand, ImportCommand };
 
+pub mod export;
+pub use self::export::{ InitializedExportCommand, ExportCommand, ExportFormat };
+
+pub mod reconcile;
+pub use self::reconcile::{ InitializedReconcileCommand, ReconcileCommand };
+
+pub mod revert;
+pub use self::revert::{ InitializedRevertCommand, RevertCommand };
+
+pub mod queue;
+pub use self::queue::{ InitializedQueueCommand, QueueCommand, QueueAction };
+
+pub mod status;
+pub use self::status::{ InitializedStatusCommand, StatusCommand, StatusFormat };
+
+pub mod lock;
+pub use self::lock::{ InitializedLockCommand, LockCommand };
+
+pub mod answers;
+pub use self::answers::{ InitializedAnswersCommand, AnswersCommand };
+
+pub mod portability;
+pub use self::portability::{ InitializedPortabilityCommand, PortabilityCommand };
+
 use clap::ArgMatches;
 use std::path::{ Path, PathBuf, MAIN_SEPARATOR };
 
+use futurecommander_filesystem::{ lossless, Filter, Pattern, OverwritePolicy, Backup, capability::{ AnswerFile, Thresholds } };
+
 pub struct Command<C>(pub C);
 
+mod accounts;
+
 mod guard;
-pub use self::guard::AvailableGuard;
+pub use self::guard::{ AvailableGuard, InteractiveGuard };
+
+mod prompter;
+pub use self::prompter::{
+    Prompter,
+    Question,
+    Answer,
+    TerminalPrompter,
+    ScriptedPrompter,
+    ChannelPrompter,
+    PrompterFactory
+};
 
 impl <C>Command<C> {
     pub fn extract_path_from_args(cwd: &Path, args: &ArgMatches<'_>, key: &str) -> Result<PathBuf, CommandError> {
         match args.value_of(key) {
             Some(str_path) => {
-                Ok(futurecommander_filesystem::tools::normalize(&cwd.join(Path::new(str_path.trim()))))
+                Ok(futurecommander_filesystem::tools::normalize(&cwd.join(lossless::decode(str_path.trim())?)))
             },
             None => Err(CommandError::ArgumentMissing("generic".to_string(), key.to_string(), args.usage().to_string()))
         }
@@ -68,22 +128,67 @@ impl <C>Command<C> {
     pub fn extract_path_and_trail_from_args(cwd: &Path, args: &ArgMatches<'_>, key: &str) -> Result<(PathBuf, bool), CommandError> {
         match args.value_of(key) {
             Some(str_path) => Ok((
-                futurecommander_filesystem::tools::normalize(&cwd.join(Path::new(str_path.trim()))),
+                futurecommander_filesystem::tools::normalize(&cwd.join(lossless::decode(str_path.trim())?)),
                 str_path.chars().last().unwrap() == MAIN_SEPARATOR
             )),
             None => Err(CommandError::ArgumentMissing("generic".to_string(), key.to_string(), args.usage().to_string()))
         }
     }
 
-    pub fn extract_available_guard(args: &ArgMatches<'_>, key: &str) -> Result<AvailableGuard, CommandError> {
-        match args.value_of(key) {
+    pub fn extract_filter(args: &ArgMatches<'_>) -> Result<Filter, CommandError> {
+        let patterns = |key: &str| -> Result<Vec<Pattern>, CommandError> {
+            match args.values_of(key) {
+                Some(values) => Ok(values.map(Pattern::new).collect::<Result<Vec<Pattern>, _>>()?),
+                None => Ok(Vec::new())
+            }
+        };
+        Ok(Filter::new(patterns("include")?, patterns("exclude")?, args.is_present("ignore_files")))
+    }
+
+    pub fn extract_overwrite_policy(args: &ArgMatches<'_>) -> Result<OverwritePolicy, CommandError> {
+        if let Some(policy) = args.value_of("overwrite_policy") {
+            Ok(policy.parse()?)
+        } else if args.is_present("update") {
+            Ok(OverwritePolicy::IfNewer)
+        } else {
+            Ok(args.is_present("overwrite").into())
+        }
+    }
+
+    //A backup directory is resolved like any other path and takes precedence over the control value
+    pub fn extract_backup(cwd: &Path, args: &ArgMatches<'_>) -> Result<Backup, CommandError> {
+        if args.is_present("backup_dir") {
+            Ok(Backup::Directory(Self::extract_path_from_args(cwd, args, "backup_dir")?))
+        } else if let Some(control) = args.value_of("backup") {
+            Ok(control.parse()?)
+        } else {
+            Ok(Backup::None)
+        }
+    }
+
+    //Thresholds from the environment apply to any guard, answers, when given, are replayed before them
+    pub fn extract_available_guard(cwd: &Path, args: &ArgMatches<'_>, key: &str) -> Result<AvailableGuard, CommandError> {
+        let guard = match args.value_of(key) {
             Some(str_guard) => {
                 if ! AvailableGuard::available(str_guard) {
                     return Err(CommandError::InvalidGuard(str_guard.to_string()));
                 }
-                Ok(AvailableGuard::from(str_guard))
+                match str_guard.strip_prefix("policy") {
+                    Some(inner) => AvailableGuard::policy(AvailableGuard::from(inner.trim_start_matches(':')))?,
+                    None => AvailableGuard::from(str_guard)
+                }
             },
-            None => Ok(AvailableGuard::default())
+            None => AvailableGuard::default()
+        };
+        let guard = match Thresholds::from_env()? {
+            Some(thresholds) => AvailableGuard::Thresholds(thresholds, Box::new(guard)),
+            None => guard
+        };
+
+        match Self::extract_path_from_args(cwd, args, "answers") {
+            Ok(path) => Ok(AvailableGuard::Answers(AnswerFile::from_file(path.as_path())?, Box::new(guard))),
+            Err(CommandError::ArgumentMissing(..)) => Ok(guard),
+            Err(error) => Err(error)
         }
     }
 }
@@ -28,7 +28,10 @@ use futurecommander_filesystem::{
     Entry,
     Listener,
     Delayer,
-    FileSystemEvent
+    FileSystemEvent,
+    Filter,
+    OverwritePolicy,
+    Backup
 };
 
 use crate::command::{
@@ -49,8 +52,16 @@ impl Command<MoveCommand> {
                 source,
                 destination,
                 merge: args.is_present("merge"),
-                overwrite: args.is_present("overwrite"),
-                guard: Self::extract_available_guard(args, "guard")?
+                overwrite: Self::extract_overwrite_policy(args)?,
+                keep_both: args.is_present("keep_both"),
+                rename_template: args.value_of("rename_template").map(str::to_string),
+                backup: Self::extract_backup(cwd, args)?,
+                filter: Self::extract_filter(args)?,
+                follow_symlinks: args.is_present("follow_symlinks"),
+                cross_device: args.is_present("cross_device"),
+                allow_dangerous: args.is_present("allow_dangerous"),
+                cwd: cwd.to_path_buf(),
+                guard: Self::extract_available_guard(cwd, args, "guard")?
             })
         )
     }
@@ -60,7 +71,15 @@ pub struct InitializedMoveCommand {
     pub source: PathBuf,
     pub destination: PathBuf,
     pub merge: bool,
-    pub overwrite: bool,
+    pub overwrite: OverwritePolicy,
+    pub keep_both: bool,
+    pub rename_template: Option<String>,
+    pub backup: Backup,
+    pub filter: Filter,
+    pub follow_symlinks: bool,
+    pub cross_device: bool,
+    pub allow_dangerous: bool,
+    pub cwd: PathBuf,
     pub guard: AvailableGuard
 }
 
@@ -84,6 +103,14 @@ impl Command<InitializedMoveCommand> {
                         self.0.merge,
                         self.0.overwrite
                     )
+                    .with_keep_both(self.0.keep_both)
+                    .with_rename_template(self.0.rename_template.as_deref())
+                    .with_backup(self.0.backup.clone())
+                    .with_filter(self.0.filter.clone())
+                    .with_follow_symlinks(self.0.follow_symlinks)
+                    .with_allow_dangerous(self.0.allow_dangerous)
+                    .with_working_directory(self.0.cwd.as_path())
+                    .with_cross_device(self.0.cross_device)
                 } else if source.is_dir() {
                     return Err(CommandError::DirectoryIntoAFile(source.to_path(), destination.to_path()))
                 } else {
@@ -96,6 +123,14 @@ impl Command<InitializedMoveCommand> {
                     self.0.merge,
                     self.0.overwrite
                 )
+                .with_keep_both(self.0.keep_both)
+                .with_rename_template(self.0.rename_template.as_deref())
+                .with_backup(self.0.backup.clone())
+                .with_filter(self.0.filter.clone())
+                .with_follow_symlinks(self.0.follow_symlinks)
+                .with_allow_dangerous(self.0.allow_dangerous)
+                    .with_working_directory(self.0.cwd.as_path())
+                .with_cross_device(self.0.cross_device)
             }
         );
 
@@ -124,7 +159,15 @@ mod tests {
             source: sample_path.join(&Path::new("F")),
             destination: sample_path.join("A"),
             merge: false,
-            overwrite: false,
+            overwrite: OverwritePolicy::Never,
+            keep_both: false,
+            rename_template: None,
+            backup: Backup::None,
+            filter: Filter::default(),
+            follow_symlinks: false,
+            cross_device: false,
+            allow_dangerous: false,
+            cwd: sample_path.clone(),
             guard: AvailableGuard::Zealed
         });
 
@@ -134,7 +177,15 @@ mod tests {
             source: sample_path.join("A/F"),
             destination: sample_path.join("B"),
             merge: false,
-            overwrite: false,
+            overwrite: OverwritePolicy::Never,
+            keep_both: false,
+            rename_template: None,
+            backup: Backup::None,
+            filter: Filter::default(),
+            follow_symlinks: false,
+            cross_device: false,
+            allow_dangerous: false,
+            cwd: sample_path.clone(),
             guard: AvailableGuard::Zealed
         });
 
@@ -144,7 +195,15 @@ mod tests {
             source: sample_path.join("B/F"),
             destination: sample_path.join("B/D/E"),
             merge: false,
-            overwrite: false,
+            overwrite: OverwritePolicy::Never,
+            keep_both: false,
+            rename_template: None,
+            backup: Backup::None,
+            filter: Filter::default(),
+            follow_symlinks: false,
+            cross_device: false,
+            allow_dangerous: false,
+            cwd: sample_path.clone(),
             guard: AvailableGuard::Zealed
         });
 
@@ -47,7 +47,7 @@ impl Command<NewDirectoryCommand> {
                     path: Self::extract_path_from_args(cwd, args, "path")?,
                     recursive: args.is_present("recursive"),
                     overwrite: args.is_present("overwrite"),
-                    guard: Self::extract_available_guard(args, "guard")?
+                    guard: Self::extract_available_guard(cwd, args, "guard")?
                 }
             )
         )
@@ -20,12 +20,16 @@
 use std::path::Path;
 use std::path::PathBuf;
 
+use chrono::{ DateTime, Local, NaiveDate, NaiveDateTime, TimeZone };
 use clap::ArgMatches;
 
 use futurecommander_filesystem::{
     Container,
     Kind,
     CreateEvent,
+    MetadataEvent,
+    ReadableFileSystem,
+    Entry,
     Listener,
     Delayer,
     FileSystemEvent
@@ -47,33 +51,73 @@ impl Command<NewFileCommand> {
                     path: Self::extract_path_from_args(cwd, args, "path")?,
                     recursive: args.is_present("recursive"),
                     overwrite: args.is_present("overwrite"),
-                    guard: Self::extract_available_guard(args, "guard")?
+                    modified: match args.value_of("date") {
+                        Some(date) => Some(Self::timestamp(date)?),
+                        None => None
+                    },
+                    guard: Self::extract_available_guard(cwd, args, "guard")?
                 }
             )
         )
     }
+
+    //Dates as touch -d takes them : @SECONDS, RFC 3339, or YYYY-MM-DD[ HH:MM[:SS]] in local time
+    pub fn timestamp(text: &str) -> Result<i64, CommandError> {
+        let text = text.trim();
+        let invalid = || CommandError::InvalidDate(text.to_string());
+
+        if let Some(seconds) = text.strip_prefix('@') {
+            return seconds.parse().map_err(|_| invalid());
+        }
+        if let Ok(date) = DateTime::parse_from_rfc3339(text) {
+            return Ok(date.timestamp());
+        }
+
+        let local = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"].iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+            .or_else(|| NaiveDate::parse_from_str(text, "%Y-%m-%d").ok().and_then(|date| date.and_hms_opt(0, 0, 0)))
+            .ok_or_else(invalid)?;
+        Local.from_local_datetime(&local)
+            .earliest()
+            .map(|date| date.timestamp())
+            .ok_or_else(invalid)
+    }
 }
 
 pub struct InitializedNewFileCommand {
     pub path: PathBuf,
     pub recursive: bool,
     pub overwrite: bool,
+    pub modified: Option<i64>,
     pub guard: AvailableGuard
 }
 
 impl Command<InitializedNewFileCommand> {
     pub fn execute(self, container: &mut Container) -> Result<(), CommandError> {
-        let event = FileSystemEvent::Create(
-            CreateEvent::new(
-                self.0.path.as_path(),
-                Kind::File,
-                self.0.recursive,
-                self.0.overwrite
-            )
-        );
+        //With a date, an existing file only gets its modification time changed, like touch does
+        if self.0.modified.is_none() || ! container.status(self.0.path.as_path())?.exists() {
+            let event = FileSystemEvent::Create(
+                CreateEvent::new(
+                    self.0.path.as_path(),
+                    Kind::File,
+                    self.0.recursive,
+                    self.0.overwrite
+                )
+            );
+
+            let guard = container.emit(&event, self.0.guard.registrar())?;
+            container.delay(event, guard);
+        }
 
-        let guard = container.emit(&event, self.0.guard.registrar())?;
-        container.delay(event, guard);
+        if let Some(modified) = self.0.modified {
+            let event = FileSystemEvent::Metadata(
+                MetadataEvent::new(self.0.path.as_path(), false)
+                    .with_modified(Some(modified))
+            );
+
+            let guard = container.emit(&event, self.0.guard.registrar())?;
+            container.delay(event, guard);
+        }
         Ok(())
     }
 }
@@ -85,7 +129,6 @@ mod tests {
 
     use futurecommander_filesystem::{
         sample::Samples,
-        ReadableFileSystem,
         EntryAdapter
     };
 
@@ -98,6 +141,7 @@ mod tests {
             path: sample_path.join(&Path::new("B/D/E/TOUCHED")),
             recursive: false,
             overwrite: false,
+            modified: None,
             guard: AvailableGuard::Zealed
         });
 
@@ -109,4 +153,42 @@ mod tests {
                 .contains(&EntryAdapter(sample_path.join("B/D/E/TOUCHED").as_path()))
         );
     }
+
+    #[test]
+    fn parse_dates(){
+        assert_eq!(Command::<NewFileCommand>::timestamp("@1000000000").unwrap(), 1_000_000_000);
+        assert_eq!(Command::<NewFileCommand>::timestamp("2001-09-09T01:46:40Z").unwrap(), 1_000_000_000);
+        assert_eq!(
+            Command::<NewFileCommand>::timestamp("2001-09-09").unwrap() + 6400,
+            Command::<NewFileCommand>::timestamp("2001-09-09 01:46:40").unwrap()
+        );
+        assert!(Command::<NewFileCommand>::timestamp("yesterday").is_err());
+    }
+
+    #[test]
+    fn touch_with_date(){
+        let chroot = Samples::init_simple_chroot("touch_with_date");
+        let mut container = Container::new();
+
+        Command(InitializedNewFileCommand {
+            path: chroot.join("RDIR/RFILEA"),
+            recursive: false,
+            overwrite: false,
+            modified: Some(1_000_000_000),
+            guard: AvailableGuard::Zealed
+        }).execute(&mut container).unwrap();
+
+        Command(InitializedNewFileCommand {
+            path: chroot.join("RDIR/NEW"),
+            recursive: false,
+            overwrite: false,
+            modified: Some(1_000_000_000),
+            guard: AvailableGuard::Zealed
+        }).execute(&mut container).unwrap();
+
+        for name in ["RDIR/RFILEA", "RDIR/NEW"].iter() {
+            assert_eq!(container.metadata(chroot.join(name).as_path()).unwrap().unwrap().modified(), Some(1_000_000_000));
+        }
+        assert!(container.status(chroot.join("RDIR/NEW").as_path()).unwrap().is_virtual());
+    }
 }
@@ -0,0 +1,147 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::io::Write;
+
+use clap::ArgMatches;
+
+use futurecommander_filesystem::{
+    Container,
+    Portability,
+    NameValidator,
+    PathComparison,
+    QueryError
+};
+
+use crate::command::{
+    Command,
+    errors::CommandError
+};
+
+pub struct PortabilityCommand {}
+
+impl Command<PortabilityCommand> {
+    pub fn initialize(args: &ArgMatches<'_>) -> Result<Command<InitializedPortabilityCommand>, CommandError> {
+        Ok(
+            Command(InitializedPortabilityCommand {
+                profile: match args.value_of("profile") {
+                    Some(profile) => Some(profile.parse::<Portability>()?),
+                    None => None
+                },
+                strict: args.is_present("strict"),
+                off: args.is_present("off"),
+                comparison: match args.value_of("compare") {
+                    Some(comparison) => Some(comparison.parse::<PathComparison>().map_err(QueryError::from)?),
+                    None => None
+                }
+            })
+        )
+    }
+}
+
+pub struct InitializedPortabilityCommand {
+    pub profile: Option<Portability>,
+    pub strict: bool,
+    pub off: bool,
+    pub comparison: Option<PathComparison>
+}
+
+impl Command<InitializedPortabilityCommand> {
+    pub fn execute<W: Write>(self, out: &mut W, container: &mut Container) -> Result<(), CommandError> {
+        if let Some(comparison) = self.0.comparison {
+            container.set_path_comparison(comparison)?;
+        }
+        if self.0.off {
+            container.validate_names(None);
+        } else if let Some(profile) = self.0.profile {
+            container.validate_names(Some(NameValidator::new(profile).with_strict(self.0.strict)));
+        }
+
+        match container.name_validator() {
+            Some(validator) => writeln!(
+                out,
+                "Names are checked for {} portability, {}",
+                validator.portability(),
+                if validator.is_strict() { "issues are errors" } else { "issues are warnings" }
+            )?,
+            None => writeln!(out, "Names are not checked")?
+        }
+        if !container.path_comparison().is_exact() {
+            writeln!(out, "Paths are compared {}", container.path_comparison())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futurecommander_filesystem::{
+        sample::Samples,
+        FileSystemEvent,
+        CreateEvent,
+        Kind,
+        Listener,
+        DomainError,
+        NameIssue
+    };
+
+    use crate::command::AvailableGuard;
+
+    #[test]
+    fn portability_refuses_reserved_names_when_strict(){
+        let chroot = Samples::init_simple_chroot("portability_refuses_reserved_names_when_strict");
+        let mut container = Container::new();
+
+        let mut stdout = Vec::new();
+        Command(InitializedPortabilityCommand { profile: Some(Portability::Windows), strict: true, off: false, comparison: None }).execute(&mut stdout, &mut container).unwrap();
+        assert_eq!(String::from_utf8(stdout).unwrap(), "Names are checked for windows portability, issues are errors\n");
+
+        let event = FileSystemEvent::Create(CreateEvent::new(chroot.join("RDIR/com1.log").as_path(), Kind::File, false, false));
+        assert!(matches!(
+            container.emit(&event, AvailableGuard::Zealed.registrar()),
+            Err(DomainError::NotPortable(_, Portability::Windows, NameIssue::Reserved))
+        ));
+
+        let mut stdout = Vec::new();
+        Command(InitializedPortabilityCommand { profile: None, strict: false, off: true, comparison: None }).execute(&mut stdout, &mut container).unwrap();
+        assert_eq!(String::from_utf8(stdout).unwrap(), "Names are not checked\n");
+        assert!(container.emit(&event, AvailableGuard::Zealed.registrar()).is_ok());
+    }
+
+    #[test]
+    fn portability_compares_paths_case_insensitively(){
+        let chroot = Samples::init_simple_chroot("portability_compares_paths_case_insensitively");
+        let mut container = Container::new();
+
+        let mut stdout = Vec::new();
+        Command(InitializedPortabilityCommand {
+            profile: None,
+            strict: false,
+            off: false,
+            comparison: Some("case-insensitive+nfd".parse().unwrap())
+        }).execute(&mut stdout, &mut container).unwrap();
+        assert_eq!(String::from_utf8(stdout).unwrap(), "Names are not checked\nPaths are compared case-insensitive+nfd\n");
+
+        let event = FileSystemEvent::Create(CreateEvent::new(chroot.join("rdir/rfilea").as_path(), Kind::File, false, false));
+        assert!(container.emit(&event, AvailableGuard::Zealed.registrar()).is_err());
+    }
+}
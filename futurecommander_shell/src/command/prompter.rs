@@ -0,0 +1,184 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+use std::{
+    collections::VecDeque,
+    fmt::{ Debug, Display, Formatter, Result as FmtResult },
+    io::{ stdin },
+    path::{ Path, PathBuf },
+    sync::{
+        Arc,
+        mpsc::{ channel, Sender }
+    }
+};
+
+use futurecommander_filesystem::{
+    DomainError,
+    capability::Capability
+};
+
+//What a guard needs to know before letting a sensible operation through
+#[derive(Debug, Clone, PartialEq)]
+pub struct Question {
+    capability: Capability,
+    target: PathBuf,
+    can_rename: bool
+}
+
+impl Question {
+    pub fn new(capability: Capability, target: &Path, can_rename: bool) -> Question {
+        Question {
+            capability,
+            target: target.to_path_buf(),
+            can_rename
+        }
+    }
+
+    pub fn capability(&self) -> Capability { self.capability }
+    pub fn target(&self) -> &Path { self.target.as_path() }
+    pub fn can_rename(&self) -> bool { self.can_rename }
+}
+
+impl Display for Question {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "Allow {} for target {} ?([skip]/skip_all/allow/allow_all{}/cancel) : ",
+            self.capability,
+            self.target.to_string_lossy(),
+            if self.can_rename { "/rename/rename_all" } else { "" }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Answer {
+    Skip,
+    SkipAll,
+    Allow,
+    AllowAll,
+    Rename,
+    RenameAll,
+    Cancel
+}
+
+impl Answer {
+    //Anything unexpected skips, renaming is only understood when the question allows it
+    pub fn parse(input: &str, question: &Question) -> Answer {
+        match input.trim() {
+            "skip_all" => Answer::SkipAll,
+            "allow" => Answer::Allow,
+            "allow_all" => Answer::AllowAll,
+            "rename" if question.can_rename() => Answer::Rename,
+            "rename_all" if question.can_rename() => Answer::RenameAll,
+            "cancel" => Answer::Cancel,
+            _ => Answer::Skip
+        }
+    }
+}
+
+pub trait Prompter : Debug + Send {
+    fn ask(&mut self, question: &Question) -> Result<Answer, DomainError>;
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TerminalPrompter;
+
+impl Prompter for TerminalPrompter {
+    fn ask(&mut self, question: &Question) -> Result<Answer, DomainError> {
+        let mut input = String::new();
+        println!("{}", question);
+        stdin().read_line(&mut input)?;
+        Ok(Answer::parse(input.as_str(), question))
+    }
+}
+
+//Answers given in advance, skipping once exhausted
+#[derive(Debug, Clone, Default)]
+pub struct ScriptedPrompter {
+    answers: VecDeque<Answer>,
+    asked: Vec<Question>
+}
+
+impl ScriptedPrompter {
+    pub fn new(answers: Vec<Answer>) -> ScriptedPrompter {
+        ScriptedPrompter {
+            answers: answers.into(),
+            asked: Vec::new()
+        }
+    }
+
+    pub fn asked(&self) -> &[Question] { &self.asked }
+}
+
+impl Prompter for ScriptedPrompter {
+    fn ask(&mut self, question: &Question) -> Result<Answer, DomainError> {
+        self.asked.push(question.clone());
+        Ok(self.answers.pop_front().unwrap_or(Answer::Skip))
+    }
+}
+
+/*
+Sends every question along with the sender of its answer, for frontends running their own loop.
+A question which is never answered cancels the operation.
+*/
+#[derive(Debug, Clone)]
+pub struct ChannelPrompter {
+    questions: Sender<(Question, Sender<Answer>)>
+}
+
+impl ChannelPrompter {
+    pub fn new(questions: Sender<(Question, Sender<Answer>)>) -> ChannelPrompter {
+        ChannelPrompter {
+            questions
+        }
+    }
+}
+
+impl Prompter for ChannelPrompter {
+    fn ask(&mut self, question: &Question) -> Result<Answer, DomainError> {
+        let (answer_sender, answer) = channel();
+        if self.questions.send((question.clone(), answer_sender)).is_err() {
+            return Ok(Answer::Cancel);
+        }
+        Ok(answer.recv().unwrap_or(Answer::Cancel))
+    }
+}
+
+/*
+Makes the prompter of each interactive guard. Given to the container with Container::attach_to_guards,
+it reaches the guards emitted afterwards, those loaded from a saved state included.
+*/
+#[derive(Clone)]
+pub struct PrompterFactory(Arc<dyn Fn() -> Box<dyn Prompter> + Send + Sync>);
+
+impl PrompterFactory {
+    pub fn new<P: Prompter + Clone + Sync + 'static>(prompter: P) -> PrompterFactory {
+        PrompterFactory(Arc::new(move || Box::new(prompter.clone())))
+    }
+
+    pub fn prompter(&self) -> Box<dyn Prompter> {
+        (self.0)()
+    }
+}
+
+impl Default for PrompterFactory {
+    fn default() -> PrompterFactory {
+        PrompterFactory::new(TerminalPrompter)
+    }
+}
@@ -0,0 +1,137 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::io::Write;
+
+use clap::ArgMatches;
+
+use futurecommander_filesystem::Container;
+
+use crate::command::{
+    Command,
+    errors::CommandError
+};
+
+pub struct QueueCommand {}
+
+impl Command<QueueCommand> {
+    pub fn initialize(args: &ArgMatches<'_>) -> Result<Command<InitializedQueueCommand>, CommandError> {
+        let action = match args.subcommand() {
+            ("list", Some(_matches)) => QueueAction::List,
+            ("drop", Some(matches)) => QueueAction::Drop(Self::extract_index(matches, "index")?),
+            ("move", Some(matches)) => QueueAction::Move(Self::extract_index(matches, "index")?, Self::extract_index(matches, "to")?),
+            _ => return Err(CommandError::InvalidCommand)
+        };
+
+        Ok(Command(InitializedQueueCommand { action }))
+    }
+
+    fn extract_index(args: &ArgMatches<'_>, key: &str) -> Result<usize, CommandError> {
+        match args.value_of(key) {
+            Some(index) => index.trim().parse().map_err(|_| CommandError::CustomError(format!("Invalid index {}", index))),
+            None => Err(CommandError::ArgumentMissing("queue".to_string(), key.to_string(), args.usage().to_string()))
+        }
+    }
+}
+
+pub enum QueueAction {
+    List,
+    Drop(usize),
+    Move(usize, usize)
+}
+
+pub struct InitializedQueueCommand {
+    pub action: QueueAction
+}
+
+impl Command<InitializedQueueCommand> {
+    pub fn execute<W: Write>(self, out: &mut W, container: &mut Container) -> Result<(), CommandError> {
+        match self.0.action {
+            QueueAction::List => {
+                if container.events().next().is_none() {
+                    writeln!(out, "Queue is empty")?;
+                }
+                for (index, event) in container.events().enumerate() {
+                    writeln!(out, "{}\t{}", index, event)?;
+                }
+            },
+            QueueAction::Drop(index) => {
+                let event = container.remove_event(index)?;
+                writeln!(out, "Dropped {}", event)?;
+            },
+            QueueAction::Move(from, to) => container.move_event(from, to)?
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futurecommander_filesystem::{
+        sample::Samples,
+        FileSystemEvent,
+        CopyEvent,
+        RemoveEvent,
+        ReadableFileSystem,
+        Entry,
+        Listener,
+        Delayer
+    };
+
+    use crate::command::AvailableGuard;
+
+    #[test]
+    fn queue_list_reorder_and_drop(){
+        let chroot = Samples::init_simple_chroot("queue_list_reorder_and_drop");
+        let mut container = Container::new();
+
+        for event in [
+            FileSystemEvent::Copy(CopyEvent::new(chroot.join("RDIR").as_path(), chroot.join("COPIED").as_path(), false, false)),
+            FileSystemEvent::Remove(RemoveEvent::new(chroot.join("RDIR2/RFILEA").as_path(), false))
+        ] {
+            let guard = container.emit(&event, AvailableGuard::Zealed.registrar()).unwrap();
+            container.delay(event, guard);
+        }
+
+        Command(InitializedQueueCommand { action: QueueAction::Move(1, 0) })
+            .execute(&mut Vec::new(), &mut container).unwrap();
+
+        let mut stdout = Vec::new();
+        Command(InitializedQueueCommand { action: QueueAction::List })
+            .execute(&mut stdout, &mut container).unwrap();
+        assert_eq!(
+            String::from_utf8(stdout).unwrap(),
+            format!(
+                "0\trm {}\n1\tcp {} {}\n",
+                chroot.join("RDIR2/RFILEA").to_string_lossy(),
+                chroot.join("RDIR").to_string_lossy(),
+                chroot.join("COPIED").to_string_lossy()
+            )
+        );
+
+        Command(InitializedQueueCommand { action: QueueAction::Drop(1) })
+            .execute(&mut Vec::new(), &mut container).unwrap();
+        assert!(!container.status(chroot.join("COPIED").as_path()).unwrap().exists());
+        assert!(Command(InitializedQueueCommand { action: QueueAction::Drop(1) })
+            .execute(&mut Vec::new(), &mut container).is_err());
+    }
+}
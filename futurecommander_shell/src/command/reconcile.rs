@@ -0,0 +1,118 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    fs::{ read_to_string },
+    io::Write,
+    path::{ Path, PathBuf }
+};
+
+use clap::ArgMatches;
+
+use futurecommander_filesystem::{ Container, Manifest };
+
+use crate::command::{
+    Command,
+    errors::CommandError
+};
+
+pub struct ReconcileCommand {}
+
+impl Command<ReconcileCommand> {
+    pub fn initialize(cwd: &Path, args: &ArgMatches<'_>) -> Result<Command<InitializedReconcileCommand>, CommandError> {
+        Ok(
+            Command(
+                InitializedReconcileCommand {
+                    path: Self::extract_path_from_args(cwd, args, "path")?
+                }
+            )
+        )
+    }
+}
+
+pub struct InitializedReconcileCommand {
+    pub path: PathBuf
+}
+
+impl Command<InitializedReconcileCommand> {
+    pub fn execute<W: Write>(self, out: &mut W, container: &mut Container) -> Result<(), CommandError> {
+        if ! self.0.path.exists() {
+            return Err(CommandError::DoesNotExists(self.0.path));
+        }
+
+        let content = read_to_string(self.0.path.as_path())?;
+        let manifest = match self.0.path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Manifest::from_toml(content.as_str())?,
+            Some("yaml") | Some("yml") => Manifest::from_yaml(content.as_str())?,
+            extension => return Err(CommandError::InvalidFormat(extension.unwrap_or("").to_string()))
+        };
+
+        let manifest_directory = self.0.path.parent().unwrap_or_else(|| Path::new("/"));
+        let staged = manifest.reconcile(manifest_directory, container)?;
+        if staged.is_empty() {
+            writeln!(out, "Already reconciled")?;
+        }
+        for event in staged.iter() {
+            writeln!(out, "{}", event)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(tarpaulin_include))]
+mod tests {
+    use super::*;
+
+    use std::{
+        fs::write
+    };
+
+    use futurecommander_filesystem::{
+        sample::Samples,
+        ReadableFileSystem,
+        Entry
+    };
+
+    #[test]
+    fn can_stage_a_manifest(){
+        let sample_path = Samples::init_advanced_chroot("can_stage_a_manifest");
+        write(
+            sample_path.join("layout.yml"),
+            "root: SITE\nentries:\n  - path: docs/C\n    from: A/C\n"
+        ).unwrap();
+
+        let mut container = Container::new();
+        let mut stdout = Vec::new();
+        Command(InitializedReconcileCommand {
+            path: sample_path.join("layout.yml")
+        }).execute(&mut stdout, &mut container).unwrap();
+
+        assert!(container.status(sample_path.join("SITE/docs/C").as_path()).unwrap().is_file());
+        assert_eq!(
+            String::from_utf8(stdout).unwrap(),
+            format!(
+                "mkdir -r {}\ncp {} {}\n",
+                sample_path.join("SITE/docs").to_string_lossy(),
+                sample_path.join("A/C").to_string_lossy(),
+                sample_path.join("SITE/docs/C").to_string_lossy()
+            )
+        );
+    }
+}
@@ -17,7 +17,10 @@
  * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use std::path::{ Path, PathBuf };
+use std::{
+    env::var,
+    path::{ Path, PathBuf }
+};
 
 use clap::ArgMatches;
 
@@ -26,7 +29,9 @@ use futurecommander_filesystem::{
     RemoveEvent,
     Listener,
     Delayer,
-    FileSystemEvent
+    FileSystemEvent,
+    Filter,
+    Trash
 };
 
 use crate::command::{
@@ -52,23 +57,47 @@ impl Command<RemoveCommand> {
                 InitializedRemoveCommand {
                     path,
                     recursive: args.is_present("recursive"),
-                    guard: Self::extract_available_guard(args, "guard")?
+                    filter: Self::extract_filter(args)?,
+                    trash: if Self::trash_requested(args) { Some(Trash::home()?) } else { None },
+                    follow_symlinks: args.is_present("follow_symlinks"),
+                    allow_dangerous: args.is_present("allow_dangerous"),
+                    cwd: cwd.to_path_buf(),
+                    guard: Self::extract_available_guard(cwd, args, "guard")?
                 }
             )
         )
     }
+
+    //FUTURECOMMANDER_TRASH makes the trash the default, unless --permanent is given
+    fn trash_requested(args: &ArgMatches<'_>) -> bool {
+        args.is_present("trash")
+            || (! args.is_present("permanent") && matches!(var("FUTURECOMMANDER_TRASH").as_deref(), Ok("1") | Ok("true") | Ok("yes")))
+    }
 }
 
 pub struct InitializedRemoveCommand {
     pub path: PathBuf,
     pub recursive: bool,
+    pub filter: Filter,
+    pub trash: Option<Trash>,
+    pub follow_symlinks: bool,
+    pub allow_dangerous: bool,
+    pub cwd: PathBuf,
     pub guard: AvailableGuard
 }
 
 impl Command<InitializedRemoveCommand> {
     pub fn execute(self, container: &mut Container) -> Result<(), CommandError> {
+        let event = RemoveEvent::new(self.0.path.as_path(), self.0.recursive)
+            .with_filter(self.0.filter)
+            .with_follow_symlinks(self.0.follow_symlinks)
+            .with_allow_dangerous(self.0.allow_dangerous)
+            .with_working_directory(self.0.cwd.as_path());
         let event = FileSystemEvent::Remove(
-            RemoveEvent::new(self.0.path.as_path(), self.0.recursive)
+            match self.0.trash {
+                Some(trash) => event.with_trash(trash),
+                None => event
+            }
         );
 
         let guard = container.emit(&event, self.0.guard.registrar())?;
@@ -85,7 +114,10 @@ mod tests {
     use futurecommander_filesystem::{
         sample::Samples,
         ReadableFileSystem,
-        Entry
+        Entry,
+        DomainError,
+        Pattern,
+        capability::{ Capability, Policy, PolicyRule, PolicyAction }
     };
 
     #[test]
@@ -100,6 +132,11 @@ mod tests {
         let remove_b = Command(InitializedRemoveCommand {
              path: b_path.to_path_buf(),
             recursive: true,
+            filter: Filter::default(),
+            trash: None,
+            follow_symlinks: false,
+            allow_dangerous: false,
+            cwd: sample_path.clone(),
             guard: AvailableGuard::Zealed
         });
 
@@ -111,4 +148,29 @@ mod tests {
                 .exists()
         )
     }
+
+    #[test]
+    fn rm_denied_by_policy_even_blind(){
+        let chroot = Samples::init_simple_chroot("rm_denied_by_policy_even_blind");
+        let mut container = Container::new();
+        let policy = Policy::new(vec![
+            PolicyRule::new(Pattern::new("RFILEA").unwrap(), vec![Capability::Delete], PolicyAction::Deny)
+        ]);
+
+        let result = Command(InitializedRemoveCommand {
+            path: chroot.join("RDIR"),
+            recursive: true,
+            filter: Filter::default(),
+            trash: None,
+            follow_symlinks: false,
+            allow_dangerous: false,
+            cwd: chroot.clone(),
+            guard: AvailableGuard::Policy(policy, Box::new(AvailableGuard::Blind))
+        }).execute(&mut container);
+
+        assert!(matches!(result, Err(CommandError::Operation(DomainError::PolicyDenied(Capability::Delete, _)))));
+        assert!(container.status(chroot.join("RDIR/RFILEA").as_path()).unwrap().exists());
+        assert!(AvailableGuard::available("policy:blind"));
+        assert!(!AvailableGuard::available("policy:policy"));
+    }
 }
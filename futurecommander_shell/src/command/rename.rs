@@ -0,0 +1,104 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    ffi::OsString,
+    path::{ Path, PathBuf }
+};
+
+use clap::ArgMatches;
+
+use futurecommander_filesystem::{
+    Container,
+    RenameEvent,
+    ReadableFileSystem,
+    Entry,
+    Listener,
+    Delayer,
+    FileSystemEvent,
+    lossless
+};
+
+use crate::command::{
+    Command,
+    errors::CommandError,
+    AvailableGuard
+};
+
+pub struct RenameCommand {}
+
+impl Command<RenameCommand> {
+    pub fn initialize(cwd: &Path, args: &ArgMatches<'_>) -> Result<Command<InitializedRenameCommand>, CommandError> {
+        match args.value_of("name") {
+            Some(name) => Ok(
+                Command(InitializedRenameCommand {
+                    path: Self::extract_path_from_args(cwd, args, "path")?,
+                    name: lossless::decode(name.trim())?.into_os_string()
+                })
+            ),
+            None => Err(CommandError::ArgumentMissing("rename".to_string(), "name".to_string(), args.usage().to_string()))
+        }
+    }
+}
+
+pub struct InitializedRenameCommand {
+    pub path: PathBuf,
+    pub name: OsString
+}
+
+impl Command<InitializedRenameCommand> {
+    pub fn execute(self, container: &mut Container) -> Result<(), CommandError> {
+        if ! container.status(self.0.path.as_path())?.exists() {
+            return Err(CommandError::DoesNotExists(self.0.path));
+        }
+
+        let event = FileSystemEvent::Rename(
+            RenameEvent::new(self.0.path.as_path(), self.0.name.as_os_str())
+        );
+
+        //A name already taken is refused before any guard is asked
+        let guard = container.emit(&event, AvailableGuard::Zealed.registrar())?;
+        container.delay(event, guard);
+        Ok(())
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futurecommander_filesystem::{
+        sample::Samples
+    };
+
+    #[test]
+    fn rename_directory_in_place(){
+        let chroot = Samples::init_simple_chroot("rename_directory_in_place");
+        let mut container = Container::new();
+
+        Command(InitializedRenameCommand {
+            path: chroot.join("RDIR"),
+            name: OsString::from("RENAMED")
+        }).execute(&mut container).unwrap();
+
+        assert!(!container.status(chroot.join("RDIR").as_path()).unwrap().exists());
+        assert!(container.status(chroot.join("RENAMED/RFILEA").as_path()).unwrap().is_file());
+    }
+}
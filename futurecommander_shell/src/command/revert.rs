@@ -0,0 +1,116 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::io::Write;
+
+use clap::ArgMatches;
+
+use futurecommander_filesystem::{
+    Container,
+    Recovery
+};
+
+use crate::command::{
+    Command,
+    errors::CommandError
+};
+
+pub struct RevertCommand {}
+
+impl Command<RevertCommand> {
+    pub fn initialize(args: &ArgMatches<'_>) -> Result<Command<InitializedRevertCommand>, CommandError> {
+        Ok(
+            Command(InitializedRevertCommand {
+                recovery: Recovery::home()?,
+                session: args.value_of("session").map(|session| session.trim().to_string())
+            })
+        )
+    }
+}
+
+pub struct InitializedRevertCommand {
+    pub recovery: Recovery,
+    pub session: Option<String>
+}
+
+impl Command<InitializedRevertCommand> {
+    //Without session, lists the sessions which can still be reverted
+    pub fn execute<W: Write>(self, out: &mut W, container: &mut Container) -> Result<(), CommandError> {
+        match self.0.session {
+            Some(session) => {
+                let revert = container.revert(&self.0.recovery, session.as_str())?;
+                writeln!(out, "Session {} reverted, revert {} to undo it", session, revert)?;
+            },
+            None => {
+                let sessions = self.0.recovery.sessions()?;
+                if sessions.is_empty() {
+                    writeln!(out, "Nothing to revert")?;
+                }
+                for session in sessions.iter() {
+                    writeln!(out, "{}\t{}\t{} operations", session.name(), session.created(), session.operations())?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futurecommander_filesystem::{
+        sample::Samples,
+        FileSystemEvent,
+        RemoveEvent,
+        Listener,
+        Delayer
+    };
+
+    use crate::command::AvailableGuard;
+
+    #[test]
+    fn revert_lists_and_restores_sessions(){
+        let chroot = Samples::init_simple_chroot("revert_lists_and_restores_sessions");
+        let recovery = Recovery::new(Samples::init_empty_chroot("revert_lists_and_restores_sessions_recovery").as_path());
+        let mut container = Container::new();
+
+        let event = FileSystemEvent::Remove(RemoveEvent::new(chroot.join("RDIR/RFILEA").as_path(), false));
+        let guard = container.emit(&event, AvailableGuard::Zealed.registrar()).unwrap();
+        container.delay(event, guard);
+        let session = container.apply_recoverable(&recovery).unwrap();
+        assert!(!chroot.join("RDIR/RFILEA").exists());
+
+        let mut stdout = Vec::new();
+        Command(InitializedRevertCommand {
+            recovery: recovery.clone(),
+            session: None
+        }).execute(&mut stdout, &mut container).unwrap();
+        let listed = String::from_utf8(stdout).unwrap();
+        assert!(listed.starts_with(format!("{}\t", session).as_str()));
+        assert!(listed.ends_with("\t1 operations\n"));
+
+        Command(InitializedRevertCommand {
+            recovery,
+            session: Some(session)
+        }).execute(&mut Vec::new(), &mut container).unwrap();
+        assert!(chroot.join("RDIR/RFILEA").exists());
+    }
+}
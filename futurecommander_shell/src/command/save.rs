@@ -81,7 +81,10 @@ mod tests {
     };
 
     use futurecommander_filesystem::{
-        sample::Samples
+        sample::Samples,
+        Filter,
+        OverwritePolicy,
+        Backup
     };
 
     #[test]
@@ -92,7 +95,12 @@ mod tests {
             source: sample_path.join("A"),
             destination: sample_path.join("APRIME"),
             merge: false,
-            overwrite: false,
+            overwrite: OverwritePolicy::Never,
+            keep_both: false,
+            rename_template: None,
+            backup: Backup::None,
+            filter: Filter::default(),
+            follow_symlinks: false,
             guard: AvailableGuard::Interactive
         });
 
@@ -0,0 +1,159 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::io::Write;
+
+use clap::ArgMatches;
+
+use futurecommander_filesystem::{
+    Container,
+    ChangeKind,
+    DomainError
+};
+
+use crate::command::{
+    Command,
+    errors::CommandError
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StatusFormat {
+    Human,
+    Porcelain,
+    Json
+}
+
+pub struct StatusCommand {}
+
+impl Command<StatusCommand> {
+    pub fn initialize(args: &ArgMatches<'_>) -> Result<Command<InitializedStatusCommand>, CommandError> {
+        Ok(
+            Command(InitializedStatusCommand {
+                format: if args.is_present("json") {
+                    StatusFormat::Json
+                } else if args.is_present("porcelain") {
+                    StatusFormat::Porcelain
+                } else {
+                    StatusFormat::Human
+                }
+            })
+        )
+    }
+}
+
+pub struct InitializedStatusCommand {
+    pub format: StatusFormat
+}
+
+impl Command<InitializedStatusCommand> {
+    pub fn execute<W: Write>(self, out: &mut W, container: &mut Container) -> Result<(), CommandError> {
+        let changes = container.changes()?;
+
+        match self.0.format {
+            StatusFormat::Json => writeln!(out, "{}", changes.to_json().map_err(DomainError::from)?)?,
+            //One change per line : kind, path, then source when there is one
+            StatusFormat::Porcelain => for change in changes.iter() {
+                match change.source() {
+                    Some(source) => writeln!(out, "{}\t{}\t{}", change.kind(), change.path().to_string_lossy(), source.to_string_lossy())?,
+                    None => writeln!(out, "{}\t{}", change.kind(), change.path().to_string_lossy())?
+                }
+            },
+            StatusFormat::Human => {
+                if changes.is_empty() {
+                    writeln!(out, "Nothing to apply")?;
+                    return Ok(());
+                }
+
+                for change in changes.iter() {
+                    let kind = format!("{}:", change.kind());
+                    match (change.kind(), change.source()) {
+                        (ChangeKind::Moved, Some(source)) => writeln!(out, "\t{:<10}{} -> {}", kind, source.to_string_lossy(), change.path().to_string_lossy())?,
+                        _ => writeln!(out, "\t{:<10}{}", kind, change.path().to_string_lossy())?
+                    }
+                }
+                writeln!(
+                    out,
+                    "{} added, {} removed, {} replaced, {} moved, {} modified, {} bytes",
+                    changes.count(ChangeKind::Added),
+                    changes.count(ChangeKind::Removed),
+                    changes.count(ChangeKind::Replaced),
+                    changes.count(ChangeKind::Moved),
+                    changes.count(ChangeKind::Modified),
+                    changes.bytes()
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futurecommander_filesystem::{
+        sample::Samples,
+        FileSystemEvent,
+        MoveEvent,
+        CreateEvent,
+        Kind,
+        Listener,
+        Delayer
+    };
+
+    use crate::command::AvailableGuard;
+
+    #[test]
+    fn status_summarize_pending_changes(){
+        let chroot = Samples::init_simple_chroot("status_summarize_pending_changes");
+        let mut container = Container::new();
+
+        let status = |container: &mut Container, format: StatusFormat| {
+            let mut stdout = Vec::new();
+            Command(InitializedStatusCommand { format }).execute(&mut stdout, container).unwrap();
+            String::from_utf8(stdout).unwrap()
+        };
+        assert_eq!(status(&mut container, StatusFormat::Human), "Nothing to apply\n");
+
+        for event in [
+            FileSystemEvent::Move(MoveEvent::new(chroot.join("RDIR/RFILEA").as_path(), chroot.join("MOVED").as_path(), false, false)),
+            FileSystemEvent::Create(CreateEvent::new(chroot.join("NEW").as_path(), Kind::Directory, false, false))
+        ] {
+            let guard = container.emit(&event, AvailableGuard::Zealed.registrar()).unwrap();
+            container.delay(event, guard);
+        }
+
+        let human = status(&mut container, StatusFormat::Human);
+        assert!(human.contains(format!("\tmoved:    {} -> {}\n", chroot.join("RDIR/RFILEA").to_string_lossy(), chroot.join("MOVED").to_string_lossy()).as_str()));
+        let bytes = chroot.join("RDIR/RFILEA").metadata().unwrap().len();
+        assert!(human.ends_with(format!("1 added, 0 removed, 0 replaced, 1 moved, 0 modified, {} bytes\n", bytes).as_str()));
+
+        assert_eq!(
+            status(&mut container, StatusFormat::Porcelain),
+            format!(
+                "moved\t{}\t{}\nadded\t{}\n",
+                chroot.join("MOVED").to_string_lossy(),
+                chroot.join("RDIR/RFILEA").to_string_lossy(),
+                chroot.join("NEW").to_string_lossy()
+            )
+        );
+        assert!(status(&mut container, StatusFormat::Json).starts_with("{\"changes\":[{\"kind\":\"moved\""));
+    }
+}
@@ -0,0 +1,131 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::path::{ Path, PathBuf };
+
+use clap::ArgMatches;
+
+use futurecommander_filesystem::{
+    Container,
+    SyncEvent,
+    SyncComparison,
+    Listener,
+    Delayer,
+    ReadableFileSystem,
+    Entry,
+    FileSystemEvent
+};
+
+use crate::{
+    command::{
+        errors::CommandError,
+        Command,
+        AvailableGuard
+    }
+};
+
+pub struct SyncCommand {}
+
+impl Command<SyncCommand> {
+    pub fn initialize(cwd: &Path, args: &ArgMatches<'_>) -> Result<Command<InitializedSyncCommand>, CommandError> {
+        Ok(
+            Command(InitializedSyncCommand {
+                source: Self::extract_path_from_args(cwd, args, "source")?,
+                destination: Self::extract_path_from_args(cwd, args, "destination")?,
+                delete: args.is_present("delete"),
+                skip_unchanged: args.is_present("skip_unchanged"),
+                comparison: if args.is_present("checksum") { SyncComparison::Content } else { SyncComparison::SizeAndTime },
+                guard: Self::extract_available_guard(cwd, args, "guard")?
+            })
+        )
+    }
+}
+
+pub struct InitializedSyncCommand {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    pub delete: bool,
+    pub skip_unchanged: bool,
+    pub comparison: SyncComparison,
+    pub guard: AvailableGuard
+}
+
+impl Command<InitializedSyncCommand> {
+    pub fn execute(self, container: &mut Container) -> Result<(), CommandError> {
+        if ! container.status(self.0.source.as_path())?.exists() {
+            return Err(CommandError::DoesNotExists(self.0.source));
+        }
+
+        let event = FileSystemEvent::Sync(
+            SyncEvent::new(self.0.source.as_path(), self.0.destination.as_path(), self.0.delete)
+                .with_skip_unchanged(self.0.skip_unchanged)
+                .with_comparison(self.0.comparison)
+        );
+
+        let guard = container.emit(&event, self.0.guard.registrar())?;
+        container.delay(event, guard);
+        Ok(())
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futurecommander_filesystem::{
+        sample::Samples
+    };
+
+    #[test]
+    fn sync_mirror_directory(){
+        let chroot = Samples::init_simple_chroot("sync_mirror_directory");
+        let mut container = Container::new();
+
+        Command(InitializedSyncCommand {
+            source: chroot.join("RDIR"),
+            destination: chroot.join("RDIR2"),
+            delete: true,
+            skip_unchanged: true,
+            comparison: SyncComparison::Content,
+            guard: AvailableGuard::Zealed
+        }).execute(&mut container).unwrap();
+
+        assert!(container.status(chroot.join("RDIR2/RFILEB").as_path()).unwrap().is_file());
+        assert!(!container.status(chroot.join("RDIR2/RFILEC").as_path()).unwrap().exists());
+    }
+
+    #[test]
+    fn sync_without_delete_keep_extraneous(){
+        let chroot = Samples::init_simple_chroot("sync_without_delete_keep_extraneous");
+        let mut container = Container::new();
+
+        Command(InitializedSyncCommand {
+            source: chroot.join("RDIR"),
+            destination: chroot.join("RDIR2"),
+            delete: false,
+            skip_unchanged: false,
+            comparison: SyncComparison::SizeAndTime,
+            guard: AvailableGuard::Quiet
+        }).execute(&mut container).unwrap();
+
+        assert!(container.status(chroot.join("RDIR2/RFILEB").as_path()).unwrap().is_file());
+        assert!(container.status(chroot.join("RDIR2/RFILEC").as_path()).unwrap().exists());
+    }
+}
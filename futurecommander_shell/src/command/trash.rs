@@ -0,0 +1,152 @@
+/*
+ * Copyright 2019 François CADEILLAN
+ *
+ * This file is part of FutureCommander.
+ *
+ * FutureCommander is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * FutureCommander is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with FutureCommander.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    ffi::OsString,
+    io::Write
+};
+
+use clap::ArgMatches;
+
+use futurecommander_filesystem::{
+    Container,
+    Trash,
+    Listener,
+    Delayer,
+    lossless
+};
+
+use crate::command::{
+    Command,
+    errors::CommandError,
+    AvailableGuard
+};
+
+pub struct TrashCommand {}
+
+impl Command<TrashCommand> {
+    pub fn initialize(args: &ArgMatches<'_>) -> Result<Command<InitializedTrashCommand>, CommandError> {
+        let action = match args.subcommand() {
+            ("list", Some(_matches)) => TrashAction::List,
+            ("restore", Some(matches)) => match matches.value_of("name") {
+                Some(name) => TrashAction::Restore(lossless::decode(name.trim())?.into_os_string()),
+                None => return Err(CommandError::ArgumentMissing("trash".to_string(), "name".to_string(), matches.usage().to_string()))
+            },
+            _ => return Err(CommandError::InvalidCommand)
+        };
+
+        Ok(
+            Command(InitializedTrashCommand {
+                trash: Trash::home()?,
+                action
+            })
+        )
+    }
+}
+
+pub enum TrashAction {
+    List,
+    Restore(OsString)
+}
+
+pub struct InitializedTrashCommand {
+    pub trash: Trash,
+    pub action: TrashAction
+}
+
+impl Command<InitializedTrashCommand> {
+    pub fn execute<W: Write>(self, out: &mut W, container: &mut Container) -> Result<(), CommandError> {
+        //Trashes of other mounted file systems are listed too
+        let mut entries = Vec::new();
+        for trash in self.0.trash.known() {
+            entries.append(&mut trash.list()?);
+        }
+
+        match self.0.action {
+            TrashAction::List => {
+                if entries.is_empty() {
+                    writeln!(out, "Trash is empty")?;
+                }
+                for entry in entries.iter() {
+                    writeln!(out, "{}\t{}\t{}", entry.deletion_date(), lossless::escape(entry.name()), lossless::escape(entry.original().as_os_str()))?;
+                }
+            },
+            TrashAction::Restore(name) => {
+                let entry = match entries.iter().find(|entry| entry.name() == name) {
+                    Some(entry) => entry,
+                    None => return Err(CommandError::CustomError(format!("{} is not in the trash", lossless::escape(name.as_os_str()))))
+                };
+
+                //An entry recreated since its deletion is never overwritten
+                for event in entry.restore() {
+                    let guard = container.emit(&event, AvailableGuard::Zealed.registrar())?;
+                    container.delay(event, guard);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(tarpaulin_include))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs::{ create_dir_all, write };
+
+    use futurecommander_filesystem::{
+        sample::Samples,
+        ReadableFileSystem,
+        Entry
+    };
+
+    #[test]
+    fn trash_list_and_restore(){
+        let chroot = Samples::init_empty_chroot("trash_list_and_restore");
+        let trash = Trash::new(chroot.join("Trash").as_path());
+        create_dir_all(trash.files()).unwrap();
+        create_dir_all(trash.info()).unwrap();
+        write(trash.files().join("notes.txt"), "notes").unwrap();
+        write(
+            trash.info().join("notes.txt.trashinfo"),
+            format!("[Trash Info]\nPath={}/My%20notes.txt\nDeletionDate=2019-10-07T10:20:30\n", chroot.to_string_lossy())
+        ).unwrap();
+        let mut container = Container::new();
+
+        let mut stdout = Vec::new();
+        Command(InitializedTrashCommand {
+            trash: trash.clone(),
+            action: TrashAction::List
+        }).execute(&mut stdout, &mut container).unwrap();
+
+        assert_eq!(
+            String::from_utf8(stdout).unwrap(),
+            format!("2019-10-07T10:20:30\tnotes.txt\t{}\n", chroot.join("My notes.txt").to_string_lossy())
+        );
+
+        Command(InitializedTrashCommand {
+            trash,
+            action: TrashAction::Restore(OsString::from("notes.txt"))
+        }).execute(&mut Vec::new(), &mut container).unwrap();
+
+        assert!(container.status(chroot.join("My notes.txt").as_path()).unwrap().is_file());
+        assert!(!container.status(chroot.join("Trash/info/notes.txt.trashinfo").as_path()).unwrap().exists());
+    }
+}
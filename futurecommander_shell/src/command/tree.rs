@@ -28,7 +28,8 @@ use futurecommander_filesystem::{
     Container,
     ReadableFileSystem,
     Entry,
-    EntryAdapter
+    EntryAdapter,
+    lossless
 };
 
 use crate::command::{
@@ -79,7 +80,7 @@ impl Command<InitializedTreeCommand> {
 
     fn tree<W: Write>(out: &mut W, container: &Container, identity: &dyn Entry, depth_list: Option<Vec<bool>>, parent_last: bool) -> Result<(), CommandError>{
         let file_name = match identity.name() {
-            Some(file_name) => file_name.to_string_lossy().to_string(),
+            Some(file_name) => lossless::escape(file_name),
             None => "/".to_string()
         };
 
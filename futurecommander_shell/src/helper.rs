@@ -23,7 +23,8 @@ use std::vec::IntoIter;
 use futurecommander_filesystem::{
     ReadableFileSystem,
     Entry,
-    Container
+    Container,
+    lossless
 };
 
 use crate::{
@@ -45,7 +46,7 @@ use rustyline::{
 static WHITE_PROMPT: &str = "\x1b[1;97m>>\x1b[0m ";
 static RED_PROMPT: &str = "\x1b[1;91m>>\x1b[0m ";
 
-const fn available_commands() -> [&'static str; 18] {
+const fn available_commands() -> [&'static str; 33] {
     [
         "exit",
         "cd",
@@ -59,11 +60,26 @@ const fn available_commands() -> [&'static str; 18] {
         "ls",
         "cp",
         "mv",
+        "ln",
         "rm",
+        "rename",
+        "batch_rename",
+        "trash",
+        "sync",
         "mkdir",
         "touch",
+        "chmod",
+        "chown",
         "tree",
+        "status",
+        "queue",
         "apply",
+        "revert",
+        "lock",
+        "portability",
+        "answers",
+        "export",
+        "reconcile",
         "history"
     ]
 }
@@ -162,26 +178,26 @@ impl  <'a>VirtualHelper<'a>  {
             Ok(collection) => {
                 for entry in collection.iter() {
                     let path = entry.path().strip_prefix(&self.cwd).unwrap_or_else(|_| entry.path());
-                    let path_str = path.as_os_str().to_str().unwrap();
-                    let score = Self::score(given, path_str);
+                    let path_str = lossless::encode(path);
+                    let score = Self::score(given, path_str.as_str());
                     if score > max_score {
                         max_score = score;
                     }
 
-                    scores.push((score, path_str.to_string(), given.len()));
+                    scores.push((score, path_str, given.len()));
                 }
             },
             Err(_) =>
                 if let Ok(collection) = self.container.read_dir(parent.as_path()) {
                     for entry in collection.iter() {
                         let path = entry.path().strip_prefix(&self.cwd).unwrap_or_else(|_| entry.path());
-                        let path_str = path.as_os_str().to_str().unwrap();
-                        let score = Self::score(given, path_str);
+                        let path_str = lossless::encode(path);
+                        let score = Self::score(given, path_str.as_str());
                         if score > max_score {
                             max_score = score;
                         }
 
-                        scores.push((score, path_str.to_string(), given.len()));
+                        scores.push((score, path_str, given.len()));
                     }
                 }
         }
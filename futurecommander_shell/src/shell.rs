@@ -33,7 +33,9 @@ use futurecommander_filesystem::{
     Container,
     Kind,
     ReadableFileSystem,
-    tools::{ absolute }
+    Recovery,
+    tools::{ absolute },
+    lossless
 };
 
 use crate::{
@@ -59,7 +61,7 @@ impl Default for Shell {
 
 impl Shell {
     fn send_matches<W: Write>(&mut self, matches: &ArgMatches, out: &mut W) -> Result<(), ShellError> {
-        match matches.subcommand() {
+        let result = match matches.subcommand() {
             ("exit", Some(_matches)) => Err(CommandError::Exit),
             ("cd",   Some(matches))  => self.cd(matches),
             ("debug_status",   Some(matches))  =>
@@ -83,8 +85,22 @@ impl Shell {
                 .and_then(|c| c.execute(&mut self.container)),
             ("mv",          Some(matches)) => Command::<MoveCommand>::initialize(&self.cwd, matches)
                 .and_then(|c| c.execute(&mut self.container)),
+            ("ln",          Some(matches)) => Command::<LinkCommand>::initialize(&self.cwd, matches)
+                .and_then(|c| c.execute(&mut self.container)),
+            ("sync",        Some(matches)) => Command::<SyncCommand>::initialize(&self.cwd, matches)
+                .and_then(|c| c.execute(&mut self.container)),
             ("rm",          Some(matches)) => Command::<RemoveCommand>::initialize(&self.cwd, matches)
                 .and_then(|c| c.execute(&mut self.container)),
+            ("chmod",       Some(matches)) => Command::<ChmodCommand>::initialize(&self.cwd, matches)
+                .and_then(|c| c.execute(&mut self.container)),
+            ("chown",       Some(matches)) => Command::<ChownCommand>::initialize(&self.cwd, matches)
+                .and_then(|c| c.execute(&mut self.container)),
+            ("rename",      Some(matches)) => Command::<RenameCommand>::initialize(&self.cwd, matches)
+                .and_then(|c| c.execute(&mut self.container)),
+            ("batch_rename", Some(matches)) => Command::<BatchRenameCommand>::initialize(&self.cwd, matches)
+                .and_then(|c| c.execute(out, &mut self.container)),
+            ("trash",       Some(matches)) => Command::<TrashCommand>::initialize(matches)
+                .and_then(|c| c.execute(out, &mut self.container)),
             ("mkdir",       Some(matches)) => Command::<NewDirectoryCommand>::initialize(&self.cwd, matches)
                 .and_then(|c| c.execute(&mut self.container)),
             ("touch",       Some(matches)) => Command::<NewFileCommand>::initialize(&self.cwd, matches)
@@ -95,9 +111,31 @@ impl Shell {
                 .and_then(|c| c.execute(&mut self.container)),
             ("import",        Some(matches)) => Command::<ImportCommand>::initialize(&self.cwd, matches)
                 .and_then(|c| c.execute(&mut self.container)),
-            ("apply",        Some(_matches)) => self.apply(),
+            ("export",        Some(matches)) => Command::<ExportCommand>::initialize(&self.cwd, matches)
+                .and_then(|c| c.execute(out, &mut self.container)),
+            ("reconcile",     Some(matches)) => Command::<ReconcileCommand>::initialize(&self.cwd, matches)
+                .and_then(|c| c.execute(out, &mut self.container)),
+            ("status",      Some(matches)) => Command::<StatusCommand>::initialize(matches)
+                .and_then(|c| c.execute(out, &mut self.container)),
+            ("queue",       Some(matches)) => Command::<QueueCommand>::initialize(matches)
+                .and_then(|c| c.execute(out, &mut self.container)),
+            ("revert",      Some(matches)) => Command::<RevertCommand>::initialize(matches)
+                .and_then(|c| c.execute(out, &mut self.container)),
+            ("lock",        Some(matches)) => Command::<LockCommand>::initialize(matches)
+                .and_then(|c| c.execute(out, &mut self.container)),
+            ("answers",     Some(matches)) => Command::<AnswersCommand>::initialize(&self.cwd, matches)
+                .and_then(|c| c.execute(out, &mut self.container)),
+            ("portability", Some(matches)) => Command::<PortabilityCommand>::initialize(matches)
+                .and_then(|c| c.execute(out, &mut self.container)),
+            ("apply",        Some(matches)) => self.apply(matches, out),
             _ => Err(CommandError::InvalidCommand)
-        }?;
+        };
+
+        //Name issues of what the command did, even when it failed halfway
+        for warning in self.container.take_warnings() {
+            writeln!(out, "Warning : {}", warning)?;
+        }
+        result?;
         Ok(())
     }
 
@@ -277,7 +315,7 @@ impl Shell {
     fn cd(&mut self, matches: &ArgMatches<'_>) -> Result<(), CommandError> {
         match matches.value_of("path") {
             Some(string_path) => {
-                let path = absolute(self.cwd.as_path(), Path::new(string_path));
+                let path = absolute(self.cwd.as_path(), lossless::decode(string_path)?.as_path());
 
                 match self.container.status(path.as_path())?.into_inner().into_existing_virtual() {
                     Some(virtual_identity) =>
@@ -294,7 +332,13 @@ impl Shell {
         }
     }
 
-    fn apply(&mut self) -> Result<(), CommandError> {
+    fn apply<W: Write>(&mut self, matches: &ArgMatches, out: &mut W) -> Result<(), CommandError> {
+        if matches.is_present("recoverable") {
+            let session = self.container.apply_recoverable(&Recovery::home()?)?;
+            writeln!(out, "Applied, revert with: revert {}", session)?;
+            return Ok(());
+        }
+
         match self.container.apply() {
             Ok(_) => Ok(()),
             Err(error) => Err(CommandError::from(error))
@@ -45,7 +45,10 @@ mod command_integration {
         sample::Samples,
         Container,
         EntryAdapter,
-        ReadableFileSystem
+        ReadableFileSystem,
+        Filter,
+        OverwritePolicy,
+        Backup
     };
 
     #[test]
@@ -57,7 +60,12 @@ mod command_integration {
             source: sample_path.join("B"),
             destination: sample_path.join("A"),
             merge: false,
-            overwrite: false,
+            overwrite: OverwritePolicy::Never,
+            keep_both: false,
+            rename_template: None,
+            backup: Backup::None,
+            filter: Filter::default(),
+            follow_symlinks: false,
             guard: AvailableGuard::Zealed
         });
         move_b_to_a.execute(&mut fs).unwrap();
@@ -66,7 +74,15 @@ mod command_integration {
             source: sample_path.join("A"),
             destination: sample_path.join("APRIME"),
             merge: false,
-            overwrite: false,
+            overwrite: OverwritePolicy::Never,
+            keep_both: false,
+            rename_template: None,
+            backup: Backup::None,
+            filter: Filter::default(),
+            follow_symlinks: false,
+            cross_device: false,
+            allow_dangerous: false,
+            cwd: sample_path.clone(),
             guard: AvailableGuard::Zealed
         });
         move_a_as_aprime.execute(&mut fs).unwrap();
@@ -102,6 +118,7 @@ mod command_integration {
             path: sample_path.join(&Path::new("TEST")),
             recursive: false,
             overwrite: false,
+            modified: None,
             guard: AvailableGuard::Zealed
         });
         touch_test.execute(&mut fs).unwrap();
@@ -110,7 +127,12 @@ mod command_integration {
             source: sample_path.join("TEST"),
             destination: sample_path.join("Z"),
             merge: false,
-            overwrite: false,
+            overwrite: OverwritePolicy::Never,
+            keep_both: false,
+            rename_template: None,
+            backup: Backup::None,
+            filter: Filter::default(),
+            follow_symlinks: false,
             guard: AvailableGuard::Zealed
         });
         copy_test_to_z.execute(&mut fs).unwrap();